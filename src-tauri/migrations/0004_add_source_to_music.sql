@@ -0,0 +1,10 @@
+-- add a new column `source` to the `music` table, and namespace existing song_id by source
+ALTER TABLE music ADD COLUMN source TEXT NOT NULL DEFAULT 'gequbao';
+
+-- playlist_music references music(song_id) without ON UPDATE CASCADE, defer the check to commit
+PRAGMA defer_foreign_keys = ON;
+
+UPDATE music SET song_id = 'gequbao:' || song_id WHERE instr(song_id, ':') = 0;
+UPDATE playlist_music SET song_id = 'gequbao:' || song_id WHERE instr(song_id, ':') = 0;
+
+CREATE INDEX IF NOT EXISTS idx_music_source ON music(source);
@@ -0,0 +1,20 @@
+-- ==== 下载/缓存任务队列 (download_job) ====
+-- status: pending / running / paused / done / failed / cancelled
+CREATE TABLE IF NOT EXISTS download_job (
+    id              INTEGER PRIMARY KEY AUTOINCREMENT,
+    song_id         TEXT NOT NULL,
+    status          TEXT NOT NULL DEFAULT 'pending',
+    priority        INTEGER NOT NULL DEFAULT 0,   -- 越大越先下载
+    retry_count     INTEGER NOT NULL DEFAULT 0,
+    max_retries     INTEGER NOT NULL DEFAULT 3,
+    last_error      TEXT,
+    created_at      TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now', 'localtime')),
+    updated_at      TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now', 'localtime')),
+
+    FOREIGN KEY (song_id) REFERENCES music (song_id) ON DELETE CASCADE
+);
+
+-- 同一首歌同时只能有一个未完成的任务
+CREATE UNIQUE INDEX IF NOT EXISTS idx_download_job_active_song
+    ON download_job(song_id) WHERE status IN ('pending', 'running', 'paused');
+CREATE INDEX IF NOT EXISTS idx_download_job_queue ON download_job(status, priority DESC, id);
@@ -0,0 +1,5 @@
+-- add `format` (container, also used as file extension) and `mime` columns to the `music` table
+ALTER TABLE music ADD COLUMN format TEXT;
+ALTER TABLE music ADD COLUMN mime TEXT;
+
+-- all files cached before this migration were saved as .mp3, the real format is detected on next access
@@ -0,0 +1,38 @@
+-- ==== 本地曲库全文检索 (music_fts) ====
+-- trigram 分词器按 3 个字符切分，中日韩文本不需要额外分词；少于 3 个字符的关键词由程序回退到 LIKE
+-- 不使用 content='music'：music 的主键是 TEXT，隐式 rowid 在 VACUUM 后可能变化，这里用 song_id 关联
+CREATE VIRTUAL TABLE IF NOT EXISTS music_fts USING fts5(
+    song_id UNINDEXED,
+    title,
+    artist,
+    lyric,
+    tokenize = 'trigram'
+);
+
+INSERT INTO music_fts (song_id, title, artist, lyric)
+SELECT song_id, title, artist, COALESCE(lyric, '') FROM music;
+
+CREATE TRIGGER IF NOT EXISTS trg_music_fts_insert
+AFTER INSERT ON music
+FOR EACH ROW
+BEGIN
+    INSERT INTO music_fts (song_id, title, artist, lyric)
+    VALUES (NEW.song_id, NEW.title, NEW.artist, COALESCE(NEW.lyric, ''));
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_music_fts_delete
+AFTER DELETE ON music
+FOR EACH ROW
+BEGIN
+    DELETE FROM music_fts WHERE song_id = OLD.song_id;
+END;
+
+-- 只在检索字段变化时重建索引，播放时间、缓存路径等频繁更新不触发
+CREATE TRIGGER IF NOT EXISTS trg_music_fts_update
+AFTER UPDATE OF song_id, title, artist, lyric ON music
+FOR EACH ROW
+BEGIN
+    DELETE FROM music_fts WHERE song_id = OLD.song_id;
+    INSERT INTO music_fts (song_id, title, artist, lyric)
+    VALUES (NEW.song_id, NEW.title, NEW.artist, COALESCE(NEW.lyric, ''));
+END;
@@ -0,0 +1,10 @@
+-- ==== 拼音检索列 ====
+-- 全拼 (zhoujielun) 和首字母 (zjl) 在写入标题/歌手/歌单名时由程序计算，
+-- 已有数据在启动时由程序补全 (值为 NULL 的行)
+ALTER TABLE music ADD COLUMN title_pinyin TEXT;
+ALTER TABLE music ADD COLUMN title_initials TEXT;
+ALTER TABLE music ADD COLUMN artist_pinyin TEXT;
+ALTER TABLE music ADD COLUMN artist_initials TEXT;
+
+ALTER TABLE playlist ADD COLUMN name_pinyin TEXT;
+ALTER TABLE playlist ADD COLUMN name_initials TEXT;
@@ -0,0 +1,17 @@
+-- ==== 播放记录 (play_event) ====
+-- 每次收听一条记录，music.last_played_at 只由有效收听 (完整播放或收听时长足够) 更新
+CREATE TABLE IF NOT EXISTS play_event (
+    id              INTEGER PRIMARY KEY AUTOINCREMENT,
+    song_id         TEXT NOT NULL,
+    started_at      TEXT NOT NULL,                    -- 开始播放的时间 (RFC 3339, UTC)，与 last_played_at 格式一致
+    listened_secs   REAL NOT NULL DEFAULT 0,          -- 实际收听的秒数 (不含拖动跳过的部分)
+    completed       INTEGER NOT NULL DEFAULT 0,       -- 是否播放到结尾
+    skipped         INTEGER NOT NULL DEFAULT 0,       -- 是否在播放完之前切到了其他歌曲
+    playlist_id     INTEGER,                          -- 从哪个歌单开始播放，搜索结果中播放时为空
+
+    FOREIGN KEY (song_id) REFERENCES music (song_id) ON DELETE CASCADE,
+    FOREIGN KEY (playlist_id) REFERENCES playlist (id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_play_event_started_at ON play_event(started_at);
+CREATE INDEX IF NOT EXISTS idx_play_event_song ON play_event(song_id, started_at);
@@ -0,0 +1,4 @@
+-- ==== 智能歌单 ====
+-- smart_rules 为 JSON 格式的规则 (见 model::SmartPlaylistRules)，不为空时歌曲由规则实时计算，
+-- 不使用 playlist_music 中的记录
+ALTER TABLE playlist ADD COLUMN smart_rules TEXT;
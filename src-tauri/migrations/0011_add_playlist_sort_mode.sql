@@ -0,0 +1,21 @@
+-- ==== 歌单排序 ====
+-- sort_mode: manual (按 position 手动排序)、added_desc、title、artist、last_played
+ALTER TABLE playlist ADD COLUMN sort_mode TEXT NOT NULL DEFAULT 'manual';
+
+-- 之前的 position 取自 COUNT(*)，删除歌曲后会重复；列表按 position 倒序显示。
+-- 按原来的显示顺序重新编号为 0..n-1，之后手动排序统一按 position 正序
+UPDATE playlist_music
+SET position = (
+    SELECT r.rn
+    FROM (
+        SELECT
+            playlist_id,
+            song_id,
+            ROW_NUMBER() OVER (
+                PARTITION BY playlist_id
+                ORDER BY position DESC, added_to_list_at DESC
+            ) - 1 AS rn
+        FROM playlist_music
+    ) r
+    WHERE r.playlist_id = playlist_music.playlist_id AND r.song_id = playlist_music.song_id
+);
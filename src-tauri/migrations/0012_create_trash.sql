@@ -0,0 +1,21 @@
+-- ==== 回收站 ====
+-- 删除歌单时只记录 deleted_at，超过保留天数后由启动时的维护任务彻底删除
+ALTER TABLE playlist ADD COLUMN deleted_at TEXT;
+
+-- 从歌单中移除的歌曲，保留原来的位置以便恢复
+CREATE TABLE IF NOT EXISTS playlist_music_trash (
+    id               INTEGER PRIMARY KEY AUTOINCREMENT,
+    playlist_id      INTEGER NOT NULL,
+    song_id          TEXT NOT NULL,
+    position         INTEGER NOT NULL,
+    added_to_list_at TEXT NOT NULL,
+    deleted_at       TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now', 'localtime')),
+
+    FOREIGN KEY (playlist_id) REFERENCES playlist (id) ON DELETE CASCADE,
+    FOREIGN KEY (song_id) REFERENCES music (song_id) ON DELETE CASCADE
+);
+
+-- 同一首歌多次移出同一个歌单时只保留最后一次
+CREATE UNIQUE INDEX IF NOT EXISTS idx_playlist_music_trash_song
+    ON playlist_music_trash(playlist_id, song_id);
+CREATE INDEX IF NOT EXISTS idx_playlist_deleted_at ON playlist(deleted_at);
@@ -0,0 +1,22 @@
+-- ==== 播放队列 ====
+-- position 从 0 开始连续编号，同一首歌可以在队列中出现多次
+CREATE TABLE IF NOT EXISTS play_queue (
+    position        INTEGER PRIMARY KEY,
+    song_id         TEXT NOT NULL,
+
+    FOREIGN KEY (song_id) REFERENCES music (song_id) ON DELETE CASCADE
+);
+
+-- ==== 播放状态 (只有一行) ====
+CREATE TABLE IF NOT EXISTS play_session (
+    id              INTEGER PRIMARY KEY CHECK (id = 1),
+    current_index   INTEGER NOT NULL DEFAULT -1,          -- 正在播放的歌曲在队列中的下标，-1 表示没有播放
+    play_mode       TEXT NOT NULL DEFAULT 'sequence',     -- sequence / single / shuffle
+    position_secs   REAL NOT NULL DEFAULT 0,              -- 当前歌曲的播放进度
+    playlist_id     INTEGER,                              -- 队列来自哪个歌单，用于播放记录
+    updated_at      TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now', 'localtime')),
+
+    FOREIGN KEY (playlist_id) REFERENCES playlist (id) ON DELETE SET NULL
+);
+
+INSERT OR IGNORE INTO play_session (id) VALUES (1);
@@ -0,0 +1,17 @@
+-- ==== 响度分析 (EBU R128) ====
+-- loudness_lufs: 整体响度 (LUFS)，true_peak: 真峰值 (线性，1.0 为满刻度)
+-- loudness_analyzed_at 为 NULL 表示还没有分析；分析失败时只记录时间，两个结果列保持 NULL
+ALTER TABLE music ADD COLUMN loudness_lufs REAL;
+ALTER TABLE music ADD COLUMN true_peak REAL;
+ALTER TABLE music ADD COLUMN loudness_analyzed_at TEXT;
+
+-- 缓存文件换了 (重新下载、合并重复歌曲) 之后需要重新分析
+CREATE TRIGGER IF NOT EXISTS trg_music_loudness_reset
+AFTER UPDATE OF file_path ON music
+FOR EACH ROW
+WHEN NEW.file_path IS NOT OLD.file_path
+BEGIN
+    UPDATE music
+    SET loudness_lufs = NULL, true_peak = NULL, loudness_analyzed_at = NULL
+    WHERE song_id = NEW.song_id;
+END;
@@ -0,0 +1,3 @@
+-- ==== 下载任务重试时间 ====
+-- 失败后重新排队的任务在 retry_at 之前不会被领取，等待期间不占用并发名额
+ALTER TABLE download_job ADD COLUMN retry_at TEXT;
@@ -0,0 +1,7 @@
+-- ==== 播放状态中的当前歌曲 ====
+-- 队列中的歌曲被删除后 current_index 会失效，恢复时按 song_id 重新定位
+ALTER TABLE play_session ADD COLUMN song_id TEXT;
+
+UPDATE play_session
+SET song_id = (SELECT song_id FROM play_queue WHERE position = play_session.current_index)
+WHERE id = 1;
@@ -0,0 +1,5 @@
+-- ==== 重新分析失败的歌曲 ====
+-- 之前分析失败的歌曲多半是当时还不支持的格式 (flac、ogg、wav)，清除分析时间让后台任务重新分析
+UPDATE music
+SET loudness_analyzed_at = NULL
+WHERE loudness_lufs IS NULL AND loudness_analyzed_at IS NOT NULL;
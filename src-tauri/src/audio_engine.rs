@@ -0,0 +1,1399 @@
+// src-tauri/src/audio_engine.rs
+
+//! 原生音频播放引擎，可以代替 webview 中的 <audio>：symphonia 解码，cpal 输出到声卡。
+//! 解码线程把采样写入共享缓冲区，输出端从缓冲区读取。下一首歌在当前歌曲解码完后
+//! 直接接在缓冲区后面，实现无缝播放；窗口隐藏后 webview 被节流也不会中断播放。
+//! 空输出 (null) 按实时速度消费采样但不发声，用于没有声卡的机器。
+
+use std::{
+    collections::VecDeque,
+    fs::File,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Condvar, Mutex,
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        mpsc::{self, RecvTimeoutError, TryRecvError},
+    },
+    thread,
+    time::Duration,
+};
+
+use symphonia::core::{
+    audio::SampleBuffer,
+    codecs::{CODEC_TYPE_NULL, Decoder, DecoderOptions},
+    errors::Error as SymphoniaError,
+    formats::{FormatOptions, FormatReader, SeekMode, SeekTo},
+    io::MediaSourceStream,
+    meta::MetadataOptions,
+    probe::Hint,
+    units::{Time, TimeBase},
+};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{
+    model::{Music, NativePlayerState},
+    music::cache_music_and_get_file_path,
+    my_util::{DbPool, get_app_setting},
+};
+
+pub const POSITION_EVENT: &str = "native-player-position";
+pub const TRACK_CHANGED_EVENT: &str = "native-player-track-changed";
+pub const ENDED_EVENT: &str = "native-player-ended";
+pub const ERROR_EVENT: &str = "native-player-error";
+
+/// 输出设备的设置项："device" (默认) 或 "null"
+pub const OUTPUT_SETTING_KEY: &str = "native_audio_output";
+
+/// 缓冲区中最多保存的时长，暂停和跳转时丢弃的数据不超过这么多
+const BUFFER_SECS: f64 = 0.5;
+const POSITION_INTERVAL: Duration = Duration::from_millis(250);
+const DECODER_POLL: Duration = Duration::from_millis(50);
+const NULL_OUTPUT_RATE: u32 = 44_100;
+const NULL_OUTPUT_CHANNELS: usize = 2;
+const NULL_OUTPUT_PERIOD: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputKind {
+    Device,
+    Null,
+}
+
+impl OutputKind {
+    pub fn from_setting(value: Option<&str>) -> Self {
+        match value {
+            Some("null") => OutputKind::Null,
+            _ => OutputKind::Device,
+        }
+    }
+}
+
+/// 要播放的歌曲：song_id 用于事件，path 为本地文件
+#[derive(Debug, Clone)]
+pub struct TrackSource {
+    pub song_id: String,
+    pub path: PathBuf,
+}
+
+pub enum PlayerEvent {
+    Position(NativePlayerState),
+    TrackChanged(NativePlayerState),
+    Ended,
+    Error(String),
+}
+
+/// 某首歌 (或跳转后) 的第一帧在输出中的帧序号
+#[derive(Debug, Clone)]
+struct Boundary {
+    frame: u64,
+    source: TrackSource,
+    offset_secs: f64,
+    duration_secs: Option<f64>,
+}
+
+struct QueueState {
+    samples: VecDeque<f32>,
+    channels: usize,
+    sample_rate: u32,
+    pushed_frames: u64,
+    played_frames: u64,
+    /// 已经写入缓冲区、还没开始播放的歌曲
+    boundaries: VecDeque<Boundary>,
+    current: Option<Boundary>,
+    /// 解码线程没有更多数据了，缓冲区播完即结束
+    finished: bool,
+}
+
+impl QueueState {
+    fn capacity(&self) -> usize {
+        (BUFFER_SECS * self.sample_rate as f64) as usize * self.channels
+    }
+
+    fn advance_boundaries(&mut self) {
+        while self
+            .boundaries
+            .front()
+            .is_some_and(|b| b.frame <= self.played_frames)
+        {
+            self.current = self.boundaries.pop_front();
+        }
+    }
+}
+
+/// 解码线程与输出端之间的采样缓冲区，采样为交错排列的 f32
+pub struct SampleQueue {
+    state: Mutex<QueueState>,
+    space: Condvar,
+    paused: AtomicBool,
+    volume: AtomicU32,
+}
+
+impl SampleQueue {
+    fn new(sample_rate: u32, channels: usize) -> Self {
+        Self {
+            state: Mutex::new(QueueState {
+                samples: VecDeque::new(),
+                channels,
+                sample_rate,
+                pushed_frames: 0,
+                played_frames: 0,
+                boundaries: VecDeque::new(),
+                current: None,
+                finished: true,
+            }),
+            space: Condvar::new(),
+            paused: AtomicBool::new(true),
+            volume: AtomicU32::new(1.0f32.to_bits()),
+        }
+    }
+
+    fn format(&self) -> (u32, usize) {
+        let state = self.state.lock().unwrap();
+        (state.sample_rate, state.channels)
+    }
+
+    /// 输出端调用：用缓冲区中的采样填满 out，不够时补静音
+    pub fn fill(&self, out: &mut [f32]) {
+        if self.paused.load(Ordering::Relaxed) {
+            out.fill(0.0);
+            return;
+        }
+        let volume = f32::from_bits(self.volume.load(Ordering::Relaxed));
+        let mut state = self.state.lock().unwrap();
+        let channels = state.channels;
+        let available = out.len().min(state.samples.len());
+        let count = available - available % channels;
+        for (o, s) in out.iter_mut().zip(state.samples.drain(..count)) {
+            *o = s * volume;
+        }
+        out[count..].fill(0.0);
+        state.played_frames += (count / channels) as u64;
+        state.advance_boundaries();
+        drop(state);
+        self.space.notify_all();
+    }
+
+    /// 等待缓冲区有空位，超时返回 false，解码线程借此定期检查命令
+    fn wait_for_space(&self, timeout: Duration) -> bool {
+        let state = self.state.lock().unwrap();
+        if state.samples.len() < state.capacity() {
+            return true;
+        }
+        let (state, _) = self.space.wait_timeout(state, timeout).unwrap();
+        state.samples.len() < state.capacity()
+    }
+
+    fn push(&self, samples: &[f32]) {
+        let mut state = self.state.lock().unwrap();
+        let channels = state.channels;
+        state.samples.extend(samples);
+        state.pushed_frames += (samples.len() / channels) as u64;
+    }
+
+    /// 后面写入的采样属于 source，从 offset_secs 开始
+    fn push_boundary(&self, source: TrackSource, offset_secs: f64, duration_secs: Option<f64>) {
+        let mut state = self.state.lock().unwrap();
+        let boundary = Boundary {
+            frame: state.pushed_frames,
+            source,
+            offset_secs,
+            duration_secs,
+        };
+        state.boundaries.push_back(boundary);
+        state.finished = false;
+        state.advance_boundaries();
+    }
+
+    /// 丢弃还没播放的采样 (加载新歌或跳转)
+    fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.samples.clear();
+        state.pushed_frames = state.played_frames;
+        state.boundaries.clear();
+        state.current = None;
+        state.finished = true;
+        drop(state);
+        self.space.notify_all();
+    }
+
+    fn mark_finished(&self) {
+        self.state.lock().unwrap().finished = true;
+    }
+
+    fn playing_source(&self) -> Option<TrackSource> {
+        let state = self.state.lock().unwrap();
+        state.current.as_ref().map(|b| b.source.clone())
+    }
+
+    /// 当前播放状态，以及缓冲区是否已经播完
+    fn snapshot(&self) -> (NativePlayerState, bool) {
+        let state = self.state.lock().unwrap();
+        let (song_id, position_secs, duration_secs) = match &state.current {
+            Some(b) => (
+                Some(b.source.song_id.clone()),
+                b.offset_secs + (state.played_frames - b.frame) as f64 / state.sample_rate as f64,
+                b.duration_secs,
+            ),
+            None => (None, 0.0, None),
+        };
+        let drained = state.finished && state.samples.is_empty();
+        let snapshot = NativePlayerState {
+            song_id,
+            position_secs,
+            duration_secs,
+            playing: !self.paused.load(Ordering::Relaxed) && !drained,
+            volume: f32::from_bits(self.volume.load(Ordering::Relaxed)),
+        };
+        (snapshot, drained)
+    }
+}
+
+/// 把任意声道数映射到输出声道数：单声道复制到每个声道，输出为单声道时取平均
+fn remap_channels(input: &[f32], in_channels: usize, out_channels: usize) -> Vec<f32> {
+    if in_channels == out_channels {
+        return input.to_vec();
+    }
+    let mut out = Vec::with_capacity(input.len() / in_channels * out_channels);
+    for frame in input.chunks_exact(in_channels) {
+        if out_channels == 1 {
+            out.push(frame.iter().sum::<f32>() / in_channels as f32);
+        } else {
+            out.extend((0..out_channels).map(|c| frame[c % in_channels]));
+        }
+    }
+    out
+}
+
+/// 线性插值重采样。保留上一块的最后一帧，块与块之间保持连续
+struct Resampler {
+    in_channels: usize,
+    out_channels: usize,
+    step: f64,
+    pos: f64,
+    last_frame: Vec<f32>,
+}
+
+impl Resampler {
+    fn new(in_rate: u32, out_rate: u32, in_channels: usize, out_channels: usize) -> Self {
+        Self {
+            in_channels,
+            out_channels,
+            step: in_rate as f64 / out_rate as f64,
+            pos: 0.0,
+            last_frame: Vec::new(),
+        }
+    }
+
+    fn process(&mut self, input: &[f32], out: &mut Vec<f32>) {
+        let mapped = remap_channels(input, self.in_channels, self.out_channels);
+        if self.step == 1.0 {
+            out.extend(mapped);
+            return;
+        }
+        let channels = self.out_channels;
+        let mut frames = std::mem::take(&mut self.last_frame);
+        frames.extend(mapped);
+        let count = frames.len() / channels;
+        if count == 0 {
+            return;
+        }
+        while self.pos + 1.0 < count as f64 {
+            let i = self.pos as usize;
+            let frac = (self.pos - i as f64) as f32;
+            for c in 0..channels {
+                let a = frames[i * channels + c];
+                let b = frames[(i + 1) * channels + c];
+                out.push(a + (b - a) * frac);
+            }
+            self.pos += self.step;
+        }
+        self.pos -= (count - 1) as f64;
+        self.last_frame = frames[(count - 1) * channels..count * channels].to_vec();
+    }
+}
+
+fn time_to_secs(time: Time) -> f64 {
+    time.seconds as f64 + time.frac
+}
+
+/// 打开的音频文件及其解码器，输出原始采样率和声道数的交错采样
+pub struct AudioFile {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    time_base: Option<TimeBase>,
+    pub duration_secs: Option<f64>,
+}
+
+impl AudioFile {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let file =
+            File::open(path).map_err(|e| format!("打开文件失败 {}: {}", path.display(), e))?;
+        let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+        let mut hint = Hint::new();
+        if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+            hint.with_extension(ext);
+        }
+        // 开启 gapless 后会去掉编码器在首尾填充的静音
+        let format_options = FormatOptions {
+            enable_gapless: true,
+            ..Default::default()
+        };
+        let probed = symphonia::default::get_probe()
+            .format(&hint, stream, &format_options, &MetadataOptions::default())
+            .map_err(|e| format!("无法识别音频格式: {}", e))?;
+        let format = probed.format;
+
+        let track = format
+            .tracks()
+            .iter()
+            .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or_else(|| "文件中没有音轨".to_string())?;
+        let track_id = track.id;
+        let time_base = track.codec_params.time_base;
+        let duration_secs = time_base
+            .zip(track.codec_params.n_frames)
+            .map(|(tb, frames)| time_to_secs(tb.calc_time(frames)));
+        let decoder = symphonia::default::get_codecs()
+            .make(&track.codec_params, &DecoderOptions::default())
+            .map_err(|e| format!("不支持的编码: {}", e))?;
+
+        Ok(Self {
+            format,
+            decoder,
+            track_id,
+            time_base,
+            duration_secs,
+        })
+    }
+
+    /// 跳转到指定位置，返回实际到达的位置
+    fn seek(&mut self, secs: f64) -> Result<f64, String> {
+        let secs = secs.max(0.0);
+        let seeked = self
+            .format
+            .seek(
+                SeekMode::Accurate,
+                SeekTo::Time {
+                    time: Time::new(secs.trunc() as u64, secs.fract()),
+                    track_id: Some(self.track_id),
+                },
+            )
+            .map_err(|e| format!("跳转失败: {}", e))?;
+        self.decoder.reset();
+        Ok(self
+            .time_base
+            .map(|tb| time_to_secs(tb.calc_time(seeked.actual_ts)))
+            .unwrap_or(secs))
+    }
+
+    /// 解码下一个包，返回 (采样率, 声道数, 交错采样)，文件结束时返回 None
+    pub fn next_buffer(&mut self) -> Result<Option<(u32, usize, Vec<f32>)>, String> {
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(SymphoniaError::IoError(e))
+                    if e.kind() == std::io::ErrorKind::UnexpectedEof =>
+                {
+                    return Ok(None);
+                }
+                Err(SymphoniaError::ResetRequired) => return Ok(None),
+                Err(e) => return Err(format!("读取音频失败: {}", e)),
+            };
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                // 个别损坏的包直接跳过
+                Err(SymphoniaError::DecodeError(e)) => {
+                    eprintln!("[Audio Engine] 跳过无法解码的数据: {}", e);
+                    continue;
+                }
+                Err(e) => return Err(format!("解码失败: {}", e)),
+            };
+            if decoded.frames() == 0 {
+                continue;
+            }
+            let spec = *decoded.spec();
+            let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+            buffer.copy_interleaved_ref(decoded);
+            return Ok(Some((
+                spec.rate,
+                spec.channels.count(),
+                buffer.samples().to_vec(),
+            )));
+        }
+    }
+}
+
+/// 正在播放的歌曲：解码后转换为输出设备的采样率和声道数
+struct OpenTrack {
+    source: TrackSource,
+    file: AudioFile,
+    resampler: Option<Resampler>,
+    out_rate: u32,
+    out_channels: usize,
+}
+
+impl OpenTrack {
+    fn open(source: TrackSource, out_rate: u32, out_channels: usize) -> Result<Self, String> {
+        let file = AudioFile::open(&source.path)?;
+        Ok(Self {
+            source,
+            file,
+            resampler: None,
+            out_rate,
+            out_channels,
+        })
+    }
+
+    fn duration_secs(&self) -> Option<f64> {
+        self.file.duration_secs
+    }
+
+    fn seek(&mut self, secs: f64) -> Result<f64, String> {
+        self.resampler = None;
+        self.file.seek(secs)
+    }
+
+    /// 返回转换为输出格式的采样，文件结束时返回 None
+    fn next_samples(&mut self) -> Result<Option<Vec<f32>>, String> {
+        let Some((rate, channels, buffer)) = self.file.next_buffer()? else {
+            return Ok(None);
+        };
+        let (out_rate, out_channels) = (self.out_rate, self.out_channels);
+        let resampler = self
+            .resampler
+            .get_or_insert_with(|| Resampler::new(rate, out_rate, channels, out_channels));
+        let mut samples = Vec::new();
+        resampler.process(&buffer, &mut samples);
+        Ok(Some(samples))
+    }
+}
+
+enum Command {
+    Load {
+        source: TrackSource,
+        start_secs: f64,
+    },
+    SetNext(Option<TrackSource>),
+    Seek(f64),
+    Stop,
+}
+
+fn run_decoder(
+    queue: Arc<SampleQueue>,
+    commands: mpsc::Receiver<Command>,
+    on_error: impl Fn(String),
+) {
+    let (out_rate, out_channels) = queue.format();
+    let open_at = |source: TrackSource, start_secs: f64| -> Result<(OpenTrack, f64), String> {
+        let mut track = OpenTrack::open(source, out_rate, out_channels)?;
+        let offset = if start_secs > 0.0 {
+            track.seek(start_secs)?
+        } else {
+            0.0
+        };
+        Ok((track, offset))
+    };
+    // 把 source 接在缓冲区后面，打开失败时返回 None
+    let append = |source: TrackSource| -> Option<OpenTrack> {
+        match open_at(source, 0.0) {
+            Ok((track, offset)) => {
+                queue.push_boundary(track.source.clone(), offset, track.duration_secs());
+                Some(track)
+            }
+            Err(e) => {
+                on_error(e);
+                None
+            }
+        }
+    };
+
+    let mut current: Option<OpenTrack> = None;
+    let mut next: Option<TrackSource> = None;
+    loop {
+        // 没有在解码时阻塞等待命令，否则只检查一下
+        let command = if current.is_none() {
+            match commands.recv() {
+                Ok(command) => Some(command),
+                Err(_) => return,
+            }
+        } else {
+            match commands.try_recv() {
+                Ok(command) => Some(command),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => return,
+            }
+        };
+
+        match command {
+            Some(Command::Load { source, start_secs }) => {
+                queue.clear();
+                current = None;
+                match open_at(source, start_secs) {
+                    Ok((track, offset)) => {
+                        queue.push_boundary(track.source.clone(), offset, track.duration_secs());
+                        current = Some(track);
+                    }
+                    Err(e) => on_error(e),
+                }
+            }
+            Some(Command::SetNext(source)) => {
+                next = source;
+                // 当前歌曲已经解码完但还没播完 (比缓冲区还短的歌曲)，直接接在后面
+                if current.is_none() && queue.playing_source().is_some() && !queue.snapshot().1 {
+                    current = next.take().and_then(append);
+                }
+            }
+            Some(Command::Seek(secs)) => {
+                // 当前歌曲可能已经解码完，正在解码的是下一首，这时重新打开正在播放的歌曲
+                let Some(playing) = queue.playing_source() else {
+                    continue;
+                };
+                let decoding = current.take();
+                queue.clear();
+                let result = match decoding {
+                    Some(mut track) if track.source.song_id == playing.song_id => {
+                        track.seek(secs).map(|offset| (track, offset))
+                    }
+                    decoding => {
+                        if let Some(track) = decoding {
+                            next = Some(track.source);
+                        }
+                        open_at(playing, secs)
+                    }
+                };
+                match result {
+                    Ok((track, offset)) => {
+                        queue.push_boundary(track.source.clone(), offset, track.duration_secs());
+                        current = Some(track);
+                    }
+                    Err(e) => on_error(e),
+                }
+            }
+            Some(Command::Stop) => {
+                queue.clear();
+                current = None;
+                next = None;
+            }
+            None => {}
+        }
+
+        let Some(track) = current.as_mut() else {
+            continue;
+        };
+        if !queue.wait_for_space(DECODER_POLL) {
+            continue;
+        }
+        match track.next_samples() {
+            Ok(Some(samples)) => queue.push(&samples),
+            Ok(None) => {
+                // 下一首直接接在缓冲区后面
+                current = next.take().and_then(append);
+                if current.is_none() {
+                    queue.mark_finished();
+                }
+            }
+            Err(e) => {
+                on_error(e);
+                current = None;
+                queue.mark_finished();
+            }
+        }
+    }
+}
+
+/// 定期发送播放进度，并检测切歌和播放结束
+fn run_monitor(
+    queue: Arc<SampleQueue>,
+    running: Arc<AtomicBool>,
+    on_event: Arc<dyn Fn(PlayerEvent) + Send + Sync>,
+) {
+    let mut last_song: Option<String> = None;
+    let mut ended = true;
+    while running.load(Ordering::Relaxed) {
+        thread::sleep(POSITION_INTERVAL);
+        let (state, drained) = queue.snapshot();
+        if state.song_id.is_some() && state.song_id != last_song {
+            last_song = state.song_id.clone();
+            // 比轮询间隔还短的歌曲第一次看到时可能已经播完，同样要发送 Ended
+            ended = false;
+            on_event(PlayerEvent::TrackChanged(state.clone()));
+        }
+        if drained {
+            if !ended && state.song_id.is_some() {
+                on_event(PlayerEvent::Ended);
+            }
+            ended = true;
+            continue;
+        }
+        ended = false;
+        if state.playing {
+            on_event(PlayerEvent::Position(state));
+        }
+    }
+}
+
+/// 输出线程的句柄，drop 时停止输出
+struct OutputHandle {
+    stop: mpsc::Sender<()>,
+}
+
+impl Drop for OutputHandle {
+    fn drop(&mut self) {
+        let _ = self.stop.send(());
+    }
+}
+
+/// 不发声的输出：按实时速度从缓冲区取走采样
+fn start_null_output() -> (Arc<SampleQueue>, OutputHandle) {
+    let queue = Arc::new(SampleQueue::new(NULL_OUTPUT_RATE, NULL_OUTPUT_CHANNELS));
+    let (stop, stop_rx) = mpsc::channel::<()>();
+    let output_queue = queue.clone();
+    thread::spawn(move || {
+        let frames = (NULL_OUTPUT_RATE as f64 * NULL_OUTPUT_PERIOD.as_secs_f64()) as usize;
+        let mut buffer = vec![0.0f32; frames * NULL_OUTPUT_CHANNELS];
+        while let Err(RecvTimeoutError::Timeout) = stop_rx.recv_timeout(NULL_OUTPUT_PERIOD) {
+            output_queue.fill(&mut buffer);
+        }
+    });
+    (queue, OutputHandle { stop })
+}
+
+#[cfg(desktop)]
+fn build_device_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    queue: Arc<SampleQueue>,
+) -> Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: cpal::SizedSample + cpal::FromSample<f32>,
+{
+    use cpal::{Sample, traits::DeviceTrait};
+
+    let mut scratch: Vec<f32> = Vec::new();
+    device.build_output_stream(
+        config,
+        move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+            scratch.resize(data.len(), 0.0);
+            queue.fill(&mut scratch);
+            for (out, sample) in data.iter_mut().zip(&scratch) {
+                *out = T::from_sample(*sample);
+            }
+        },
+        |e| eprintln!("[Audio Engine] 输出错误: {}", e),
+        None,
+    )
+}
+
+/// 打开默认声卡。cpal::Stream 不能跨线程移动，所以在输出线程中创建并持有它
+#[cfg(desktop)]
+fn start_device_output() -> Result<(Arc<SampleQueue>, OutputHandle), String> {
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+    let (stop, stop_rx) = mpsc::channel::<()>();
+    let (ready_tx, ready_rx) = mpsc::channel::<Result<Arc<SampleQueue>, String>>();
+    thread::spawn(move || {
+        let open = || -> Result<(Arc<SampleQueue>, cpal::Stream), String> {
+            let device = cpal::default_host()
+                .default_output_device()
+                .ok_or_else(|| "没有可用的音频输出设备".to_string())?;
+            let supported = device
+                .default_output_config()
+                .map_err(|e| format!("读取输出设备配置失败: {}", e))?;
+            let sample_format = supported.sample_format();
+            let config: cpal::StreamConfig = supported.into();
+            let queue = Arc::new(SampleQueue::new(
+                config.sample_rate.0,
+                config.channels as usize,
+            ));
+            let stream = match sample_format {
+                cpal::SampleFormat::F32 => {
+                    build_device_stream::<f32>(&device, &config, queue.clone())
+                }
+                cpal::SampleFormat::I16 => {
+                    build_device_stream::<i16>(&device, &config, queue.clone())
+                }
+                cpal::SampleFormat::U16 => {
+                    build_device_stream::<u16>(&device, &config, queue.clone())
+                }
+                other => return Err(format!("不支持的输出采样格式: {:?}", other)),
+            }
+            .map_err(|e| format!("打开输出设备失败: {}", e))?;
+            stream
+                .play()
+                .map_err(|e| format!("启动输出设备失败: {}", e))?;
+            Ok((queue, stream))
+        };
+        match open() {
+            Ok((queue, stream)) => {
+                let _ = ready_tx.send(Ok(queue));
+                // 阻塞到引擎关闭，stream 随之释放
+                let _ = stop_rx.recv();
+                drop(stream);
+            }
+            Err(e) => {
+                let _ = ready_tx.send(Err(e));
+            }
+        }
+    });
+    let queue = ready_rx
+        .recv()
+        .map_err(|_| "输出线程意外退出".to_string())??;
+    Ok((queue, OutputHandle { stop }))
+}
+
+#[cfg(mobile)]
+fn start_device_output() -> Result<(Arc<SampleQueue>, OutputHandle), String> {
+    Err("移动端暂不支持原生音频输出".to_string())
+}
+
+/// 播放引擎。暂停和音量直接修改共享状态，其它操作交给解码线程
+pub struct AudioEngine {
+    queue: Arc<SampleQueue>,
+    commands: mpsc::Sender<Command>,
+    running: Arc<AtomicBool>,
+    _output: OutputHandle,
+}
+
+impl AudioEngine {
+    pub fn start(
+        output: OutputKind,
+        on_event: impl Fn(PlayerEvent) + Send + Sync + 'static,
+    ) -> Result<Self, String> {
+        let (queue, output) = match output {
+            OutputKind::Device => start_device_output()?,
+            OutputKind::Null => start_null_output(),
+        };
+        Ok(Self::with_output(queue, output, on_event))
+    }
+
+    /// 启动解码和进度线程，采样由 output 从 queue 中取走
+    fn with_output(
+        queue: Arc<SampleQueue>,
+        output: OutputHandle,
+        on_event: impl Fn(PlayerEvent) + Send + Sync + 'static,
+    ) -> Self {
+        let on_event: Arc<dyn Fn(PlayerEvent) + Send + Sync> = Arc::new(on_event);
+
+        let (commands, commands_rx) = mpsc::channel();
+        let decoder_queue = queue.clone();
+        let on_decoder_event = on_event.clone();
+        thread::spawn(move || {
+            run_decoder(decoder_queue, commands_rx, |e| {
+                eprintln!("[Audio Engine] {}", e);
+                on_decoder_event(PlayerEvent::Error(e));
+            })
+        });
+
+        let running = Arc::new(AtomicBool::new(true));
+        let monitor_queue = queue.clone();
+        let monitor_running = running.clone();
+        thread::spawn(move || run_monitor(monitor_queue, monitor_running, on_event));
+
+        Self {
+            queue,
+            commands,
+            running,
+            _output: output,
+        }
+    }
+
+    fn send(&self, command: Command) -> Result<(), String> {
+        self.commands
+            .send(command)
+            .map_err(|_| "播放引擎已停止".to_string())
+    }
+
+    /// 加载并从 start_secs 开始播放
+    pub fn load(&self, source: TrackSource, start_secs: f64) -> Result<(), String> {
+        self.send(Command::Load { source, start_secs })?;
+        self.play();
+        Ok(())
+    }
+
+    /// 设置当前歌曲之后无缝播放的歌曲，None 表示播完当前歌曲就停止
+    pub fn set_next(&self, source: Option<TrackSource>) -> Result<(), String> {
+        self.send(Command::SetNext(source))
+    }
+
+    pub fn play(&self) {
+        self.queue.paused.store(false, Ordering::Relaxed);
+    }
+
+    pub fn pause(&self) {
+        self.queue.paused.store(true, Ordering::Relaxed);
+    }
+
+    pub fn seek(&self, position_secs: f64) -> Result<(), String> {
+        self.send(Command::Seek(position_secs))
+    }
+
+    pub fn set_volume(&self, volume: f32) {
+        self.queue
+            .volume
+            .store(volume.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn stop(&self) -> Result<(), String> {
+        self.pause();
+        self.send(Command::Stop)
+    }
+
+    pub fn state(&self) -> NativePlayerState {
+        self.queue.snapshot().0
+    }
+}
+
+impl Drop for AudioEngine {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        // commands 随之关闭，解码线程退出；输出线程由 OutputHandle 停止
+    }
+}
+
+/// Tauri 状态：引擎在第一次使用时才创建，没用到时不会占用声卡
+pub struct NativePlayer {
+    engine: Mutex<Option<AudioEngine>>,
+}
+
+impl NativePlayer {
+    pub fn new() -> Self {
+        Self {
+            engine: Mutex::new(None),
+        }
+    }
+
+    fn with_engine<T>(&self, f: impl FnOnce(&AudioEngine) -> T) -> Result<T, String> {
+        match self.engine.lock().unwrap().as_ref() {
+            Some(engine) => Ok(f(engine)),
+            None => Err("原生播放引擎未启动".to_string()),
+        }
+    }
+}
+
+impl Default for NativePlayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn emit_event(app_handle: &AppHandle, event: PlayerEvent) {
+    let result = match event {
+        PlayerEvent::Position(state) => app_handle.emit(POSITION_EVENT, state),
+        PlayerEvent::TrackChanged(state) => app_handle.emit(TRACK_CHANGED_EVENT, state),
+        PlayerEvent::Ended => app_handle.emit(ENDED_EVENT, ()),
+        PlayerEvent::Error(message) => app_handle.emit(ERROR_EVENT, message),
+    };
+    if let Err(e) = result {
+        eprintln!("发送播放引擎事件失败: {}", e);
+    }
+}
+
+async fn ensure_engine(app_handle: &AppHandle, pool: &DbPool) -> Result<(), String> {
+    let player = app_handle.state::<NativePlayer>();
+    if player.engine.lock().unwrap().is_some() {
+        return Ok(());
+    }
+    let setting = get_app_setting(pool, OUTPUT_SETTING_KEY.to_string())
+        .await
+        .map_err(|e| e.to_string())?;
+    let output = OutputKind::from_setting(setting.as_deref());
+
+    let mut engine = player.engine.lock().unwrap();
+    if engine.is_none() {
+        let handle = app_handle.clone();
+        *engine = Some(AudioEngine::start(output, move |event| {
+            emit_event(&handle, event)
+        })?);
+    }
+    Ok(())
+}
+
+/// 原生引擎只能播放本地文件，还没缓存的歌曲先下载到缓存
+async fn resolve_track(
+    app_handle: &AppHandle,
+    pool: &DbPool,
+    music: Music,
+) -> Result<TrackSource, String> {
+    let song_id = music.song_id.clone();
+    let fallback = music.file_path.clone();
+    cache_music_and_get_file_path(app_handle.clone(), pool, music).await?;
+
+    let file_path: Option<String> =
+        sqlx::query_scalar("SELECT file_path FROM music WHERE song_id = ?")
+            .bind(&song_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| e.to_string())?
+            .flatten();
+    let path = file_path
+        .or(fallback)
+        .filter(|p| Path::new(p).exists())
+        .ok_or_else(|| "歌曲文件不存在".to_string())?;
+    Ok(TrackSource {
+        song_id,
+        path: PathBuf::from(path),
+    })
+}
+
+pub async fn play(
+    app_handle: &AppHandle,
+    pool: &DbPool,
+    music: Music,
+    start_secs: f64,
+) -> Result<(), String> {
+    let source = resolve_track(app_handle, pool, music).await?;
+    ensure_engine(app_handle, pool).await?;
+    app_handle
+        .state::<NativePlayer>()
+        .with_engine(|engine| engine.load(source, start_secs))?
+}
+
+pub async fn set_next(
+    app_handle: &AppHandle,
+    pool: &DbPool,
+    music: Option<Music>,
+) -> Result<(), String> {
+    let source = match music {
+        Some(music) => Some(resolve_track(app_handle, pool, music).await?),
+        None => None,
+    };
+    ensure_engine(app_handle, pool).await?;
+    app_handle
+        .state::<NativePlayer>()
+        .with_engine(|engine| engine.set_next(source))?
+}
+
+pub fn pause(player: &NativePlayer) -> Result<(), String> {
+    player.with_engine(|engine| engine.pause())
+}
+
+pub fn resume(player: &NativePlayer) -> Result<(), String> {
+    player.with_engine(|engine| engine.play())
+}
+
+pub fn seek(player: &NativePlayer, position_secs: f64) -> Result<(), String> {
+    player.with_engine(|engine| engine.seek(position_secs))?
+}
+
+pub fn set_volume(player: &NativePlayer, volume: f32) -> Result<(), String> {
+    player.with_engine(|engine| engine.set_volume(volume))
+}
+
+/// 停止播放。引擎没有启动时什么也不做
+pub fn stop(player: &NativePlayer) -> Result<(), String> {
+    match player.engine.lock().unwrap().as_ref() {
+        Some(engine) => engine.stop(),
+        None => Ok(()),
+    }
+}
+
+pub fn get_state(player: &NativePlayer) -> NativePlayerState {
+    match player.engine.lock().unwrap().as_ref() {
+        Some(engine) => engine.state(),
+        None => NativePlayerState {
+            song_id: None,
+            position_secs: 0.0,
+            duration_secs: None,
+            playing: false,
+            volume: 1.0,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{f32::consts::TAU, io::Write, time::Instant};
+
+    /// 只用作等待事件的上限，慢的机器上也不会误判。播放进度由测试手动推进，不依赖时间
+    const WAIT: Duration = Duration::from_secs(30);
+
+    /// 写一个 16 位 PCM 的 WAV 文件，内容为 440Hz 正弦波
+    fn write_wav(path: &Path, sample_rate: u32, channels: u16, secs: f64) {
+        let frames = (sample_rate as f64 * secs) as u32;
+        let data_len = frames * channels as u32 * 2;
+        let mut bytes = Vec::new();
+        bytes.extend(b"RIFF");
+        bytes.extend((36 + data_len).to_le_bytes());
+        bytes.extend(b"WAVEfmt ");
+        bytes.extend(16u32.to_le_bytes());
+        bytes.extend(1u16.to_le_bytes());
+        bytes.extend(channels.to_le_bytes());
+        bytes.extend(sample_rate.to_le_bytes());
+        bytes.extend((sample_rate * channels as u32 * 2).to_le_bytes());
+        bytes.extend((channels * 2).to_le_bytes());
+        bytes.extend(16u16.to_le_bytes());
+        bytes.extend(b"data");
+        bytes.extend(data_len.to_le_bytes());
+        for i in 0..frames {
+            let t = i as f32 / sample_rate as f32;
+            let sample = ((TAU * 440.0 * t).sin() * 0.5 * i16::MAX as f32) as i16;
+            for _ in 0..channels {
+                bytes.extend(sample.to_le_bytes());
+            }
+        }
+        File::create(path).unwrap().write_all(&bytes).unwrap();
+    }
+
+    fn source(
+        dir: &Path,
+        song_id: &str,
+        sample_rate: u32,
+        channels: u16,
+        secs: f64,
+    ) -> TrackSource {
+        let path = dir.join(format!("{}.wav", song_id));
+        write_wav(&path, sample_rate, channels, secs);
+        TrackSource {
+            song_id: song_id.to_string(),
+            path,
+        }
+    }
+
+    /// 按实时速度播放的引擎
+    fn start_engine() -> (AudioEngine, mpsc::Receiver<PlayerEvent>) {
+        let (tx, rx) = mpsc::channel();
+        let engine = AudioEngine::start(OutputKind::Null, move |event| {
+            let _ = tx.send(event);
+        })
+        .unwrap();
+        (engine, rx)
+    }
+
+    /// 没有输出线程的引擎，由 play_for 取走采样
+    fn start_manual_engine() -> (AudioEngine, mpsc::Receiver<PlayerEvent>) {
+        let (tx, rx) = mpsc::channel();
+        let queue = Arc::new(SampleQueue::new(NULL_OUTPUT_RATE, NULL_OUTPUT_CHANNELS));
+        let (stop, _) = mpsc::channel();
+        let engine = AudioEngine::with_output(queue, OutputHandle { stop }, move |event| {
+            let _ = tx.send(event);
+        });
+        (engine, rx)
+    }
+
+    /// 从缓冲区取走 secs 秒的采样。缓冲区暂时为空时等待解码线程，播完或暂停时提前返回。
+    /// 调用前需要确认解码线程已经处理了 load，否则缓冲区看起来已经播完
+    fn play_for(engine: &AudioEngine, secs: f64) {
+        let queue = &engine.queue;
+        let played = || queue.state.lock().unwrap().played_frames;
+        let mut remaining = (secs * NULL_OUTPUT_RATE as f64).round() as u64;
+        let mut buffer = vec![0.0f32; 441 * NULL_OUTPUT_CHANNELS];
+        let deadline = Instant::now() + WAIT;
+        while remaining > 0 && !queue.paused.load(Ordering::Relaxed) {
+            let frames = remaining.min(441) as usize;
+            let before = played();
+            queue.fill(&mut buffer[..frames * NULL_OUTPUT_CHANNELS]);
+            let consumed = played() - before;
+            if consumed == 0 {
+                if queue.snapshot().1 {
+                    return;
+                }
+                assert!(Instant::now() < deadline, "解码线程没有写入数据");
+                thread::sleep(Duration::from_millis(1));
+            }
+            remaining -= consumed;
+        }
+    }
+
+    /// 轮询直到条件满足
+    fn wait_until(mut condition: impl FnMut() -> bool) {
+        let deadline = Instant::now() + WAIT;
+        while !condition() {
+            assert!(Instant::now() < deadline, "等待超时");
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// 等待满足条件的事件，超时返回 None
+    fn wait_for(
+        rx: &mpsc::Receiver<PlayerEvent>,
+        mut matches: impl FnMut(&PlayerEvent) -> bool,
+    ) -> Option<PlayerEvent> {
+        let deadline = Instant::now() + WAIT;
+        loop {
+            let remaining = deadline.checked_duration_since(Instant::now())?;
+            match rx.recv_timeout(remaining) {
+                Ok(event) if matches(&event) => return Some(event),
+                Ok(_) => {}
+                Err(_) => return None,
+            }
+        }
+    }
+
+    fn collect(mut resampler: Resampler, chunks: &[&[f32]]) -> Vec<f32> {
+        let mut out = Vec::new();
+        for chunk in chunks {
+            resampler.process(chunk, &mut out);
+        }
+        out
+    }
+
+    #[test]
+    fn remap_channels_between_layouts() {
+        assert_eq!(
+            remap_channels(&[0.1, 0.2, 0.3, 0.4], 2, 2),
+            vec![0.1, 0.2, 0.3, 0.4]
+        );
+        // 单声道复制到每个声道
+        assert_eq!(remap_channels(&[0.1, 0.2], 1, 2), vec![0.1, 0.1, 0.2, 0.2]);
+        // 输出为单声道时取平均
+        assert_eq!(remap_channels(&[0.2, 0.4, -1.0, 1.0], 2, 1), vec![0.3, 0.0]);
+        // 多声道输出到立体声取前两个声道
+        let surround = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+        assert_eq!(remap_channels(&surround, 6, 2), vec![1.0, 2.0]);
+        // 立体声输出到四声道时循环使用
+        assert_eq!(remap_channels(&[1.0, 2.0], 2, 4), vec![1.0, 2.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn resampler_passes_through_same_rate() {
+        let out = collect(Resampler::new(44_100, 44_100, 1, 2), &[&[0.1, 0.2], &[0.3]]);
+        assert_eq!(out, vec![0.1, 0.1, 0.2, 0.2, 0.3, 0.3]);
+    }
+
+    #[test]
+    fn resampler_interpolates_when_upsampling() {
+        let out = collect(
+            Resampler::new(22_050, 44_100, 1, 1),
+            &[&[0.0, 1.0, 2.0, 3.0]],
+        );
+        assert_eq!(out, vec![0.0, 0.5, 1.0, 1.5, 2.0, 2.5]);
+    }
+
+    #[test]
+    fn resampler_is_continuous_across_chunks() {
+        let whole = collect(
+            Resampler::new(22_050, 44_100, 1, 1),
+            &[&[0.0, 1.0, 2.0, 3.0, 4.0, 5.0]],
+        );
+        let chunked = collect(
+            Resampler::new(22_050, 44_100, 1, 1),
+            &[&[0.0, 1.0], &[2.0], &[], &[3.0, 4.0, 5.0]],
+        );
+        assert_eq!(chunked, whole);
+
+        let input: Vec<f32> = (0..8).map(|i| i as f32).collect();
+        let whole = collect(Resampler::new(48_000, 24_000, 1, 1), &[&input]);
+        assert_eq!(whole, vec![0.0, 2.0, 4.0, 6.0]);
+        let chunked = collect(
+            Resampler::new(48_000, 24_000, 1, 1),
+            &[&input[..3], &input[3..4], &input[4..]],
+        );
+        assert_eq!(chunked, whole);
+    }
+
+    #[test]
+    fn resampler_keeps_channels_interleaved() {
+        // 左声道为 0、1，右声道为 10、11，插值不能串声道
+        let out = collect(
+            Resampler::new(22_050, 44_100, 2, 2),
+            &[&[0.0, 10.0], &[1.0, 11.0, 2.0, 12.0]],
+        );
+        assert_eq!(out, vec![0.0, 10.0, 0.5, 10.5, 1.0, 11.0, 1.5, 11.5]);
+    }
+
+    #[test]
+    fn audio_file_decodes_wav() {
+        let dir = tempfile::tempdir().unwrap();
+        let track = source(dir.path(), "wav", 22_050, 1, 0.5);
+        let mut file = AudioFile::open(&track.path).unwrap();
+        let duration = file.duration_secs.unwrap();
+        assert!((duration - 0.5).abs() < 0.01, "{}", duration);
+
+        let mut frames = 0;
+        while let Some((rate, channels, samples)) = file.next_buffer().unwrap() {
+            assert_eq!((rate, channels), (22_050, 1));
+            frames += samples.len();
+        }
+        assert_eq!(frames, 11_025);
+    }
+
+    #[test]
+    fn audio_file_rejects_unknown_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("song.mp3");
+        std::fs::write(&path, b"not audio").unwrap();
+        assert!(AudioFile::open(&path).is_err());
+        assert!(AudioFile::open(&dir.path().join("missing.wav")).is_err());
+    }
+
+    #[test]
+    fn engine_plays_track_to_end() {
+        let dir = tempfile::tempdir().unwrap();
+        let (engine, rx) = start_engine();
+        engine
+            .load(source(dir.path(), "a", 22_050, 1, 0.6), 0.0)
+            .unwrap();
+
+        let Some(PlayerEvent::TrackChanged(state)) =
+            wait_for(&rx, |e| matches!(e, PlayerEvent::TrackChanged(_)))
+        else {
+            panic!("没有收到切歌事件");
+        };
+        assert_eq!(state.song_id.as_deref(), Some("a"));
+        assert!((state.duration_secs.unwrap() - 0.6).abs() < 0.01);
+
+        let position = wait_for(
+            &rx,
+            |e| matches!(e, PlayerEvent::Position(s) if s.position_secs > 0.2),
+        );
+        assert!(position.is_some(), "进度没有推进");
+        assert!(wait_for(&rx, |e| matches!(e, PlayerEvent::Ended)).is_some());
+        assert!(!engine.state().playing);
+    }
+
+    #[test]
+    fn engine_seeks_and_pauses() {
+        let dir = tempfile::tempdir().unwrap();
+        let (engine, rx) = start_manual_engine();
+        engine
+            .load(source(dir.path(), "a", 44_100, 2, 5.0), 0.0)
+            .unwrap();
+        wait_for(&rx, |e| matches!(e, PlayerEvent::TrackChanged(_))).unwrap();
+
+        engine.seek(3.0).unwrap();
+        // 解码器跳到 3 秒附近的数据包
+        wait_until(|| (engine.state().position_secs - 3.0).abs() < 0.05);
+        let seeked = engine.state().position_secs;
+        play_for(&engine, 0.5);
+        let state = engine.state();
+        assert!(
+            (state.position_secs - seeked - 0.5).abs() < 0.01,
+            "{}",
+            state.position_secs
+        );
+        assert_eq!(state.song_id.as_deref(), Some("a"));
+
+        engine.pause();
+        let paused = engine.state();
+        assert!(!paused.playing);
+        play_for(&engine, 0.5);
+        assert_eq!(engine.state().position_secs, paused.position_secs);
+
+        engine.play();
+        play_for(&engine, 0.2);
+        let position = engine.state().position_secs;
+        assert!(
+            (position - paused.position_secs - 0.2).abs() < 0.01,
+            "{}",
+            position
+        );
+    }
+
+    #[test]
+    fn engine_starts_at_offset() {
+        let dir = tempfile::tempdir().unwrap();
+        let (engine, rx) = start_manual_engine();
+        engine
+            .load(source(dir.path(), "a", 44_100, 2, 3.0), 2.0)
+            .unwrap();
+        let Some(PlayerEvent::TrackChanged(state)) =
+            wait_for(&rx, |e| matches!(e, PlayerEvent::TrackChanged(_)))
+        else {
+            panic!("没有收到切歌事件");
+        };
+        // 还没有取走任何采样，位置是 2 秒附近的数据包
+        assert!(
+            (state.position_secs - 2.0).abs() < 0.05,
+            "{}",
+            state.position_secs
+        );
+    }
+
+    #[test]
+    fn engine_plays_next_track_gaplessly() {
+        let dir = tempfile::tempdir().unwrap();
+        let (engine, rx) = start_manual_engine();
+        engine
+            .load(source(dir.path(), "a", 44_100, 2, 0.4), 0.0)
+            .unwrap();
+        engine
+            .set_next(Some(source(dir.path(), "b", 22_050, 1, 0.4)))
+            .unwrap();
+
+        let mut tracks = Vec::new();
+        let mut ended = false;
+        let mut record = |e: &PlayerEvent| {
+            match e {
+                PlayerEvent::TrackChanged(state) => tracks.push(state.song_id.clone().unwrap()),
+                PlayerEvent::Ended => ended = true,
+                _ => {}
+            }
+            matches!(e, PlayerEvent::TrackChanged(_) | PlayerEvent::Ended)
+        };
+        wait_for(&rx, &mut record).unwrap();
+        // 播完第一首之后停在第二首的开头，等进度线程看到切歌
+        play_for(&engine, 0.45);
+        wait_for(&rx, &mut record).unwrap();
+        play_for(&engine, 1.0);
+        wait_for(&rx, &mut record).unwrap();
+
+        assert_eq!(tracks, vec!["a", "b"]);
+        // 两首歌之间没有 Ended
+        assert!(ended);
+    }
+
+    #[test]
+    fn engine_appends_next_track_after_decoding_finished() {
+        let dir = tempfile::tempdir().unwrap();
+        let (engine, rx) = start_manual_engine();
+        engine
+            .load(source(dir.path(), "a", 44_100, 2, 0.4), 0.0)
+            .unwrap();
+        wait_for(&rx, |e| matches!(e, PlayerEvent::TrackChanged(_))).unwrap();
+        // 比缓冲区短的歌曲已经解码完，但还没有播放
+        wait_until(|| engine.queue.state.lock().unwrap().finished);
+        engine
+            .set_next(Some(source(dir.path(), "b", 44_100, 2, 0.4)))
+            .unwrap();
+        wait_until(|| !engine.queue.state.lock().unwrap().finished);
+
+        play_for(&engine, 0.45);
+        let Some(PlayerEvent::TrackChanged(state)) = wait_for(&rx, |e| {
+            matches!(e, PlayerEvent::TrackChanged(_) | PlayerEvent::Ended)
+        }) else {
+            panic!("下一首没有接着播放");
+        };
+        assert_eq!(state.song_id.as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn engine_ignores_next_track_after_ended() {
+        let dir = tempfile::tempdir().unwrap();
+        let (engine, rx) = start_manual_engine();
+        engine
+            .load(source(dir.path(), "a", 44_100, 2, 0.2), 0.0)
+            .unwrap();
+        wait_for(&rx, |e| matches!(e, PlayerEvent::TrackChanged(_))).unwrap();
+        play_for(&engine, 1.0);
+        wait_for(&rx, |e| matches!(e, PlayerEvent::Ended)).unwrap();
+        engine
+            .set_next(Some(source(dir.path(), "b", 44_100, 2, 0.4)))
+            .unwrap();
+        // 没有事件可以等待，只能给解码线程一点时间。机器慢时最多漏检，不会误报
+        thread::sleep(Duration::from_millis(300));
+        assert!(engine.queue.state.lock().unwrap().boundaries.is_empty());
+        assert!(
+            rx.try_iter()
+                .all(|e| !matches!(e, PlayerEvent::TrackChanged(_)))
+        );
+        assert!(!engine.state().playing);
+    }
+
+    #[test]
+    fn engine_reports_load_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let (engine, rx) = start_manual_engine();
+        let missing = TrackSource {
+            song_id: "missing".to_string(),
+            path: dir.path().join("missing.wav"),
+        };
+        engine.load(missing, 0.0).unwrap();
+        assert!(wait_for(&rx, |e| matches!(e, PlayerEvent::Error(_))).is_some());
+        assert_eq!(engine.state().song_id, None);
+    }
+}
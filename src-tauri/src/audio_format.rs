@@ -0,0 +1,185 @@
+// src-tauri/src/audio_format.rs
+
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+/// 缓存中支持识别的音频容器格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioFormat {
+    Mp3,
+    M4a,
+    Flac,
+    Ogg,
+    Wav,
+    Aac,
+}
+
+/// 旧版本缓存的文件一律以 .mp3 命名
+pub const DEFAULT_FORMAT: AudioFormat = AudioFormat::Mp3;
+
+/// 判断格式需要读取的文件头长度 (ID3 标签之后的帧头另外读取)
+const SNIFF_LEN: usize = 64;
+
+impl AudioFormat {
+    pub const ALL: [AudioFormat; 6] = [
+        AudioFormat::Mp3,
+        AudioFormat::M4a,
+        AudioFormat::Flac,
+        AudioFormat::Ogg,
+        AudioFormat::Wav,
+        AudioFormat::Aac,
+    ];
+
+    /// 存入数据库 format 列的名称，同时也是文件扩展名
+    pub fn extension(self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::M4a => "m4a",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Ogg => "ogg",
+            AudioFormat::Wav => "wav",
+            AudioFormat::Aac => "aac",
+        }
+    }
+
+    pub fn mime(self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "audio/mpeg",
+            AudioFormat::M4a => "audio/mp4",
+            AudioFormat::Flac => "audio/flac",
+            AudioFormat::Ogg => "audio/ogg",
+            AudioFormat::Wav => "audio/wav",
+            AudioFormat::Aac => "audio/aac",
+        }
+    }
+
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_ascii_lowercase().as_str() {
+            "mp3" => Some(AudioFormat::Mp3),
+            "m4a" | "mp4" => Some(AudioFormat::M4a),
+            "flac" => Some(AudioFormat::Flac),
+            "ogg" | "oga" | "opus" => Some(AudioFormat::Ogg),
+            "wav" => Some(AudioFormat::Wav),
+            "aac" => Some(AudioFormat::Aac),
+            _ => None,
+        }
+    }
+
+    /// 根据文件头的魔数判断容器格式
+    pub fn sniff(header: &[u8]) -> Option<Self> {
+        if header.starts_with(b"ID3") {
+            return Some(AudioFormat::Mp3);
+        }
+        if header.starts_with(b"fLaC") {
+            return Some(AudioFormat::Flac);
+        }
+        if header.starts_with(b"OggS") {
+            return Some(AudioFormat::Ogg);
+        }
+        if header.len() >= 12 && &header[..4] == b"RIFF" && &header[8..12] == b"WAVE" {
+            return Some(AudioFormat::Wav);
+        }
+        if header.len() >= 8 && &header[4..8] == b"ftyp" {
+            return Some(AudioFormat::M4a);
+        }
+        // 没有 ID3 标签时看第一个帧同步字：ADTS 的 layer 位为 00，MPEG 音频为非 00
+        if header.len() >= 2 && header[0] == 0xFF && header[1] & 0xE0 == 0xE0 {
+            return if header[1] & 0x06 == 0 {
+                Some(AudioFormat::Aac)
+            } else {
+                Some(AudioFormat::Mp3)
+            };
+        }
+        None
+    }
+
+    /// 读取文件头判断格式。带 ID3v2 标签的文件需要跳过标签，看真正的音频帧
+    pub fn sniff_file(path: &Path) -> Option<Self> {
+        let mut file = File::open(path).ok()?;
+        let mut header = [0u8; SNIFF_LEN];
+        let len = read_up_to(&mut file, &mut header);
+        let header = &header[..len];
+
+        if header.len() >= 10 && header.starts_with(b"ID3") {
+            // ID3v2 标签大小使用 4 个 7 位字节 (synchsafe) 表示
+            let tag_size = header[6..10]
+                .iter()
+                .fold(0u64, |acc, b| (acc << 7) | (*b as u64 & 0x7F));
+            let footer = if header[5] & 0x10 != 0 { 10 } else { 0 };
+            let audio_start = 10 + tag_size + footer;
+
+            let mut frame = [0u8; SNIFF_LEN];
+            let frame_len = match file.seek(SeekFrom::Start(audio_start)) {
+                Ok(_) => read_up_to(&mut file, &mut frame),
+                Err(_) => 0,
+            };
+            // 标签后面不是可识别的帧时，仍然按 MP3 处理
+            return Self::sniff(&frame[..frame_len]).or(Some(AudioFormat::Mp3));
+        }
+
+        Self::sniff(header)
+    }
+}
+
+fn read_up_to(file: &mut File, buf: &mut [u8]) -> usize {
+    let mut total = 0;
+    while total < buf.len() {
+        match file.read(&mut buf[total..]) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => total += n,
+        }
+    }
+    total
+}
+
+/// 识别刚下载完成的缓存文件，扩展名与实际格式不符时重命名，返回最终路径和格式
+pub async fn identify_cached_file(path: &Path) -> Result<(PathBuf, AudioFormat), String> {
+    let sniff_path = path.to_path_buf();
+    let format = tauri::async_runtime::spawn_blocking(move || AudioFormat::sniff_file(&sniff_path))
+        .await
+        .map_err(|e| e.to_string())?
+        .unwrap_or_else(|| {
+            path.extension()
+                .and_then(|e| e.to_str())
+                .and_then(AudioFormat::from_extension)
+                .unwrap_or(DEFAULT_FORMAT)
+        });
+
+    let current_ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    if current_ext.eq_ignore_ascii_case(format.extension()) {
+        return Ok((path.to_path_buf(), format));
+    }
+
+    let renamed = path.with_extension(format.extension());
+    tokio::fs::rename(path, &renamed)
+        .await
+        .map_err(|e| format!("重命名缓存文件失败: {}", e))?;
+    println!(
+        "缓存文件实际格式为 {}，已重命名: {}",
+        format.extension(),
+        renamed.display()
+    );
+    Ok((renamed, format))
+}
+
+/// 本地媒体服务器根据扩展名返回的 Content-Type
+pub fn content_type_for_path(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    if let Some(format) = AudioFormat::from_extension(&ext) {
+        return format.mime();
+    }
+    match ext.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        _ => "application/octet-stream",
+    }
+}
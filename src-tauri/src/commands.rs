@@ -2,23 +2,41 @@
 
 use super::my_util;
 use crate::{
+    audio_engine::{self, NativePlayer},
+    download_manager, duplicates, external_import, library_search, listening_stats, loudness,
+    lyric, media_session,
     model::{
-        CacheAnalysisResult, CachedMusicInfo, Music, PlaylistCacheInfo, PlaylistInfo,
-        PlaylistMusicItem, ToggleMusicPayload, UpdateDetailPayload,
+        CacheAnalysisResult, CachedMusicInfo, DownloadJob, DuplicateGroup, ExternalImportReport,
+        LibrarySearchHit, ListeningStats, MediaServerInfo, MergeDuplicatesResult, Music,
+        NativePlayerState, ParsedLyric, PinyinSearchResult, PlayHistoryPage, PlayQueueState,
+        PlaylistCacheInfo, PlaylistImportReport, PlaylistInfo, PlaylistMusicItem,
+        RecordPlayPayload, SearchResult, SmartPlaylistRules, ToggleMusicPayload, TrackGain,
+        TrashContents, UpdateDetailPayload, YearReview,
     },
     music::{self},
     music_cache,
     my_util::DbPool,
+    pinyin_search, play_history, play_queue,
     playlist::{self},
-    updater,
+    playlist_io, smart_playlist,
+    source::{self, SourceRegistry},
+    trash, updater,
 };
 
-use tauri::{AppHandle, ipc::Invoke};
+use tauri::{AppHandle, Manager, ipc::Invoke};
+
+/// 歌单列表或正在播放的歌单变化后，更新托盘菜单中的最近歌单
+fn refresh_tray(app_handle: &AppHandle) {
+    #[cfg(desktop)]
+    crate::tray::refresh(app_handle);
+    #[cfg(mobile)]
+    let _ = app_handle;
+}
 
 #[tauri::command]
 async fn save_music(music_list: Vec<Music>, state: tauri::State<'_, DbPool>) -> Result<(), String> {
     let pool = state.inner();
-    music::save_music(pool, music_list)
+    music::save_music(pool, &music_list)
         .await
         .map_err(|e| e.to_string())
 }
@@ -45,21 +63,65 @@ async fn toggle_music_in_playlist(
 }
 
 #[tauri::command]
-async fn create_playlist(state: tauri::State<'_, DbPool>) -> Result<i64, String> {
+async fn create_playlist(
+    app_handle: AppHandle,
+    state: tauri::State<'_, DbPool>,
+) -> Result<i64, String> {
     playlist::create_playlist(state.inner())
         .await
         .map_err(|e| e.to_string())
+        .inspect(|_| refresh_tray(&app_handle))
 }
 
 #[tauri::command]
-async fn delete_playlist(playlist_id: i64, state: tauri::State<'_, DbPool>) -> Result<(), String> {
+async fn delete_playlist(
+    app_handle: AppHandle,
+    playlist_id: i64,
+    state: tauri::State<'_, DbPool>,
+) -> Result<(), String> {
     playlist::delete_playlist(state.inner(), playlist_id)
         .await
         .map_err(|e| e.to_string())
+        .inspect(|_| refresh_tray(&app_handle))
+}
+
+#[tauri::command]
+async fn list_trash(state: tauri::State<'_, DbPool>) -> Result<TrashContents, String> {
+    trash::list_trash(state.inner())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn restore_playlist(
+    app_handle: AppHandle,
+    playlist_id: i64,
+    state: tauri::State<'_, DbPool>,
+) -> Result<(), String> {
+    trash::restore_playlist(state.inner(), playlist_id)
+        .await
+        .inspect(|_| refresh_tray(&app_handle))
+}
+
+/// trash_ids 为 list_trash 返回的移除歌曲记录 ID
+#[tauri::command]
+async fn restore_removed_songs(
+    trash_ids: Vec<i64>,
+    state: tauri::State<'_, DbPool>,
+) -> Result<(), String> {
+    trash::restore_removed_songs(state.inner(), trash_ids).await
+}
+
+#[tauri::command]
+async fn empty_trash(state: tauri::State<'_, DbPool>) -> Result<(), String> {
+    trash::empty_trash(state.inner())
+        .await
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 async fn rename_playlist(
+    app_handle: AppHandle,
     playlist_id: i64,
     new_name: String,
     state: tauri::State<'_, DbPool>,
@@ -67,6 +129,7 @@ async fn rename_playlist(
     playlist::rename_playlist(state.inner(), playlist_id, new_name)
         .await
         .map_err(|e| e.to_string())
+        .inspect(|_| refresh_tray(&app_handle))
 }
 
 #[tauri::command]
@@ -79,6 +142,69 @@ async fn get_all_playlists(
         .map_err(|e| e.to_string())
 }
 
+/// sort_mode: manual、added_desc、title、artist、last_played
+#[tauri::command]
+async fn set_playlist_sort_mode(
+    playlist_id: i64,
+    sort_mode: String,
+    state: tauri::State<'_, DbPool>,
+) -> Result<(), String> {
+    playlist::set_playlist_sort_mode(state.inner(), playlist_id, &sort_mode).await
+}
+
+/// 拖动排序：把选中的歌曲移动到 to_index，歌单会切换为手动排序
+#[tauri::command]
+async fn move_songs_in_playlist(
+    playlist_id: i64,
+    song_ids: Vec<String>,
+    to_index: usize,
+    state: tauri::State<'_, DbPool>,
+) -> Result<(), String> {
+    playlist::move_songs_in_playlist(state.inner(), playlist_id, song_ids, to_index).await
+}
+
+#[tauri::command]
+async fn set_playlist_order(
+    playlist_id: i64,
+    song_ids: Vec<String>,
+    state: tauri::State<'_, DbPool>,
+) -> Result<(), String> {
+    playlist::set_playlist_order(state.inner(), playlist_id, song_ids).await
+}
+
+/// 新建智能歌单，歌曲由规则实时计算
+#[tauri::command]
+async fn create_smart_playlist(
+    app_handle: AppHandle,
+    name: String,
+    rules: SmartPlaylistRules,
+    state: tauri::State<'_, DbPool>,
+) -> Result<i64, String> {
+    smart_playlist::create_smart_playlist(state.inner(), &name, &rules)
+        .await
+        .inspect(|_| refresh_tray(&app_handle))
+}
+
+#[tauri::command]
+async fn update_smart_playlist_rules(
+    playlist_id: i64,
+    rules: SmartPlaylistRules,
+    state: tauri::State<'_, DbPool>,
+) -> Result<(), String> {
+    smart_playlist::update_smart_playlist_rules(state.inner(), playlist_id, &rules).await
+}
+
+/// 普通歌单返回 null
+#[tauri::command]
+async fn get_smart_playlist_rules(
+    playlist_id: i64,
+    state: tauri::State<'_, DbPool>,
+) -> Result<Option<SmartPlaylistRules>, String> {
+    smart_playlist::get_smart_playlist_rules(state.inner(), playlist_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn get_music_by_playlist_id(
     playlist_id: i64,
@@ -147,15 +273,197 @@ pub async fn update_music_cache_path(
         .map_err(|e| e.to_string())
 }
 #[tauri::command]
-pub async fn update_music_last_play_time(
-    song_id: String,
+pub async fn get_play_queue(state: tauri::State<'_, DbPool>) -> Result<PlayQueueState, String> {
+    play_queue::get_play_queue(state.inner()).await
+}
+
+#[tauri::command]
+pub async fn set_play_queue(
+    app_handle: AppHandle,
+    songs: Vec<Music>,
+    start_index: i64,
+    playlist_id: Option<i64>,
+    state: tauri::State<'_, DbPool>,
+) -> Result<PlayQueueState, String> {
+    play_queue::set_play_queue(&app_handle, state.inner(), songs, start_index, playlist_id)
+        .await
+        .inspect(|_| refresh_tray(&app_handle))
+}
+
+#[tauri::command]
+pub async fn enqueue_songs(
+    app_handle: AppHandle,
+    songs: Vec<Music>,
+    state: tauri::State<'_, DbPool>,
+) -> Result<PlayQueueState, String> {
+    play_queue::enqueue(&app_handle, state.inner(), songs).await
+}
+
+#[tauri::command]
+pub async fn insert_next_songs(
+    app_handle: AppHandle,
+    songs: Vec<Music>,
+    state: tauri::State<'_, DbPool>,
+) -> Result<PlayQueueState, String> {
+    play_queue::insert_next(&app_handle, state.inner(), songs).await
+}
+
+#[tauri::command]
+pub async fn remove_from_queue(
+    app_handle: AppHandle,
+    indices: Vec<usize>,
+    state: tauri::State<'_, DbPool>,
+) -> Result<PlayQueueState, String> {
+    play_queue::remove_from_queue(&app_handle, state.inner(), indices).await
+}
+
+#[tauri::command]
+pub async fn move_in_queue(
+    app_handle: AppHandle,
+    from: usize,
+    to: usize,
+    state: tauri::State<'_, DbPool>,
+) -> Result<PlayQueueState, String> {
+    play_queue::move_in_queue(&app_handle, state.inner(), from, to).await
+}
+
+#[tauri::command]
+pub async fn set_queue_index(
+    app_handle: AppHandle,
+    index: i64,
+    state: tauri::State<'_, DbPool>,
+) -> Result<PlayQueueState, String> {
+    play_queue::set_queue_index(&app_handle, state.inner(), index).await
+}
+
+#[tauri::command]
+pub async fn set_play_mode(
+    app_handle: AppHandle,
+    mode: String,
+    state: tauri::State<'_, DbPool>,
+) -> Result<PlayQueueState, String> {
+    play_queue::set_play_mode(&app_handle, state.inner(), mode).await
+}
+
+#[tauri::command]
+pub async fn clear_play_queue(
+    app_handle: AppHandle,
+    state: tauri::State<'_, DbPool>,
+) -> Result<PlayQueueState, String> {
+    play_queue::clear_play_queue(&app_handle, state.inner())
+        .await
+        .inspect(|_| refresh_tray(&app_handle))
+}
+
+#[tauri::command]
+pub async fn save_play_position(
+    position_secs: f64,
     state: tauri::State<'_, DbPool>,
 ) -> Result<(), String> {
-    music::update_music_last_play_time(state.inner(), &song_id)
+    play_queue::save_play_position(state.inner(), position_secs)
         .await
         .map_err(|e| e.to_string())
 }
 
+/// 用原生引擎播放，没有缓存的歌曲会先下载
+#[tauri::command]
+pub async fn native_play(
+    app_handle: AppHandle,
+    music: Music,
+    start_secs: Option<f64>,
+    state: tauri::State<'_, DbPool>,
+) -> Result<(), String> {
+    audio_engine::play(&app_handle, state.inner(), music, start_secs.unwrap_or(0.0)).await
+}
+
+/// 预加载下一首，当前歌曲播完后无缝衔接
+#[tauri::command]
+pub async fn native_set_next(
+    app_handle: AppHandle,
+    music: Option<Music>,
+    state: tauri::State<'_, DbPool>,
+) -> Result<(), String> {
+    audio_engine::set_next(&app_handle, state.inner(), music).await
+}
+
+#[tauri::command]
+pub fn native_pause(player: tauri::State<'_, NativePlayer>) -> Result<(), String> {
+    audio_engine::pause(player.inner())
+}
+
+#[tauri::command]
+pub fn native_resume(player: tauri::State<'_, NativePlayer>) -> Result<(), String> {
+    audio_engine::resume(player.inner())
+}
+
+#[tauri::command]
+pub fn native_seek(
+    position_secs: f64,
+    player: tauri::State<'_, NativePlayer>,
+) -> Result<(), String> {
+    audio_engine::seek(player.inner(), position_secs)
+}
+
+#[tauri::command]
+pub fn native_set_volume(
+    volume: f32,
+    player: tauri::State<'_, NativePlayer>,
+) -> Result<(), String> {
+    audio_engine::set_volume(player.inner(), volume)
+}
+
+#[tauri::command]
+pub fn native_stop(player: tauri::State<'_, NativePlayer>) -> Result<(), String> {
+    audio_engine::stop(player.inner())
+}
+
+#[tauri::command]
+pub fn get_native_player_state(player: tauri::State<'_, NativePlayer>) -> NativePlayerState {
+    audio_engine::get_state(player.inner())
+}
+
+/// 歌曲的响度和音量均衡增益，还没分析过的已缓存歌曲会当场分析
+#[tauri::command]
+pub async fn get_track_gain(
+    song_id: String,
+    state: tauri::State<'_, DbPool>,
+) -> Result<TrackGain, String> {
+    loudness::get_track_gain(state.inner(), &song_id).await
+}
+
+/// 同步正在播放的歌曲和播放状态到系统媒体控制，song_id 为空表示已停止
+#[tauri::command]
+pub async fn update_media_session(
+    app_handle: AppHandle,
+    song_id: Option<String>,
+    playing: bool,
+    position_secs: f64,
+    state: tauri::State<'_, DbPool>,
+) -> Result<(), String> {
+    media_session::update_media_session(&app_handle, state.inner(), song_id, playing, position_secs)
+        .await
+}
+
+/// 记录一次收听 (切歌、播放结束或关闭播放器时由前端上报)
+#[tauri::command]
+pub async fn record_play_event(
+    payload: RecordPlayPayload,
+    state: tauri::State<'_, DbPool>,
+) -> Result<i64, String> {
+    play_history::record_play(state.inner(), payload).await
+}
+
+/// 按时间倒序分页获取播放历史，song_id 为空时返回所有歌曲
+#[tauri::command]
+pub async fn get_play_history(
+    song_id: Option<String>,
+    limit: i64,
+    offset: i64,
+    state: tauri::State<'_, DbPool>,
+) -> Result<PlayHistoryPage, String> {
+    play_history::get_play_history(state.inner(), song_id.as_deref(), limit, offset).await
+}
+
 #[tauri::command]
 pub async fn cache_music_and_get_file_path(
     app_handle: AppHandle,
@@ -178,6 +486,69 @@ pub async fn export_music_file(
         .map_err(|e| e.to_string())
 }
 
+/// format: m3u8、xspf 或 json。include_audio 为 true 时同时导出已缓存的音频文件
+#[tauri::command]
+pub async fn export_playlist(
+    playlist_id: i64,
+    format: String,
+    include_audio: bool,
+    app_handle: AppHandle,
+    state: tauri::State<'_, DbPool>,
+) -> Result<String, String> {
+    playlist_io::export_playlist(
+        app_handle,
+        state.inner(),
+        playlist_id,
+        &format,
+        include_audio,
+    )
+    .await
+}
+
+/// 导入 M3U8/XSPF/JSON 歌单文件，新建歌单并返回匹配结果
+#[tauri::command]
+pub async fn import_playlist(
+    path: String,
+    content: String,
+    app_handle: AppHandle,
+    state: tauri::State<'_, DbPool>,
+) -> Result<PlaylistImportReport, String> {
+    playlist_io::import_playlist(&app_handle, state.inner(), &path, &content)
+        .await
+        .inspect(|_| refresh_tray(&app_handle))
+}
+
+/// 导入网易云/QQ 音乐导出的歌单 JSON 或 "歌名 - 歌手" 文本列表
+#[tauri::command]
+pub async fn import_external_playlist(
+    path: String,
+    content: String,
+    search_source: bool,
+    app_handle: AppHandle,
+    registry: tauri::State<'_, SourceRegistry>,
+    state: tauri::State<'_, DbPool>,
+) -> Result<ExternalImportReport, String> {
+    external_import::import_external_playlist(
+        state.inner(),
+        registry.inner(),
+        &path,
+        &content,
+        search_source,
+    )
+    .await
+    .inspect(|_| refresh_tray(&app_handle))
+}
+
+/// 用户确认有歧义的条目后，把选中的歌曲追加到导入的歌单
+#[tauri::command]
+pub async fn confirm_import_matches(
+    playlist_id: i64,
+    song_ids: Vec<String>,
+    state: tauri::State<'_, DbPool>,
+) -> Result<(), String> {
+    playlist::append_songs_to_playlist(state.inner(), playlist_id, &song_ids).await
+}
+
 #[tauri::command]
 pub async fn export_db_file(
     app_handle: AppHandle,
@@ -232,6 +603,23 @@ pub async fn get_cached_music_for_playlist(
     music_cache::get_cached_music_for_playlist(pool.inner(), playlist_id).await
 }
 
+#[tauri::command]
+pub async fn find_duplicate_songs(
+    state: tauri::State<'_, DbPool>,
+) -> Result<Vec<DuplicateGroup>, String> {
+    duplicates::find_duplicate_groups(state.inner()).await
+}
+
+/// 保留 keep_song_id，把 duplicate_ids 合并到这首歌上
+#[tauri::command]
+pub async fn merge_duplicate_songs(
+    keep_song_id: String,
+    duplicate_ids: Vec<String>,
+    state: tauri::State<'_, DbPool>,
+) -> Result<MergeDuplicatesResult, String> {
+    duplicates::merge_duplicate_songs(state.inner(), &keep_song_id, duplicate_ids).await
+}
+
 #[tauri::command]
 pub async fn update_playlist_cover(
     playlist_id: i64,
@@ -249,7 +637,192 @@ pub async fn import_database_from_bytes(
     bytes: Vec<u8>,
     mode: String,
 ) -> Result<String, String> {
-    playlist::import_database_from_bytes(app_handle, bytes, &mode)
+    playlist::import_database_from_bytes(app_handle.clone(), bytes, &mode)
+        .await
+        .map_err(|e| e.to_string())
+        .inspect(|_| refresh_tray(&app_handle))
+}
+
+#[tauri::command]
+pub async fn search_music(
+    keyword: String,
+    source_id: Option<String>,
+    registry: tauri::State<'_, SourceRegistry>,
+    state: tauri::State<'_, DbPool>,
+) -> Result<SearchResult, String> {
+    source::search_music(
+        state.inner(),
+        registry.inner(),
+        source_id.as_deref(),
+        &keyword,
+    )
+    .await
+}
+
+#[tauri::command]
+pub async fn fetch_music_detail(
+    app_handle: AppHandle,
+    song_id: String,
+    registry: tauri::State<'_, SourceRegistry>,
+    state: tauri::State<'_, DbPool>,
+) -> Result<Music, String> {
+    source::fetch_music_detail(&app_handle, state.inner(), registry.inner(), &song_id).await
+}
+
+#[tauri::command]
+pub async fn resolve_play_url(
+    song_id: String,
+    registry: tauri::State<'_, SourceRegistry>,
+    state: tauri::State<'_, DbPool>,
+) -> Result<String, String> {
+    source::resolve_play_url(state.inner(), registry.inner(), &song_id).await
+}
+
+/// 在本地曲库 (标题/歌手/歌词) 中全文检索
+#[tauri::command]
+pub async fn search_library(
+    query: String,
+    limit: i64,
+    offset: i64,
+    state: tauri::State<'_, DbPool>,
+) -> Result<Vec<LibrarySearchHit>, String> {
+    library_search::search_library(state.inner(), &query, limit, offset).await
+}
+
+/// 日期范围 (本地日期 YYYY-MM-DD，含两端) 内的收听统计
+#[tauri::command]
+pub async fn get_listening_stats(
+    start_date: String,
+    end_date: String,
+    state: tauri::State<'_, DbPool>,
+) -> Result<ListeningStats, String> {
+    listening_stats::get_listening_stats(state.inner(), &start_date, &end_date).await
+}
+
+#[tauri::command]
+pub async fn get_year_review(
+    year: i32,
+    state: tauri::State<'_, DbPool>,
+) -> Result<YearReview, String> {
+    listening_stats::get_year_review(state.inner(), year).await
+}
+
+/// 导出年度报告 (JSON + 单文件 HTML) 到下载目录
+#[tauri::command]
+pub async fn export_year_review(
+    app_handle: AppHandle,
+    year: i32,
+    state: tauri::State<'_, DbPool>,
+) -> Result<String, String> {
+    listening_stats::export_year_review(&app_handle, state.inner(), year).await
+}
+
+/// 按汉字、全拼 (zhoujielun) 或首字母 (zjl) 检索本地歌曲和歌单
+#[tauri::command]
+pub async fn search_by_pinyin(
+    query: String,
+    limit: i64,
+    state: tauri::State<'_, DbPool>,
+) -> Result<PinyinSearchResult, String> {
+    pinyin_search::search_by_pinyin(state.inner(), &query, limit).await
+}
+
+/// 数据库中歌曲的歌词解析结果，没有歌词时 lines 为空
+#[tauri::command]
+pub async fn get_parsed_lyric(
+    song_id: String,
+    state: tauri::State<'_, DbPool>,
+) -> Result<ParsedLyric, String> {
+    let lyric = music::get_music_lyric(state.inner(), &song_id).await?;
+    Ok(lyric.as_deref().map(lyric::parse_lrc).unwrap_or_default())
+}
+
+/// 播放到 position_ms 时应高亮的歌词行下标
+#[tauri::command]
+pub async fn get_lyric_line_at(
+    song_id: String,
+    position_ms: u64,
+    state: tauri::State<'_, DbPool>,
+) -> Result<Option<usize>, String> {
+    let lyric = music::get_music_lyric(state.inner(), &song_id).await?;
+    Ok(lyric
+        .as_deref()
+        .map(lyric::parse_lrc)
+        .and_then(|parsed| parsed.line_index_at(position_ms)))
+}
+
+/// 本地媒体服务器的地址和访问令牌 (端口可能因为冲突而变化)
+#[tauri::command]
+pub fn get_media_server_info(app_handle: AppHandle) -> Result<MediaServerInfo, String> {
+    app_handle
+        .try_state::<MediaServerInfo>()
+        .map(|info| info.inner().clone())
+        .ok_or("本地媒体服务器未启动".to_string())
+}
+
+#[tauri::command]
+pub async fn get_music_sources(
+    registry: tauri::State<'_, SourceRegistry>,
+    state: tauri::State<'_, DbPool>,
+) -> Result<Vec<String>, String> {
+    Ok(registry
+        .fallback_order(state.inner())
+        .await
+        .iter()
+        .map(|s| s.id().to_string())
+        .collect())
+}
+
+#[tauri::command]
+pub async fn enqueue_downloads(
+    app_handle: AppHandle,
+    song_ids: Vec<String>,
+    priority: Option<i64>,
+    state: tauri::State<'_, DbPool>,
+) -> Result<Vec<i64>, String> {
+    download_manager::enqueue_downloads(&app_handle, state.inner(), song_ids, priority.unwrap_or(0))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_download_jobs(
+    state: tauri::State<'_, DbPool>,
+) -> Result<Vec<DownloadJob>, String> {
+    download_manager::get_download_jobs(state.inner())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn pause_download(
+    app_handle: AppHandle,
+    job_id: Option<i64>,
+    state: tauri::State<'_, DbPool>,
+) -> Result<(), String> {
+    download_manager::pause_download(&app_handle, state.inner(), job_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn resume_download(
+    app_handle: AppHandle,
+    job_id: Option<i64>,
+    state: tauri::State<'_, DbPool>,
+) -> Result<(), String> {
+    download_manager::resume_download(&app_handle, state.inner(), job_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn cancel_download(
+    app_handle: AppHandle,
+    job_id: Option<i64>,
+    state: tauri::State<'_, DbPool>,
+) -> Result<(), String> {
+    download_manager::cancel_download(&app_handle, state.inner(), job_id)
         .await
         .map_err(|e| e.to_string())
 }
@@ -261,16 +834,50 @@ pub fn get_command_handler() -> impl Fn(Invoke) -> bool {
         toggle_music_in_playlist,
         create_playlist,
         delete_playlist,
+        list_trash,
+        restore_playlist,
+        restore_removed_songs,
+        empty_trash,
         rename_playlist,
         get_all_playlists,
         get_music_by_playlist_id,
+        set_playlist_sort_mode,
+        move_songs_in_playlist,
+        set_playlist_order,
+        create_smart_playlist,
+        update_smart_playlist_rules,
+        get_smart_playlist_rules,
         save_app_setting,
         get_app_setting,
         check_for_updates,
         ignore_update,
         get_music_list_by_ids,
         update_music_cache_path,
-        update_music_last_play_time,
+        get_play_queue,
+        set_play_queue,
+        enqueue_songs,
+        insert_next_songs,
+        remove_from_queue,
+        move_in_queue,
+        set_queue_index,
+        set_play_mode,
+        clear_play_queue,
+        save_play_position,
+        native_play,
+        native_set_next,
+        native_pause,
+        native_resume,
+        native_seek,
+        native_set_volume,
+        native_stop,
+        get_native_player_state,
+        get_track_gain,
+        update_media_session,
+        record_play_event,
+        get_play_history,
+        get_listening_stats,
+        get_year_review,
+        export_year_review,
         cache_music_and_get_file_path,
         export_music_file,
         get_cache_size,
@@ -279,8 +886,28 @@ pub fn get_command_handler() -> impl Fn(Invoke) -> bool {
         get_all_playlists_cache_info,
         clear_cache_by_ids,
         get_cached_music_for_playlist,
+        find_duplicate_songs,
+        merge_duplicate_songs,
         update_playlist_cover,
         export_db_file,
+        export_playlist,
+        import_playlist,
+        import_external_playlist,
+        confirm_import_matches,
         import_database_from_bytes,
+        search_music,
+        fetch_music_detail,
+        resolve_play_url,
+        get_music_sources,
+        get_media_server_info,
+        search_library,
+        search_by_pinyin,
+        get_parsed_lyric,
+        get_lyric_line_at,
+        enqueue_downloads,
+        get_download_jobs,
+        pause_download,
+        resume_download,
+        cancel_download,
     ]
 }
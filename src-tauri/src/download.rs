@@ -0,0 +1,545 @@
+// src-tauri/src/download.rs
+
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_http::reqwest::{
+    self, StatusCode,
+    header::{CONTENT_RANGE, RANGE},
+};
+use tokio::{fs::OpenOptions, io::AsyncWriteExt};
+
+use crate::{model::CacheProgressPayload, my_util::DbPool};
+
+/// 缓存下载进度事件，前端通过 listen 订阅
+pub const CACHE_PROGRESS_EVENT: &str = "music-cache-progress";
+
+/// 每下载这么多字节发送一次进度事件，避免事件过于频繁
+const PROGRESS_EMIT_STEP: u64 = 256 * 1024;
+
+/// 超过这个时间没有被续传的 .part 文件视为残留，启动时清理
+const PART_FILE_MAX_AGE: Duration = Duration::from_secs(3 * 24 * 60 * 60);
+
+const PART_EXTENSION: &str = "part";
+
+/// 下载过程中写入的临时文件路径，例如 "xxx.mp3.part"
+pub fn part_path(local_path: &Path) -> PathBuf {
+    let mut path = local_path.as_os_str().to_owned();
+    path.push(".");
+    path.push(PART_EXTENSION);
+    PathBuf::from(path)
+}
+
+/// 解析 "bytes 100-999/1000"，返回 (起始位置, 文件总大小)
+fn parse_content_range(value: &str) -> Option<(u64, Option<u64>)> {
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let start = range.split_once('-')?.0.parse::<u64>().ok()?;
+    Some((start, total.parse::<u64>().ok()))
+}
+
+fn emit_progress(
+    app_handle: &AppHandle,
+    song_id: &str,
+    downloaded: u64,
+    total: Option<u64>,
+    finished: bool,
+) {
+    let payload = CacheProgressPayload {
+        song_id: song_id.to_string(),
+        downloaded_bytes: downloaded,
+        total_bytes: total,
+        finished,
+    };
+    if let Err(e) = app_handle.emit(CACHE_PROGRESS_EVENT, payload) {
+        eprintln!("发送缓存进度事件失败: {}", e);
+    }
+}
+
+/// 每写入一块数据后的回调 (已写入的字节数, 文件总大小)，回调时数据已经刷新到 .part 文件中，
+/// 边下边播时据此读取已下载的部分
+pub type ChunkObserver<'a> = &'a (dyn Fn(u64, Option<u64>) + Send + Sync);
+
+/// 缓存进度的回调 (已下载的字节数, 文件总大小, 是否完成)
+type ProgressObserver<'a> = &'a (dyn Fn(u64, Option<u64>, bool) + Send + Sync);
+
+/// 流式下载到 .part 文件，完成后原子性地重命名为 local_path。
+/// 如果存在上次中断留下的 .part 文件，会通过 HTTP Range 从断点继续下载。
+pub async fn download_with_resume(
+    app_handle: &AppHandle,
+    song_id: &str,
+    url: &str,
+    local_path: &Path,
+    on_chunk: Option<ChunkObserver<'_>>,
+) -> Result<(), String> {
+    let on_progress = |downloaded: u64, total: Option<u64>, finished: bool| {
+        emit_progress(app_handle, song_id, downloaded, total, finished)
+    };
+    resume_download(song_id, url, local_path, on_chunk, &on_progress).await
+}
+
+/// 丢弃上次中断留下的 .part 文件。播放地址换成了另一个音源时，
+/// 远程文件的内容可能不同，不能在旧数据后面续传
+pub async fn discard_partial(local_path: &Path) -> Result<(), String> {
+    match tokio::fs::remove_file(part_path(local_path)).await {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("删除临时文件失败: {}", e)),
+    }
+}
+
+async fn resume_download(
+    song_id: &str,
+    url: &str,
+    local_path: &Path,
+    on_chunk: Option<ChunkObserver<'_>>,
+    on_progress: ProgressObserver<'_>,
+) -> Result<(), String> {
+    let part_path = part_path(local_path);
+    let client = reqwest::Client::new();
+
+    let mut offset = tokio::fs::metadata(&part_path)
+        .await
+        .map(|m| m.len())
+        .unwrap_or(0);
+
+    let mut request = client.get(url);
+    if offset > 0 {
+        println!("断点续传: {} 从 {} 字节继续", song_id, offset);
+        request = request.header(RANGE, format!("bytes={}-", offset));
+    }
+    let mut response = request
+        .send()
+        .await
+        .map_err(|e| format!("网络请求失败: {}", e))?;
+
+    // 断点超出了远程文件的范围 (远程文件可能已变化)，丢弃 .part 重新下载
+    if offset > 0 && response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        println!("断点无效，重新下载: {}", song_id);
+        let _ = tokio::fs::remove_file(&part_path).await;
+        offset = 0;
+        response = client
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| format!("网络请求失败: {}", e))?;
+    }
+
+    if !response.status().is_success() {
+        return Err(format!("下载失败，状态码: {}", response.status()));
+    }
+
+    let content_range = response
+        .headers()
+        .get(CONTENT_RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_content_range);
+
+    // 只有 Content-Range 的起点与 .part 的长度一致时才能追加写入
+    let partial = offset > 0 && response.status() == StatusCode::PARTIAL_CONTENT;
+    let (resumed, total) = match content_range {
+        Some((start, total)) if partial => {
+            if start != offset {
+                let _ = tokio::fs::remove_file(&part_path).await;
+                return Err(format!(
+                    "续传位置不一致 (期望 {}，实际 {})，已丢弃临时文件",
+                    offset, start
+                ));
+            }
+            (true, total)
+        }
+        // 206 却没有 Content-Range，无法确定这段数据从哪里开始
+        None if partial => {
+            let _ = tokio::fs::remove_file(&part_path).await;
+            return Err("续传响应缺少 Content-Range，已丢弃临时文件".to_string());
+        }
+        _ => {
+            if offset > 0 {
+                println!("服务端不支持断点续传，重新下载: {}", song_id);
+            }
+            offset = 0;
+            (false, response.content_length())
+        }
+    };
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resumed)
+        .truncate(!resumed)
+        .open(&part_path)
+        .await
+        .map_err(|e| format!("创建临时文件失败: {}", e))?;
+
+    let mut downloaded = offset;
+    let mut last_emitted = offset;
+    on_progress(downloaded, total, false);
+    if let Some(on_chunk) = on_chunk {
+        on_chunk(downloaded, total);
+    }
+
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("下载中断 ({} 字节): {}", downloaded, e))?
+    {
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| format!("写入临时文件失败: {}", e))?;
+        downloaded += chunk.len() as u64;
+
+        if let Some(on_chunk) = on_chunk {
+            // tokio 的文件写入是异步提交的，先刷新再通知，保证读取方能读到这部分数据
+            file.flush().await.map_err(|e| e.to_string())?;
+            on_chunk(downloaded, total);
+        }
+
+        if downloaded - last_emitted >= PROGRESS_EMIT_STEP {
+            on_progress(downloaded, total, false);
+            last_emitted = downloaded;
+        }
+    }
+
+    file.flush().await.map_err(|e| e.to_string())?;
+    file.sync_all().await.map_err(|e| e.to_string())?;
+    drop(file);
+
+    if let Some(total) = total.filter(|t| *t != downloaded) {
+        return Err(format!("下载不完整: {}/{} 字节", downloaded, total));
+    }
+
+    tokio::fs::rename(&part_path, local_path)
+        .await
+        .map_err(|e| format!("重命名缓存文件失败: {}", e))?;
+
+    on_progress(downloaded, total.or(Some(downloaded)), true);
+    Ok(())
+}
+
+/// 启动时的维护任务：清理长时间未续传的 .part 文件，并修正指向不存在文件的缓存记录
+pub async fn cleanup_partial_downloads(
+    app_handle: &AppHandle,
+    pool: &DbPool,
+) -> Result<(), String> {
+    let cache_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("无法获取应用数据目录: {:?}", e))?
+        .join("music_cache");
+
+    let (removed, kept) = cleanup_part_files(&cache_dir, PART_FILE_MAX_AGE).await?;
+    if removed > 0 || kept > 0 {
+        println!(
+            "[Partial Cleanup] 清理残留临时文件 {} 个，保留可续传文件 {} 个",
+            removed, kept
+        );
+    }
+
+    clear_missing_cache_paths(pool).await
+}
+
+/// 删除超过 max_age 没有修改的 .part 文件，返回 (删除的数量, 保留的数量)
+async fn cleanup_part_files(cache_dir: &Path, max_age: Duration) -> Result<(usize, usize), String> {
+    if !cache_dir.is_dir() {
+        return Ok((0, 0));
+    }
+
+    let mut entries = tokio::fs::read_dir(cache_dir)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut removed = 0;
+    let mut kept = 0;
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(PART_EXTENSION) {
+            continue;
+        }
+
+        let is_stale = entry
+            .metadata()
+            .await
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .is_none_or(|age| age > max_age);
+
+        if is_stale {
+            match tokio::fs::remove_file(&path).await {
+                Ok(_) => removed += 1,
+                Err(e) => eprintln!("删除残留文件 {} 失败: {}", path.display(), e),
+            }
+        } else {
+            kept += 1;
+        }
+    }
+
+    Ok((removed, kept))
+}
+
+/// 缓存文件被外部删除时，数据库中的 file_path 也需要清空，避免被当成缓存命中
+async fn clear_missing_cache_paths(pool: &DbPool) -> Result<(), String> {
+    let cached: Vec<(String, String)> = sqlx::query_as(
+        "SELECT song_id, file_path FROM music WHERE file_path IS NOT NULL AND file_path != ''",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    for (song_id, file_path) in cached {
+        if !Path::new(&file_path).exists() {
+            sqlx::query("UPDATE music SET file_path = NULL WHERE song_id = ?")
+                .bind(&song_id)
+                .execute(pool)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{
+        io::Cursor,
+        sync::{Arc, Mutex},
+        thread,
+    };
+
+    use tiny_http::{Header, Response, Server};
+
+    use crate::my_util::test_pool;
+
+    const CONTENT: &[u8] = b"0123456789";
+
+    type Reply = Response<Cursor<Vec<u8>>>;
+
+    /// 本地 HTTP 服务，handler 根据请求的 Range 头返回响应。返回地址和收到的所有 Range 头
+    fn serve(
+        handler: impl Fn(Option<&str>) -> Reply + Send + 'static,
+    ) -> (String, Arc<Mutex<Vec<Option<String>>>>) {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/song.mp3", server.server_addr());
+        let ranges = Arc::new(Mutex::new(Vec::new()));
+        let seen = ranges.clone();
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let range = request
+                    .headers()
+                    .iter()
+                    .find(|h| h.field.equiv("Range"))
+                    .map(|h| h.value.to_string());
+                let response = handler(range.as_deref());
+                seen.lock().unwrap().push(range);
+                let _ = request.respond(response);
+            }
+        });
+        (url, ranges)
+    }
+
+    fn reply(status: u16, content_range: Option<String>, body: &[u8]) -> Reply {
+        let mut response = Response::from_data(body.to_vec()).with_status_code(status);
+        if let Some(value) = content_range {
+            response.add_header(Header::from_bytes("Content-Range", value).unwrap());
+        }
+        response
+    }
+
+    /// 支持 "bytes=N-" 形式的 Range 请求
+    fn ranged(content: &'static [u8]) -> impl Fn(Option<&str>) -> Reply + Send + 'static {
+        move |range| {
+            let start = range.and_then(|r| {
+                r.strip_prefix("bytes=")?
+                    .strip_suffix('-')?
+                    .parse::<usize>()
+                    .ok()
+            });
+            match start {
+                Some(start) => reply(
+                    206,
+                    Some(format!(
+                        "bytes {}-{}/{}",
+                        start,
+                        content.len() - 1,
+                        content.len()
+                    )),
+                    &content[start..],
+                ),
+                None => reply(200, None, content),
+            }
+        }
+    }
+
+    async fn download(url: &str, local_path: &Path) -> Result<(), String> {
+        resume_download("song", url, local_path, None, &|_, _, _| {}).await
+    }
+
+    fn setup(part: &[u8]) -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let local_path = dir.path().join("song.mp3");
+        if !part.is_empty() {
+            std::fs::write(part_path(&local_path), part).unwrap();
+        }
+        (dir, local_path)
+    }
+
+    #[test]
+    fn parses_content_range() {
+        assert_eq!(
+            parse_content_range("bytes 100-999/1000"),
+            Some((100, Some(1000)))
+        );
+        assert_eq!(parse_content_range("bytes 0-9/*"), Some((0, None)));
+        assert_eq!(parse_content_range("bytes */1000"), None);
+        assert_eq!(parse_content_range("items 0-9/10"), None);
+        assert_eq!(parse_content_range("bytes 0-9"), None);
+    }
+
+    #[tokio::test]
+    async fn resumes_from_part_file() {
+        let (_dir, local_path) = setup(b"01234");
+        let (url, ranges) = serve(ranged(CONTENT));
+
+        download(&url, &local_path).await.unwrap();
+        assert_eq!(std::fs::read(&local_path).unwrap(), CONTENT);
+        assert!(!part_path(&local_path).exists());
+        assert_eq!(*ranges.lock().unwrap(), [Some("bytes=5-".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn restarts_when_range_not_satisfiable() {
+        let (_dir, local_path) = setup(b"0123456789abc");
+        let (url, ranges) = serve(|range| match range {
+            Some(_) => reply(416, Some("bytes */10".to_string()), b""),
+            None => reply(200, None, CONTENT),
+        });
+
+        download(&url, &local_path).await.unwrap();
+        assert_eq!(std::fs::read(&local_path).unwrap(), CONTENT);
+        assert_eq!(
+            *ranges.lock().unwrap(),
+            [Some("bytes=13-".to_string()), None]
+        );
+    }
+
+    #[tokio::test]
+    async fn partial_without_content_range_discards_part() {
+        let (_dir, local_path) = setup(b"01234");
+        let (url, _) = serve(|_| reply(206, None, b"56789"));
+
+        assert!(download(&url, &local_path).await.is_err());
+        assert!(!part_path(&local_path).exists());
+        assert!(!local_path.exists());
+    }
+
+    #[tokio::test]
+    async fn mismatched_range_start_discards_part() {
+        let (_dir, local_path) = setup(b"01234");
+        let (url, _) = serve(|_| reply(206, Some("bytes 3-9/10".to_string()), b"3456789"));
+
+        assert!(download(&url, &local_path).await.is_err());
+        assert!(!part_path(&local_path).exists());
+        assert!(!local_path.exists());
+    }
+
+    #[tokio::test]
+    async fn full_response_overwrites_part() {
+        let (_dir, local_path) = setup(b"xxxxx");
+        let (url, _) = serve(|_| reply(200, None, CONTENT));
+
+        download(&url, &local_path).await.unwrap();
+        assert_eq!(std::fs::read(&local_path).unwrap(), CONTENT);
+    }
+
+    #[tokio::test]
+    async fn changed_url_restarts_from_zero() {
+        // 旧音源中断时留下的 .part，新音源的文件内容不同但同样支持 Range
+        let (_dir, local_path) = setup(b"abcde");
+        let (url, ranges) = serve(ranged(CONTENT));
+
+        discard_partial(&local_path).await.unwrap();
+        download(&url, &local_path).await.unwrap();
+        assert_eq!(std::fs::read(&local_path).unwrap(), CONTENT);
+        assert_eq!(*ranges.lock().unwrap(), [None]);
+
+        // 没有 .part 时也不报错
+        discard_partial(&local_path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn removes_only_stale_part_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let stale = dir.path().join("stale.mp3.part");
+        let fresh = dir.path().join("fresh.mp3.part");
+        let cached = dir.path().join("cached.mp3");
+        for path in [&stale, &fresh, &cached] {
+            std::fs::write(path, CONTENT).unwrap();
+        }
+        let old = SystemTime::now() - PART_FILE_MAX_AGE - Duration::from_secs(60);
+        for path in [&stale, &cached] {
+            std::fs::File::options()
+                .write(true)
+                .open(path)
+                .unwrap()
+                .set_modified(old)
+                .unwrap();
+        }
+
+        assert_eq!(
+            cleanup_part_files(dir.path(), PART_FILE_MAX_AGE).await,
+            Ok((1, 1))
+        );
+        assert!(!stale.exists());
+        assert!(fresh.exists());
+        assert!(cached.exists());
+
+        let missing = dir.path().join("missing");
+        assert_eq!(
+            cleanup_part_files(&missing, PART_FILE_MAX_AGE).await,
+            Ok((0, 0))
+        );
+    }
+
+    #[tokio::test]
+    async fn clears_file_path_of_missing_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let existing = dir.path().join("a.mp3");
+        std::fs::write(&existing, CONTENT).unwrap();
+        let missing = dir.path().join("b.mp3");
+
+        let pool = test_pool().await;
+        for (song_id, file_path) in [("a", &existing), ("b", &missing)] {
+            sqlx::query(
+                "INSERT INTO music (song_id, title, artist, url, file_path) VALUES (?, ?, '', '', ?)",
+            )
+            .bind(song_id)
+            .bind(song_id)
+            .bind(file_path.to_string_lossy())
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        clear_missing_cache_paths(&pool).await.unwrap();
+        let rows: Vec<(String, Option<String>)> =
+            sqlx::query_as("SELECT song_id, file_path FROM music ORDER BY song_id")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            rows,
+            [
+                (
+                    "a".to_string(),
+                    Some(existing.to_string_lossy().into_owned())
+                ),
+                ("b".to_string(), None),
+            ]
+        );
+    }
+}
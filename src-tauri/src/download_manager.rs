@@ -0,0 +1,466 @@
+// src-tauri/src/download_manager.rs
+
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use tauri::{AppHandle, Emitter, Manager, async_runtime::JoinHandle};
+use tokio::sync::Notify;
+
+use crate::{
+    download,
+    model::DownloadJob,
+    music,
+    my_util::{DbPool, get_app_setting, save_app_setting},
+    stream_proxy::InflightDownloads,
+};
+
+/// 任务状态变化事件，负载为最新的 DownloadJob
+pub const DOWNLOAD_JOB_EVENT: &str = "download-job-changed";
+
+const DEFAULT_CONCURRENCY: usize = 3;
+const MAX_CONCURRENCY: usize = 8;
+
+/// 没有被唤醒时，调度循环也会定期检查一次队列
+const DISPATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 失败后重试前的等待时间，按重试次数递增
+const RETRY_DELAY: Duration = Duration::from_secs(3);
+
+const JOB_COLUMNS: &str =
+    "id, song_id, status, priority, retry_count, max_retries, last_error, created_at, updated_at";
+
+/// 后台下载管理器：任务持久化在 download_job 表中，内存中只记录正在运行的任务
+pub struct DownloadManager {
+    wake: Notify,
+    running: Mutex<HashMap<i64, JoinHandle<()>>>,
+}
+
+impl DownloadManager {
+    pub fn new() -> Self {
+        Self {
+            wake: Notify::new(),
+            running: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 唤醒调度循环，立即检查队列
+    pub fn wake(&self) {
+        self.wake.notify_one();
+    }
+
+    fn running_count(&self) -> usize {
+        self.running.lock().unwrap().len()
+    }
+
+    /// 中止正在运行的任务，已下载的部分保留在 .part 文件中，之后可以续传
+    fn abort(&self, job_id: i64) {
+        if let Some(handle) = self.running.lock().unwrap().remove(&job_id) {
+            handle.abort();
+        }
+    }
+
+    fn abort_all(&self) -> Vec<i64> {
+        let mut running = self.running.lock().unwrap();
+        running
+            .drain()
+            .map(|(job_id, handle)| {
+                handle.abort();
+                job_id
+            })
+            .collect()
+    }
+}
+
+impl Default for DownloadManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+async fn get_concurrency(pool: &DbPool) -> usize {
+    get_app_setting(pool, "download_concurrency".to_string())
+        .await
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_CONCURRENCY)
+        .clamp(1, MAX_CONCURRENCY)
+}
+
+async fn is_queue_paused(pool: &DbPool) -> bool {
+    get_app_setting(pool, "download_queue_paused".to_string())
+        .await
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse::<bool>().ok())
+        .unwrap_or(false)
+}
+
+async fn get_job(pool: &DbPool, job_id: i64) -> Result<Option<DownloadJob>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT {} FROM download_job WHERE id = ?",
+        JOB_COLUMNS
+    ))
+    .bind(job_id)
+    .fetch_optional(pool)
+    .await
+}
+
+async fn emit_job_changed(app_handle: &AppHandle, pool: &DbPool, job_id: i64) {
+    let Ok(Some(job)) = get_job(pool, job_id).await else {
+        return;
+    };
+    if let Err(e) = app_handle.emit(DOWNLOAD_JOB_EVENT, job) {
+        eprintln!("发送下载任务事件失败: {}", e);
+    }
+}
+
+/// 启动调度循环。上次退出时仍在运行的任务会重新排队，从 .part 文件断点续传
+pub fn start(app_handle: AppHandle, pool: DbPool) {
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = recover_interrupted_jobs(&pool).await {
+            eprintln!("[Download Queue] 恢复任务失败: {}", e);
+        }
+
+        loop {
+            if let Err(e) = dispatch(&app_handle, &pool).await {
+                eprintln!("[Download Queue] 调度失败: {}", e);
+            }
+
+            let manager = app_handle.state::<DownloadManager>();
+            tokio::select! {
+                _ = manager.wake.notified() => {}
+                _ = tokio::time::sleep(DISPATCH_INTERVAL) => {}
+            }
+        }
+    });
+}
+
+async fn recover_interrupted_jobs(pool: &DbPool) -> Result<(), sqlx::Error> {
+    let result = sqlx::query("UPDATE download_job SET status = 'pending' WHERE status = 'running'")
+        .execute(pool)
+        .await?;
+    if result.rows_affected() > 0 {
+        println!(
+            "[Download Queue] 恢复 {} 个中断的下载任务",
+            result.rows_affected()
+        );
+    }
+
+    // 已结束的任务只保留一周
+    sqlx::query(
+        r#"
+            DELETE FROM download_job
+            WHERE status IN ('done', 'cancelled')
+            AND updated_at < strftime('%Y-%m-%d %H:%M:%f', 'now', 'localtime', '-7 days')
+        "#,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// 按优先级领取待下载的任务，直到达到并发上限
+async fn dispatch(app_handle: &AppHandle, pool: &DbPool) -> Result<(), sqlx::Error> {
+    if is_queue_paused(pool).await {
+        return Ok(());
+    }
+
+    let concurrency = get_concurrency(pool).await;
+    let manager = app_handle.state::<DownloadManager>();
+
+    while manager.running_count() < concurrency {
+        let job: Option<DownloadJob> = sqlx::query_as(&format!(
+            r#"
+                UPDATE download_job
+                SET status = 'running',
+                    updated_at = strftime('%Y-%m-%d %H:%M:%f', 'now', 'localtime')
+                WHERE id = (
+                    SELECT id FROM download_job
+                    WHERE status = 'pending'
+                        AND (retry_at IS NULL
+                            OR retry_at <= strftime('%Y-%m-%d %H:%M:%f', 'now', 'localtime'))
+                    ORDER BY priority DESC, id ASC
+                    LIMIT 1
+                )
+                RETURNING {}
+            "#,
+            JOB_COLUMNS
+        ))
+        .fetch_optional(pool)
+        .await?;
+
+        let Some(job) = job else {
+            break;
+        };
+
+        let job_id = job.id;
+        {
+            // 持有锁直到句柄插入完成，避免任务先结束、后插入导致句柄残留
+            let mut running = manager.running.lock().unwrap();
+            let handle =
+                tauri::async_runtime::spawn(run_job(app_handle.clone(), pool.clone(), job));
+            running.insert(job_id, handle);
+        }
+
+        emit_job_changed(app_handle, pool, job_id).await;
+    }
+
+    Ok(())
+}
+
+async fn run_job(app_handle: AppHandle, pool: DbPool, job: DownloadJob) {
+    let result = match music::get_music_list_by_ids(&pool, vec![job.song_id.clone()]).await {
+        Ok(Some(mut list)) if !list.is_empty() => {
+            music::cache_music_and_get_file_path(app_handle.clone(), &pool, list.remove(0))
+                .await
+                .map(|_| ())
+        }
+        Ok(_) => Err("歌曲不存在".to_string()),
+        Err(e) => Err(e),
+    };
+
+    // 失败的任务带着 retry_at 重新排队，立即让出并发名额，等待结束后再由调度循环领取
+    let retry_delay = RETRY_DELAY * (job.retry_count as u32 + 1);
+    let will_retry = result.is_err() && job.retry_count + 1 < job.max_retries;
+    if let Err(e) = &result {
+        eprintln!(
+            "[Download Queue] 任务 {} ({}) 失败: {}",
+            job.id, job.song_id, e
+        );
+    }
+
+    if let Err(e) = finish_job(&pool, &job, result, retry_delay).await {
+        eprintln!("[Download Queue] 更新任务 {} 状态失败: {}", job.id, e);
+    }
+
+    let manager = app_handle.state::<DownloadManager>();
+    manager.running.lock().unwrap().remove(&job.id);
+    manager.wake();
+
+    if will_retry {
+        let app_handle = app_handle.clone();
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(retry_delay).await;
+            app_handle.state::<DownloadManager>().wake();
+        });
+    }
+
+    emit_job_changed(&app_handle, &pool, job.id).await;
+}
+
+/// 只更新仍处于 running 的任务，期间被暂停/取消的任务保持原状态。
+/// 失败后还能重试的任务在 retry_delay 之后才会被重新领取
+async fn finish_job(
+    pool: &DbPool,
+    job: &DownloadJob,
+    result: Result<(), String>,
+    retry_delay: Duration,
+) -> Result<(), sqlx::Error> {
+    let query = match result {
+        Ok(_) => sqlx::query(
+            r#"
+                UPDATE download_job
+                SET status = 'done', last_error = NULL, retry_at = NULL,
+                    updated_at = strftime('%Y-%m-%d %H:%M:%f', 'now', 'localtime')
+                WHERE id = ? AND status = 'running'
+            "#,
+        )
+        .bind(job.id),
+        Err(e) => sqlx::query(
+            r#"
+                UPDATE download_job
+                SET retry_count = retry_count + 1,
+                    status = CASE WHEN retry_count + 1 < max_retries THEN 'pending' ELSE 'failed' END,
+                    last_error = ?,
+                    retry_at = strftime('%Y-%m-%d %H:%M:%f', 'now', 'localtime', ?),
+                    updated_at = strftime('%Y-%m-%d %H:%M:%f', 'now', 'localtime')
+                WHERE id = ? AND status = 'running'
+            "#,
+        )
+        .bind(e)
+        .bind(format!("+{} seconds", retry_delay.as_secs_f64()))
+        .bind(job.id),
+    };
+    query.execute(pool).await?;
+    Ok(())
+}
+
+/// 将歌曲加入下载队列。已有未完成任务的歌曲只会提高其优先级
+pub async fn enqueue_downloads(
+    app_handle: &AppHandle,
+    pool: &DbPool,
+    song_ids: Vec<String>,
+    priority: i64,
+) -> Result<Vec<i64>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let mut job_ids = Vec::with_capacity(song_ids.len());
+
+    for song_id in &song_ids {
+        let (job_id,): (i64,) = sqlx::query_as(
+            r#"
+                INSERT INTO download_job (song_id, priority) VALUES (?, ?)
+                ON CONFLICT(song_id) WHERE status IN ('pending', 'running', 'paused')
+                DO UPDATE SET priority = MAX(priority, excluded.priority)
+                RETURNING id
+            "#,
+        )
+        .bind(song_id)
+        .bind(priority)
+        .fetch_one(&mut *tx)
+        .await?;
+        job_ids.push(job_id);
+    }
+
+    tx.commit().await?;
+
+    for job_id in &job_ids {
+        emit_job_changed(app_handle, pool, *job_id).await;
+    }
+    app_handle.state::<DownloadManager>().wake();
+
+    Ok(job_ids)
+}
+
+pub async fn get_download_jobs(pool: &DbPool) -> Result<Vec<DownloadJob>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        r#"
+            SELECT {} FROM download_job
+            ORDER BY
+                CASE status WHEN 'running' THEN 0 WHEN 'pending' THEN 1 WHEN 'paused' THEN 2 ELSE 3 END,
+                priority DESC, id ASC
+        "#,
+        JOB_COLUMNS
+    ))
+    .fetch_all(pool)
+    .await
+}
+
+/// 暂停任务。job_id 为 None 时暂停整个队列，正在运行的任务会重新排队等待恢复
+pub async fn pause_download(
+    app_handle: &AppHandle,
+    pool: &DbPool,
+    job_id: Option<i64>,
+) -> Result<(), sqlx::Error> {
+    let manager = app_handle.state::<DownloadManager>();
+
+    match job_id {
+        Some(job_id) => {
+            sqlx::query(
+                "UPDATE download_job SET status = 'paused' WHERE id = ? AND status IN ('pending', 'running')",
+            )
+            .bind(job_id)
+            .execute(pool)
+            .await?;
+            manager.abort(job_id);
+            emit_job_changed(app_handle, pool, job_id).await;
+        }
+        None => {
+            save_app_setting(
+                pool,
+                "download_queue_paused".to_string(),
+                "true".to_string(),
+            )
+            .await?;
+            for job_id in manager.abort_all() {
+                sqlx::query(
+                    "UPDATE download_job SET status = 'pending' WHERE id = ? AND status = 'running'",
+                )
+                .bind(job_id)
+                .execute(pool)
+                .await?;
+                emit_job_changed(app_handle, pool, job_id).await;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 恢复任务。失败的任务也可以通过恢复重新开始，重试次数会被重置。
+/// job_id 为 None 时恢复整个队列
+pub async fn resume_download(
+    app_handle: &AppHandle,
+    pool: &DbPool,
+    job_id: Option<i64>,
+) -> Result<(), sqlx::Error> {
+    match job_id {
+        Some(job_id) => {
+            sqlx::query(
+                r#"
+                    UPDATE download_job SET status = 'pending', retry_count = 0, retry_at = NULL
+                    WHERE id = ? AND status IN ('paused', 'failed')
+                "#,
+            )
+            .bind(job_id)
+            .execute(pool)
+            .await?;
+            emit_job_changed(app_handle, pool, job_id).await;
+        }
+        None => {
+            save_app_setting(
+                pool,
+                "download_queue_paused".to_string(),
+                "false".to_string(),
+            )
+            .await?;
+        }
+    }
+    app_handle.state::<DownloadManager>().wake();
+    Ok(())
+}
+
+/// 取消任务并删除未完成的临时文件。job_id 为 None 时取消所有未完成的任务
+pub async fn cancel_download(
+    app_handle: &AppHandle,
+    pool: &DbPool,
+    job_id: Option<i64>,
+) -> Result<(), sqlx::Error> {
+    let jobs: Vec<DownloadJob> = match job_id {
+        Some(job_id) => get_job(pool, job_id).await?.into_iter().collect(),
+        None => {
+            sqlx::query_as(&format!(
+                "SELECT {} FROM download_job WHERE status IN ('pending', 'running', 'paused')",
+                JOB_COLUMNS
+            ))
+            .fetch_all(pool)
+            .await?
+        }
+    };
+
+    let manager = app_handle.state::<DownloadManager>();
+    let cache_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map(|dir| dir.join("music_cache"))
+        .ok();
+
+    for job in jobs {
+        if !job.is_active() {
+            continue;
+        }
+        manager.abort(job.id);
+        sqlx::query("UPDATE download_job SET status = 'cancelled' WHERE id = ?")
+            .bind(job.id)
+            .execute(pool)
+            .await?;
+
+        if let (Some(cache_dir), Ok(Some(music))) = (
+            &cache_dir,
+            music::get_music_list_by_ids(pool, vec![job.song_id.clone()])
+                .await
+                .map(|list| list.and_then(|l| l.into_iter().next())),
+        ) {
+            // 同一首歌可能还在被边下边播的请求下载，这时保留临时文件
+            let streaming = app_handle
+                .state::<InflightDownloads>()
+                .get(&music.song_id)
+                .is_some();
+            if !streaming {
+                let part_path =
+                    download::part_path(&cache_dir.join(music::cache_file_name(&music)));
+                let _ = tokio::fs::remove_file(part_path).await;
+            }
+        }
+        emit_job_changed(app_handle, pool, job.id).await;
+    }
+    Ok(())
+}
@@ -0,0 +1,712 @@
+// src-tauri/src/duplicates.rs
+
+//! 查找并合并曲库中的重复歌曲。
+//! 标题和歌手归一化后相同且时长接近的歌曲，或缓存文件内容完全相同的歌曲视为重复。
+
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    path::Path,
+};
+
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection};
+
+use crate::{
+    audio_format::AudioFormat,
+    model::{DuplicateGroup, DuplicateSong, MergeDuplicatesResult},
+    my_util::{DbPool, calculate_file_hash},
+    playlist::compact_positions,
+    source::normalize_for_match,
+};
+
+/// 时长相差不超过这个秒数视为同一版本，不同音源的时长通常有一两秒误差
+const DURATION_TOLERANCE_SECS: f64 = 3.0;
+
+const REASON_METADATA: &str = "metadata";
+const REASON_FILE_HASH: &str = "file_hash";
+
+const DUPLICATE_SONG_COLUMNS: &str = "
+    SELECT
+        m.song_id, m.title, m.artist, m.source,
+        CAST(m.duration_secs AS REAL) AS duration_secs,
+        m.file_path, m.format, m.added_at,
+        (SELECT COUNT(*) FROM playlist_music pm WHERE pm.song_id = m.song_id) AS playlist_count,
+        (SELECT COUNT(*) FROM play_event e WHERE e.song_id = m.song_id) AS play_count
+    FROM music m";
+
+/// 简单的并查集，用于把两两重复的关系合并成组
+struct DisjointSet {
+    parent: Vec<usize>,
+}
+
+impl DisjointSet {
+    fn new(len: usize) -> Self {
+        Self {
+            parent: (0..len).collect(),
+        }
+    }
+
+    fn find(&mut self, i: usize) -> usize {
+        let mut root = i;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        let mut i = i;
+        while self.parent[i] != root {
+            let next = self.parent[i];
+            self.parent[i] = root;
+            i = next;
+        }
+        root
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (ra, rb) = (self.find(a), self.find(b));
+        if ra != rb {
+            self.parent[rb] = ra;
+        }
+    }
+}
+
+/// 补上缓存文件的大小。文件已经不存在的歌曲按未缓存处理
+async fn fill_file_sizes(songs: &mut [DuplicateSong]) {
+    for song in songs.iter_mut() {
+        let Some(path) = song.file_path.as_deref().filter(|p| !p.is_empty()) else {
+            continue;
+        };
+        match tokio::fs::metadata(path).await {
+            Ok(meta) if meta.is_file() => song.file_size_bytes = meta.len(),
+            _ => song.file_path = None,
+        }
+    }
+}
+
+fn durations_close(a: Option<f64>, b: Option<f64>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) if a > 0.0 && b > 0.0 => (a - b).abs() <= DURATION_TOLERANCE_SECS,
+        // 缺少时长信息时只看标题和歌手
+        _ => true,
+    }
+}
+
+/// 按 (标题, 歌手) 分桶后两两比较时长
+fn metadata_pairs(songs: &[DuplicateSong]) -> Vec<(usize, usize)> {
+    let mut buckets: HashMap<(String, String), Vec<usize>> = HashMap::new();
+    for (i, song) in songs.iter().enumerate() {
+        let title = normalize_for_match(&song.title);
+        if title.is_empty() {
+            continue;
+        }
+        buckets
+            .entry((title, normalize_for_match(&song.artist)))
+            .or_default()
+            .push(i);
+    }
+
+    let mut pairs = Vec::new();
+    for indices in buckets.values().filter(|v| v.len() > 1) {
+        for (n, &a) in indices.iter().enumerate() {
+            for &b in &indices[n + 1..] {
+                if durations_close(songs[a].duration_secs, songs[b].duration_secs) {
+                    pairs.push((a, b));
+                }
+            }
+        }
+    }
+    pairs
+}
+
+/// 只有大小相同的文件才可能内容相同，先按大小分桶，避免对整个缓存目录计算哈希
+async fn file_hash_pairs(songs: &[DuplicateSong]) -> Result<Vec<(usize, usize)>, String> {
+    let mut by_size: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (i, song) in songs.iter().enumerate() {
+        if song.file_path.is_some() && song.file_size_bytes > 0 {
+            by_size.entry(song.file_size_bytes).or_default().push(i);
+        }
+    }
+    let to_hash: Vec<(usize, String)> = by_size
+        .into_values()
+        .filter(|v| v.len() > 1)
+        .flatten()
+        .filter_map(|i| songs[i].file_path.clone().map(|p| (i, p)))
+        .collect();
+    if to_hash.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let hashes = tokio::task::spawn_blocking(move || {
+        to_hash
+            .into_iter()
+            .filter_map(|(i, path)| calculate_file_hash(&path).ok().map(|hash| (hash, i)))
+            .collect::<Vec<_>>()
+    })
+    .await
+    .map_err(|e| format!("计算文件哈希失败: {}", e))?;
+
+    let mut first_by_hash: HashMap<String, usize> = HashMap::new();
+    let mut pairs = Vec::new();
+    for (hash, i) in hashes {
+        match first_by_hash.get(&hash) {
+            Some(&first) => pairs.push((first, i)),
+            None => {
+                first_by_hash.insert(hash, i);
+            }
+        }
+    }
+    Ok(pairs)
+}
+
+/// 建议保留的歌曲：有缓存的优先，其次是所在歌单更多、播放次数更多、更早加入曲库的
+fn suggested_keep(songs: &[DuplicateSong]) -> &DuplicateSong {
+    songs
+        .iter()
+        .min_by(|a, b| {
+            b.file_path
+                .is_some()
+                .cmp(&a.file_path.is_some())
+                .then(b.playlist_count.cmp(&a.playlist_count))
+                .then(b.play_count.cmp(&a.play_count))
+                .then(a.added_at.cmp(&b.added_at))
+        })
+        .expect("duplicate group is never empty")
+}
+
+pub async fn find_duplicate_groups(pool: &DbPool) -> Result<Vec<DuplicateGroup>, String> {
+    let mut songs: Vec<DuplicateSong> =
+        sqlx::query_as(&format!("{} ORDER BY m.added_at", DUPLICATE_SONG_COLUMNS))
+            .fetch_all(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+    fill_file_sizes(&mut songs).await;
+
+    let mut set = DisjointSet::new(songs.len());
+    let mut edges: Vec<(usize, &str)> = Vec::new();
+    for (a, b) in metadata_pairs(&songs) {
+        set.union(a, b);
+        edges.push((a, REASON_METADATA));
+    }
+    for (a, b) in file_hash_pairs(&songs).await? {
+        set.union(a, b);
+        edges.push((a, REASON_FILE_HASH));
+    }
+
+    let mut reasons: HashMap<usize, BTreeSet<&str>> = HashMap::new();
+    for (i, reason) in edges {
+        let root = set.find(i);
+        reasons.entry(root).or_default().insert(reason);
+    }
+
+    let mut members: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..songs.len() {
+        let root = set.find(i);
+        if reasons.contains_key(&root) {
+            members.entry(root).or_default().push(i);
+        }
+    }
+
+    let mut slots: Vec<Option<DuplicateSong>> = songs.into_iter().map(Some).collect();
+    let mut groups: Vec<DuplicateGroup> = members
+        .into_iter()
+        .map(|(root, indices)| {
+            let songs: Vec<DuplicateSong> = indices
+                .into_iter()
+                .filter_map(|i| slots[i].take())
+                .collect();
+            DuplicateGroup {
+                reasons: reasons[&root].iter().map(|r| r.to_string()).collect(),
+                keep_song_id: suggested_keep(&songs).song_id.clone(),
+                songs,
+            }
+        })
+        .collect();
+    groups.sort_by(|a, b| {
+        a.songs[0]
+            .title
+            .to_lowercase()
+            .cmp(&b.songs[0].title.to_lowercase())
+    });
+    Ok(groups)
+}
+
+fn is_lossless(song: &DuplicateSong) -> bool {
+    matches!(
+        song.format.as_deref().and_then(AudioFormat::from_extension),
+        Some(AudioFormat::Flac | AudioFormat::Wav)
+    )
+}
+
+/// 保留无损格式，其次是体积更大 (通常码率更高) 的缓存文件
+fn best_cached(songs: &[DuplicateSong]) -> Option<&DuplicateSong> {
+    songs
+        .iter()
+        .filter(|s| s.file_path.is_some())
+        .max_by_key(|s| (is_lossless(s), s.file_size_bytes))
+}
+
+fn push_id_list(builder: &mut QueryBuilder<'_, Sqlite>, song_ids: &[String]) {
+    builder.push("(");
+    let mut separated = builder.separated(", ");
+    for id in song_ids {
+        separated.push_bind(id.clone());
+    }
+    builder.push(")");
+}
+
+/// 把重复歌曲在歌单、回收站、播放记录、播放队列中的引用改到保留的歌曲上
+async fn move_references(
+    conn: &mut SqliteConnection,
+    keep_song_id: &str,
+    duplicate_id: &str,
+) -> Result<(), sqlx::Error> {
+    // 保留的歌曲已经在歌单中时忽略，避免违反 (playlist_id, song_id) 主键
+    sqlx::query(
+        "INSERT OR IGNORE INTO playlist_music (playlist_id, song_id, position, added_to_list_at)
+         SELECT playlist_id, ?, position, added_to_list_at FROM playlist_music WHERE song_id = ?",
+    )
+    .bind(keep_song_id)
+    .bind(duplicate_id)
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query("DELETE FROM playlist_music WHERE song_id = ?")
+        .bind(duplicate_id)
+        .execute(&mut *conn)
+        .await?;
+
+    // 从歌单中移出的记录也要改过来，否则删除歌曲时会被外键级联删除。
+    // 保留的歌曲在同一歌单中已有回收站记录时以它为准
+    sqlx::query("UPDATE OR IGNORE playlist_music_trash SET song_id = ? WHERE song_id = ?")
+        .bind(keep_song_id)
+        .bind(duplicate_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query("DELETE FROM playlist_music_trash WHERE song_id = ?")
+        .bind(duplicate_id)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query("UPDATE play_event SET song_id = ? WHERE song_id = ?")
+        .bind(keep_song_id)
+        .bind(duplicate_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query("UPDATE play_queue SET song_id = ? WHERE song_id = ?")
+        .bind(keep_song_id)
+        .bind(duplicate_id)
+        .execute(&mut *conn)
+        .await?;
+
+    // 保留的歌曲缺少的歌词、封面、时长从重复歌曲补上，最后播放时间取较晚的
+    sqlx::query(
+        "UPDATE music SET
+            lyric = COALESCE(NULLIF(music.lyric, ''), d.lyric),
+            cover_url = COALESCE(NULLIF(music.cover_url, ''), d.cover_url),
+            duration_secs = COALESCE(music.duration_secs, d.duration_secs),
+            last_played_at = CASE
+                WHEN d.last_played_at > COALESCE(music.last_played_at, '') THEN d.last_played_at
+                ELSE music.last_played_at
+            END
+         FROM (SELECT lyric, cover_url, duration_secs, last_played_at FROM music WHERE song_id = ?) AS d
+         WHERE music.song_id = ?",
+    )
+    .bind(duplicate_id)
+    .bind(keep_song_id)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// 合并重复歌曲：保留 keep_song_id 这一条记录，其余歌曲的歌单、播放记录合并过来后删除。
+/// 缓存文件只保留质量最好的一份，其余文件从 music_cache 中删除
+pub async fn merge_duplicate_songs(
+    pool: &DbPool,
+    keep_song_id: &str,
+    duplicate_ids: Vec<String>,
+) -> Result<MergeDuplicatesResult, String> {
+    let mut seen = HashSet::new();
+    let duplicate_ids: Vec<String> = duplicate_ids
+        .into_iter()
+        .filter(|id| id != keep_song_id && seen.insert(id.clone()))
+        .collect();
+    if duplicate_ids.is_empty() {
+        return Err("没有需要合并的歌曲".to_string());
+    }
+
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+
+    let mut all_ids = vec![keep_song_id.to_string()];
+    all_ids.extend(duplicate_ids.iter().cloned());
+    let mut builder = QueryBuilder::<Sqlite>::new(DUPLICATE_SONG_COLUMNS);
+    builder.push(" WHERE m.song_id IN ");
+    push_id_list(&mut builder, &all_ids);
+    let mut songs: Vec<DuplicateSong> = builder
+        .build_query_as()
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    if songs.len() != duplicate_ids.len() + 1 {
+        return Err("部分歌曲已不存在，请重新查找重复歌曲".to_string());
+    }
+    fill_file_sizes(&mut songs).await;
+
+    // 正在下载的任务完成后会写入被删除的歌曲，留下无人引用的缓存文件
+    let mut builder = QueryBuilder::<Sqlite>::new(
+        "SELECT COUNT(*) FROM download_job WHERE status = 'running' AND song_id IN ",
+    );
+    push_id_list(&mut builder, &duplicate_ids);
+    let running: i64 = builder
+        .build_query_scalar()
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    if running > 0 {
+        return Err("有歌曲正在下载，请等待下载完成后再合并".to_string());
+    }
+
+    let mut builder = QueryBuilder::<Sqlite>::new(
+        "SELECT DISTINCT playlist_id FROM playlist_music WHERE song_id IN ",
+    );
+    push_id_list(&mut builder, &duplicate_ids);
+    let playlist_ids: Vec<i64> = builder
+        .build_query_scalar()
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    for duplicate_id in &duplicate_ids {
+        move_references(&mut tx, keep_song_id, duplicate_id)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    let best = best_cached(&songs);
+    if let Some(best) = best.filter(|b| b.song_id != keep_song_id) {
+        let path = best.file_path.as_deref().unwrap_or_default();
+        let format = best
+            .format
+            .as_deref()
+            .and_then(AudioFormat::from_extension)
+            .or_else(|| {
+                Path::new(path)
+                    .extension()
+                    .and_then(|ext| AudioFormat::from_extension(&ext.to_string_lossy()))
+            });
+        sqlx::query("UPDATE music SET file_path = ?, format = ?, mime = ? WHERE song_id = ?")
+            .bind(path)
+            .bind(format.map(|f| f.extension()))
+            .bind(format.map(|f| f.mime()))
+            .bind(keep_song_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    let kept_path = best.and_then(|b| b.file_path.clone());
+
+    // download_job、music_fts 中的记录随歌曲一起删除
+    let mut builder = QueryBuilder::<Sqlite>::new("DELETE FROM music WHERE song_id IN ");
+    push_id_list(&mut builder, &duplicate_ids);
+    builder
+        .build()
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    for playlist_id in playlist_ids {
+        compact_positions(&mut tx, playlist_id)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    // 数据库提交后再删除文件，事务失败时不会丢失缓存
+    let mut removed_files = 0;
+    for path in songs.iter().filter_map(|s| s.file_path.as_deref()) {
+        if Some(path) == kept_path.as_deref() {
+            continue;
+        }
+        match tokio::fs::remove_file(path).await {
+            Ok(()) => removed_files += 1,
+            Err(e) => eprintln!("Failed to delete cache file {}: {}", path, e),
+        }
+    }
+
+    Ok(MergeDuplicatesResult {
+        kept_song_id: keep_song_id.to_string(),
+        merged: duplicate_ids.len(),
+        removed_files,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::my_util::test_pool;
+
+    async fn insert_song(
+        pool: &DbPool,
+        song_id: &str,
+        title: &str,
+        duration_secs: Option<i64>,
+        file: Option<(&Path, &str)>,
+    ) {
+        sqlx::query(
+            "INSERT INTO music (song_id, title, artist, url, duration_secs, file_path, format)
+             VALUES (?, ?, '周杰伦', '', ?, ?, ?)",
+        )
+        .bind(song_id)
+        .bind(title)
+        .bind(duration_secs)
+        .bind(file.map(|(path, _)| path.to_string_lossy().into_owned()))
+        .bind(file.map(|(_, format)| format))
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    /// keep 和 dup 两首同名歌曲，两个空歌单
+    async fn setup() -> DbPool {
+        let pool = test_pool().await;
+        for song_id in ["keep", "dup"] {
+            insert_song(&pool, song_id, "晴天", None, None).await;
+        }
+        sqlx::query("INSERT INTO playlist (id, name) VALUES (1, '一'), (2, '二')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        pool
+    }
+
+    async fn playlist_rows(pool: &DbPool) -> Vec<(i64, String, i64)> {
+        sqlx::query_as(
+            "SELECT playlist_id, song_id, position FROM playlist_music ORDER BY playlist_id, position",
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+
+    async fn trash_rows(pool: &DbPool) -> Vec<(i64, String, i64)> {
+        sqlx::query_as(
+            "SELECT playlist_id, song_id, position FROM playlist_music_trash ORDER BY playlist_id",
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+
+    #[test]
+    fn durations_within_tolerance() {
+        assert!(durations_close(Some(200.0), Some(203.0)));
+        assert!(!durations_close(Some(200.0), Some(203.5)));
+        // 缺少时长时不比较
+        assert!(durations_close(None, Some(200.0)));
+        assert!(durations_close(Some(0.0), Some(200.0)));
+    }
+
+    #[test]
+    fn disjoint_set_joins_transitively() {
+        let mut set = DisjointSet::new(5);
+        set.union(0, 1);
+        set.union(2, 3);
+        assert_ne!(set.find(1), set.find(2));
+        set.union(3, 1);
+        let root = set.find(0);
+        assert!((0..4).all(|i| set.find(i) == root));
+        assert_ne!(set.find(4), root);
+    }
+
+    #[tokio::test]
+    async fn groups_by_metadata_and_file_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let (same_a, same_b) = (dir.path().join("a.mp3"), dir.path().join("b.mp3"));
+        std::fs::write(&same_a, b"same audio").unwrap();
+        std::fs::write(&same_b, b"same audio").unwrap();
+        let other = dir.path().join("c.mp3");
+        std::fs::write(&other, b"other audi").unwrap();
+
+        let pool = test_pool().await;
+        insert_song(&pool, "qt1", "晴天", Some(269), None).await;
+        insert_song(&pool, "qt2", "晴天 ", Some(271), None).await;
+        // 时长相差太多，是另一个版本
+        insert_song(&pool, "qt_live", "晴天", Some(320), None).await;
+        // 标题不同但缓存文件内容相同
+        insert_song(&pool, "f1", "七里香", None, Some((&same_a, "mp3"))).await;
+        insert_song(&pool, "f2", "Qi Li Xiang", None, Some((&same_b, "mp3"))).await;
+        // 大小相同但内容不同
+        insert_song(&pool, "f3", "稻香", None, Some((&other, "mp3"))).await;
+
+        let groups = find_duplicate_groups(&pool).await.unwrap();
+        let summary: Vec<(Vec<String>, Vec<&str>, &str)> = groups
+            .iter()
+            .map(|g| {
+                let mut ids: Vec<&str> = g.songs.iter().map(|s| s.song_id.as_str()).collect();
+                ids.sort();
+                (g.reasons.clone(), ids, g.keep_song_id.as_str())
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (vec!["file_hash".to_string()], vec!["f1", "f2"], "f1"),
+                (vec!["metadata".to_string()], vec!["qt1", "qt2"], "qt1"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn merge_rewrites_playlists_without_primary_key_conflicts() {
+        let pool = setup().await;
+        // 歌单 1 同时包含两首，歌单 2 只包含重复的那首
+        sqlx::query(
+            "INSERT INTO playlist_music (playlist_id, song_id, position) VALUES
+                (1, 'keep', 0), (1, 'dup', 1), (2, 'dup', 0);
+             INSERT INTO play_event (song_id, started_at) VALUES ('dup', '2026-01-01T00:00:00Z');
+             INSERT INTO play_queue (position, song_id) VALUES (0, 'dup'), (1, 'keep');",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let result = merge_duplicate_songs(
+            &pool,
+            "keep",
+            vec!["dup".to_string(), "keep".to_string(), "dup".to_string()],
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.merged, 1);
+        assert_eq!(
+            playlist_rows(&pool).await,
+            [(1, "keep".to_string(), 0), (2, "keep".to_string(), 0)]
+        );
+        let events: Vec<String> = sqlx::query_scalar("SELECT song_id FROM play_event")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(events, ["keep"]);
+        let queue: Vec<String> =
+            sqlx::query_scalar("SELECT song_id FROM play_queue ORDER BY position")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(queue, ["keep", "keep"]);
+        let remaining: Vec<String> = sqlx::query_scalar("SELECT song_id FROM music")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(remaining, ["keep"]);
+    }
+
+    #[tokio::test]
+    async fn merge_keeps_trash_entries_of_duplicates() {
+        let pool = setup().await;
+        // 歌单 1 中两首都被移出过，歌单 2 中只移出过重复的那首
+        sqlx::query(
+            "INSERT INTO playlist_music_trash (playlist_id, song_id, position, added_to_list_at) VALUES
+                (1, 'keep', 0, '2026-01-01'), (1, 'dup', 5, '2026-01-01'), (2, 'dup', 3, '2026-01-01')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        merge_duplicate_songs(&pool, "keep", vec!["dup".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(
+            trash_rows(&pool).await,
+            [(1, "keep".to_string(), 0), (2, "keep".to_string(), 3)]
+        );
+    }
+
+    #[tokio::test]
+    async fn merge_keeps_best_cache_file_and_deletes_the_rest() {
+        let dir = tempfile::tempdir().unwrap();
+        let small_mp3 = dir.path().join("small.mp3");
+        let big_mp3 = dir.path().join("big.mp3");
+        let flac = dir.path().join("song.flac");
+        std::fs::write(&small_mp3, vec![0u8; 10]).unwrap();
+        std::fs::write(&big_mp3, vec![0u8; 30]).unwrap();
+        std::fs::write(&flac, vec![1u8; 20]).unwrap();
+
+        let pool = test_pool().await;
+        insert_song(&pool, "keep", "晴天", None, Some((&small_mp3, "mp3"))).await;
+        insert_song(&pool, "big", "晴天", None, Some((&big_mp3, "mp3"))).await;
+        insert_song(&pool, "lossless", "晴天", None, Some((&flac, "flac"))).await;
+        // 缓存文件已经不存在的歌曲按未缓存处理
+        let missing = dir.path().join("missing.mp3");
+        insert_song(&pool, "missing", "晴天", None, Some((&missing, "mp3"))).await;
+
+        let result = merge_duplicate_songs(
+            &pool,
+            "keep",
+            vec![
+                "big".to_string(),
+                "lossless".to_string(),
+                "missing".to_string(),
+            ],
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.merged, 3);
+        assert_eq!(result.removed_files, 2);
+
+        // 无损格式优先于体积更大的文件
+        let (file_path, format, mime): (String, String, String) =
+            sqlx::query_as("SELECT file_path, format, mime FROM music WHERE song_id = 'keep'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(file_path, flac.to_string_lossy());
+        assert_eq!(format, "flac");
+        assert_eq!(mime, "audio/flac");
+        assert!(flac.exists());
+        assert!(!small_mp3.exists());
+        assert!(!big_mp3.exists());
+    }
+
+    #[tokio::test]
+    async fn merge_fills_missing_metadata_from_duplicates() {
+        let pool = setup().await;
+        sqlx::query(
+            "UPDATE music SET lyric = '[00:01.00]故事的小黄花', cover_url = 'https://img/1.jpg',
+                duration_secs = 269, last_played_at = '2026-02-01T00:00:00Z'
+             WHERE song_id = 'dup';
+             UPDATE music SET cover_url = 'https://img/keep.jpg', last_played_at = '2026-01-01T00:00:00Z'
+             WHERE song_id = 'keep';",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        merge_duplicate_songs(&pool, "keep", vec!["dup".to_string()])
+            .await
+            .unwrap();
+        let row: (String, String, i64, String) = sqlx::query_as(
+            "SELECT lyric, cover_url, duration_secs, last_played_at FROM music WHERE song_id = 'keep'",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            row,
+            (
+                "[00:01.00]故事的小黄花".to_string(),
+                "https://img/keep.jpg".to_string(),
+                269,
+                "2026-02-01T00:00:00Z".to_string()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn merge_refuses_missing_songs() {
+        let pool = setup().await;
+        let result = merge_duplicate_songs(&pool, "keep", vec!["gone".to_string()]).await;
+        assert!(result.is_err());
+        let result = merge_duplicate_songs(&pool, "keep", vec!["keep".to_string()]).await;
+        assert_eq!(
+            result.map(|r| r.merged),
+            Err("没有需要合并的歌曲".to_string())
+        );
+    }
+}
@@ -0,0 +1,393 @@
+// src-tauri/src/external_import.rs
+
+//! 从网易云音乐、QQ 音乐导出的歌单 JSON 或 "歌名 - 歌手" 文本列表导入歌单。
+//! 每一首歌按标题/歌手的相似度与曲库匹配，也可以在音源上搜索，
+//! 匹配结果分为已匹配、有歧义 (需要用户确认) 和未找到三类。
+
+use std::{collections::HashSet, path::Path, sync::LazyLock};
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::{
+    model::{ExternalImportReport, ExternalTrack, ExternalTrackMatch, ImportCandidate, Music},
+    my_util::DbPool,
+    playlist::create_playlist_with_songs,
+    source::{self, SourceRegistry, normalize_for_match},
+};
+
+/// 达到这个相似度直接视为同一首歌
+const MATCH_SCORE: f64 = 0.9;
+/// 低于这个相似度的歌曲不作为候选
+const CANDIDATE_MIN_SCORE: f64 = 0.6;
+const MAX_CANDIDATES: usize = 5;
+/// 一次导入最多在音源上搜索的次数，避免短时间内大量请求
+const MAX_SOURCE_SEARCHES: usize = 100;
+/// 时长相差超过这个秒数时降低相似度 (例如现场版)
+const DURATION_TOLERANCE_SECS: f64 = 10.0;
+
+const TITLE_KEYS: [&str; 4] = ["name", "songname", "songName", "title"];
+const ARTIST_KEYS: [&str; 6] = ["ar", "artists", "singer", "singers", "artist", "singerName"];
+const ALBUM_KEYS: [&str; 4] = ["al", "album", "albumname", "albumName"];
+const PLAYLIST_NAME_KEYS: [&str; 4] = ["name", "dissname", "title", "listName"];
+/// QQ 音乐部分导出格式中歌曲信息包在这些字段里
+const TRACK_WRAPPER_KEYS: [&str; 3] = ["songInfo", "song", "data"];
+
+/// 文本列表每行开头的序号，例如 "1. "、"01、"、"3) "
+static LINE_NUMBER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\d+\s*[.、)\]:：]\s*").unwrap());
+/// 标题中的括号部分，例如 "(Live)"、"（伴奏）"、"【官方版】"
+static BRACKETED: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\([^)]*\)|（[^）]*）|\[[^\]]*\]|【[^】]*】").unwrap());
+
+fn string_field<'a>(obj: &'a Value, keys: &[&str]) -> Option<&'a str> {
+    keys.iter()
+        .filter_map(|key| obj.get(*key))
+        .find_map(|v| v.as_str())
+        .filter(|s| !s.trim().is_empty())
+}
+
+/// 歌手字段可能是字符串、字符串数组或 {name} 对象数组
+fn artist_field(obj: &Value) -> String {
+    for key in ARTIST_KEYS {
+        match obj.get(key) {
+            Some(Value::String(s)) if !s.trim().is_empty() => return s.trim().to_string(),
+            Some(Value::Array(list)) => {
+                let names: Vec<&str> = list
+                    .iter()
+                    .filter_map(|a| a.as_str().or_else(|| a.get("name").and_then(Value::as_str)))
+                    .collect();
+                if !names.is_empty() {
+                    return names.join("/");
+                }
+            }
+            Some(Value::Object(_)) => {
+                if let Some(name) = obj[key].get("name").and_then(Value::as_str) {
+                    return name.to_string();
+                }
+            }
+            _ => {}
+        }
+    }
+    String::new()
+}
+
+fn album_field(obj: &Value) -> Option<String> {
+    ALBUM_KEYS.iter().find_map(|key| match obj.get(*key)? {
+        Value::String(s) if !s.is_empty() => Some(s.clone()),
+        Value::Object(_) => obj[*key]
+            .get("name")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        _ => None,
+    })
+}
+
+/// 网易云的 dt、duration 为毫秒，QQ 音乐的 interval 为秒
+fn duration_field(obj: &Value) -> Option<f64> {
+    if let Some(secs) = obj.get("interval").and_then(Value::as_f64) {
+        return Some(secs);
+    }
+    ["dt", "duration"]
+        .iter()
+        .find_map(|key| obj.get(*key).and_then(Value::as_f64))
+        .map(|d| if d > 10_000.0 { d / 1000.0 } else { d })
+}
+
+/// 取出真正包含歌曲信息的对象
+fn unwrap_track(item: &Value) -> &Value {
+    TRACK_WRAPPER_KEYS
+        .iter()
+        .filter_map(|key| item.get(*key))
+        .find(|inner| inner.is_object() && string_field(inner, &TITLE_KEYS).is_some())
+        .unwrap_or(item)
+}
+
+/// 有标题，并且有歌手或时长字段 (用来区分歌手列表 ar: [{name}] 这类数组)
+fn looks_like_track(item: &Value) -> bool {
+    let item = unwrap_track(item);
+    item.is_object()
+        && string_field(item, &TITLE_KEYS).is_some()
+        && (ARTIST_KEYS.iter().any(|k| item.get(*k).is_some())
+            || ["dt", "duration", "interval"]
+                .iter()
+                .any(|k| item.get(*k).is_some()))
+}
+
+/// 深度优先查找第一个歌曲数组，返回 (所在对象中的歌单名, 歌曲数组)
+fn find_track_list(value: &Value) -> Option<(Option<String>, &Vec<Value>)> {
+    match value {
+        Value::Array(items) if items.first().is_some_and(looks_like_track) => Some((None, items)),
+        Value::Array(items) => items.iter().find_map(find_track_list),
+        Value::Object(map) => {
+            let direct = map.values().find_map(|v| match v {
+                Value::Array(items) if items.first().is_some_and(looks_like_track) => Some(items),
+                _ => None,
+            });
+            if let Some(items) = direct {
+                let name = string_field(value, &PLAYLIST_NAME_KEYS).map(str::to_string);
+                return Some((name, items));
+            }
+            map.values().find_map(find_track_list)
+        }
+        _ => None,
+    }
+}
+
+/// 解析网易云/QQ 音乐导出的歌单 JSON，返回 (歌单名, 歌曲)
+pub fn parse_app_json(content: &str) -> Result<(Option<String>, Vec<ExternalTrack>), String> {
+    let value: Value = serde_json::from_str(content.trim_start_matches('\u{feff}'))
+        .map_err(|e| format!("JSON 格式错误: {}", e))?;
+    let (name, items) = find_track_list(&value).ok_or("文件中没有找到歌曲列表".to_string())?;
+
+    let tracks = items
+        .iter()
+        .map(unwrap_track)
+        .filter_map(|item| {
+            Some(ExternalTrack {
+                title: string_field(item, &TITLE_KEYS)?.trim().to_string(),
+                artist: artist_field(item),
+                album: album_field(item),
+                duration_secs: duration_field(item),
+            })
+        })
+        .collect();
+    Ok((name, tracks))
+}
+
+/// 解析每行一首的文本列表，格式为 "歌名 - 歌手"，只有歌名也可以。
+/// 空行和以 # 开头的行会被忽略
+pub fn parse_text_list(content: &str) -> Vec<ExternalTrack> {
+    content
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let line = LINE_NUMBER.replace(line, "");
+            let (title, artist) = [" - ", " – ", " — ", "\t"]
+                .iter()
+                .find_map(|sep| line.split_once(sep))
+                .unwrap_or((&line, ""));
+            ExternalTrack {
+                title: title.trim().to_string(),
+                artist: artist.trim().to_string(),
+                ..Default::default()
+            }
+        })
+        .filter(|track| !track.title.is_empty())
+        .collect()
+}
+
+/// 两个字符串的相似度 (字符二元组的 Dice 系数)
+fn bigram_similarity(a: &str, b: &str) -> f64 {
+    if a == b {
+        return 1.0;
+    }
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len() < 2 || b.len() < 2 {
+        return 0.0;
+    }
+    let bigrams_a: Vec<(char, char)> = a.windows(2).map(|w| (w[0], w[1])).collect();
+    let mut bigrams_b: Vec<(char, char)> = b.windows(2).map(|w| (w[0], w[1])).collect();
+    let total = bigrams_a.len() + bigrams_b.len();
+    let mut shared = 0;
+    for bigram in bigrams_a {
+        if let Some(pos) = bigrams_b.iter().position(|b| *b == bigram) {
+            bigrams_b.swap_remove(pos);
+            shared += 1;
+        }
+    }
+    2.0 * shared as f64 / total as f64
+}
+
+fn title_similarity(a: &str, b: &str) -> f64 {
+    let (na, nb) = (normalize_for_match(a), normalize_for_match(b));
+    if na == nb {
+        return 1.0;
+    }
+    let stripped_a = normalize_for_match(&BRACKETED.replace_all(a, ""));
+    let stripped_b = normalize_for_match(&BRACKETED.replace_all(b, ""));
+    if !stripped_a.is_empty() && stripped_a == stripped_b {
+        return 0.95;
+    }
+    bigram_similarity(&stripped_a, &stripped_b)
+}
+
+fn split_artists(artist: &str) -> Vec<String> {
+    artist
+        .split(['/', '、', ',', '，', '&', ';', '；'])
+        .flat_map(|part| part.split(" feat. ").flat_map(|p| p.split(" ft. ")))
+        .map(normalize_for_match)
+        .filter(|a| !a.is_empty())
+        .collect()
+}
+
+/// 任意一位歌手相同即视为一致；条目没有歌手信息时给中间分
+fn artist_similarity(a: &str, b: &str) -> f64 {
+    let (list_a, list_b) = (split_artists(a), split_artists(b));
+    if list_a.is_empty() {
+        return 0.6;
+    }
+    list_a
+        .iter()
+        .flat_map(|x| list_b.iter().map(move |y| bigram_similarity(x, y)))
+        .fold(0.0, f64::max)
+}
+
+pub fn match_score(track: &ExternalTrack, title: &str, artist: &str, duration: Option<f64>) -> f64 {
+    let mut score = 0.7 * title_similarity(&track.title, title)
+        + 0.3 * artist_similarity(&track.artist, artist);
+    if let (Some(a), Some(b)) = (track.duration_secs, duration)
+        && (a - b).abs() > DURATION_TOLERANCE_SECS
+    {
+        score -= 0.1;
+    }
+    score
+}
+
+#[derive(sqlx::FromRow)]
+struct LibrarySong {
+    song_id: String,
+    title: String,
+    artist: String,
+    source: Option<String>,
+    duration_secs: Option<f64>,
+}
+
+impl LibrarySong {
+    fn candidate(&self, track: &ExternalTrack) -> ImportCandidate {
+        ImportCandidate {
+            song_id: self.song_id.clone(),
+            title: self.title.clone(),
+            artist: self.artist.clone(),
+            source: self.source.clone(),
+            score: match_score(track, &self.title, &self.artist, self.duration_secs),
+        }
+    }
+}
+
+impl From<Music> for LibrarySong {
+    fn from(music: Music) -> Self {
+        Self {
+            song_id: music.song_id,
+            title: music.title,
+            artist: music.artist,
+            source: music.source,
+            duration_secs: music.duration_secs,
+        }
+    }
+}
+
+async fn load_library(pool: &DbPool) -> Result<Vec<LibrarySong>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT song_id, title, artist, source, CAST(duration_secs AS REAL) AS duration_secs FROM music ORDER BY added_at",
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// 合并候选并按相似度排序，同一首歌只保留一次
+fn rank_candidates(mut candidates: Vec<ImportCandidate>) -> Vec<ImportCandidate> {
+    candidates.retain(|c| c.score >= CANDIDATE_MIN_SCORE);
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let mut seen = HashSet::new();
+    candidates.retain(|c| seen.insert(c.song_id.clone()));
+    candidates.truncate(MAX_CANDIDATES);
+    candidates
+}
+
+fn is_confident(candidates: &[ImportCandidate]) -> bool {
+    candidates.first().is_some_and(|c| c.score >= MATCH_SCORE)
+}
+
+/// 导入外部歌单：匹配到的歌曲立即加入新建的歌单，返回完整的匹配报告。
+/// search_source 为 true 时，曲库中找不到的歌曲会在音源上搜索 (搜索结果会存入曲库)
+pub async fn import_external_playlist(
+    pool: &DbPool,
+    registry: &SourceRegistry,
+    path: &str,
+    content: &str,
+    search_source: bool,
+) -> Result<ExternalImportReport, String> {
+    let path = Path::new(path);
+    let is_json = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("json"))
+        || content
+            .trim_start_matches('\u{feff}')
+            .trim_start()
+            .starts_with(['{', '[']);
+    let (name, tracks) = if is_json {
+        parse_app_json(content)?
+    } else {
+        (None, parse_text_list(content))
+    };
+    if tracks.is_empty() {
+        return Err("文件中没有可导入的歌曲".to_string());
+    }
+    let name = name.unwrap_or_else(|| {
+        path.file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("导入的歌单")
+            .to_string()
+    });
+
+    let library = load_library(pool).await.map_err(|e| e.to_string())?;
+    let mut searches = 0;
+    let mut matched = Vec::new();
+    let mut ambiguous = Vec::new();
+    let mut missing = Vec::new();
+    for (index, track) in tracks.iter().enumerate() {
+        let mut candidates = rank_candidates(library.iter().map(|s| s.candidate(track)).collect());
+
+        if search_source && !is_confident(&candidates) && searches < MAX_SOURCE_SEARCHES {
+            searches += 1;
+            let keyword = format!("{} {}", track.title, track.artist);
+            match source::search_music(pool, registry, None, keyword.trim()).await {
+                Ok(result) => {
+                    candidates.extend(
+                        result
+                            .music_list
+                            .into_iter()
+                            .map(|m| LibrarySong::from(m).candidate(track)),
+                    );
+                    candidates = rank_candidates(candidates);
+                }
+                Err(e) => eprintln!("[Import] 搜索 {} 失败: {}", keyword, e),
+            }
+        }
+
+        let entry = ExternalTrackMatch {
+            index,
+            track: track.clone(),
+            candidates,
+        };
+        if is_confident(&entry.candidates) {
+            matched.push(entry);
+        } else if entry.candidates.is_empty() {
+            missing.push(entry.track);
+        } else {
+            ambiguous.push(entry);
+        }
+    }
+
+    let song_ids: Vec<String> = matched
+        .iter()
+        .map(|m| m.candidates[0].song_id.clone())
+        .collect();
+    let playlist_id = create_playlist_with_songs(pool, &name, &song_ids)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(ExternalImportReport {
+        playlist_id,
+        name,
+        total: tracks.len(),
+        matched,
+        ambiguous,
+        missing,
+    })
+}
@@ -1,140 +1,41 @@
 // src-tauri/src/lib.rs
 
+pub mod audio_engine;
+pub mod audio_format;
 pub mod commands;
+pub mod download;
+pub mod download_manager;
+pub mod duplicates;
+pub mod external_import;
 pub mod ffi;
+pub mod library_search;
+pub mod listening_stats;
+pub mod loudness;
+pub mod lyric;
+pub mod media_server;
+pub mod media_session;
 pub mod model;
+#[cfg(target_os = "linux")]
+pub mod mpris;
 pub mod music;
 pub mod music_cache;
 pub mod my_util;
+pub mod pinyin_search;
+pub mod play_history;
+pub mod play_queue;
 pub mod playlist;
+pub mod playlist_io;
+pub mod smart_playlist;
+pub mod source;
+pub mod stream_proxy;
+pub mod tagging;
+pub mod trash;
+#[cfg(desktop)]
+pub mod tray;
 pub mod updater;
 
-use std::{
-    fs::File,
-    io::{Read, Seek, SeekFrom},
-    path::PathBuf,
-};
-
-use crate::my_util::{MEDIA_ADDR, parse_range};
 use tauri::Manager; // 确保导入 Manager
 
-#[cfg(desktop)]
-use tauri::{
-    image::Image,
-    menu::{Menu, MenuItem},
-    tray::{TrayIconBuilder, TrayIconEvent},
-};
-
-fn start_media_server(base_data_path: PathBuf) {
-    // 克隆路径以便在线程中使用
-    let base_path = base_data_path.clone();
-
-    std::thread::spawn(move || {
-        let server_addr = MEDIA_ADDR;
-        let server = match tiny_http::Server::http(server_addr) {
-            Ok(s) => s,
-            Err(e) => {
-                eprintln!("无法启动本地媒体服务器: {}", e);
-                return;
-            }
-        };
-        println!("本地媒体服务器已在 http://{} 启动", server_addr);
-
-        for request in server.incoming_requests() {
-            let requested_url_path = match request.url().split('?').next() {
-                Some(path) => path.trim_start_matches('/'),
-                None => {
-                    let _ = request.respond(tiny_http::Response::empty(404));
-                    continue;
-                }
-            };
-
-            let decoded_path_str = match urlencoding::decode(requested_url_path) {
-                Ok(decoded) => decoded.into_owned(),
-                Err(_) => {
-                    let _ = request.respond(tiny_http::Response::empty(400)); // Bad Request
-                    continue;
-                }
-            };
-
-            // [核心修改 2] 安全和路由检查
-            // 1. 安全检查：防止目录遍历攻击 (e.g., "music_cache/../../database.db")
-            if decoded_path_str.contains("..") {
-                let _ = request.respond(tiny_http::Response::empty(400)); // Bad Request
-                continue;
-            }
-
-            // 2. 路由检查：确保请求的是我们允许的两个缓存目录之一
-            let file_path: PathBuf;
-            if decoded_path_str.starts_with("music_cache/")
-                || decoded_path_str.starts_with("cover_cache/")
-            {
-                // 如果路径合法，则拼接基础路径
-                file_path = base_path.join(&decoded_path_str);
-            } else {
-                // 否则，禁止访问
-                eprintln!("Forbidden access attempt: {}", decoded_path_str);
-                let _ = request.respond(tiny_http::Response::empty(403)); // Forbidden
-                continue;
-            }
-
-            // 后续的文件服务逻辑 (Range Request等) 保持不变
-            if let Ok(mut file) = File::open(&file_path) {
-                let total_size = file.metadata().map(|m| m.len()).unwrap_or(0);
-                let accept_ranges_header =
-                    tiny_http::Header::from_bytes(&b"Accept-Ranges"[..], &b"bytes"[..]).unwrap();
-
-                if let Some(range_header) =
-                    request.headers().iter().find(|h| h.field.equiv("Range"))
-                {
-                    let range_str = range_header.value.as_str();
-                    if let Some(range) = parse_range(range_str, total_size) {
-                        let (start, end) = range;
-                        let len = end - start + 1;
-
-                        let mut buffer = vec![0; len as usize];
-                        if file.seek(SeekFrom::Start(start)).is_ok()
-                            && file.read_exact(&mut buffer).is_ok()
-                        {
-                            let response = tiny_http::Response::from_data(buffer)
-                                .with_status_code(206) // Partial Content
-                                .with_header(
-                                    tiny_http::Header::from_bytes(
-                                        &b"Content-Range"[..],
-                                        format!("bytes {}-{}/{}", start, end, total_size)
-                                            .as_bytes(),
-                                    )
-                                    .unwrap(),
-                                )
-                                .with_header(
-                                    tiny_http::Header::from_bytes(
-                                        &b"Content-Length"[..],
-                                        len.to_string().as_bytes(),
-                                    )
-                                    .unwrap(),
-                                )
-                                .with_header(accept_ranges_header);
-                            let _ = request.respond(response);
-                        } else {
-                            let _ = request.respond(tiny_http::Response::empty(500));
-                        }
-                    } else {
-                        let response =
-                            tiny_http::Response::from_file(file).with_header(accept_ranges_header);
-                        let _ = request.respond(response);
-                    }
-                } else {
-                    let response =
-                        tiny_http::Response::from_file(file).with_header(accept_ranges_header);
-                    let _ = request.respond(response);
-                }
-            } else {
-                let _ = request.respond(tiny_http::Response::empty(404));
-            }
-        }
-    });
-}
-
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let mut builder = tauri::Builder::default();
@@ -154,6 +55,15 @@ pub fn run() {
             // 初始化数据库连接池
             let app_handle = app.handle().clone();
 
+            // 注册音源，搜索/详情/播放地址的解析都在 Rust 端完成
+            app.manage(source::SourceRegistry::new());
+            app.manage(download_manager::DownloadManager::new());
+            app.manage(stream_proxy::InflightDownloads::new());
+            app.manage(audio_engine::NativePlayer::new());
+            app.manage(media_session::MediaSession::new());
+            // 接入系统媒体控制 (Linux 上为 MPRIS)
+            media_session::start(app_handle.clone());
+
             if let Ok(app_data_dir) = app_handle.path().app_data_dir() {
                 // 1. 确保 music_cache 存在
                 let music_cache_dir = app_data_dir.join("music_cache");
@@ -168,11 +78,23 @@ pub fn run() {
                 }
 
                 // 3. 将父目录 (app_data_dir) 传递给服务器，使其可以访问其下的所有子目录
-                start_media_server(app_data_dir);
+                match media_server::start_media_server(app_handle.clone(), app_data_dir) {
+                    Ok(info) => {
+                        app.manage(info);
+                    }
+                    Err(e) => eprintln!("严重错误：{}", e),
+                }
             } else {
                 eprintln!("严重错误：无法获取 app_data_dir！");
             }
 
+            // --- 仅桌面端的 Setup 逻辑 ---
+            #[cfg(desktop)]
+            {
+                // 托盘菜单：播放控制和最近歌单，最近歌单在数据库就绪后填充
+                tray::create(app.handle())?;
+            }
+
             tauri::async_runtime::spawn(async move {
                 // 1. 初始化数据库连接池
                 let pool = my_util::init_db_pool(&app_handle)
@@ -182,50 +104,33 @@ pub fn run() {
                 // 2. 将连接池纳入 Tauri 的状态管理
                 app_handle.manage(pool.clone()); // 克隆一份 pool 给状态管理
 
+                // 启动后台下载队列，继续上次未完成的任务
+                download_manager::start(app_handle.clone(), pool.clone());
+
+                // 恢复上次的播放队列
+                if let Err(e) = play_queue::restore_session(&app_handle, &pool).await {
+                    eprintln!("[Play Queue] Restore failed: {}", e);
+                }
+                #[cfg(desktop)]
+                tray::refresh(&app_handle);
+
                 // 3. 在这里触发自动清理任务
                 //    现在我们可以确信 app_handle 和 pool 都是有效的
                 tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                if let Err(e) = download::cleanup_partial_downloads(&app_handle, &pool).await {
+                    eprintln!("[Partial Cleanup] Failed: {}", e);
+                }
                 if let Err(e) = music_cache::run_auto_cache_cleanup(&app_handle, &pool).await {
                     eprintln!("[Startup Cleanup] Failed: {}", e);
                 }
+                if let Err(e) = trash::purge_expired_trash(&pool).await {
+                    eprintln!("[Trash] Purge failed: {}", e);
+                }
+
+                // 在后台分析缓存歌曲的响度，用于音量均衡
+                loudness::start(pool);
             });
 
-            // --- 仅桌面端的 Setup 逻辑 ---
-            #[cfg(desktop)]
-            {
-                let show = MenuItem::with_id(app, "show", "显示", true, None::<&str>)?;
-                let quit = MenuItem::with_id(app, "quit", "退出", true, None::<&str>)?;
-                let menu = Menu::with_items(app, &[&show, &quit])?;
-
-                let _tray = TrayIconBuilder::new()
-                    .menu(&menu)
-                    .tooltip("MusicBox") // 更新了一下提示文本
-                    .icon(Image::from_bytes(include_bytes!("../icons/icon.png"))?)
-                    .on_menu_event(move |app, event| match event.id().as_ref() {
-                        "quit" => app.exit(0),
-                        "show" => {
-                            if let Some(window) = app.get_webview_window("main") {
-                                let _ = window.show();
-                                let _ = window.set_focus();
-                            }
-                        }
-                        _ => {}
-                    })
-                    .on_tray_icon_event(|tray, event| {
-                        if let TrayIconEvent::Click {
-                            button: tauri::tray::MouseButton::Left,
-                            ..
-                        } = event
-                        {
-                            let app = tray.app_handle();
-                            if let Some(window) = app.get_webview_window("main") {
-                                let _ = window.show();
-                                let _ = window.set_focus();
-                            }
-                        }
-                    })
-                    .build(app)?;
-            }
             Ok(())
         })
         .invoke_handler(commands::get_command_handler());
@@ -0,0 +1,210 @@
+// src-tauri/src/library_search.rs
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::{model::LibrarySearchHit, my_util::DbPool};
+
+/// trigram 分词器无法匹配少于 3 个字符的关键词
+const TRIGRAM_MIN_CHARS: usize = 3;
+/// 关键词前后保留的字符数 (LIKE 回退时由程序生成摘要)
+const SNIPPET_CONTEXT_CHARS: usize = 12;
+const MARK_START: &str = "<mark>";
+const MARK_END: &str = "</mark>";
+const ELLIPSIS: &str = "…";
+
+/// 歌词以 LRC 原文存入索引，摘要中需要去掉时间标签
+static LRC_TIMESTAMP: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[\d{1,3}:\d{1,2}(?:[.:]\d{1,3})?\]").unwrap());
+/// 摘要边界 (省略号或首尾) 处被截断的时间标签，例如 "[00:3…" 或 "…9.62]"
+static PARTIAL_TIMESTAMP: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[[\d:.]*(…|$)|(^|…)[\d:.]*\]").unwrap());
+
+fn clean_snippet(snippet: &str) -> String {
+    let without_tags = LRC_TIMESTAMP.replace_all(snippet, "");
+    let without_tags = PARTIAL_TIMESTAMP.replace_all(&without_tags, "$1$2");
+    without_tags
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 按空白切分关键词，每个词作为 FTS5 短语，多个词之间为 AND
+fn fts_query(terms: &[&str]) -> String {
+    terms
+        .iter()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 转义 LIKE 中的通配符，配合 ESCAPE '\\' 使用
+pub fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+fn like_pattern(term: &str) -> String {
+    format!("%{}%", escape_like(term))
+}
+
+/// 在字符序列中查找 needle (ASCII 不区分大小写，与 SQLite 的 LIKE 一致)
+fn find_chars(haystack: &[char], needle: &[char], from: usize) -> Option<usize> {
+    if needle.is_empty() || haystack.len() < needle.len() {
+        return None;
+    }
+    (from..=haystack.len() - needle.len()).find(|&start| {
+        haystack[start..start + needle.len()]
+            .iter()
+            .zip(needle)
+            .all(|(a, b)| a.eq_ignore_ascii_case(b))
+    })
+}
+
+/// 截取第一个命中位置附近的文本，并标记窗口内所有关键词
+fn build_snippet(text: &str, terms: &[&str]) -> Option<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let needles: Vec<Vec<char>> = terms.iter().map(|t| t.chars().collect()).collect();
+    let first = needles
+        .iter()
+        .filter_map(|needle| find_chars(&chars, needle, 0))
+        .min()?;
+
+    let start = first.saturating_sub(SNIPPET_CONTEXT_CHARS);
+    let end = (first + SNIPPET_CONTEXT_CHARS * 2).min(chars.len());
+    let window = &chars[start..end];
+
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push_str(ELLIPSIS);
+    }
+    let mut pos = 0;
+    while pos < window.len() {
+        let hit = needles
+            .iter()
+            .filter(|needle| find_chars(window, needle, pos) == Some(pos))
+            .map(|needle| needle.len())
+            .max();
+        match hit {
+            Some(len) => {
+                snippet.push_str(MARK_START);
+                snippet.extend(&window[pos..(pos + len).min(window.len())]);
+                snippet.push_str(MARK_END);
+                pos += len;
+            }
+            None => {
+                snippet.push(window[pos]);
+                pos += 1;
+            }
+        }
+    }
+    if end < chars.len() {
+        snippet.push_str(ELLIPSIS);
+    }
+    Some(snippet)
+}
+
+/// 关键词都不少于 3 个字符时走 FTS5 索引，按 bm25 排序 (标题 > 歌手 > 歌词)
+async fn search_fts(
+    pool: &DbPool,
+    terms: &[&str],
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<LibrarySearchHit>, String> {
+    let sql = format!(
+        "SELECT m.*, snippet(music_fts, -1, '{}', '{}', '{}', 24) AS snippet
+         FROM music_fts
+         JOIN music m ON m.song_id = music_fts.song_id
+         WHERE music_fts MATCH ?
+         ORDER BY bm25(music_fts, 0.0, 10.0, 5.0, 1.0)
+         LIMIT ? OFFSET ?",
+        MARK_START, MARK_END, ELLIPSIS
+    );
+    let mut hits = sqlx::query_as::<_, LibrarySearchHit>(&sql)
+        .bind(fts_query(terms))
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    for hit in hits.iter_mut() {
+        hit.snippet = clean_snippet(&hit.snippet);
+    }
+    Ok(hits)
+}
+
+/// 含有短关键词 (常见于两个字的中文歌名) 时回退到 LIKE，按命中的字段排序
+async fn search_like(
+    pool: &DbPool,
+    terms: &[&str],
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<LibrarySearchHit>, String> {
+    let mut builder = sqlx::QueryBuilder::new("SELECT m.*, '' AS snippet FROM music m WHERE 1 = 1");
+    for term in terms {
+        let pattern = like_pattern(term);
+        builder
+            .push(" AND (m.title LIKE ")
+            .push_bind(pattern.clone())
+            .push(" ESCAPE '\\' OR m.artist LIKE ")
+            .push_bind(pattern.clone())
+            .push(" ESCAPE '\\' OR m.lyric LIKE ")
+            .push_bind(pattern)
+            .push(" ESCAPE '\\')");
+    }
+    let first = like_pattern(terms[0]);
+    builder
+        .push(" ORDER BY CASE WHEN m.title LIKE ")
+        .push_bind(first.clone())
+        .push(" ESCAPE '\\' THEN 0 WHEN m.artist LIKE ")
+        .push_bind(first)
+        .push(" ESCAPE '\\' THEN 1 ELSE 2 END, m.title LIMIT ")
+        .push_bind(limit)
+        .push(" OFFSET ")
+        .push_bind(offset);
+
+    let mut hits = builder
+        .build_query_as::<LibrarySearchHit>()
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    for hit in hits.iter_mut() {
+        let lyric = hit.music.lyric.as_deref().map(clean_snippet);
+        hit.snippet = [
+            Some(hit.music.title.as_str()),
+            Some(hit.music.artist.as_str()),
+            lyric.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .find_map(|text| build_snippet(text, terms))
+        .unwrap_or_default();
+    }
+    Ok(hits)
+}
+
+/// 在本地曲库的标题、歌手和歌词中检索，返回按相关度排序的歌曲和高亮摘要
+pub async fn search_library(
+    pool: &DbPool,
+    query: &str,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<LibrarySearchHit>, String> {
+    let terms: Vec<&str> = query.split_whitespace().collect();
+    if terms.is_empty() {
+        return Ok(Vec::new());
+    }
+    let limit = limit.clamp(1, 200);
+    let offset = offset.max(0);
+
+    if terms
+        .iter()
+        .all(|term| term.chars().count() >= TRIGRAM_MIN_CHARS)
+    {
+        search_fts(pool, &terms, limit, offset).await
+    } else {
+        search_like(pool, &terms, limit, offset).await
+    }
+}
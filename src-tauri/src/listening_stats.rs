@@ -0,0 +1,489 @@
+// src-tauri/src/listening_stats.rs
+
+use chrono::{Local, NaiveDate, SecondsFormat, TimeZone, Utc};
+use tauri::AppHandle;
+
+use crate::{
+    model::{ListeningStats, ListeningStreak, TopArtistStat, TopSongStat, YearReview},
+    my_util::{DbPool, resolve_export_dir},
+    play_history::real_listen_sql,
+};
+
+const TOP_LIMIT: i64 = 10;
+const WEEKDAY_NAMES: [&str; 7] = ["周一", "周二", "周三", "周四", "周五", "周六", "周日"];
+
+/// 本地日期 00:00 对应的 UTC 时间，格式与 play_event.started_at 一致
+fn local_day_start_utc(date: NaiveDate) -> Result<String, String> {
+    let midnight = date.and_hms_opt(0, 0, 0).ok_or("无效的日期")?;
+    let local = Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .ok_or_else(|| format!("无法转换本地时间 {}", date))?;
+    Ok(local
+        .with_timezone(&Utc)
+        .to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+fn parse_date(date: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+        .map_err(|e| format!("无效的日期 {}: {}", date, e))
+}
+
+/// 按连续日期分段，返回最长的一段和截止到 anchor (或前一天) 仍在延续的一段
+fn compute_streaks(days: &[NaiveDate], anchor: NaiveDate) -> (ListeningStreak, ListeningStreak) {
+    let streak = |start: NaiveDate, end: NaiveDate| ListeningStreak {
+        days: (end - start).num_days() + 1,
+        start_date: Some(start.to_string()),
+        end_date: Some(end.to_string()),
+    };
+
+    let mut longest = ListeningStreak::default();
+    let mut current = ListeningStreak::default();
+    let mut run_start: Option<NaiveDate> = None;
+
+    for (i, day) in days.iter().enumerate() {
+        let start = match (run_start, i.checked_sub(1).map(|p| days[p])) {
+            (Some(start), Some(prev)) if prev.succ_opt() == Some(*day) => start,
+            _ => *day,
+        };
+        run_start = Some(start);
+
+        let run = streak(start, *day);
+        if run.days > longest.days {
+            longest = run;
+        }
+    }
+
+    // 今天还没有听歌时，截止到昨天的连续记录仍然算作进行中
+    if let (Some(start), Some(last)) = (run_start, days.last())
+        && (*last == anchor || last.succ_opt() == Some(anchor))
+    {
+        current = streak(start, *last);
+    }
+
+    (longest, current)
+}
+
+/// 统计 [start_date, end_date] (本地日期，含两端) 内的收听数据，聚合全部在 SQLite 中完成
+pub async fn get_listening_stats(
+    pool: &DbPool,
+    start_date: &str,
+    end_date: &str,
+) -> Result<ListeningStats, String> {
+    let start = parse_date(start_date)?;
+    let end = parse_date(end_date)?;
+    if end < start {
+        return Err("结束日期不能早于开始日期".to_string());
+    }
+    let range_start = local_day_start_utc(start)?;
+    let range_end = local_day_start_utc(end.succ_opt().ok_or("无效的结束日期")?)?;
+
+    let from = "FROM play_event e JOIN music m ON m.song_id = e.song_id \
+                WHERE e.started_at >= ?1 AND e.started_at < ?2";
+
+    let (total_plays, total_listened_secs, distinct_songs): (i64, f64, i64) =
+        sqlx::query_as(&format!(
+            "SELECT
+                COUNT(CASE WHEN {real} THEN 1 END),
+                COALESCE(SUM(e.listened_secs), 0.0),
+                COUNT(DISTINCT CASE WHEN {real} THEN e.song_id END)
+             {from}",
+            real = real_listen_sql(),
+        ))
+        .bind(&range_start)
+        .bind(&range_end)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let top_songs = sqlx::query_as::<_, TopSongStat>(&format!(
+        "SELECT
+            m.song_id, m.title, m.artist, m.cover_url,
+            SUM(CASE WHEN {real} THEN 1 ELSE 0 END) AS play_count,
+            COALESCE(SUM(e.listened_secs), 0.0) AS listened_secs
+         {from}
+         GROUP BY m.song_id
+         HAVING play_count > 0
+         ORDER BY play_count DESC, listened_secs DESC
+         LIMIT ?3",
+        real = real_listen_sql(),
+    ))
+    .bind(&range_start)
+    .bind(&range_end)
+    .bind(TOP_LIMIT)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let top_artists = sqlx::query_as::<_, TopArtistStat>(&format!(
+        "SELECT
+            m.artist,
+            SUM(CASE WHEN {real} THEN 1 ELSE 0 END) AS play_count,
+            COALESCE(SUM(e.listened_secs), 0.0) AS listened_secs
+         {from}
+         GROUP BY m.artist
+         HAVING play_count > 0
+         ORDER BY play_count DESC, listened_secs DESC
+         LIMIT ?3",
+        real = real_listen_sql(),
+    ))
+    .bind(&range_start)
+    .bind(&range_end)
+    .bind(TOP_LIMIT)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let by_hour: Vec<(i64, f64)> = sqlx::query_as(&format!(
+        "SELECT CAST(strftime('%H', e.started_at, 'localtime') AS INTEGER) AS hour,
+                COALESCE(SUM(e.listened_secs), 0.0)
+         {from}
+         GROUP BY hour"
+    ))
+    .bind(&range_start)
+    .bind(&range_end)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    let mut listened_secs_by_hour = vec![0.0; 24];
+    for (hour, secs) in by_hour {
+        if let Some(slot) = listened_secs_by_hour.get_mut(hour as usize) {
+            *slot = secs;
+        }
+    }
+
+    // SQLite 的 %w 以星期日为 0，这里转换为星期一为 0
+    let by_weekday: Vec<(i64, f64)> = sqlx::query_as(&format!(
+        "SELECT CAST(strftime('%w', e.started_at, 'localtime') AS INTEGER) AS weekday,
+                COALESCE(SUM(e.listened_secs), 0.0)
+         {from}
+         GROUP BY weekday"
+    ))
+    .bind(&range_start)
+    .bind(&range_end)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    let mut listened_secs_by_weekday = vec![0.0; 7];
+    for (weekday, secs) in by_weekday {
+        if let Some(slot) = listened_secs_by_weekday.get_mut(((weekday + 6) % 7) as usize) {
+            *slot = secs;
+        }
+    }
+
+    let listen_days: Vec<String> = sqlx::query_scalar(&format!(
+        "SELECT DISTINCT date(e.started_at, 'localtime') AS day
+         {from} AND {real}
+         ORDER BY day",
+        real = real_listen_sql(),
+    ))
+    .bind(&range_start)
+    .bind(&range_end)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    let listen_days: Vec<NaiveDate> = listen_days
+        .iter()
+        .filter_map(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .collect();
+    let anchor = end.min(Local::now().date_naive());
+    let (longest_streak, current_streak) = compute_streaks(&listen_days, anchor);
+
+    Ok(ListeningStats {
+        start_date: start.to_string(),
+        end_date: end.to_string(),
+        total_plays,
+        total_listened_secs,
+        distinct_songs,
+        top_songs,
+        top_artists,
+        listened_secs_by_hour,
+        listened_secs_by_weekday,
+        longest_streak,
+        current_streak,
+    })
+}
+
+pub async fn get_year_review(pool: &DbPool, year: i32) -> Result<YearReview, String> {
+    let start = NaiveDate::from_ymd_opt(year, 1, 1).ok_or("无效的年份")?;
+    let end = NaiveDate::from_ymd_opt(year, 12, 31).ok_or("无效的年份")?;
+    let stats = get_listening_stats(pool, &start.to_string(), &end.to_string()).await?;
+    Ok(YearReview {
+        year,
+        generated_at: Local::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        stats,
+    })
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+fn format_listen_time(secs: f64) -> String {
+    let minutes = (secs / 60.0).round() as i64;
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{} 分钟", m),
+        (h, 0) => format!("{} 小时", h),
+        (h, m) => format!("{} 小时 {} 分钟", h, m),
+    }
+}
+
+/// 纵向柱状图，高度按最大值等比缩放
+fn render_bars(values: &[f64], labels: &[String]) -> String {
+    let max = values.iter().cloned().fold(0.0, f64::max);
+    let bars: String = values
+        .iter()
+        .zip(labels)
+        .map(|(value, label)| {
+            let height = if max > 0.0 { value / max * 100.0 } else { 0.0 };
+            format!(
+                r#"<div class="bar" title="{label} {time}"><div class="fill" style="height:{height:.1}%"></div><span>{label}</span></div>"#,
+                label = escape_html(label),
+                time = format_listen_time(*value),
+                height = height,
+            )
+        })
+        .collect();
+    format!(r#"<div class="bars">{}</div>"#, bars)
+}
+
+/// 不依赖外部资源的单文件 HTML 报告
+fn render_year_review_html(review: &YearReview) -> String {
+    let stats = &review.stats;
+
+    let top_songs: String = stats
+        .top_songs
+        .iter()
+        .map(|song| {
+            format!(
+                "<li><b>{}</b> - {}<em>{} 次 · {}</em></li>",
+                escape_html(&song.title),
+                escape_html(&song.artist),
+                song.play_count,
+                format_listen_time(song.listened_secs)
+            )
+        })
+        .collect();
+    let top_artists: String = stats
+        .top_artists
+        .iter()
+        .map(|artist| {
+            format!(
+                "<li><b>{}</b><em>{} 次 · {}</em></li>",
+                escape_html(&artist.artist),
+                artist.play_count,
+                format_listen_time(artist.listened_secs)
+            )
+        })
+        .collect();
+
+    let hour_labels: Vec<String> = (0..24).map(|h| h.to_string()).collect();
+    let weekday_labels: Vec<String> = WEEKDAY_NAMES.iter().map(|d| d.to_string()).collect();
+    let streak = &stats.longest_streak;
+    let streak_range = match (&streak.start_date, &streak.end_date) {
+        (Some(start), Some(end)) => format!("{} 至 {}", start, end),
+        _ => "暂无".to_string(),
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="zh-CN">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>MusicBox {year} 年度听歌报告</title>
+<style>
+body {{ margin: 0; padding: 24px; font-family: -apple-system, "PingFang SC", "Microsoft YaHei", sans-serif; background: #fcf0f0; color: #363636; }}
+main {{ max-width: 720px; margin: 0 auto; }}
+h1 {{ color: #F08080; }}
+section {{ background: #fff5f5; border: 1px solid #ffb5b5; border-radius: 12px; padding: 16px 20px; margin-bottom: 16px; }}
+.summary {{ display: flex; flex-wrap: wrap; gap: 16px; }}
+.summary div {{ flex: 1; min-width: 140px; }}
+.summary strong {{ display: block; font-size: 24px; color: #F08080; }}
+li {{ margin: 6px 0; }}
+li em {{ float: right; font-style: normal; color: #888; }}
+.bars {{ display: flex; align-items: flex-end; gap: 4px; height: 140px; }}
+.bar {{ flex: 1; display: flex; flex-direction: column; justify-content: flex-end; height: 100%; text-align: center; font-size: 11px; color: #888; }}
+.fill {{ background: #F08080; border-radius: 3px 3px 0 0; }}
+footer {{ text-align: center; color: #aaa; font-size: 12px; }}
+</style>
+</head>
+<body>
+<main>
+<h1>{year} 年度听歌报告</h1>
+<section class="summary">
+<div>听歌时长<strong>{total_time}</strong></div>
+<div>有效收听<strong>{total_plays} 次</strong></div>
+<div>听过的歌<strong>{distinct_songs} 首</strong></div>
+<div>最长连续<strong>{streak_days} 天</strong><small>{streak_range}</small></div>
+</section>
+<section><h2>最常听的歌曲</h2><ol>{top_songs}</ol></section>
+<section><h2>最常听的歌手</h2><ol>{top_artists}</ol></section>
+<section><h2>一天中的听歌时段</h2>{hour_bars}</section>
+<section><h2>一周中的听歌分布</h2>{weekday_bars}</section>
+<footer>生成于 {generated_at}</footer>
+</main>
+</body>
+</html>
+"#,
+        year = review.year,
+        total_time = format_listen_time(stats.total_listened_secs),
+        total_plays = stats.total_plays,
+        distinct_songs = stats.distinct_songs,
+        streak_days = streak.days,
+        streak_range = streak_range,
+        top_songs = top_songs,
+        top_artists = top_artists,
+        hour_bars = render_bars(&stats.listened_secs_by_hour, &hour_labels),
+        weekday_bars = render_bars(&stats.listened_secs_by_weekday, &weekday_labels),
+        generated_at = escape_html(&review.generated_at),
+    )
+}
+
+/// 将年度报告导出为 JSON 和 HTML 两个文件，保存在导出目录中
+pub async fn export_year_review(
+    app_handle: &AppHandle,
+    pool: &DbPool,
+    year: i32,
+) -> Result<String, String> {
+    let review = get_year_review(pool, year).await?;
+    let (export_dir, _) = resolve_export_dir(app_handle, pool).await?;
+
+    let json = serde_json::to_string_pretty(&review).map_err(|e| e.to_string())?;
+    let json_path = export_dir.join(format!("musicbox_review_{}.json", year));
+    tokio::fs::write(&json_path, json)
+        .await
+        .map_err(|e| format!("写入 {} 失败: {}", json_path.display(), e))?;
+
+    let html_path = export_dir.join(format!("musicbox_review_{}.html", year));
+    tokio::fs::write(&html_path, render_year_review_html(&review))
+        .await
+        .map_err(|e| format!("写入 {} 失败: {}", html_path.display(), e))?;
+
+    Ok(format!("年度报告已导出至 {}", export_dir.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::DateTime;
+
+    use crate::my_util::test_pool;
+
+    fn date(s: &str) -> NaiveDate {
+        parse_date(s).unwrap()
+    }
+
+    fn summary(streak: &ListeningStreak) -> (i64, Option<&str>, Option<&str>) {
+        (
+            streak.days,
+            streak.start_date.as_deref(),
+            streak.end_date.as_deref(),
+        )
+    }
+
+    #[test]
+    fn streaks_continue_across_month_and_year_boundaries() {
+        let days = [
+            "2023-12-30",
+            "2023-12-31",
+            "2024-01-01",
+            "2024-02-27",
+            "2024-02-28",
+            "2024-02-29",
+            "2024-03-01",
+            "2024-03-02",
+        ]
+        .map(date);
+
+        let (longest, current) = compute_streaks(&days, date("2024-03-02"));
+        assert_eq!(
+            summary(&longest),
+            (5, Some("2024-02-27"), Some("2024-03-02"))
+        );
+        assert_eq!(
+            summary(&current),
+            (5, Some("2024-02-27"), Some("2024-03-02"))
+        );
+
+        let (longest, _) = compute_streaks(&days[..3], date("2024-01-01"));
+        assert_eq!(
+            summary(&longest),
+            (3, Some("2023-12-30"), Some("2024-01-01"))
+        );
+    }
+
+    #[test]
+    fn current_streak_ends_yesterday_at_the_latest() {
+        let days = ["2024-01-30", "2024-01-31"].map(date);
+
+        // 今天 (2 月 1 日) 还没有听歌，截止到昨天的连续记录仍然有效
+        let (_, current) = compute_streaks(&days, date("2024-02-01"));
+        assert_eq!(
+            summary(&current),
+            (2, Some("2024-01-30"), Some("2024-01-31"))
+        );
+
+        let (longest, current) = compute_streaks(&days, date("2024-02-02"));
+        assert_eq!(longest.days, 2);
+        assert_eq!(summary(&current), (0, None, None));
+    }
+
+    #[test]
+    fn gaps_split_streaks() {
+        let days = ["2024-01-01", "2024-01-03", "2024-01-04"].map(date);
+        let (longest, current) = compute_streaks(&days, date("2024-01-04"));
+        assert_eq!(
+            summary(&longest),
+            (2, Some("2024-01-03"), Some("2024-01-04"))
+        );
+        assert_eq!(current.days, 2);
+
+        let (longest, current) = compute_streaks(&[], date("2024-01-04"));
+        assert_eq!((longest.days, current.days), (0, 0));
+    }
+
+    /// 本地时间 23:59 和次日 00:01 的收听分别算在两天，连成两天的记录
+    #[tokio::test]
+    async fn listens_around_local_midnight_count_as_two_days() {
+        let pool = test_pool().await;
+        sqlx::query(
+            "INSERT INTO music (song_id, title, artist, url, duration_secs) VALUES ('a', 'a', '', '', 200)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let midnight =
+            DateTime::parse_from_rfc3339(&local_day_start_utc(date("2024-01-01")).unwrap())
+                .unwrap();
+        for started_at in [
+            midnight - chrono::Duration::minutes(1),
+            midnight + chrono::Duration::minutes(1),
+        ] {
+            sqlx::query(
+                "INSERT INTO play_event (song_id, started_at, listened_secs, completed, skipped) VALUES ('a', ?, 60, 0, 0)",
+            )
+            .bind(started_at.with_timezone(&Utc).to_rfc3339_opts(SecondsFormat::Secs, true))
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let stats = get_listening_stats(&pool, "2023-12-01", "2024-01-31")
+            .await
+            .unwrap();
+        assert_eq!(stats.total_plays, 2);
+        assert_eq!(
+            summary(&stats.longest_streak),
+            (2, Some("2023-12-31"), Some("2024-01-01"))
+        );
+        // 统计范围在今天之前，最后一天与范围结束日期不相邻
+        assert_eq!(stats.current_streak.days, 0);
+    }
+}
@@ -0,0 +1,372 @@
+// src-tauri/src/loudness.rs
+
+//! 响度分析：解码已缓存的歌曲，按 EBU R128 计算整体响度和真峰值并保存到 music 表。
+//! 播放时按目标响度换算增益，让不同歌曲的音量保持一致。
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use ebur128::{EbuR128, Mode};
+
+use crate::{audio_engine::AudioFile, model::TrackGain, my_util::DbPool};
+
+/// 目标响度，与 ReplayGain 2.0 的参考响度相同
+pub const TARGET_LUFS: f64 = -18.0;
+const BATCH_SIZE: i64 = 20;
+/// 后台任务每隔这么久检查一次新缓存的歌曲
+const ANALYSIS_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(sqlx::FromRow)]
+struct LoudnessRow {
+    file_path: Option<String>,
+    loudness_lufs: Option<f64>,
+    true_peak: Option<f64>,
+    loudness_analyzed_at: Option<String>,
+}
+
+/// 解码整个文件，返回 (整体响度 LUFS, 线性真峰值)
+pub fn analyze_file(path: &Path) -> Result<(f64, f64), String> {
+    measure(AudioFile::open(path)?)
+}
+
+fn measure(mut file: AudioFile) -> Result<(f64, f64), String> {
+    let mut meter: Option<(EbuR128, usize)> = None;
+    while let Some((rate, channels, samples)) = file.next_buffer()? {
+        if meter.is_none() {
+            let m = EbuR128::new(channels as u32, rate, Mode::I | Mode::TRUE_PEAK)
+                .map_err(|e| format!("初始化响度分析失败: {}", e))?;
+            meter = Some((m, channels));
+        }
+        let (m, meter_channels) = meter.as_mut().unwrap();
+        // 个别文件中途改变声道数，这部分数据无法计入
+        if channels != *meter_channels {
+            continue;
+        }
+        m.add_frames_f32(&samples)
+            .map_err(|e| format!("响度分析失败: {}", e))?;
+    }
+
+    let (meter, channels) = meter.ok_or_else(|| "文件中没有音频数据".to_string())?;
+    let loudness = meter
+        .loudness_global()
+        .map_err(|e| format!("响度分析失败: {}", e))?;
+    // 全程静音时响度为负无穷
+    if !loudness.is_finite() {
+        return Err("歌曲没有声音".to_string());
+    }
+    let mut peak = 0.0f64;
+    for channel in 0..channels {
+        let channel_peak = meter
+            .true_peak(channel as u32)
+            .map_err(|e| format!("真峰值分析失败: {}", e))?;
+        peak = peak.max(channel_peak);
+    }
+    Ok((loudness, peak))
+}
+
+/// 分析一首歌并保存结果。解码失败时也记录分析时间，避免反复分析同一个坏文件；
+/// 文件打不开或格式不支持时不记录，支持了新格式或重新缓存后还会再分析。返回是否记录了结果
+async fn analyze_song(pool: &DbPool, song_id: &str, path: PathBuf) -> Result<bool, String> {
+    let file_path = path.to_string_lossy().to_string();
+    let result = tokio::task::spawn_blocking(move || AudioFile::open(&path).map(measure))
+        .await
+        .map_err(|e| e.to_string())?;
+    let (loudness, peak) = match result {
+        Ok(Ok((loudness, peak))) => (Some(loudness), Some(peak)),
+        Err(e) => {
+            eprintln!("[Loudness] {} 无法打开: {}", song_id, e);
+            return Ok(false);
+        }
+        Ok(Err(e)) => {
+            eprintln!("[Loudness] {} 分析失败: {}", song_id, e);
+            (None, None)
+        }
+    };
+
+    // 分析期间缓存文件可能被替换，这时结果作废
+    sqlx::query(
+        "UPDATE music SET loudness_lufs = ?, true_peak = ?,
+            loudness_analyzed_at = strftime('%Y-%m-%d %H:%M:%f', 'now', 'localtime')
+         WHERE song_id = ? AND file_path = ?",
+    )
+    .bind(loudness)
+    .bind(peak)
+    .bind(song_id)
+    .bind(&file_path)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+/// 分析所有还没有分析过的缓存歌曲，返回分析的数量。
+/// 文件不存在的歌曲跳过，等重新缓存后再分析
+pub async fn analyze_pending(pool: &DbPool) -> Result<usize, String> {
+    let mut analyzed = 0;
+    let mut last_song_id = String::new();
+    loop {
+        let batch: Vec<(String, String)> = sqlx::query_as(
+            "SELECT song_id, file_path FROM music
+             WHERE file_path IS NOT NULL AND file_path != ''
+                AND loudness_analyzed_at IS NULL AND song_id > ?
+             ORDER BY song_id LIMIT ?",
+        )
+        .bind(&last_song_id)
+        .bind(BATCH_SIZE)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+        let Some((song_id, _)) = batch.last() else {
+            break;
+        };
+        last_song_id = song_id.clone();
+
+        for (song_id, file_path) in batch {
+            let path = PathBuf::from(file_path);
+            if !path.exists() {
+                continue;
+            }
+            if analyze_song(pool, &song_id, path).await? {
+                analyzed += 1;
+            }
+        }
+    }
+    Ok(analyzed)
+}
+
+/// 启动后台分析任务：启动时分析一遍，之后定期分析新缓存的歌曲
+pub fn start(pool: DbPool) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            match analyze_pending(&pool).await {
+                Ok(0) => {}
+                Ok(count) => println!("[Loudness] Analyzed {} songs.", count),
+                Err(e) => eprintln!("[Loudness] Analysis failed: {}", e),
+            }
+            tokio::time::sleep(ANALYSIS_INTERVAL).await;
+        }
+    });
+}
+
+/// 按目标响度计算增益，并保证加上增益后真峰值不超过满刻度
+pub fn gain_for(loudness_lufs: f64, true_peak: f64) -> f64 {
+    let gain = TARGET_LUFS - loudness_lufs;
+    if true_peak > 0.0 {
+        gain.min(-20.0 * true_peak.log10())
+    } else {
+        gain
+    }
+}
+
+async fn load_loudness(pool: &DbPool, song_id: &str) -> Result<Option<LoudnessRow>, String> {
+    sqlx::query_as(
+        "SELECT file_path, loudness_lufs, true_peak, loudness_analyzed_at FROM music WHERE song_id = ?",
+    )
+    .bind(song_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// 返回歌曲的音量增益。已缓存但后台任务还没分析到的歌曲当场分析
+pub async fn get_track_gain(pool: &DbPool, song_id: &str) -> Result<TrackGain, String> {
+    let mut row = load_loudness(pool, song_id)
+        .await?
+        .ok_or_else(|| "歌曲不存在".to_string())?;
+
+    let pending_path = row
+        .file_path
+        .as_deref()
+        .filter(|p| !p.is_empty() && row.loudness_analyzed_at.is_none())
+        .map(PathBuf::from)
+        .filter(|p| p.exists());
+    if let Some(path) = pending_path {
+        if !analyze_song(pool, song_id, path).await? {
+            return Ok(TrackGain {
+                song_id: song_id.to_string(),
+                loudness_lufs: None,
+                true_peak: None,
+                gain_db: None,
+                volume_scale: 1.0,
+            });
+        }
+        row = load_loudness(pool, song_id)
+            .await?
+            .ok_or_else(|| "歌曲不存在".to_string())?;
+    }
+
+    let gain_db = row
+        .loudness_lufs
+        .map(|lufs| gain_for(lufs, row.true_peak.unwrap_or(0.0)));
+    Ok(TrackGain {
+        song_id: song_id.to_string(),
+        loudness_lufs: row.loudness_lufs,
+        true_peak: row.true_peak,
+        gain_db,
+        volume_scale: gain_db.map(|db| 10f64.powf(db / 20.0)).unwrap_or(1.0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::f64::consts::TAU;
+
+    use crate::my_util::test_pool;
+
+    /// 写一个 16 位 PCM 的 WAV 文件，内容为 997Hz 正弦波，amplitude 为线性峰值
+    fn write_wav(path: &Path, channels: u16, amplitude: f64) {
+        let sample_rate = 48_000u32;
+        let frames = sample_rate * 3;
+        let data_len = frames * channels as u32 * 2;
+        let mut bytes = Vec::new();
+        bytes.extend(b"RIFF");
+        bytes.extend((36 + data_len).to_le_bytes());
+        bytes.extend(b"WAVEfmt ");
+        bytes.extend(16u32.to_le_bytes());
+        bytes.extend(1u16.to_le_bytes());
+        bytes.extend(channels.to_le_bytes());
+        bytes.extend(sample_rate.to_le_bytes());
+        bytes.extend((sample_rate * channels as u32 * 2).to_le_bytes());
+        bytes.extend((channels * 2).to_le_bytes());
+        bytes.extend(16u16.to_le_bytes());
+        bytes.extend(b"data");
+        bytes.extend(data_len.to_le_bytes());
+        for i in 0..frames {
+            let t = i as f64 / sample_rate as f64;
+            let sample = ((TAU * 997.0 * t).sin() * amplitude * i16::MAX as f64) as i16;
+            for _ in 0..channels {
+                bytes.extend(sample.to_le_bytes());
+            }
+        }
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    async fn insert_song(pool: &DbPool, song_id: &str, path: &Path) {
+        sqlx::query(
+            "INSERT INTO music (song_id, title, artist, url, file_path)
+             VALUES (?, '晴天', '周杰伦', '', ?)",
+        )
+        .bind(song_id)
+        .bind(path.to_string_lossy().into_owned())
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    async fn loudness_row(pool: &DbPool, song_id: &str) -> LoudnessRow {
+        load_loudness(pool, song_id).await.unwrap().unwrap()
+    }
+
+    #[test]
+    fn gain_for_targets_reference_loudness() {
+        assert!((gain_for(-24.0, 0.1) - 6.0).abs() < 1e-9);
+        assert!((gain_for(-10.0, 1.0) + 8.0).abs() < 1e-9);
+        // 没有峰值数据时不限制
+        assert!((gain_for(-30.0, 0.0) - 12.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn gain_for_is_limited_by_true_peak() {
+        // 需要 +12dB，但峰值 0.5 只允许 +6dB
+        let gain = gain_for(-30.0, 0.5);
+        assert!((gain - 6.0206).abs() < 1e-3, "{}", gain);
+        // 已经削波的歌曲即使偏轻也要降低音量
+        assert!(gain_for(-20.0, 2.0) < 0.0);
+    }
+
+    #[test]
+    fn analyze_file_measures_sine() {
+        let dir = tempfile::tempdir().unwrap();
+        let mono = dir.path().join("mono.wav");
+        write_wav(&mono, 1, 0.5);
+        let (loudness, peak) = analyze_file(&mono).unwrap();
+        // -6dBFS 的正弦波单声道为 -9 LUFS 左右
+        assert!((loudness + 9.0).abs() < 0.5, "{}", loudness);
+        assert!((peak - 0.5).abs() < 0.01, "{}", peak);
+
+        // 两个声道的能量相加，响度高 3dB
+        let stereo = dir.path().join("stereo.wav");
+        write_wav(&stereo, 2, 0.5);
+        let (stereo_loudness, stereo_peak) = analyze_file(&stereo).unwrap();
+        assert!((stereo_loudness - loudness - 3.0).abs() < 0.1);
+        assert!((stereo_peak - peak).abs() < 1e-6);
+    }
+
+    #[test]
+    fn analyze_file_rejects_silence_and_unknown_formats() {
+        let dir = tempfile::tempdir().unwrap();
+        let silent = dir.path().join("silent.wav");
+        write_wav(&silent, 2, 0.0);
+        assert_eq!(analyze_file(&silent).unwrap_err(), "歌曲没有声音");
+
+        let garbage = dir.path().join("garbage.mp3");
+        std::fs::write(&garbage, b"not audio").unwrap();
+        assert!(analyze_file(&garbage).is_err());
+        assert!(analyze_file(&dir.path().join("missing.flac")).is_err());
+    }
+
+    #[tokio::test]
+    async fn analyze_pending_records_results_and_keeps_unreadable_songs_pending() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = test_pool().await;
+        let good = dir.path().join("good.wav");
+        write_wav(&good, 2, 0.5);
+        let silent = dir.path().join("silent.wav");
+        write_wav(&silent, 2, 0.0);
+        let garbage = dir.path().join("garbage.ogg");
+        std::fs::write(&garbage, b"not audio").unwrap();
+        insert_song(&pool, "good", &good).await;
+        insert_song(&pool, "silent", &silent).await;
+        insert_song(&pool, "garbage", &garbage).await;
+        insert_song(&pool, "missing", &dir.path().join("missing.wav")).await;
+
+        assert_eq!(analyze_pending(&pool).await.unwrap(), 2);
+
+        let row = loudness_row(&pool, "good").await;
+        assert!(row.loudness_lufs.is_some() && row.true_peak.is_some());
+        assert!(row.loudness_analyzed_at.is_some());
+        // 解码成功但没有声音，记录时间不再分析
+        let row = loudness_row(&pool, "silent").await;
+        assert!(row.loudness_lufs.is_none() && row.loudness_analyzed_at.is_some());
+        // 格式无法识别和文件不存在的歌曲以后还会再分析
+        for song_id in ["garbage", "missing"] {
+            let row = loudness_row(&pool, song_id).await;
+            assert!(row.loudness_analyzed_at.is_none(), "{}", song_id);
+        }
+
+        assert_eq!(analyze_pending(&pool).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn get_track_gain_analyzes_on_demand() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = test_pool().await;
+        let good = dir.path().join("good.wav");
+        write_wav(&good, 1, 0.5);
+        let garbage = dir.path().join("garbage.flac");
+        std::fs::write(&garbage, b"not audio").unwrap();
+        insert_song(&pool, "good", &good).await;
+        insert_song(&pool, "garbage", &garbage).await;
+
+        let gain = get_track_gain(&pool, "good").await.unwrap();
+        let lufs = gain.loudness_lufs.unwrap();
+        let gain_db = gain.gain_db.unwrap();
+        assert!((gain_db - gain_for(lufs, gain.true_peak.unwrap())).abs() < 1e-9);
+        assert!((gain.volume_scale - 10f64.powf(gain_db / 20.0)).abs() < 1e-9);
+        assert!(
+            loudness_row(&pool, "good")
+                .await
+                .loudness_analyzed_at
+                .is_some()
+        );
+
+        let gain = get_track_gain(&pool, "garbage").await.unwrap();
+        assert_eq!(gain.gain_db, None);
+        assert_eq!(gain.volume_scale, 1.0);
+        assert!(get_track_gain(&pool, "nope").await.is_err());
+    }
+}
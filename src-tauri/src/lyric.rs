@@ -0,0 +1,305 @@
+// src-tauri/src/lyric.rs
+
+use crate::model::{LyricLine, LyricMetadata, ParsedLyric};
+
+/// 解析 "mm:ss"、"mm:ss.xx"、"mm:ss.xxx" 以及少见的 "mm:ss:xx" 形式的时间标签，返回毫秒
+fn parse_timestamp(tag: &str) -> Option<u64> {
+    let (minutes, rest) = tag.split_once(':')?;
+    let minutes = minutes.trim().parse::<u64>().ok()?;
+    let rest = rest.trim();
+    let (seconds, fraction) = match rest.split_once(['.', ':']) {
+        Some((seconds, fraction)) => (seconds, fraction),
+        None => (rest, ""),
+    };
+    let seconds = seconds.parse::<u64>().ok()?;
+    if seconds >= 60 || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    // 小数部分按位数换算：".5" 为 500 毫秒，".05" 为 50 毫秒，超过 3 位的部分舍去
+    let fraction_ms = fraction
+        .chars()
+        .chain(std::iter::repeat('0'))
+        .take(3)
+        .collect::<String>()
+        .parse::<u64>()
+        .ok()?;
+    Some(minutes * 60_000 + seconds * 1000 + fraction_ms)
+}
+
+/// 处理 [key:value] 形式的元信息标签，不认识的标签直接忽略
+fn apply_metadata_tag(tag: &str, metadata: &mut LyricMetadata, offset_ms: &mut i64) {
+    let Some((key, value)) = tag.split_once(':') else {
+        return;
+    };
+    let value = value.trim();
+    let value_opt = (!value.is_empty()).then(|| value.to_string());
+    match key.trim().to_ascii_lowercase().as_str() {
+        "ti" => metadata.title = value_opt,
+        "ar" => metadata.artist = value_opt,
+        "al" => metadata.album = value_opt,
+        "by" => metadata.by = value_opt,
+        "offset" => {
+            if let Ok(offset) = value.trim_start_matches('+').parse::<i64>() {
+                *offset_ms = offset;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 解析 LRC 歌词：
+/// 一行可以带多个时间标签；[ti:] 等元信息标签单独成行；
+/// [offset:] 作用于所有时间；时间相同的多行视为原文和翻译。
+/// 不含任何时间标签的歌词按纯文本逐行返回
+pub fn parse_lrc(lyric: &str) -> ParsedLyric {
+    let mut metadata = LyricMetadata::default();
+    let mut offset_ms = 0i64;
+    let mut timed = Vec::new();
+    let mut plain = Vec::new();
+
+    for line in lyric.lines() {
+        let mut rest = line.trim();
+        let mut timestamps = Vec::new();
+        let mut is_metadata = false;
+        while let Some(inner) = rest.strip_prefix('[') {
+            let Some((tag, after)) = inner.split_once(']') else {
+                break;
+            };
+            match parse_timestamp(tag) {
+                Some(ms) => timestamps.push(ms),
+                None if timestamps.is_empty() => {
+                    apply_metadata_tag(tag, &mut metadata, &mut offset_ms);
+                    is_metadata = true;
+                }
+                // 时间标签之后的方括号属于歌词正文
+                None => break,
+            }
+            rest = after;
+        }
+
+        let text = rest.trim();
+        if !timestamps.is_empty() {
+            for ms in timestamps {
+                timed.push((ms, text.to_string()));
+            }
+        } else if !is_metadata && !text.is_empty() {
+            plain.push(text.to_string());
+        }
+    }
+
+    if timed.is_empty() {
+        return ParsedLyric {
+            metadata,
+            offset_ms,
+            synced: false,
+            lines: plain
+                .into_iter()
+                .map(|text| LyricLine {
+                    time_ms: 0,
+                    text,
+                    translation: None,
+                })
+                .collect(),
+        };
+    }
+
+    // 稳定排序，保证同一时间的原文排在翻译之前
+    timed.sort_by_key(|(ms, _)| *ms);
+    let mut lines: Vec<LyricLine> = Vec::new();
+    for (ms, text) in timed {
+        let time_ms = (ms as i64 - offset_ms).max(0) as u64;
+        match lines.last_mut() {
+            Some(last) if last.time_ms == time_ms && !text.is_empty() => {
+                if last.text.is_empty() {
+                    last.text = text;
+                } else {
+                    match last.translation.as_mut() {
+                        Some(translation) => {
+                            translation.push('\n');
+                            translation.push_str(&text);
+                        }
+                        None => last.translation = Some(text),
+                    }
+                }
+            }
+            // 同一时间的空行不影响已有的歌词
+            Some(last) if last.time_ms == time_ms => {}
+            _ => lines.push(LyricLine {
+                time_ms,
+                text,
+                translation: None,
+            }),
+        }
+    }
+
+    ParsedLyric {
+        metadata,
+        offset_ms,
+        synced: true,
+        lines,
+    }
+}
+
+impl ParsedLyric {
+    /// 播放到 position_ms 时应高亮的歌词行下标。第一行之前或没有时间标签时返回 None
+    pub fn line_index_at(&self, position_ms: u64) -> Option<usize> {
+        if !self.synced {
+            return None;
+        }
+        self.lines
+            .partition_point(|line| line.time_ms <= position_ms)
+            .checked_sub(1)
+    }
+
+    /// 去掉时间标签后的纯文本，翻译紧跟在原文之后
+    pub fn plain_text(&self) -> String {
+        self.lines
+            .iter()
+            .flat_map(|line| std::iter::once(line.text.as_str()).chain(line.translation.as_deref()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(parsed: &ParsedLyric) -> Vec<(u64, &str, Option<&str>)> {
+        parsed
+            .lines
+            .iter()
+            .map(|l| (l.time_ms, l.text.as_str(), l.translation.as_deref()))
+            .collect()
+    }
+
+    #[test]
+    fn timestamp_forms() {
+        assert_eq!(parse_timestamp("01:02"), Some(62_000));
+        assert_eq!(parse_timestamp("01:02.5"), Some(62_500));
+        assert_eq!(parse_timestamp("01:02.05"), Some(62_050));
+        assert_eq!(parse_timestamp("01:02.345"), Some(62_345));
+        assert_eq!(parse_timestamp("01:02.3456"), Some(62_345));
+        assert_eq!(parse_timestamp("01:02:34"), Some(62_340));
+        assert_eq!(parse_timestamp("01:60"), None);
+        assert_eq!(parse_timestamp("ti:晴天"), None);
+    }
+
+    #[test]
+    fn multiple_timestamps_on_one_line() {
+        let parsed = parse_lrc("[00:10.00][00:30.00]副歌\n[00:20.00]主歌");
+        assert!(parsed.synced);
+        assert_eq!(
+            summary(&parsed),
+            [
+                (10_000, "副歌", None),
+                (20_000, "主歌", None),
+                (30_000, "副歌", None)
+            ]
+        );
+    }
+
+    #[test]
+    fn metadata_tags() {
+        let parsed = parse_lrc(
+            "[ti:晴天]\n[ar:周杰伦]\n[al:叶惠美]\n[by:]\n[re:编辑器]\n[00:01.00]故事的小黄花",
+        );
+        assert_eq!(parsed.metadata.title.as_deref(), Some("晴天"));
+        assert_eq!(parsed.metadata.artist.as_deref(), Some("周杰伦"));
+        assert_eq!(parsed.metadata.album.as_deref(), Some("叶惠美"));
+        assert_eq!(parsed.metadata.by, None);
+        // 元信息标签不会成为歌词行
+        assert_eq!(summary(&parsed), [(1_000, "故事的小黄花", None)]);
+    }
+
+    #[test]
+    fn brackets_after_timestamp_are_text() {
+        let parsed = parse_lrc("[00:01.00][合唱]一起唱");
+        assert_eq!(summary(&parsed), [(1_000, "[合唱]一起唱", None)]);
+    }
+
+    #[test]
+    fn offset_applies_to_all_lines() {
+        let parsed = parse_lrc("[offset:+500]\n[00:00.20]第一句\n[00:02.00]第二句");
+        assert_eq!(parsed.offset_ms, 500);
+        // 提前显示，不会小于 0
+        assert_eq!(
+            summary(&parsed),
+            [(0, "第一句", None), (1_500, "第二句", None)]
+        );
+
+        let parsed = parse_lrc("[offset:-500]\n[00:02.00]第二句");
+        assert_eq!(parsed.offset_ms, -500);
+        assert_eq!(summary(&parsed), [(2_500, "第二句", None)]);
+    }
+
+    #[test]
+    fn same_timestamp_is_translation() {
+        let parsed = parse_lrc(
+            "[00:01.00]Hello\n[00:01.00]你好\n[00:01.00]哈喽\n[00:03.00]\n[00:03.00]World\n[00:05.00]Bye\n[00:05.00]",
+        );
+        assert_eq!(
+            summary(&parsed),
+            [
+                (1_000, "Hello", Some("你好\n哈喽")),
+                (3_000, "World", None),
+                (5_000, "Bye", None)
+            ]
+        );
+        assert_eq!(parsed.plain_text(), "Hello\n你好\n哈喽\nWorld\nBye");
+    }
+
+    #[test]
+    fn unsynced_lyric_keeps_order() {
+        let parsed = parse_lrc("[ti:晴天]\n第一句\n\n第二句");
+        assert!(!parsed.synced);
+        assert_eq!(summary(&parsed), [(0, "第一句", None), (0, "第二句", None)]);
+        assert_eq!(parsed.line_index_at(10_000), None);
+    }
+
+    #[test]
+    fn line_index_boundaries() {
+        let parsed = parse_lrc("[00:01.00]一\n[00:02.00]二\n[00:03.00]三");
+        assert_eq!(parsed.line_index_at(0), None);
+        assert_eq!(parsed.line_index_at(999), None);
+        assert_eq!(parsed.line_index_at(1_000), Some(0));
+        assert_eq!(parsed.line_index_at(1_999), Some(0));
+        assert_eq!(parsed.line_index_at(2_000), Some(1));
+        assert_eq!(parsed.line_index_at(3_000), Some(2));
+        assert_eq!(parsed.line_index_at(u64::MAX), Some(2));
+        assert_eq!(parse_lrc("").line_index_at(0), None);
+    }
+
+    /// 音源详情页返回的歌词：以 <br /> 分行，带元信息和 offset 标签
+    #[test]
+    fn lyric_from_gequbao_detail_page() {
+        let html = include_str!("../tests/fixtures/gequbao_detail.html");
+        let lyric = crate::source::gequbao::parse_detail_html("7516", html)
+            .unwrap()
+            .lyric
+            .unwrap();
+        let parsed = parse_lrc(&lyric);
+
+        assert!(parsed.synced);
+        assert_eq!(parsed.metadata.title.as_deref(), Some("晴天"));
+        assert_eq!(parsed.metadata.artist.as_deref(), Some("周杰伦"));
+        assert_eq!(parsed.metadata.album.as_deref(), Some("叶惠美"));
+        assert_eq!(parsed.offset_ms, 200);
+        assert_eq!(
+            summary(&parsed),
+            [
+                (0, "晴天 - 周杰伦", None),
+                (29_390, "故事的小黄花", None),
+                (32_900, "从出生那年就飘着", None),
+                (36_430, "童年的荡秋千", None),
+                (39_930, "随记忆一直晃到现在", None),
+            ]
+        );
+
+        assert_eq!(parsed.line_index_at(0), Some(0));
+        assert_eq!(parsed.line_index_at(29_389), Some(0));
+        assert_eq!(parsed.line_index_at(29_390), Some(1));
+        assert_eq!(parsed.line_index_at(36_000), Some(2));
+        assert_eq!(parsed.line_index_at(u64::MAX), Some(4));
+    }
+}
@@ -0,0 +1,712 @@
+// src-tauri/src/media_server.rs
+
+use std::{
+    fs::File,
+    io::{ErrorKind, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use chrono::{DateTime, Utc};
+
+use tauri::AppHandle;
+
+use crate::{audio_format, model::MediaServerInfo, my_util::MEDIA_ADDR, stream_proxy};
+
+/// 允许通过媒体服务器访问的目录 (相对于 app_data_dir)
+const ALLOWED_DIRS: [&str; 2] = ["music_cache/", "cover_cache/"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaMethod {
+    Get,
+    Head,
+    Other,
+}
+
+/// 与 HTTP 库无关的请求，只包含处理需要的部分，方便单独测试
+#[derive(Debug, Clone)]
+pub struct MediaRequest {
+    pub method: MediaMethod,
+    pub url: String,
+    pub range: Option<String>,
+    pub if_none_match: Option<String>,
+    pub if_range: Option<String>,
+    /// 查询参数 token 或请求头 X-Media-Token
+    pub token: Option<String>,
+}
+
+pub enum MediaBody {
+    Empty,
+    /// 从 start 开始读取 len 字节的文件内容
+    File {
+        file: File,
+        start: u64,
+        len: u64,
+    },
+    /// 边下边播时的数据流，len 为空表示长度未知，使用分块传输
+    Stream {
+        reader: Box<dyn Read + Send>,
+        len: Option<u64>,
+    },
+}
+
+pub struct MediaResponse {
+    pub status: u16,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: MediaBody,
+}
+
+impl MediaResponse {
+    pub fn empty(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: MediaBody::Empty,
+        }
+    }
+
+    pub fn with_headers(mut self, headers: Vec<(&'static str, String)>) -> Self {
+        self.headers.extend(headers);
+        self
+    }
+}
+
+/// Range 请求头的解析结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+    /// 闭区间 [start, end]
+    Satisfiable(u64, u64),
+    /// 语法正确但超出文件范围，应返回 416
+    Unsatisfiable,
+    /// 无法识别或包含多个区间，按普通请求返回整个文件
+    Ignored,
+}
+
+/// 解析单个区间的 Range 头，支持 "bytes=100-"、"bytes=100-199" 和后缀形式 "bytes=-500"
+pub fn parse_range(range_str: &str, total_size: u64) -> ByteRange {
+    let Some(spec) = range_str.trim().strip_prefix("bytes=") else {
+        return ByteRange::Ignored;
+    };
+    if spec.contains(',') {
+        return ByteRange::Ignored;
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return ByteRange::Ignored;
+    };
+    let (start, end) = (start.trim(), end.trim());
+
+    if start.is_empty() {
+        // 后缀区间：最后 N 个字节
+        return match end.parse::<u64>() {
+            Ok(0) => ByteRange::Unsatisfiable,
+            Ok(_) if total_size == 0 => ByteRange::Unsatisfiable,
+            Ok(suffix) => ByteRange::Satisfiable(total_size.saturating_sub(suffix), total_size - 1),
+            Err(_) => ByteRange::Ignored,
+        };
+    }
+
+    let Ok(start) = start.parse::<u64>() else {
+        return ByteRange::Ignored;
+    };
+    let end = if end.is_empty() {
+        None
+    } else {
+        match end.parse::<u64>() {
+            Ok(end) if end >= start => Some(end),
+            _ => return ByteRange::Ignored,
+        }
+    };
+
+    if start >= total_size {
+        return ByteRange::Unsatisfiable;
+    }
+    // 结束位置超出文件大小时截断到最后一个字节
+    let end = end.map_or(total_size - 1, |e| e.min(total_size - 1));
+    ByteRange::Satisfiable(start, end)
+}
+
+fn http_date(time: DateTime<Utc>) -> String {
+    time.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+/// If-None-Match 可能包含多个 ETag 或 "*"，弱比较时忽略 W/ 前缀
+fn etag_matches(header: &str, etag: &str) -> bool {
+    header.split(',').map(str::trim).any(|candidate| {
+        candidate == "*" || candidate.trim_start_matches("W/") == etag.trim_start_matches("W/")
+    })
+}
+
+/// 把 URL 路径映射到允许访问的文件，失败时返回对应的错误状态码
+fn resolve_path(base_path: &Path, url: &str) -> Result<PathBuf, u16> {
+    let requested = url.split('?').next().unwrap_or("").trim_start_matches('/');
+    let decoded = urlencoding::decode(requested).map_err(|_| 400u16)?;
+
+    let Some(dir) = ALLOWED_DIRS.iter().find(|dir| decoded.starts_with(*dir)) else {
+        eprintln!("Forbidden access attempt: {}", decoded);
+        return Err(403);
+    };
+
+    // 防止目录遍历攻击 (e.g., "music_cache/../../database.db" 或指向外部的符号链接)：
+    // 比较规范化之后的真实路径，而不是检查字符串
+    let allowed_root = base_path.join(dir).canonicalize().map_err(|_| 404u16)?;
+    let file_path = base_path
+        .join(decoded.as_ref())
+        .canonicalize()
+        .map_err(|_| 404u16)?;
+    if !file_path.starts_with(&allowed_root) {
+        eprintln!("Forbidden access attempt: {}", decoded);
+        return Err(403);
+    }
+    Ok(file_path)
+}
+
+/// 处理一个媒体请求：只读文件，支持 HEAD、条件请求和单区间 Range
+pub fn handle_request(base_path: &Path, request: &MediaRequest) -> MediaResponse {
+    if request.method == MediaMethod::Other {
+        return MediaResponse::empty(405).with_headers(vec![("Allow", "GET, HEAD".to_string())]);
+    }
+
+    match resolve_path(base_path, &request.url) {
+        Ok(path) => serve_file(&path, request),
+        Err(status) => MediaResponse::empty(status),
+    }
+}
+
+/// 返回文件内容，处理 ETag/Last-Modified 条件请求和 Range
+pub fn serve_file(file_path: &Path, request: &MediaRequest) -> MediaResponse {
+    let file = match File::open(file_path) {
+        Ok(file) => file,
+        Err(_) => return MediaResponse::empty(404),
+    };
+    let metadata = match file.metadata() {
+        Ok(metadata) if metadata.is_file() => metadata,
+        _ => return MediaResponse::empty(404),
+    };
+
+    let total_size = metadata.len();
+    let modified = metadata.modified().ok();
+    let mtime_nanos = modified
+        .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_nanos());
+    let etag = format!("\"{:x}-{:x}\"", total_size, mtime_nanos);
+    let last_modified = modified.map(|m| http_date(m.into()));
+
+    let mut headers = vec![
+        ("Accept-Ranges", "bytes".to_string()),
+        (
+            "Content-Type",
+            audio_format::content_type_for_path(file_path).to_string(),
+        ),
+        ("ETag", etag.clone()),
+    ];
+    if let Some(last_modified) = &last_modified {
+        headers.push(("Last-Modified", last_modified.clone()));
+    }
+
+    // 客户端缓存的版本仍然有效
+    if request
+        .if_none_match
+        .as_deref()
+        .is_some_and(|h| etag_matches(h, &etag))
+    {
+        return MediaResponse::empty(304).with_headers(headers);
+    }
+
+    // If-Range 与当前版本不一致时，说明文件已变化，忽略 Range 返回整个文件
+    let range_allowed = match request.if_range.as_deref().map(str::trim) {
+        None => true,
+        Some(value) => value == etag || last_modified.as_deref() == Some(value),
+    };
+
+    let range = match request.range.as_deref() {
+        Some(range) if range_allowed => parse_range(range, total_size),
+        _ => ByteRange::Ignored,
+    };
+
+    match range {
+        ByteRange::Satisfiable(start, end) => {
+            headers.push((
+                "Content-Range",
+                format!("bytes {}-{}/{}", start, end, total_size),
+            ));
+            MediaResponse {
+                status: 206,
+                headers,
+                body: MediaBody::File {
+                    file,
+                    start,
+                    len: end - start + 1,
+                },
+            }
+        }
+        ByteRange::Unsatisfiable => {
+            headers.push(("Content-Range", format!("bytes */{}", total_size)));
+            MediaResponse::empty(416).with_headers(headers)
+        }
+        ByteRange::Ignored => MediaResponse {
+            status: 200,
+            headers,
+            body: MediaBody::File {
+                file,
+                start: 0,
+                len: total_size,
+            },
+        },
+    }
+}
+
+/// 逐字节比较全部内容，耗时不随第一个不同字节的位置变化，避免通过响应时间逐位猜出 token
+fn token_matches(expected: &str, provided: &str) -> bool {
+    let (expected, provided) = (expected.as_bytes(), provided.as_bytes());
+    expected.len() == provided.len()
+        && expected
+            .iter()
+            .zip(provided)
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// 请求没有携带本次启动生成的 token 时返回 401
+fn authorize(request: &MediaRequest, token: &str) -> Result<(), MediaResponse> {
+    match request.token.as_deref() {
+        Some(provided) if token_matches(token, provided) => Ok(()),
+        _ => Err(MediaResponse::empty(401)),
+    }
+}
+
+/// 校验本次启动生成的 token，边下边播的请求交给 stream_proxy，其余按缓存文件处理
+fn route(
+    app_handle: &AppHandle,
+    base_path: &Path,
+    token: &str,
+    request: &MediaRequest,
+) -> MediaResponse {
+    if let Err(response) = authorize(request, token) {
+        return response;
+    }
+
+    let path = request.url.split('?').next().unwrap_or("");
+    match path
+        .trim_start_matches('/')
+        .strip_prefix(stream_proxy::STREAM_PREFIX)
+    {
+        Some(song_id) if request.method != MediaMethod::Other => {
+            match urlencoding::decode(song_id) {
+                Ok(song_id) => stream_proxy::handle_stream(app_handle, &song_id, request),
+                Err(_) => MediaResponse::empty(400),
+            }
+        }
+        _ => handle_request(base_path, request),
+    }
+}
+
+impl MediaRequest {
+    fn from_tiny_http(request: &tiny_http::Request) -> Self {
+        let header = |name: &'static str| {
+            request
+                .headers()
+                .iter()
+                .find(|h| h.field.equiv(name))
+                .map(|h| h.value.as_str().to_string())
+        };
+        let method = match request.method() {
+            tiny_http::Method::Get => MediaMethod::Get,
+            tiny_http::Method::Head => MediaMethod::Head,
+            _ => MediaMethod::Other,
+        };
+        // <audio>/<img> 无法设置请求头，token 通常放在查询参数中
+        let query_token = request
+            .url()
+            .split_once('?')
+            .and_then(|(_, query)| {
+                query
+                    .split('&')
+                    .find_map(|pair| pair.strip_prefix("token="))
+            })
+            .and_then(|t| urlencoding::decode(t).ok())
+            .map(|t| t.into_owned());
+        Self {
+            method,
+            url: request.url().to_string(),
+            range: header("Range"),
+            if_none_match: header("If-None-Match"),
+            if_range: header("If-Range"),
+            token: query_token.or_else(|| header("X-Media-Token")),
+        }
+    }
+}
+
+/// 把 MediaResponse 写回 tiny_http。文件内容按区间流式读取，不会整体读入内存；
+/// HEAD 请求由 tiny_http 自动省略响应体，但保留 Content-Length
+fn respond(request: tiny_http::Request, response: MediaResponse) {
+    let status = tiny_http::StatusCode(response.status);
+    let headers: Vec<tiny_http::Header> = response
+        .headers
+        .iter()
+        .filter_map(|(name, value)| {
+            tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()).ok()
+        })
+        .collect();
+
+    let result = match response.body {
+        MediaBody::File {
+            mut file,
+            start,
+            len,
+        } => {
+            if let Err(e) = file.seek(SeekFrom::Start(start)) {
+                eprintln!("读取媒体文件失败: {}", e);
+                let _ = request.respond(tiny_http::Response::empty(500));
+                return;
+            }
+            let reader = file.take(len);
+            let response =
+                tiny_http::Response::new(status, headers, reader, Some(len as usize), None)
+                    // 始终使用 Content-Length 而不是分块传输，播放器需要知道总长度才能拖动进度
+                    .with_chunked_threshold(usize::MAX);
+            request.respond(response)
+        }
+        MediaBody::Stream { reader, len } => {
+            let response =
+                tiny_http::Response::new(status, headers, reader, len.map(|l| l as usize), None);
+            let response = match len {
+                Some(_) => response.with_chunked_threshold(usize::MAX),
+                None => response,
+            };
+            request.respond(response)
+        }
+        MediaBody::Empty => {
+            let response =
+                tiny_http::Response::new(status, headers, std::io::empty(), Some(0), None);
+            request.respond(response)
+        }
+    };
+
+    // 播放器拖动进度时会主动断开旧连接，这类错误不需要处理
+    match result {
+        Err(e)
+            if !matches!(
+                e.kind(),
+                ErrorKind::BrokenPipe | ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted
+            ) =>
+        {
+            eprintln!("发送媒体响应失败: {}", e);
+        }
+        _ => {}
+    }
+}
+
+/// 优先使用固定端口，让前端保存的地址在重启后依然有效；被占用时由系统分配一个空闲端口
+fn bind_server() -> Result<tiny_http::Server, String> {
+    match tiny_http::Server::http(MEDIA_ADDR) {
+        Ok(server) => Ok(server),
+        Err(e) => {
+            eprintln!("端口 {} 不可用 ({})，改用系统分配的端口", MEDIA_ADDR, e);
+            tiny_http::Server::http("127.0.0.1:0")
+                .map_err(|e| format!("无法启动本地媒体服务器: {}", e))
+        }
+    }
+}
+
+/// 每次启动随机生成，防止本机其他进程读取缓存
+fn generate_token() -> Result<String, String> {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).map_err(|e| format!("生成访问令牌失败: {}", e))?;
+    Ok(hex::encode(bytes))
+}
+
+/// 启动本地媒体服务器，返回实际使用的地址和本次启动的访问令牌
+pub fn start_media_server(
+    app_handle: AppHandle,
+    base_data_path: PathBuf,
+) -> Result<MediaServerInfo, String> {
+    let server = bind_server()?;
+    let server_addr = server
+        .server_addr()
+        .to_ip()
+        .ok_or("无法获取媒体服务器的监听地址".to_string())?;
+    let token = generate_token()?;
+    let info = MediaServerInfo {
+        base_url: format!("http://{}", server_addr),
+        token: token.clone(),
+    };
+    println!("本地媒体服务器已在 {} 启动", info.base_url);
+
+    std::thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let base_path = base_data_path.clone();
+            let app_handle = app_handle.clone();
+            let token = token.clone();
+            // 每个请求在独立线程中处理，避免一个大文件的传输阻塞其他请求 (例如封面)
+            std::thread::spawn(move || {
+                let media_request = MediaRequest::from_tiny_http(&request);
+                let response = route(&app_handle, &base_path, &token, &media_request);
+                respond(request, response);
+            });
+        }
+    });
+
+    Ok(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT: &[u8] = b"0123456789";
+
+    /// 临时的 app_data_dir，music_cache 中有一个 10 字节的文件
+    fn setup() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("music_cache")).unwrap();
+        std::fs::write(dir.path().join("music_cache/song.mp3"), CONTENT).unwrap();
+        std::fs::write(dir.path().join("database.db"), b"secret").unwrap();
+        dir
+    }
+
+    fn request(url: &str) -> MediaRequest {
+        MediaRequest {
+            method: MediaMethod::Get,
+            url: url.to_string(),
+            range: None,
+            if_none_match: None,
+            if_range: None,
+            token: None,
+        }
+    }
+
+    fn header<'a>(response: &'a MediaResponse, name: &str) -> Option<&'a str> {
+        response
+            .headers
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn body(response: MediaResponse) -> Vec<u8> {
+        match response.body {
+            MediaBody::File {
+                mut file,
+                start,
+                len,
+            } => {
+                file.seek(SeekFrom::Start(start)).unwrap();
+                let mut buf = Vec::new();
+                file.take(len).read_to_end(&mut buf).unwrap();
+                buf
+            }
+            MediaBody::Stream { mut reader, .. } => {
+                let mut buf = Vec::new();
+                reader.read_to_end(&mut buf).unwrap();
+                buf
+            }
+            MediaBody::Empty => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn parse_range_forms() {
+        assert_eq!(parse_range("bytes=0-", 10), ByteRange::Satisfiable(0, 9));
+        assert_eq!(parse_range("bytes=2-5", 10), ByteRange::Satisfiable(2, 5));
+        assert_eq!(parse_range("bytes=8-100", 10), ByteRange::Satisfiable(8, 9));
+        // 后缀区间，超过文件大小时返回整个文件
+        assert_eq!(parse_range("bytes=-3", 10), ByteRange::Satisfiable(7, 9));
+        assert_eq!(parse_range("bytes=-30", 10), ByteRange::Satisfiable(0, 9));
+        assert_eq!(parse_range("bytes=-0", 10), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=-3", 0), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=10-", 10), ByteRange::Unsatisfiable);
+        // 无法识别或多个区间时忽略
+        assert_eq!(parse_range("bytes=5-2", 10), ByteRange::Ignored);
+        assert_eq!(parse_range("bytes=0-1,4-5", 10), ByteRange::Ignored);
+        assert_eq!(parse_range("items=0-1", 10), ByteRange::Ignored);
+        assert_eq!(parse_range("bytes=abc", 10), ByteRange::Ignored);
+    }
+
+    #[test]
+    fn get_returns_whole_file() {
+        let dir = setup();
+        let response = handle_request(dir.path(), &request("/music_cache/song.mp3?token=x"));
+        assert_eq!(response.status, 200);
+        assert_eq!(header(&response, "Accept-Ranges"), Some("bytes"));
+        assert_eq!(header(&response, "Content-Type"), Some("audio/mpeg"));
+        assert!(header(&response, "ETag").is_some());
+        assert!(header(&response, "Last-Modified").is_some());
+        assert_eq!(body(response), CONTENT);
+    }
+
+    #[test]
+    fn head_has_same_headers_as_get() {
+        let dir = setup();
+        let get = handle_request(dir.path(), &request("/music_cache/song.mp3"));
+        let head = handle_request(
+            dir.path(),
+            &MediaRequest {
+                method: MediaMethod::Head,
+                ..request("/music_cache/song.mp3")
+            },
+        );
+        assert_eq!(head.status, 200);
+        assert_eq!(head.headers, get.headers);
+        // 响应体由 tiny_http 省略，长度仍用于 Content-Length
+        assert!(matches!(head.body, MediaBody::File { len: 10, .. }));
+    }
+
+    #[test]
+    fn other_methods_are_rejected() {
+        let dir = setup();
+        let response = handle_request(
+            dir.path(),
+            &MediaRequest {
+                method: MediaMethod::Other,
+                ..request("/music_cache/song.mp3")
+            },
+        );
+        assert_eq!(response.status, 405);
+        assert_eq!(header(&response, "Allow"), Some("GET, HEAD"));
+    }
+
+    #[test]
+    fn range_returns_partial_content() {
+        let dir = setup();
+        let response = handle_request(
+            dir.path(),
+            &MediaRequest {
+                range: Some("bytes=2-4".to_string()),
+                ..request("/music_cache/song.mp3")
+            },
+        );
+        assert_eq!(response.status, 206);
+        assert_eq!(header(&response, "Content-Range"), Some("bytes 2-4/10"));
+        assert_eq!(body(response), b"234");
+
+        let response = handle_request(
+            dir.path(),
+            &MediaRequest {
+                range: Some("bytes=-4".to_string()),
+                ..request("/music_cache/song.mp3")
+            },
+        );
+        assert_eq!(response.status, 206);
+        assert_eq!(header(&response, "Content-Range"), Some("bytes 6-9/10"));
+        assert_eq!(body(response), b"6789");
+    }
+
+    #[test]
+    fn range_past_end_is_unsatisfiable() {
+        let dir = setup();
+        let response = handle_request(
+            dir.path(),
+            &MediaRequest {
+                range: Some("bytes=10-".to_string()),
+                ..request("/music_cache/song.mp3")
+            },
+        );
+        assert_eq!(response.status, 416);
+        assert_eq!(header(&response, "Content-Range"), Some("bytes */10"));
+        assert!(matches!(response.body, MediaBody::Empty));
+    }
+
+    #[test]
+    fn matching_etag_returns_not_modified() {
+        let dir = setup();
+        let first = handle_request(dir.path(), &request("/music_cache/song.mp3"));
+        let etag = header(&first, "ETag").unwrap().to_string();
+
+        for if_none_match in [
+            etag.clone(),
+            format!("W/{}", etag),
+            format!("\"x\", {}", etag),
+        ] {
+            let response = handle_request(
+                dir.path(),
+                &MediaRequest {
+                    if_none_match: Some(if_none_match),
+                    ..request("/music_cache/song.mp3")
+                },
+            );
+            assert_eq!(response.status, 304);
+            assert!(matches!(response.body, MediaBody::Empty));
+        }
+
+        let response = handle_request(
+            dir.path(),
+            &MediaRequest {
+                if_none_match: Some("\"other\"".to_string()),
+                ..request("/music_cache/song.mp3")
+            },
+        );
+        assert_eq!(response.status, 200);
+    }
+
+    #[test]
+    fn if_range_mismatch_returns_whole_file() {
+        let dir = setup();
+        let first = handle_request(dir.path(), &request("/music_cache/song.mp3"));
+        let etag = header(&first, "ETag").unwrap().to_string();
+        let ranged = |if_range: &str| MediaRequest {
+            range: Some("bytes=2-4".to_string()),
+            if_range: Some(if_range.to_string()),
+            ..request("/music_cache/song.mp3")
+        };
+
+        let response = handle_request(dir.path(), &ranged(&etag));
+        assert_eq!(response.status, 206);
+
+        let response = handle_request(dir.path(), &ranged("\"stale\""));
+        assert_eq!(response.status, 200);
+        assert_eq!(header(&response, "Content-Range"), None);
+        assert_eq!(body(response), CONTENT);
+    }
+
+    #[test]
+    fn paths_outside_allowed_dirs_are_forbidden() {
+        let dir = setup();
+        for url in [
+            "/database.db",
+            "/music_cache/../database.db",
+            "/music_cache/%2E%2E/database.db",
+            "/cover_cache_evil/x.jpg",
+        ] {
+            let response = handle_request(dir.path(), &request(url));
+            assert_eq!(response.status, 403, "{}", url);
+        }
+        let response = handle_request(dir.path(), &request("/music_cache/missing.mp3"));
+        assert_eq!(response.status, 404);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_escaping_allowed_dir_is_forbidden() {
+        let dir = setup();
+        std::os::unix::fs::symlink(
+            dir.path().join("database.db"),
+            dir.path().join("music_cache/link.mp3"),
+        )
+        .unwrap();
+        let response = handle_request(dir.path(), &request("/music_cache/link.mp3"));
+        assert_eq!(response.status, 403);
+    }
+
+    #[test]
+    fn missing_or_wrong_token_is_unauthorized() {
+        let with_token = |token: Option<&str>| MediaRequest {
+            token: token.map(str::to_string),
+            ..request("/music_cache/song.mp3")
+        };
+
+        for token in [None, Some(""), Some("secret-token-x"), Some("secret-toke")] {
+            let response = authorize(&with_token(token), "secret-token").unwrap_err();
+            assert_eq!(response.status, 401, "{:?}", token);
+            assert!(matches!(response.body, MediaBody::Empty));
+        }
+        assert!(authorize(&with_token(Some("secret-token")), "secret-token").is_ok());
+    }
+
+    #[test]
+    fn token_comparison() {
+        assert!(token_matches("abc", "abc"));
+        assert!(!token_matches("abc", "abd"));
+        assert!(!token_matches("abc", "ab"));
+        assert!(!token_matches("abc", ""));
+    }
+}
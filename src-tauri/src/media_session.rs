@@ -0,0 +1,251 @@
+// src-tauri/src/media_session.rs
+
+//! 系统媒体控制共用的“正在播放”状态。前端在切歌、播放/暂停和跳转进度时调用 update_media_session，
+//! 系统一侧的操作 (Linux 上的 MPRIS、托盘菜单) 以 media-control 事件发给前端，由前端的播放器执行。
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{music::cover_file_name, my_util::DbPool};
+
+pub const MEDIA_CONTROL_EVENT: &str = "media-control";
+
+/// 前端上报的进度与推算的进度相差超过这么多秒时，视为跳转了进度
+const SEEK_TOLERANCE_SECS: f64 = 1.5;
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum MediaControl {
+    Play,
+    Pause,
+    PlayPause,
+    Next,
+    Previous,
+    Stop,
+    Seek {
+        position_secs: f64,
+    },
+    /// 从头播放歌单，来自托盘的最近歌单菜单
+    PlayPlaylist {
+        playlist_id: i64,
+    },
+    /// 显示主窗口，由 Rust 端直接处理
+    Raise,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct NowPlaying {
+    pub song_id: Option<String>,
+    pub title: String,
+    pub artist: String,
+    /// 已缓存的封面为 file:// 地址，否则为远程地址
+    pub art_url: Option<String>,
+    pub duration_secs: Option<f64>,
+    pub playing: bool,
+    position_secs: f64,
+    updated_at: Option<Instant>,
+}
+
+impl NowPlaying {
+    /// 播放中的进度按上次更新后经过的时间推算，前端不需要持续同步进度
+    pub fn position_secs(&self) -> f64 {
+        let elapsed = match (self.playing, self.updated_at) {
+            (true, Some(updated_at)) => updated_at.elapsed().as_secs_f64(),
+            _ => 0.0,
+        };
+        let position = self.position_secs + elapsed;
+        match self.duration_secs {
+            Some(duration) => position.min(duration),
+            None => position,
+        }
+    }
+}
+
+/// 一次更新改变了哪些内容，用于决定要通知客户端的属性
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SessionChange {
+    pub track: bool,
+    pub status: bool,
+    pub seeked: bool,
+}
+
+#[derive(sqlx::FromRow)]
+struct TrackRow {
+    title: String,
+    artist: String,
+    cover_url: Option<String>,
+    duration_secs: Option<f64>,
+}
+
+pub struct MediaSession {
+    now_playing: Arc<Mutex<NowPlaying>>,
+    #[cfg(target_os = "linux")]
+    mpris: std::sync::OnceLock<crate::mpris::MprisServer>,
+}
+
+impl MediaSession {
+    pub fn new() -> Self {
+        Self {
+            now_playing: Arc::new(Mutex::new(NowPlaying::default())),
+            #[cfg(target_os = "linux")]
+            mpris: std::sync::OnceLock::new(),
+        }
+    }
+
+    pub fn now_playing(&self) -> NowPlaying {
+        self.now_playing.lock().unwrap().clone()
+    }
+
+    /// 更新播放状态。track 为 None 表示歌曲没有变化，只更新播放状态和进度
+    fn apply(
+        &self,
+        song_id: Option<String>,
+        track: Option<TrackRow>,
+        art_url: Option<String>,
+        playing: bool,
+        position_secs: f64,
+    ) -> SessionChange {
+        let mut now_playing = self.now_playing.lock().unwrap();
+        let mut change = SessionChange::default();
+        if now_playing.song_id != song_id {
+            change.track = true;
+            now_playing.song_id = song_id;
+            let track = track.unwrap_or(TrackRow {
+                title: String::new(),
+                artist: String::new(),
+                cover_url: None,
+                duration_secs: None,
+            });
+            now_playing.title = track.title;
+            now_playing.artist = track.artist;
+            now_playing.duration_secs = track.duration_secs;
+            now_playing.art_url = art_url;
+        } else if (now_playing.position_secs() - position_secs).abs() > SEEK_TOLERANCE_SECS {
+            change.seeked = true;
+        }
+        if change.track || now_playing.playing != playing {
+            change.status = true;
+        }
+        now_playing.playing = playing;
+        now_playing.position_secs = position_secs.max(0.0);
+        now_playing.updated_at = Some(Instant::now());
+        change
+    }
+
+    async fn notify(&self, change: SessionChange) {
+        #[cfg(target_os = "linux")]
+        if let Some(server) = self.mpris.get() {
+            let position_secs = self.now_playing().position_secs();
+            if let Err(e) = server.notify(change, position_secs).await {
+                eprintln!("[MPRIS] 通知播放状态失败: {}", e);
+            }
+        }
+        #[cfg(not(target_os = "linux"))]
+        let _ = change;
+    }
+}
+
+impl Default for MediaSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 把系统媒体控制的操作交给前端的播放器
+pub fn emit_control(app_handle: &AppHandle, control: MediaControl) {
+    if control == MediaControl::Raise {
+        if let Some(window) = app_handle.get_webview_window("main") {
+            let _ = window.show();
+            let _ = window.set_focus();
+        }
+        return;
+    }
+    if let Err(e) = app_handle.emit(MEDIA_CONTROL_EVENT, control) {
+        eprintln!("发送媒体控制事件失败: {}", e);
+    }
+}
+
+/// 封面已缓存时使用本地文件，系统媒体控制不一定能加载远程图片
+fn art_url_for(app_handle: &AppHandle, song_id: &str, cover_url: Option<&str>) -> Option<String> {
+    let cover_url = cover_url.filter(|url| !url.is_empty())?;
+    let local_path = app_handle
+        .path()
+        .app_data_dir()
+        .ok()
+        .map(|dir| {
+            dir.join("cover_cache")
+                .join(cover_file_name(cover_url, song_id))
+        })
+        .filter(|path| path.exists());
+    match local_path {
+        Some(path) => Some(format!(
+            "file://{}",
+            urlencoding::encode(&path.to_string_lossy()).replace("%2F", "/")
+        )),
+        None if cover_url.starts_with("http") => Some(cover_url.to_string()),
+        None => None,
+    }
+}
+
+/// 前端在切歌、播放/暂停和跳转进度时调用。song_id 为 None 表示停止播放
+pub async fn update_media_session(
+    app_handle: &AppHandle,
+    pool: &DbPool,
+    song_id: Option<String>,
+    playing: bool,
+    position_secs: f64,
+) -> Result<(), String> {
+    let session = app_handle.state::<MediaSession>();
+    let track_changed = session.now_playing().song_id != song_id;
+
+    let mut track = None;
+    let mut art_url = None;
+    if let (true, Some(id)) = (track_changed, song_id.as_deref()) {
+        // duration_secs 列为 INTEGER，整数值不能直接解码为 f64
+        let row: Option<TrackRow> = sqlx::query_as(
+            "SELECT title, artist, cover_url, CAST(duration_secs AS REAL) AS duration_secs
+             FROM music WHERE song_id = ?",
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+        let row = row.ok_or_else(|| "歌曲不存在".to_string())?;
+        art_url = art_url_for(app_handle, id, row.cover_url.as_deref());
+        track = Some(row);
+    }
+
+    let change = session.apply(song_id, track, art_url, playing, position_secs);
+    session.notify(change).await;
+    // 托盘菜单中的播放/暂停和提示文本跟着更新
+    #[cfg(desktop)]
+    if change.track || change.status {
+        crate::tray::refresh(app_handle);
+    }
+    Ok(())
+}
+
+/// 启动系统媒体控制服务。目前只有 Linux 桌面上的 MPRIS
+pub fn start(app_handle: AppHandle) {
+    #[cfg(target_os = "linux")]
+    tauri::async_runtime::spawn(async move {
+        let session = app_handle.state::<MediaSession>();
+        let handle = app_handle.clone();
+        let on_control: crate::mpris::ControlSink =
+            Arc::new(move |control| emit_control(&handle, control));
+        match crate::mpris::MprisServer::start(None, session.now_playing.clone(), on_control).await
+        {
+            Ok(server) => {
+                let _ = session.mpris.set(server);
+            }
+            Err(e) => eprintln!("[MPRIS] 启动失败: {}", e),
+        }
+    });
+    #[cfg(not(target_os = "linux"))]
+    let _ = app_handle;
+}
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize, Serialize, sqlx::FromRow)]
+#[derive(Debug, Clone, Deserialize, Serialize, sqlx::FromRow)]
 pub struct Music {
     pub song_id: String,
     pub title: String,
@@ -18,6 +18,158 @@ pub struct Music {
     pub play_id: Option<String>,
     pub file_path: Option<String>,
     pub last_played_at: Option<String>,
+    // 旧版本导出的数据库和前端传来的对象中可能没有该字段
+    #[serde(default)]
+    #[sqlx(default)]
+    pub source: Option<String>,
+    // 缓存文件的实际容器格式 (同时也是扩展名) 和对应的 MIME 类型，未缓存时为空
+    #[serde(default)]
+    #[sqlx(default)]
+    pub format: Option<String>,
+    #[serde(default)]
+    #[sqlx(default)]
+    pub mime: Option<String>,
+}
+
+/// 本地媒体服务器的实际地址和访问令牌，前端拼接播放/封面地址时使用
+#[derive(Debug, Clone, Serialize)]
+pub struct MediaServerInfo {
+    pub base_url: String,
+    pub token: String,
+}
+
+/// 解析后的一行歌词。双语歌词中与原文时间相同的第二行作为翻译
+#[derive(Debug, Clone, Serialize)]
+pub struct LyricLine {
+    /// 已应用 [offset:] 的开始时间 (毫秒)，无时间标签的纯文本歌词为 0
+    pub time_ms: u64,
+    pub text: String,
+    pub translation: Option<String>,
+}
+
+/// LRC 中的元信息标签 ([ti:] [ar:] [al:] [by:])
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LyricMetadata {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub by: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ParsedLyric {
+    pub metadata: LyricMetadata,
+    /// [offset:] 标签的值 (毫秒)，正数表示歌词提前显示
+    pub offset_ms: i64,
+    /// 是否带有时间标签，为 false 时 lines 按原文顺序排列且不能按播放位置定位
+    pub synced: bool,
+    pub lines: Vec<LyricLine>,
+}
+
+/// 本地曲库检索结果，snippet 中命中的部分用 <mark></mark> 包裹
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct LibrarySearchHit {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub music: Music,
+    pub snippet: String,
+}
+
+/// 汉字/拼音/首字母检索本地歌曲和歌单的结果
+#[derive(Debug, Serialize)]
+pub struct PinyinSearchResult {
+    pub music_list: Vec<Music>,
+    pub playlists: Vec<Playlist>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RecordPlayPayload {
+    pub song_id: String,
+    /// 开始播放的时间 (RFC 3339)，为空时使用当前时间
+    pub started_at: Option<String>,
+    pub listened_secs: f64,
+    #[serde(default)]
+    pub completed: bool,
+    #[serde(default)]
+    pub skipped: bool,
+    pub playlist_id: Option<i64>,
+}
+
+/// 播放历史中的一条记录，附带歌曲信息
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct PlayHistoryItem {
+    pub event_id: i64,
+    pub started_at: String,
+    pub listened_secs: f64,
+    pub completed: bool,
+    pub skipped: bool,
+    pub playlist_id: Option<i64>,
+    #[sqlx(flatten)]
+    pub music: Music,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlayHistoryPage {
+    pub items: Vec<PlayHistoryItem>,
+    pub total: i64,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct TopSongStat {
+    pub song_id: String,
+    pub title: String,
+    pub artist: String,
+    pub cover_url: Option<String>,
+    pub play_count: i64,
+    pub listened_secs: f64,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct TopArtistStat {
+    pub artist: String,
+    pub play_count: i64,
+    pub listened_secs: f64,
+}
+
+/// 连续有收听记录的天数，日期为本地时间 (YYYY-MM-DD)
+#[derive(Debug, Default, Serialize)]
+pub struct ListeningStreak {
+    pub days: i64,
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+}
+
+/// 一段日期范围内的收听统计。
+/// play_count 只计有效收听，listened_secs 包含所有收听 (含跳过) 的实际时长
+#[derive(Debug, Serialize)]
+pub struct ListeningStats {
+    pub start_date: String,
+    pub end_date: String,
+    pub total_plays: i64,
+    pub total_listened_secs: f64,
+    pub distinct_songs: i64,
+    pub top_songs: Vec<TopSongStat>,
+    pub top_artists: Vec<TopArtistStat>,
+    /// 按本地时间 0-23 点统计的收听秒数
+    pub listened_secs_by_hour: Vec<f64>,
+    /// 按星期统计的收听秒数，下标 0 为星期一
+    pub listened_secs_by_weekday: Vec<f64>,
+    pub longest_streak: ListeningStreak,
+    /// 截止到范围结束日 (或今天) 仍在延续的连续收听
+    pub current_streak: ListeningStreak,
+}
+
+#[derive(Debug, Serialize)]
+pub struct YearReview {
+    pub year: i32,
+    pub generated_at: String,
+    pub stats: ListeningStats,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SearchResult {
+    pub music_list: Vec<Music>,
+    pub has_more: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -63,6 +215,11 @@ pub struct PlaylistInfo {
     pub song_count: i64,
 
     pub is_in: bool,
+    /// 智能歌单的歌曲由规则计算，不能手动添加或移除
+    #[sqlx(default)]
+    pub is_smart: bool,
+    /// 歌单内歌曲的排序方式，见 playlist::playlist_order_clause
+    pub sort_mode: String,
 }
 
 #[derive(Debug, serde::Serialize, sqlx::FromRow)]
@@ -134,4 +291,306 @@ pub struct Playlist {
     pub description: Option<String>,
     pub created_at: String,
     pub updated_at: String,
+    #[serde(default)]
+    #[sqlx(default)]
+    pub smart_rules: Option<String>,
+    /// 旧版本导出的数据库中没有这一列
+    #[serde(default)]
+    #[sqlx(default)]
+    pub sort_mode: Option<String>,
+    /// 不为空表示歌单在回收站中
+    #[serde(default)]
+    #[sqlx(default)]
+    pub deleted_at: Option<String>,
+}
+
+/// 智能歌单中的单条规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SmartRule {
+    TitleContains {
+        value: String,
+    },
+    ArtistContains {
+        value: String,
+    },
+    /// 来自指定音源 (例如 "gequbao")
+    Source {
+        value: String,
+    },
+    /// 已缓存到本地
+    CachedOnly,
+    /// N 天内没有有效收听 (包括从未播放过)
+    NotPlayedInDays {
+        days: i64,
+    },
+    /// N 天内有过有效收听
+    PlayedInDays {
+        days: i64,
+    },
+    /// N 天内加入本地曲库
+    AddedInDays {
+        days: i64,
+    },
+    /// 在指定的普通歌单中
+    InPlaylist {
+        playlist_id: i64,
+    },
+    NotInPlaylist {
+        playlist_id: i64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmartMatchMode {
+    /// 满足全部规则
+    #[default]
+    All,
+    /// 满足任意一条规则
+    Any,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmartOrder {
+    #[default]
+    AddedDesc,
+    LastPlayedDesc,
+    Title,
+    Random,
+}
+
+/// 智能歌单的定义，以 JSON 存入 playlist.smart_rules
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmartPlaylistRules {
+    #[serde(default)]
+    pub match_mode: SmartMatchMode,
+    pub rules: Vec<SmartRule>,
+    #[serde(default)]
+    pub order: SmartOrder,
+    /// 最多包含的歌曲数量，为空时不限制
+    pub limit: Option<i64>,
+}
+
+/// 歌单导出的 JSON 文件 (扩展名 .json)，format 固定为 "musicbox-playlist"。
+/// 导入 M3U8/XSPF 时也先转换为这个结构
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistFile {
+    pub format: String,
+    /// 格式版本，目前为 1
+    pub version: u32,
+    pub name: String,
+    /// 导出时间 (RFC 3339)
+    #[serde(default)]
+    pub exported_at: Option<String>,
+    pub tracks: Vec<PlaylistFileTrack>,
+}
+
+/// 歌单文件中的一首歌。除 title 外都可以省略
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PlaylistFileTrack {
+    /// 带音源前缀的 song_id，导入时优先按它匹配
+    #[serde(default)]
+    pub song_id: Option<String>,
+    pub title: String,
+    #[serde(default)]
+    pub artist: String,
+    #[serde(default)]
+    pub duration_secs: Option<f64>,
+    #[serde(default)]
+    pub source: Option<String>,
+    /// 歌曲来源页面 URL
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub cover_url: Option<String>,
+    /// 音频文件位置：相对于歌单文件的路径、绝对路径、file:// 或 http(s) 地址
+    #[serde(default)]
+    pub location: Option<String>,
+}
+
+/// 导入歌单文件的结果
+#[derive(Debug, Serialize)]
+pub struct PlaylistImportReport {
+    pub playlist_id: i64,
+    pub name: String,
+    pub total: usize,
+    /// 与曲库中已有歌曲匹配上的数量
+    pub matched: usize,
+    /// 由本地文件新建的歌曲数量
+    pub added_local: usize,
+    pub unmatched: Vec<PlaylistFileTrack>,
+}
+
+/// 从其他音乐 App 导出的歌单或文本列表中读取的一首歌
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ExternalTrack {
+    pub title: String,
+    pub artist: String,
+    pub album: Option<String>,
+    pub duration_secs: Option<f64>,
+}
+
+/// 曲库 (或音源搜索结果) 中可能对应的歌曲，score 为 0~1 的相似度
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportCandidate {
+    pub song_id: String,
+    pub title: String,
+    pub artist: String,
+    pub source: Option<String>,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExternalTrackMatch {
+    /// 在原歌单中的序号
+    pub index: usize,
+    pub track: ExternalTrack,
+    /// 按相似度从高到低排列，已匹配的条目使用第一个
+    pub candidates: Vec<ImportCandidate>,
+}
+
+/// 导入外部歌单的结果。已匹配的歌曲直接加入新歌单，
+/// 有歧义的条目由用户确认后通过 confirm_import_matches 加入
+#[derive(Debug, Serialize)]
+pub struct ExternalImportReport {
+    pub playlist_id: i64,
+    pub name: String,
+    pub total: usize,
+    pub matched: Vec<ExternalTrackMatch>,
+    pub ambiguous: Vec<ExternalTrackMatch>,
+    pub missing: Vec<ExternalTrack>,
+}
+
+/// 后端保存的播放队列和播放状态，每次修改后通过 play-queue-changed 事件发送给前端
+#[derive(Debug, Clone, Serialize)]
+pub struct PlayQueueState {
+    pub songs: Vec<Music>,
+    /// 正在播放的歌曲在队列中的下标，没有播放时为 -1
+    pub current_index: i64,
+    /// sequence (顺序播放)、single (单曲循环)、shuffle (随机播放)
+    pub play_mode: String,
+    pub position_secs: f64,
+    pub playlist_id: Option<i64>,
+}
+
+/// 原生播放引擎的状态，也是 native-player-position 等事件的负载
+#[derive(Debug, Clone, Serialize)]
+pub struct NativePlayerState {
+    /// 正在播放的歌曲，没有加载歌曲时为 None
+    pub song_id: Option<String>,
+    pub position_secs: f64,
+    pub duration_secs: Option<f64>,
+    pub playing: bool,
+    pub volume: f32,
+}
+
+/// get_track_gain 的返回值，歌曲还没有分析 (或没有缓存) 时各项为 None，volume_scale 为 1
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackGain {
+    pub song_id: String,
+    pub loudness_lufs: Option<f64>,
+    /// 线性真峰值，1.0 为满刻度
+    pub true_peak: Option<f64>,
+    /// 调整到目标响度所需的增益 (dB)，已按真峰值限制，不会因此削波
+    pub gain_db: Option<f64>,
+    /// gain_db 换算成的音量倍数
+    pub volume_scale: f64,
+}
+
+/// 回收站中的歌单
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct TrashedPlaylist {
+    pub id: i64,
+    pub name: String,
+    pub cover_path: Option<String>,
+    pub song_count: i64,
+    pub is_smart: bool,
+    pub deleted_at: String,
+}
+
+/// 从歌单中移除的歌曲，id 为回收站记录的 ID
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct TrashedSong {
+    pub id: i64,
+    pub playlist_id: i64,
+    pub playlist_name: String,
+    pub song_id: String,
+    pub title: String,
+    pub artist: String,
+    pub cover_url: Option<String>,
+    pub deleted_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TrashContents {
+    pub playlists: Vec<TrashedPlaylist>,
+    pub songs: Vec<TrashedSong>,
+    /// 超过这个天数自动彻底删除，0 表示不自动删除
+    pub retention_days: i64,
+}
+
+/// 重复歌曲组中的一首
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct DuplicateSong {
+    pub song_id: String,
+    pub title: String,
+    pub artist: String,
+    pub source: Option<String>,
+    pub duration_secs: Option<f64>,
+    pub file_path: Option<String>,
+    pub format: Option<String>,
+    pub added_at: String,
+    /// 所在歌单的数量
+    pub playlist_count: i64,
+    pub play_count: i64,
+    #[sqlx(skip)]
+    pub file_size_bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DuplicateGroup {
+    /// 判定依据：metadata (标题/歌手/时长一致)、file_hash (缓存文件相同) 或两者
+    pub reasons: Vec<String>,
+    /// 建议保留的歌曲
+    pub keep_song_id: String,
+    pub songs: Vec<DuplicateSong>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MergeDuplicatesResult {
+    pub kept_song_id: String,
+    pub merged: usize,
+    /// 删除的多余缓存文件数量
+    pub removed_files: usize,
+}
+
+// 缓存下载进度事件的负载
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheProgressPayload {
+    pub song_id: String,
+    pub downloaded_bytes: u64,
+    pub total_bytes: Option<u64>,
+    pub finished: bool,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct DownloadJob {
+    pub id: i64,
+    pub song_id: String,
+    pub status: String,
+    pub priority: i64,
+    pub retry_count: i64,
+    pub max_retries: i64,
+    pub last_error: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl DownloadJob {
+    /// 未结束的任务：等待中、下载中或已暂停
+    pub fn is_active(&self) -> bool {
+        matches!(self.status.as_str(), "pending" | "running" | "paused")
+    }
 }
@@ -0,0 +1,613 @@
+// src-tauri/src/mpris.rs
+
+//! Linux 桌面的 MPRIS2 D-Bus 服务，GNOME/KDE 的媒体控制和键盘媒体键通过它控制播放。
+//! 歌曲信息和播放状态读取 MediaSession，收到的 Play/Pause/Next/Previous/Seek 等调用交给 on_control。
+//! start 可以指定总线地址，连接到私有的会话总线上测试。
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use zbus::{
+    Connection, connection,
+    fdo::RequestNameFlags,
+    interface,
+    object_server::SignalEmitter,
+    zvariant::{ObjectPath, OwnedValue, Value},
+};
+
+use crate::media_session::{MediaControl, NowPlaying, SessionChange};
+
+pub const BUS_NAME: &str = "org.mpris.MediaPlayer2.musicbox";
+pub const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+/// 规范中表示“没有歌曲”的 trackid
+const NO_TRACK: &str = "/org/mpris/MediaPlayer2/TrackList/NoTrack";
+
+pub type ControlSink = Arc<dyn Fn(MediaControl) + Send + Sync>;
+
+/// 歌曲的 trackid。D-Bus 对象路径只能包含字母、数字和下划线，所以对 song_id 做十六进制编码。
+/// 空的 song_id 编码后不是合法的路径，当作没有歌曲
+pub fn track_id(song_id: &str) -> ObjectPath<'static> {
+    if song_id.is_empty() {
+        return ObjectPath::from_static_str_unchecked(NO_TRACK);
+    }
+    ObjectPath::from_string_unchecked(format!("/org/musicbox/track/{}", hex::encode(song_id)))
+}
+
+fn to_micros(secs: f64) -> i64 {
+    (secs * 1_000_000.0) as i64
+}
+
+fn owned<'a>(value: impl Into<Value<'a>>) -> OwnedValue {
+    // 只有包含文件描述符的值会转换失败
+    value.into().try_to_owned().expect("元数据中没有文件描述符")
+}
+
+struct Root {
+    on_control: ControlSink,
+}
+
+#[interface(name = "org.mpris.MediaPlayer2")]
+impl Root {
+    fn raise(&self) {
+        (self.on_control)(MediaControl::Raise);
+    }
+
+    /// 关闭窗口只是隐藏到托盘，不允许通过媒体控制退出
+    fn quit(&self) {}
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> String {
+        "MusicBox".to_string()
+    }
+
+    #[zbus(property)]
+    fn desktop_entry(&self) -> String {
+        "musicbox".to_string()
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+struct Player {
+    now_playing: Arc<Mutex<NowPlaying>>,
+    on_control: ControlSink,
+}
+
+impl Player {
+    fn snapshot(&self) -> NowPlaying {
+        self.now_playing.lock().unwrap().clone()
+    }
+
+    /// 按规范，跳到开头之前从头播放，跳过结尾相当于下一首
+    fn seek_to(&self, now_playing: &NowPlaying, position_us: i64) {
+        if now_playing.song_id.is_none() {
+            return;
+        }
+        let position_secs = position_us.max(0) as f64 / 1_000_000.0;
+        let past_end = now_playing
+            .duration_secs
+            .is_some_and(|duration| position_secs > duration);
+        if past_end {
+            (self.on_control)(MediaControl::Next);
+        } else {
+            (self.on_control)(MediaControl::Seek { position_secs });
+        }
+    }
+}
+
+#[interface(name = "org.mpris.MediaPlayer2.Player")]
+impl Player {
+    fn next(&self) {
+        (self.on_control)(MediaControl::Next);
+    }
+
+    fn previous(&self) {
+        (self.on_control)(MediaControl::Previous);
+    }
+
+    fn pause(&self) {
+        (self.on_control)(MediaControl::Pause);
+    }
+
+    fn play_pause(&self) {
+        (self.on_control)(MediaControl::PlayPause);
+    }
+
+    fn stop(&self) {
+        (self.on_control)(MediaControl::Stop);
+    }
+
+    fn play(&self) {
+        (self.on_control)(MediaControl::Play);
+    }
+
+    /// offset 为相对当前进度的微秒数，可以为负
+    fn seek(&self, offset: i64) {
+        let now_playing = self.snapshot();
+        let position = to_micros(now_playing.position_secs()).saturating_add(offset);
+        self.seek_to(&now_playing, position);
+    }
+
+    /// trackid 与当前歌曲不一致说明调用方看到的是旧歌曲，按规范忽略
+    fn set_position(&self, track_id: ObjectPath<'_>, position: i64) {
+        let now_playing = self.snapshot();
+        let is_current = now_playing
+            .song_id
+            .as_deref()
+            .is_some_and(|id| !id.is_empty() && self::track_id(id).as_str() == track_id.as_str());
+        if is_current && position >= 0 {
+            self.seek_to(&now_playing, position);
+        }
+    }
+
+    fn open_uri(&self, _uri: String) -> zbus::fdo::Result<()> {
+        Err(zbus::fdo::Error::NotSupported("不支持打开 URI".to_string()))
+    }
+
+    #[zbus(property)]
+    fn playback_status(&self) -> String {
+        let now_playing = self.snapshot();
+        let status = if now_playing.song_id.is_none() {
+            "Stopped"
+        } else if now_playing.playing {
+            "Playing"
+        } else {
+            "Paused"
+        };
+        status.to_string()
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn volume(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        let now_playing = self.snapshot();
+        let mut metadata = HashMap::new();
+        let song_id = now_playing.song_id.as_deref().unwrap_or_default();
+        metadata.insert("mpris:trackid".to_string(), owned(track_id(song_id)));
+        if song_id.is_empty() {
+            return metadata;
+        }
+        metadata.insert("xesam:title".to_string(), owned(now_playing.title));
+        metadata.insert("xesam:artist".to_string(), owned(vec![now_playing.artist]));
+        if let Some(duration) = now_playing.duration_secs {
+            metadata.insert("mpris:length".to_string(), owned(to_micros(duration)));
+        }
+        if let Some(art_url) = now_playing.art_url {
+            metadata.insert("mpris:artUrl".to_string(), owned(art_url));
+        }
+        metadata
+    }
+
+    /// 进度由客户端按需读取，规范要求这个属性不发送变更信号
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn position(&self) -> i64 {
+        to_micros(self.snapshot().position_secs())
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        true
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn can_control(&self) -> bool {
+        true
+    }
+
+    /// 进度发生跳变 (不是正常播放推进) 时发送
+    #[zbus(signal)]
+    async fn seeked(emitter: &SignalEmitter<'_>, position: i64) -> zbus::Result<()>;
+}
+
+pub struct MprisServer {
+    connection: Connection,
+}
+
+impl MprisServer {
+    /// 在会话总线 (address 为 None) 或指定地址的总线上发布服务。
+    /// 已有另一个实例占用了总线名时，按规范在名字后加上进程号
+    pub async fn start(
+        address: Option<&str>,
+        now_playing: Arc<Mutex<NowPlaying>>,
+        on_control: ControlSink,
+    ) -> Result<Self, String> {
+        let builder = match address {
+            Some(address) => connection::Builder::address(address).map_err(|e| e.to_string())?,
+            None => connection::Builder::session().map_err(|e| e.to_string())?,
+        };
+        let connection = builder
+            .serve_at(
+                OBJECT_PATH,
+                Root {
+                    on_control: on_control.clone(),
+                },
+            )
+            .map_err(|e| e.to_string())?
+            .serve_at(
+                OBJECT_PATH,
+                Player {
+                    now_playing,
+                    on_control,
+                },
+            )
+            .map_err(|e| e.to_string())?
+            .build()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        // 不排队、也不抢占其他实例已经注册的名字
+        let flags = RequestNameFlags::DoNotQueue.into();
+        if connection
+            .request_name_with_flags(BUS_NAME, flags)
+            .await
+            .is_err()
+        {
+            let instance_name = format!("{}.instance{}", BUS_NAME, std::process::id());
+            connection
+                .request_name_with_flags(instance_name, flags)
+                .await
+                .map_err(|e| format!("注册 MPRIS 服务名失败: {}", e))?;
+        }
+        Ok(Self { connection })
+    }
+
+    /// 播放状态更新后通知客户端
+    pub async fn notify(&self, change: SessionChange, position_secs: f64) -> Result<(), String> {
+        let iface = self
+            .connection
+            .object_server()
+            .interface::<_, Player>(OBJECT_PATH)
+            .await
+            .map_err(|e| e.to_string())?;
+        let emitter = iface.signal_emitter();
+        let player = iface.get().await;
+        if change.track {
+            player
+                .metadata_changed(emitter)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        if change.status {
+            player
+                .playback_status_changed(emitter)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        if change.seeked {
+            Player::seeked(emitter, to_micros(position_secs))
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{
+        io::{BufRead, BufReader},
+        process::{Child, Command, Stdio},
+    };
+
+    use zbus::zvariant::OwnedObjectPath;
+
+    const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+    /// 测试用的私有会话总线，drop 时关闭
+    struct PrivateBus {
+        daemon: Child,
+        address: String,
+    }
+
+    impl Drop for PrivateBus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    fn start_bus() -> PrivateBus {
+        let mut daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--nopidfile", "--print-address"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("需要 dbus-daemon");
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take().unwrap())
+            .read_line(&mut address)
+            .unwrap();
+        PrivateBus {
+            daemon,
+            address: address.trim().to_string(),
+        }
+    }
+
+    struct Fixture {
+        _bus: PrivateBus,
+        _server: MprisServer,
+        client: Connection,
+        now_playing: Arc<Mutex<NowPlaying>>,
+        controls: Arc<Mutex<Vec<MediaControl>>>,
+    }
+
+    impl Fixture {
+        async fn start() -> Self {
+            let bus = start_bus();
+            let now_playing = Arc::new(Mutex::new(NowPlaying::default()));
+            let controls = Arc::new(Mutex::new(Vec::new()));
+            let sink = controls.clone();
+            let on_control: ControlSink =
+                Arc::new(move |control| sink.lock().unwrap().push(control));
+            let server = MprisServer::start(Some(&bus.address), now_playing.clone(), on_control)
+                .await
+                .unwrap();
+            let client = connection::Builder::address(bus.address.as_str())
+                .unwrap()
+                .build()
+                .await
+                .unwrap();
+            Self {
+                _bus: bus,
+                _server: server,
+                client,
+                now_playing,
+                controls,
+            }
+        }
+
+        fn set_song(&self, song_id: Option<&str>, duration_secs: Option<f64>) {
+            let mut now_playing = self.now_playing.lock().unwrap();
+            now_playing.song_id = song_id.map(str::to_string);
+            now_playing.title = "晴天".to_string();
+            now_playing.artist = "周杰伦".to_string();
+            now_playing.duration_secs = duration_secs;
+        }
+
+        /// 调用方法，返回期间收到的控制操作
+        async fn call<B>(&self, method: &str, body: &B) -> Vec<MediaControl>
+        where
+            B: serde::Serialize + zbus::zvariant::DynamicType,
+        {
+            self.client
+                .call_method(
+                    Some(BUS_NAME),
+                    OBJECT_PATH,
+                    Some(PLAYER_INTERFACE),
+                    method,
+                    body,
+                )
+                .await
+                .unwrap();
+            std::mem::take(&mut *self.controls.lock().unwrap())
+        }
+
+        async fn property(&self, name: &str) -> OwnedValue {
+            let reply = self
+                .client
+                .call_method(
+                    Some(BUS_NAME),
+                    OBJECT_PATH,
+                    Some("org.freedesktop.DBus.Properties"),
+                    "Get",
+                    &(PLAYER_INTERFACE, name),
+                )
+                .await
+                .unwrap();
+            reply.body().deserialize().unwrap()
+        }
+
+        async fn metadata(&self) -> HashMap<String, OwnedValue> {
+            self.property("Metadata").await.try_into().unwrap()
+        }
+    }
+
+    fn trackid(metadata: &HashMap<String, OwnedValue>) -> String {
+        let path: OwnedObjectPath = metadata["mpris:trackid"]
+            .try_clone()
+            .unwrap()
+            .try_into()
+            .unwrap();
+        path.as_str().to_string()
+    }
+
+    #[test]
+    fn track_id_is_valid_object_path() {
+        for song_id in ["abc", "周杰伦-晴天", "a/b c.mp3"] {
+            let path = track_id(song_id);
+            assert!(ObjectPath::try_from(path.as_str()).is_ok(), "{}", path);
+        }
+        assert_ne!(track_id("a"), track_id("b"));
+        assert_eq!(track_id("").as_str(), NO_TRACK);
+    }
+
+    #[tokio::test]
+    async fn forwards_control_methods() {
+        let fixture = Fixture::start().await;
+        assert_eq!(fixture.call("Play", &()).await, vec![MediaControl::Play]);
+        assert_eq!(fixture.call("Pause", &()).await, vec![MediaControl::Pause]);
+        assert_eq!(
+            fixture.call("PlayPause", &()).await,
+            vec![MediaControl::PlayPause]
+        );
+        assert_eq!(fixture.call("Next", &()).await, vec![MediaControl::Next]);
+        assert_eq!(
+            fixture.call("Previous", &()).await,
+            vec![MediaControl::Previous]
+        );
+        assert_eq!(fixture.call("Stop", &()).await, vec![MediaControl::Stop]);
+    }
+
+    #[tokio::test]
+    async fn seek_is_relative_and_clamped() {
+        let fixture = Fixture::start().await;
+        // 没有歌曲时忽略
+        assert!(fixture.call("Seek", &(5_000_000i64)).await.is_empty());
+
+        fixture.set_song(Some("song"), Some(200.0));
+        assert_eq!(
+            fixture.call("Seek", &(15_000_000i64)).await,
+            vec![MediaControl::Seek {
+                position_secs: 15.0
+            }]
+        );
+        // 跳到开头之前从头播放
+        assert_eq!(
+            fixture.call("Seek", &(-5_000_000i64)).await,
+            vec![MediaControl::Seek { position_secs: 0.0 }]
+        );
+        // 跳过结尾相当于下一首
+        assert_eq!(
+            fixture.call("Seek", &(300_000_000i64)).await,
+            vec![MediaControl::Next]
+        );
+    }
+
+    #[tokio::test]
+    async fn set_position_checks_trackid() {
+        let fixture = Fixture::start().await;
+        fixture.set_song(Some("song"), Some(200.0));
+        let current = track_id("song");
+        assert_eq!(
+            fixture
+                .call("SetPosition", &(&current, 30_000_000i64))
+                .await,
+            vec![MediaControl::Seek {
+                position_secs: 30.0
+            }]
+        );
+        // 旧歌曲的 trackid、负数位置都忽略
+        let old = track_id("old");
+        assert!(
+            fixture
+                .call("SetPosition", &(&old, 30_000_000i64))
+                .await
+                .is_empty()
+        );
+        assert!(
+            fixture
+                .call("SetPosition", &(&current, -1i64))
+                .await
+                .is_empty()
+        );
+
+        // 空的 song_id 不能用 NoTrack 跳转
+        fixture.set_song(Some(""), None);
+        let no_track = ObjectPath::from_static_str_unchecked(NO_TRACK);
+        assert!(
+            fixture
+                .call("SetPosition", &(&no_track, 1_000_000i64))
+                .await
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn metadata_describes_current_song() {
+        let fixture = Fixture::start().await;
+        let metadata = fixture.metadata().await;
+        assert_eq!(trackid(&metadata), NO_TRACK);
+        assert_eq!(metadata.len(), 1);
+        let status: String = fixture.property("PlaybackStatus").await.try_into().unwrap();
+        assert_eq!(status, "Stopped");
+
+        fixture.set_song(Some("周杰伦-晴天"), Some(269.5));
+        let metadata = fixture.metadata().await;
+        assert_eq!(trackid(&metadata), track_id("周杰伦-晴天").as_str());
+        let title: String = metadata["xesam:title"]
+            .try_clone()
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(title, "晴天");
+        let artist: Vec<String> = metadata["xesam:artist"]
+            .try_clone()
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(artist, vec!["周杰伦"]);
+        let length: i64 = metadata["mpris:length"]
+            .try_clone()
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(length, 269_500_000);
+        assert!(!metadata.contains_key("mpris:artUrl"));
+        let status: String = fixture.property("PlaybackStatus").await.try_into().unwrap();
+        assert_eq!(status, "Paused");
+    }
+
+    #[tokio::test]
+    async fn metadata_with_empty_song_id_has_no_track() {
+        let fixture = Fixture::start().await;
+        fixture.set_song(Some(""), Some(100.0));
+        let metadata = fixture.metadata().await;
+        assert_eq!(trackid(&metadata), NO_TRACK);
+        assert_eq!(metadata.len(), 1);
+    }
+}
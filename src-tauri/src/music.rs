@@ -1,34 +1,49 @@
 use std::path::{Path, PathBuf};
 
-use chrono::{SecondsFormat, Utc};
 use sqlx::QueryBuilder;
 use tauri::{AppHandle, Manager};
 use tauri_plugin_http::reqwest;
 
 use crate::{
-    model::{ExistingMusicDetail, Music, ToggleMusicPayload, UpdateDetailPayload},
-    my_util::{DbPool, calculate_file_hash, get_app_setting},
+    audio_format::{self, AudioFormat},
+    download, loudness,
+    model::{ExistingMusicDetail, Music, ToggleMusicPayload, TrackGain, UpdateDetailPayload},
+    my_util::{DbPool, calculate_file_hash, get_app_setting, resolve_export_dir},
+    pinyin_search::pinyin_keys,
+    playlist::{compact_positions, is_live_playlist, is_smart_playlist},
+    source::{self, SourceRegistry, canonical_song_id, split_song_id},
+    stream_proxy::{InflightDownloads, InflightGuard},
+    tagging, trash,
 };
 
-pub async fn save_music(pool: &DbPool, music_list: Vec<Music>) -> Result<(), sqlx::Error> {
+pub async fn save_music(pool: &DbPool, music_list: &[Music]) -> Result<(), sqlx::Error> {
     let mut tx = pool.begin().await?;
 
     let sql = r#"
         INSERT INTO music (
             song_id, title, artist, url, lyric, download_mp3, download_extra,
-            cover_url, duration_secs, download_mp3_id, play_url
-        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            cover_url, duration_secs, download_mp3_id, play_url, source,
+            title_pinyin, title_initials, artist_pinyin, artist_initials
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         ON CONFLICT(song_id) DO UPDATE SET
             title = excluded.title, artist = excluded.artist, url = excluded.url,
-            lyric = excluded.lyric, download_mp3 = excluded.download_mp3,
-            download_extra = excluded.download_extra, cover_url = excluded.cover_url,
-            duration_secs = excluded.duration_secs, download_mp3_id = excluded.download_mp3_id,
-            play_url = excluded.play_url
+            title_pinyin = excluded.title_pinyin, title_initials = excluded.title_initials,
+            artist_pinyin = excluded.artist_pinyin, artist_initials = excluded.artist_initials,
+            lyric = COALESCE(excluded.lyric, music.lyric),
+            download_mp3 = COALESCE(excluded.download_mp3, music.download_mp3),
+            download_extra = COALESCE(excluded.download_extra, music.download_extra),
+            cover_url = COALESCE(excluded.cover_url, music.cover_url),
+            duration_secs = COALESCE(excluded.duration_secs, music.duration_secs),
+            download_mp3_id = COALESCE(excluded.download_mp3_id, music.download_mp3_id),
+            play_url = COALESCE(excluded.play_url, music.play_url)
     "#;
 
     for music in music_list {
+        let song_id = canonical_song_id(&music.song_id);
+        let title_keys = pinyin_keys(&music.title);
+        let artist_keys = pinyin_keys(&music.artist);
         sqlx::query(sql)
-            .bind(&music.song_id)
+            .bind(&song_id)
             .bind(&music.title)
             .bind(&music.artist)
             .bind(&music.url)
@@ -39,6 +54,16 @@ pub async fn save_music(pool: &DbPool, music_list: Vec<Music>) -> Result<(), sql
             .bind(music.duration_secs)
             .bind(&music.download_mp3_id)
             .bind(&music.play_url)
+            .bind(
+                music
+                    .source
+                    .as_deref()
+                    .unwrap_or_else(|| split_song_id(&song_id).0),
+            )
+            .bind(title_keys.full)
+            .bind(title_keys.initials)
+            .bind(artist_keys.full)
+            .bind(artist_keys.initials)
             .execute(&mut *tx)
             .await?;
     }
@@ -117,13 +142,7 @@ pub async fn update_music_detail(
         };
 
         // 这自动处理了您指出的“同一专辑同一封面”的去重问题
-        let filename = PathBuf::from(new_remote_url)
-            .file_name()
-            .and_then(|s| s.to_str())
-            .map_or_else(
-                || format!("{}.jpg", payload.song_id), // 如果无法解析，使用song_id作为备用
-                |s| s.to_string(),
-            );
+        let filename = cover_file_name(new_remote_url, &payload.song_id);
 
         // 2. 获取本地缓存目录的完整路径
         let app_data_dir = app_handle
@@ -196,11 +215,6 @@ pub async fn update_music_detail(
         return Ok(());
     }
 
-    builder
-        .push(separator)
-        .push("last_played_at = ")
-        .push_bind(Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true));
-
     // 4. 完成并执行 SQL 查询
     builder
         .push(" WHERE song_id = ")
@@ -221,14 +235,26 @@ pub async fn toggle_music_in_playlist(
         Some(id) => id,
         None => {
             // 因为 init_db_pool 保证了歌单一定存在，我们可以安全地直接获取第一个
-            let (id,): (i64,) =
-                sqlx::query_as("SELECT id FROM playlist ORDER BY created_at LIMIT 1")
-                    .fetch_one(&mut *tx) // 使用 fetch_one，因为它保证能找到一个
-                    .await?;
+            let (id,): (i64,) = sqlx::query_as(
+                "SELECT id FROM playlist WHERE smart_rules IS NULL AND deleted_at IS NULL ORDER BY created_at LIMIT 1",
+            )
+            .fetch_one(&mut *tx) // 使用 fetch_one，因为它保证能找到一个
+            .await?;
             id
         }
     };
 
+    // 回收站中的歌单不能再修改，否则恢复后会出现意外的歌曲
+    if !is_live_playlist(&mut tx, playlist_id).await? {
+        return Err(sqlx::Error::Protocol("歌单不存在或已被删除".to_string()));
+    }
+    if is_smart_playlist(&mut tx, playlist_id).await? {
+        return Err(sqlx::Error::Protocol(
+            "智能歌单的歌曲由规则决定，不能手动添加或移除".to_string(),
+        ));
+    }
+
+    let mut removed = false;
     for song_id in &payload.song_ids {
         let existing: Option<(i64,)> = sqlx::query_as(
             "SELECT playlist_id FROM playlist_music WHERE playlist_id = ? AND song_id = ?",
@@ -239,28 +265,32 @@ pub async fn toggle_music_in_playlist(
         .await?;
 
         if existing.is_some() {
+            trash::record_removed_song(&mut tx, playlist_id, song_id).await?;
             sqlx::query("DELETE FROM playlist_music WHERE playlist_id = ? AND song_id = ?")
                 .bind(playlist_id)
                 .bind(song_id)
                 .execute(&mut *tx)
                 .await?;
+            removed = true;
         } else {
-            let position: (i64,) =
-                sqlx::query_as("SELECT COUNT(*) FROM playlist_music WHERE playlist_id = ?")
-                    .bind(playlist_id)
-                    .fetch_one(&mut *tx)
-                    .await?;
+            // 新加入的歌曲排在手动顺序的最前面
+            sqlx::query("UPDATE playlist_music SET position = position + 1 WHERE playlist_id = ?")
+                .bind(playlist_id)
+                .execute(&mut *tx)
+                .await?;
 
             sqlx::query(
-                "INSERT INTO playlist_music (playlist_id, song_id, position) VALUES (?, ?, ?)",
+                "INSERT INTO playlist_music (playlist_id, song_id, position) VALUES (?, ?, 0)",
             )
             .bind(playlist_id)
             .bind(song_id)
-            .bind(position.0)
             .execute(&mut *tx)
             .await?;
         }
     }
+    if removed {
+        compact_positions(&mut tx, playlist_id).await?;
+    }
 
     let playlist_cover: (Option<String>,) =
         sqlx::query_as("SELECT cover_path FROM playlist WHERE id = ?")
@@ -309,6 +339,16 @@ pub async fn get_music_list_by_ids(
     Ok(Some(music_list))
 }
 
+/// 单独读取歌词，歌曲不存在时返回错误
+pub async fn get_music_lyric(pool: &DbPool, song_id: &str) -> Result<Option<String>, String> {
+    sqlx::query_scalar::<_, Option<String>>("SELECT lyric FROM music WHERE song_id = ?")
+        .bind(song_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("数据库中未找到该歌曲".to_string())
+}
+
 pub async fn update_music_cache_path(
     pool: &DbPool,
     song_id: &str, // 使用 &str 避免不必要的内存分配
@@ -323,42 +363,125 @@ pub async fn update_music_cache_path(
     Ok(())
 }
 
-pub async fn update_music_last_play_time(pool: &DbPool, song_id: &str) -> Result<(), sqlx::Error> {
-    // 1. 获取当前的 UTC 时间
-    let formatted_now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
-
-    // 3. 执行 SQL 更新，并按正确的顺序绑定参数
-    sqlx::query("UPDATE music SET last_played_at = ?1 WHERE song_id = ?2")
-        .bind(formatted_now) // ?1 对应第一个 .bind()
-        .bind(song_id) // ?2 对应第二个 .bind()
+pub async fn update_music_cache_file(
+    pool: &DbPool,
+    song_id: &str,
+    file_path: &str,
+    format: AudioFormat,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE music SET file_path = ?, format = ?, mime = ? WHERE song_id = ?")
+        .bind(file_path)
+        .bind(format.extension())
+        .bind(format.mime())
+        .bind(song_id)
         .execute(pool)
         .await?;
-
     Ok(())
 }
 
-pub async fn cache_music_and_get_file_path(
-    app_handle: AppHandle,
+pub async fn update_music_play_url(
     pool: &DbPool,
-    music: Music,
-) -> Result<String, String> {
-    // 4. 使用 play_id (如果存在) 或 song_id 作为唯一文件名，避免冲突
+    song_id: &str,
+    play_url: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE music SET play_url = ?1, download_mp3 = ?1 WHERE song_id = ?2")
+        .bind(play_url)
+        .bind(song_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// 封面在 cover_cache 中的文件名：取远程 URL 的文件名部分，同一专辑的封面只会缓存一份。
+/// 无法解析时使用 song_id 作为备用
+pub fn cover_file_name(cover_url: &str, song_id: &str) -> String {
+    PathBuf::from(cover_url)
+        .file_name()
+        .and_then(|s| s.to_str())
+        .map_or_else(|| format!("{}.jpg", song_id), |s| s.to_string())
+}
+
+/// 缓存文件名 (不含扩展名)：使用 song_id 作为前缀保证唯一，避免冲突
+pub fn cache_file_stem(music: &Music) -> String {
     let sanitized_title = music
         .title
         .replace(&['/', '\\', ':', '*', '?', '"', '<', '>', '|'][..], "");
     let sanitized_artist = music
         .artist
         .replace(&['/', '\\', ':', '*', '?', '"', '<', '>', '|'][..], "");
-    let file_name = format!(
-        "{}_{}-{}.mp3",
-        music.song_id, sanitized_title, sanitized_artist
-    );
+    // song_id 带有音源前缀 (例如 "gequbao:65537")，冒号不能出现在 Windows 文件名中
+    let sanitized_song_id = music.song_id.replace(':', "_");
+    format!(
+        "{}_{}-{}",
+        sanitized_song_id, sanitized_title, sanitized_artist
+    )
+}
+
+/// 下载时使用的缓存文件名。下载前无法知道实际格式，统一先以 .mp3 保存，
+/// 完成后再由 register_cached_file 按文件头修正扩展名
+pub fn cache_file_name(music: &Music) -> String {
+    format!(
+        "{}.{}",
+        cache_file_stem(music),
+        audio_format::DEFAULT_FORMAT.extension()
+    )
+}
+
+/// 返回给前端使用的文件名 (URL 编码)，前端拼接为 music_cache/{file_name}
+fn encoded_file_name(path: &Path) -> String {
+    let file_name = path
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    urlencoding::encode(file_name).to_string()
+}
+
+/// 识别缓存文件的实际格式 (必要时重命名)，把路径和格式写入数据库，返回最终路径
+pub async fn register_cached_file(
+    pool: &DbPool,
+    song_id: &str,
+    path: &Path,
+) -> Result<PathBuf, String> {
+    let (path, format) = audio_format::identify_cached_file(path).await?;
+    update_music_cache_file(pool, song_id, &path.to_string_lossy(), format)
+        .await
+        .map_err(|e| format!("更新数据库失败: {}", e))?;
+    Ok(path)
+}
+
+/// 检查缓存目录中是否已有这首歌 (防止数据库与文件系统不同步)。
+/// 下载过程中只写入 .part 临时文件，完成后才重命名，所以存在即代表完整；
+/// 文件可能已经按实际格式改过扩展名，逐个检查
+pub fn find_cached_file(cache_dir: &Path, music: &Music) -> Option<PathBuf> {
+    let file_stem = cache_file_stem(music);
+    AudioFormat::ALL
+        .iter()
+        .map(|f| cache_dir.join(format!("{}.{}", file_stem, f.extension())))
+        .find(|p| p.exists())
+}
+
+pub async fn cache_music_and_get_file_path(
+    app_handle: AppHandle,
+    pool: &DbPool,
+    music: Music,
+) -> Result<String, String> {
+    let file_name = cache_file_name(&music);
 
     // 1. 优先检查从前端传来的 music 对象中是否已包含有效的缓存路径
     if let Some(path_str) = music.file_path.as_deref() {
         if !path_str.is_empty() && Path::new(path_str).exists() {
             println!("缓存命中 (来自前端对象): {}", path_str);
-            return Ok(urlencoding::encode(&file_name).to_string());
+            if music.format.is_none() {
+                // 旧版本缓存的文件还没有识别过格式
+                return register_cached_file(pool, &music.song_id, Path::new(path_str))
+                    .await
+                    .map(|path| encoded_file_name(&path));
+            }
+            let cached_name = Path::new(path_str)
+                .file_name()
+                .and_then(|s| s.to_str())
+                .unwrap_or(&file_name);
+            return Ok(urlencoding::encode(cached_name).to_string());
         }
     }
 
@@ -378,7 +501,6 @@ pub async fn cache_music_and_get_file_path(
     }
 
     let local_path = cache_dir.join(&file_name);
-    let local_path_str = local_path.to_string_lossy().into_owned();
 
     let should_download_cover = match music.cover_url.as_deref() {
         None => false,
@@ -386,16 +508,10 @@ pub async fn cache_music_and_get_file_path(
     };
 
     if should_download_cover {
-        let filename = music
-            .cover_url
-            .as_deref()
-            .and_then(|u| {
-                PathBuf::from(u)
-                    .file_name()
-                    .and_then(|s| s.to_str())
-                    .map(|s| s.to_string())
-            })
-            .unwrap_or_else(|| format!("{}.jpg", music.song_id));
+        let filename = cover_file_name(
+            music.cover_url.as_deref().unwrap_or_default(),
+            &music.song_id,
+        );
 
         let local_path = app_data_dir.join("cover_cache").join(&filename);
 
@@ -433,50 +549,108 @@ pub async fn cache_music_and_get_file_path(
     }
 
     // 5. 再次检查文件是否已在磁盘上存在 (防止数据库与文件系统不同步)
-    if local_path.exists() {
-        println!("缓存命中 (来自磁盘检查): {:?}", &local_path);
-        update_music_cache_path(&pool, &music.song_id, &local_path_str)
+    if let Some(existing_path) = find_cached_file(&cache_dir, &music) {
+        println!("缓存命中 (来自磁盘检查): {:?}", &existing_path);
+        return register_cached_file(pool, &music.song_id, &existing_path)
             .await
-            .map_err(|e| format!("(同步)更新数据库失败: {}", e))?;
-        return Ok(urlencoding::encode(&file_name).to_string());
+            .map(|path| encoded_file_name(&path))
+            .map_err(|e| format!("(同步){}", e));
     }
 
-    // --- 文件不存在，开始下载 ---
-    // [修复] 使用 `?` 解包 play_url 的 Result
-    let play_url = music
-        .play_url
-        .as_deref()
-        .ok_or("歌曲缺少 play_url，无法下载".to_string())?;
-
-    println!("开始缓存: {} -> {}", music.title, &local_path_str);
+    // 6. 同一首歌可能正在下载 (例如正在边下边播)，等待它完成而不是重复下载
+    let inflight = app_handle.state::<InflightDownloads>();
+    let guard = match inflight.begin(&music.song_id, download::part_path(&local_path)) {
+        Ok(guard) => guard,
+        Err(existing) => {
+            println!("等待进行中的下载: {}", music.title);
+            return existing
+                .wait_finished()
+                .await
+                .map(|path| encoded_file_name(&path));
+        }
+    };
 
-    // [修复] 使用 `?` 解包网络请求的 Result
-    let response = reqwest::get(play_url)
+    download_into_cache(&app_handle, pool, &music, &local_path, guard)
         .await
-        .map_err(|e| format!("网络请求失败: {}", e))?;
+        .map(|path| encoded_file_name(&path))
+}
 
-    // [修复] 现在 response 是 Response 类型，可以安全调用 .status()
-    if !response.status().is_success() {
-        return Err(format!("下载失败，状态码: {}", response.status()));
-    }
+/// 下载歌曲到 local_path (播放地址失效时回退到其他音源)，完成后识别格式并更新数据库。
+/// 下载进度会同步到 guard 中，边下边播的请求据此读取已下载的部分
+pub async fn download_into_cache(
+    app_handle: &AppHandle,
+    pool: &DbPool,
+    music: &Music,
+    local_path: &Path,
+    guard: InflightGuard,
+) -> Result<PathBuf, String> {
+    let result = download_and_register(app_handle, pool, music, local_path, &guard).await;
+    guard.finish(result.clone());
+    result
+}
 
-    // [修复] 使用 `?` 解包获取 bytes 的 Result
-    let bytes = response.bytes().await.map_err(|e| e.to_string())?;
-    tokio::fs::write(&local_path, &bytes)
-        .await
-        .map_err(|e| e.to_string())?;
+async fn download_and_register(
+    app_handle: &AppHandle,
+    pool: &DbPool,
+    music: &Music,
+    local_path: &Path,
+    guard: &InflightGuard,
+) -> Result<PathBuf, String> {
+    println!("开始缓存: {} -> {}", music.title, local_path.display());
+
+    let inflight = guard.download().clone();
+    let on_chunk = move |downloaded: u64, total: Option<u64>| inflight.update(downloaded, total);
+    let on_chunk: download::ChunkObserver = &on_chunk;
+
+    let first_attempt = match music.play_url.as_deref() {
+        Some(play_url) => {
+            download::download_with_resume(
+                app_handle,
+                &music.song_id,
+                play_url,
+                local_path,
+                Some(on_chunk),
+            )
+            .await
+        }
+        None => Err("歌曲缺少 play_url".to_string()),
+    };
+
+    // 播放地址失效或当前音源不可用时，按回退顺序重新获取播放地址后再试一次
+    if let Err(e) = first_attempt {
+        eprintln!("缓存失败 ({}): {}，尝试回退到其他音源", music.title, e);
+        let registry = app_handle.state::<SourceRegistry>();
+        let play_url = source::resolve_play_url_with_fallback(pool, registry.inner(), music)
+            .await
+            .map_err(|fallback_err| format!("{}；回退失败: {}", e, fallback_err))?;
+        // 新地址可能来自另一个音源，文件内容不同，不能续传到旧的 .part 后面
+        if music.play_url.as_deref() != Some(play_url.as_str()) {
+            download::discard_partial(local_path).await?;
+            guard.download().reset();
+        }
+        download::download_with_resume(
+            app_handle,
+            &music.song_id,
+            &play_url,
+            local_path,
+            Some(on_chunk),
+        )
+        .await?;
+        update_music_play_url(pool, &music.song_id, &play_url)
+            .await
+            .map_err(|e| format!("更新播放地址失败: {}", e))?;
+    }
 
     println!("缓存完成: {}", music.title);
 
-    // 7. 下载成功后，更新数据库记录
-    update_music_cache_path(&pool, &music.song_id, &local_path_str)
+    // 下载成功后，识别实际格式并更新数据库记录
+    register_cached_file(pool, &music.song_id, local_path)
         .await
-        .map_err(|e| format!("(下载后)更新数据库失败: {}", e))?;
-    return Ok(urlencoding::encode(&file_name).to_string());
+        .map_err(|e| format!("(下载后){}", e))
 }
 
 pub async fn export_music_file(
-    _app_handle: AppHandle,
+    app_handle: AppHandle,
     pool: &DbPool,
     music_ids: Vec<String>,
 ) -> Result<String, String> {
@@ -488,49 +662,30 @@ pub async fn export_music_file(
         .await?
         .ok_or("未找到任何歌曲".to_string())?;
 
-    // 1. 获取下载子路径，提供默认值 "MusicBox"
-    let sub_path = get_app_setting(pool, "download_path".to_string())
-        .await
-        .map_err(|e| e.to_string())?
-        .unwrap_or_else(|| "MusicBox".to_string());
+    // 1. 文件名格式和是否移除空格
+    let (name_format, remove_spaces) = export_name_settings(pool).await?;
 
-    // 2. 获取文件名格式，提供默认值 "title_artist"
-    let name_format = get_app_setting(pool, "filename_format".to_string())
+    // 2. 是否在导出的文件中写入 ID3 标签 (标题/歌手/封面/歌词)，默认开启
+    let write_tags = get_app_setting(pool, "export_write_tags".to_string())
         .await
         .map_err(|e| e.to_string())?
-        .unwrap_or_else(|| "title_artist".to_string());
-
-    // 3. 获取是否移除空格的设置，默认为 "false"
-    let remove_spaces = get_app_setting(pool, "filename_remove_spaces".to_string())
+        .unwrap_or_else(|| "true".to_string())
+        .parse::<bool>()
+        .unwrap_or(true);
+    // 同时写入 ReplayGain 音量信息，需要先做响度分析，默认关闭
+    let write_replaygain = get_app_setting(pool, "export_replaygain".to_string())
         .await
         .map_err(|e| e.to_string())?
-        .unwrap_or_else(|| "false".to_string())
-        .parse::<bool>()
-        .unwrap_or(false);
+        .is_some_and(|v| v == "true");
 
-    // 在安卓平台上，我们硬编码到公共的 Download 目录
-    #[cfg(target_os = "android")]
-    use std::path::PathBuf;
-
-    #[cfg(target_os = "android")]
-    let base_path = PathBuf::from("/storage/emulated/0/Download");
-
-    #[cfg(not(target_os = "android"))]
-    let base_path = _app_handle
+    let cover_dir = app_handle
         .path()
-        .download_dir()
-        .or_else(|_| Err("无法获取系统的下载目录".to_string()))?;
-
-    // 2. 将基础目录与从数据库读取的子目录名拼接
-    //    同时进行安全净化，防止 ".." 等路径遍历字符
-    let download_path = base_path.join(sub_path.replace("..", ""));
+        .app_data_dir()
+        .map_err(|_| "无法获取应用数据目录".to_string())?
+        .join("cover_cache");
 
-    // 3. 确保最终的目录存在，如果不存在则创建
-    if !download_path.exists() {
-        tokio::fs::create_dir_all(&download_path)
-            .await
-            .map_err(|e| format!("创建下载目录 '{}' 失败: {}", download_path.display(), e))?;
-    }
+    // 3. 导出目录：系统下载目录下的 download_path 子目录
+    let (download_path, sub_path) = resolve_export_dir(&app_handle, pool).await?;
 
     // --- 后续逻辑的微小调整 ---
 
@@ -540,15 +695,42 @@ pub async fn export_music_file(
     let mut skipped_identical_count = 0; // 新增：因文件已存在且内容一致而跳过的计数器
 
     'music_loop: for music in music_list {
-        if let Some(source_path_str) = music.file_path.filter(|p| !p.is_empty()) {
-            let source_path = PathBuf::from(&source_path_str);
+        if let Some(source_path_str) = music.file_path.as_deref().filter(|p| !p.is_empty()) {
+            let source_path = PathBuf::from(source_path_str);
+
+            let audio_format = exported_audio_format(&music, &source_path).await;
+            let base_filename = format!(
+                "{}.{}",
+                export_file_stem(&music, &name_format, remove_spaces),
+                audio_format.extension()
+            );
+
+            let initial_dest_path = download_path.join(&base_filename);
 
-            let source_metadata = match tokio::fs::metadata(&source_path).await {
+            // 需要写入标签时，先在目标目录生成带标签的临时文件，缓存中的原文件保持不变
+            // ID3 标签只适用于 MP3，其他格式直接复制
+            let tagged_path = if write_tags && audio_format == AudioFormat::Mp3 {
+                let gain = if write_replaygain {
+                    loudness::get_track_gain(pool, &music.song_id)
+                        .await
+                        .map_err(|e| eprintln!("获取 {} 的响度失败: {}", music.title, e))
+                        .ok()
+                } else {
+                    None
+                };
+                prepare_tagged_copy(&source_path, &initial_dest_path, &music, &cover_dir, gain)
+                    .await
+            } else {
+                None
+            };
+            let export_source = tagged_path.as_deref().unwrap_or(&source_path);
+
+            let source_metadata = match tokio::fs::metadata(export_source).await {
                 Ok(meta) => meta,
                 Err(e) => {
                     println!(
                         "无法读取源文件 '{}' 的元数据: {}, 跳过导出。",
-                        source_path.display(),
+                        export_source.display(),
                         e
                     );
                     fail_count += 1;
@@ -556,33 +738,19 @@ pub async fn export_music_file(
                 }
             };
 
-            let sanitized_title = music
-                .title
-                .replace(&['/', '\\', ':', '*', '?', '"', '<', '>', '|'][..], "");
-            let sanitized_artist = music
-                .artist
-                .replace(&['/', '\\', ':', '*', '?', '"', '<', '>', '|'][..], "");
-
-            let mut base_filename_stem = match name_format.as_str() {
-                "artist_title" => format!("{} - {}", sanitized_artist, sanitized_title),
-                _ => format!("{} - {}", sanitized_title, sanitized_artist),
-            };
-            if remove_spaces {
-                base_filename_stem = base_filename_stem.replace(" ", "");
-            }
-            let base_filename = format!("{}.mp3", base_filename_stem);
-
-            let initial_dest_path = download_path.join(&base_filename);
-
             let final_path: PathBuf;
 
             if let Ok(dest_metadata) = tokio::fs::metadata(&initial_dest_path).await {
                 // 目标文件已存在，进行元数据比较
                 if source_metadata.len() == dest_metadata.len() {
                     // 文件大小一致，再比较哈希值
-                    let source_hash = calculate_file_hash(&source_path).ok();
+                    let source_hash = calculate_file_hash(export_source).ok();
                     let dest_hash = calculate_file_hash(&initial_dest_path).ok();
 
+                    if let Some(tagged_path) = &tagged_path {
+                        let _ = tokio::fs::remove_file(tagged_path).await;
+                    }
+
                     if source_hash.is_some() && source_hash == dest_hash {
                         // 哈希值也一致，确定是相同文件，跳过
                         println!(
@@ -600,11 +768,19 @@ pub async fn export_music_file(
                 final_path = initial_dest_path;
             }
 
-            // [优化] 使用异步文件复制 tokio::fs::copy，避免阻塞线程
-            match tokio::fs::copy(&source_path, &final_path).await {
+            // 带标签的临时文件已经在目标目录中，直接重命名即可
+            let result = match &tagged_path {
+                Some(tagged_path) => tokio::fs::rename(tagged_path, &final_path).await,
+                // [优化] 使用异步文件复制 tokio::fs::copy，避免阻塞线程
+                None => tokio::fs::copy(&source_path, &final_path).await.map(|_| ()),
+            };
+            match result {
                 Ok(_) => success_count += 1,
                 Err(e) => {
                     println!("复制文件 {} 失败: {}", source_path.display(), e);
+                    if let Some(tagged_path) = &tagged_path {
+                        let _ = tokio::fs::remove_file(tagged_path).await;
+                    }
                     fail_count += 1;
                 }
             }
@@ -632,14 +808,212 @@ pub async fn export_music_file(
         #[cfg(target_os = "android")]
         let final_message = format!(
             "导出完成！{} 文件已保存至手机 Download/{} 文件夹。",
-            final_summary,
-            sub_path.replace("..", "")
+            final_summary, sub_path
         );
         #[cfg(not(target_os = "android"))]
-        let final_message = format!("导出完成！{}", final_summary);
+        let final_message = {
+            let _ = sub_path;
+            format!("导出完成！{}", final_summary)
+        };
 
         Ok(final_message)
     } else {
         Err(format!("导出失败。{}", final_summary))
     }
 }
+
+/// 导出文件名设置：(filename_format，默认 "title_artist"；filename_remove_spaces，默认 false)
+pub async fn export_name_settings(pool: &DbPool) -> Result<(String, bool), String> {
+    let name_format = get_app_setting(pool, "filename_format".to_string())
+        .await
+        .map_err(|e| e.to_string())?
+        .unwrap_or_else(|| "title_artist".to_string());
+    let remove_spaces = get_app_setting(pool, "filename_remove_spaces".to_string())
+        .await
+        .map_err(|e| e.to_string())?
+        .unwrap_or_else(|| "false".to_string())
+        .parse::<bool>()
+        .unwrap_or(false);
+    Ok((name_format, remove_spaces))
+}
+
+/// 导出文件名 (不含扩展名)，例如 "晴天 - 周杰伦"
+pub fn export_file_stem(music: &Music, name_format: &str, remove_spaces: bool) -> String {
+    let sanitized_title = music
+        .title
+        .replace(&['/', '\\', ':', '*', '?', '"', '<', '>', '|'][..], "");
+    let sanitized_artist = music
+        .artist
+        .replace(&['/', '\\', ':', '*', '?', '"', '<', '>', '|'][..], "");
+
+    let stem = match name_format {
+        "artist_title" => format!("{} - {}", sanitized_artist, sanitized_title),
+        _ => format!("{} - {}", sanitized_title, sanitized_artist),
+    };
+    if remove_spaces {
+        stem.replace(" ", "")
+    } else {
+        stem
+    }
+}
+
+/// 导出文件的扩展名使用缓存文件的实际格式，读取文件头失败时使用数据库中记录的格式
+pub async fn exported_audio_format(music: &Music, source_path: &Path) -> AudioFormat {
+    let sniff_path = source_path.to_path_buf();
+    tauri::async_runtime::spawn_blocking(move || AudioFormat::sniff_file(&sniff_path))
+        .await
+        .ok()
+        .flatten()
+        .or_else(|| {
+            music
+                .format
+                .as_deref()
+                .and_then(AudioFormat::from_extension)
+        })
+        .unwrap_or(audio_format::DEFAULT_FORMAT)
+}
+
+/// 把缓存文件复制到目标文件旁的临时文件并写入 ID3 标签。
+/// 写入失败时删除临时文件并返回 None，调用方退回到直接复制原文件
+async fn prepare_tagged_copy(
+    source_path: &Path,
+    dest_path: &Path,
+    music: &Music,
+    cover_dir: &Path,
+    gain: Option<TrackGain>,
+) -> Option<PathBuf> {
+    let mut tagged_path = dest_path.as_os_str().to_owned();
+    tagged_path.push(".tagging");
+    let tagged_path = PathBuf::from(tagged_path);
+    if let Err(e) = tokio::fs::copy(source_path, &tagged_path).await {
+        println!("复制文件 {} 失败: {}", source_path.display(), e);
+        return None;
+    }
+
+    let cover_path = music
+        .cover_url
+        .as_deref()
+        .map(|url| cover_dir.join(cover_file_name(url, &music.song_id)))
+        .filter(|path| path.exists());
+
+    let music = music.clone();
+    let path = tagged_path.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        tagging::write_id3_tags(&path, &music, cover_path.as_deref(), gain.as_ref())
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|r| r);
+
+    match result {
+        Ok(_) => Some(tagged_path),
+        Err(e) => {
+            eprintln!(
+                "写入标签失败 ({}): {}，导出未加标签的文件",
+                dest_path.display(),
+                e
+            );
+            let _ = tokio::fs::remove_file(&tagged_path).await;
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        my_util::test_pool,
+        playlist::{append_songs_to_playlist, delete_playlist},
+    };
+
+    async fn setup() -> DbPool {
+        let pool = test_pool().await;
+        sqlx::query(
+            "INSERT INTO music (song_id, title, artist, url) VALUES ('a', 'a', '', '');
+             INSERT INTO playlist (id, name) VALUES (1, '歌单');",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        pool
+    }
+
+    async fn playlist_songs(pool: &DbPool, playlist_id: i64) -> Vec<String> {
+        sqlx::query_scalar("SELECT song_id FROM playlist_music WHERE playlist_id = ?")
+            .bind(playlist_id)
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn deleted_playlist_cannot_be_modified() {
+        let pool = setup().await;
+        delete_playlist(&pool, 1).await.unwrap();
+
+        let result = toggle_music_in_playlist(
+            &pool,
+            ToggleMusicPayload {
+                playlist_id: Some(1),
+                song_ids: vec!["a".to_string()],
+            },
+        )
+        .await;
+        assert!(result.is_err());
+        let result = append_songs_to_playlist(&pool, 1, &["a".to_string()]).await;
+        assert_eq!(result, Err("歌单不存在或已被删除".to_string()));
+        assert!(playlist_songs(&pool, 1).await.is_empty());
+
+        // 不存在的歌单同样拒绝，而不是写入悬空的关系
+        let result = append_songs_to_playlist(&pool, 99, &["a".to_string()]).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn toggle_adds_and_removes() {
+        let pool = setup().await;
+        let payload = || ToggleMusicPayload {
+            playlist_id: Some(1),
+            song_ids: vec!["a".to_string()],
+        };
+        toggle_music_in_playlist(&pool, payload()).await.unwrap();
+        assert_eq!(playlist_songs(&pool, 1).await, ["a"]);
+        toggle_music_in_playlist(&pool, payload()).await.unwrap();
+        assert!(playlist_songs(&pool, 1).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn save_music_maps_bare_song_ids_to_default_source() {
+        let pool = setup().await;
+        sqlx::query(
+            "INSERT INTO music (song_id, title, artist, url, lyric) VALUES ('gequbao:7516', '晴天', '周杰伦', '/music/7516', '歌词')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        // 旧版本前端缓存中的歌曲没有音源前缀
+        let cached: Music = serde_json::from_value(serde_json::json!({
+            "song_id": "7516", "title": "晴天", "artist": "周杰伦 ", "url": "/music/7516"
+        }))
+        .unwrap();
+        save_music(&pool, &[cached]).await.unwrap();
+
+        let rows: Vec<(String, String, String, Option<String>)> =
+            sqlx::query_as("SELECT song_id, artist, source, lyric FROM music WHERE title = '晴天'")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            rows,
+            [(
+                "gequbao:7516".to_string(),
+                "周杰伦 ".to_string(),
+                "gequbao".to_string(),
+                Some("歌词".to_string())
+            )]
+        );
+    }
+}
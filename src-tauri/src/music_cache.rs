@@ -6,6 +6,7 @@ use walkdir::WalkDir;
 use crate::{
     model::{CacheAnalysisResult, CachedMusicInfo, MusicToDelete, PlaylistCacheInfo},
     my_util::{DbPool, format_size, get_app_setting},
+    playlist::get_playlist_order_clause,
 };
 
 pub fn get_cache_size(app_handle: AppHandle) -> Result<String, String> {
@@ -114,6 +115,8 @@ pub async fn get_all_playlists_cache_info(pool: &DbPool) -> Result<Vec<PlaylistC
             playlist_music pm ON p.id = pm.playlist_id
         LEFT JOIN
             music m ON pm.song_id = m.song_id
+        WHERE
+            p.deleted_at IS NULL
         GROUP BY
             p.id
         ORDER BY
@@ -223,7 +226,11 @@ pub async fn get_cached_music_for_playlist(
     pool: &DbPool,
     playlist_id: i64, // 接收一个 playlist_id
 ) -> Result<Vec<CachedMusicInfo>, String> {
-    let mut music_list: Vec<CachedMusicInfo> = sqlx::query_as(
+    // 与歌单页面使用相同的排序方式
+    let order_by = get_playlist_order_clause(pool, playlist_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let sql = format!(
         r#"
             SELECT
                 m.song_id,
@@ -239,13 +246,15 @@ pub async fn get_cached_music_for_playlist(
             WHERE
                 pm.playlist_id = ? -- 按指定的 playlist_id 筛选
             ORDER BY
-                pm.position ASC
+                {}
         "#,
-    )
-    .bind(playlist_id) // 绑定 ID
-    .fetch_all(pool)
-    .await
-    .map_err(|e| e.to_string())?;
+        order_by
+    );
+    let mut music_list: Vec<CachedMusicInfo> = sqlx::query_as(&sql)
+        .bind(playlist_id) // 绑定 ID
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
 
     // 在 Rust 中计算文件大小的逻辑保持不变
     for music in music_list.iter_mut() {
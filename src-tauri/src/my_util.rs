@@ -11,29 +11,6 @@ pub type DbPool = SqlitePool;
 
 pub const MEDIA_ADDR: &str = "127.0.0.1:38915";
 
-pub fn parse_range(range_str: &str, total_size: u64) -> Option<(u64, u64)> {
-    if !range_str.starts_with("bytes=") {
-        return None;
-    }
-    let parts: Vec<&str> = range_str[6..].split('-').collect();
-    if parts.len() != 2 {
-        return None;
-    }
-
-    let start = parts[0].parse::<u64>().ok()?;
-    let end = if parts[1].is_empty() {
-        total_size - 1
-    } else {
-        parts[1].parse::<u64>().ok()?
-    };
-
-    if start > end || end >= total_size {
-        None
-    } else {
-        Some((start, end))
-    }
-}
-
 pub async fn save_app_setting(
     pool: &DbPool,
     key: String,
@@ -54,6 +31,38 @@ pub async fn save_app_setting(
     Ok(())
 }
 
+/// 导出文件的目录：系统下载目录 (Android 为公共 Download 目录) 下的 download_path 子目录，不存在时创建。
+/// 返回 (导出目录, 提示用户时显示的子目录名)
+pub async fn resolve_export_dir(
+    app_handle: &AppHandle,
+    pool: &DbPool,
+) -> Result<(PathBuf, String), String> {
+    let sub_path = get_app_setting(pool, "download_path".to_string())
+        .await
+        .map_err(|e| e.to_string())?
+        .unwrap_or_else(|| "MusicBox".to_string());
+
+    #[cfg(target_os = "android")]
+    let base_path = {
+        let _ = app_handle;
+        PathBuf::from("/storage/emulated/0/Download")
+    };
+
+    #[cfg(not(target_os = "android"))]
+    let base_path = app_handle
+        .path()
+        .download_dir()
+        .map_err(|_| "无法获取系统的下载目录".to_string())?;
+
+    // 防止 ".." 等路径遍历字符
+    let sub_path = sub_path.replace("..", "");
+    let export_dir = base_path.join(&sub_path);
+    tokio::fs::create_dir_all(&export_dir)
+        .await
+        .map_err(|e| format!("创建下载目录 '{}' 失败: {}", export_dir.display(), e))?;
+    Ok((export_dir, sub_path))
+}
+
 /// 通用函数：根据 key 获取一个设置项的值
 pub async fn get_app_setting(pool: &DbPool, key: String) -> Result<Option<String>, sqlx::Error> {
     let result = sqlx::query_as::<_, (String,)>("SELECT value FROM app_setting WHERE key = ?")
@@ -85,17 +94,21 @@ pub async fn init_db_pool(app_handle: &AppHandle) -> Result<DbPool, Box<dyn std:
 
     MIGRATOR.run(&pool).await?;
 
-    let playlist_count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM playlist")
-        .fetch_one(&pool)
-        .await?;
+    let playlist_count: (i64,) =
+        sqlx::query_as("SELECT COUNT(*) FROM playlist WHERE deleted_at IS NULL")
+            .fetch_one(&pool)
+            .await?;
 
-    // 如果没有任何歌单，就创建一个默认的
+    // 如果没有任何歌单 (回收站中的不算)，就创建一个默认的
     if playlist_count.0 == 0 {
         sqlx::query("INSERT INTO playlist (name) VALUES ('我的歌单')")
             .execute(&pool)
             .await?;
     }
 
+    // 拼音列在迁移中无法计算，补全旧数据
+    crate::pinyin_search::backfill_pinyin(&pool).await?;
+
     Ok(pool)
 }
 
@@ -133,3 +146,16 @@ pub fn calculate_file_hash<P: AsRef<Path>>(path: P) -> io::Result<String> {
 
     Ok(hex::encode(hasher.finalize()))
 }
+
+/// 测试用的内存数据库，已经执行了所有迁移。
+/// 只能有一个连接，否则每个连接各自是一个空数据库
+#[cfg(test)]
+pub(crate) async fn test_pool() -> DbPool {
+    let pool = sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    MIGRATOR.run(&pool).await.unwrap();
+    pool
+}
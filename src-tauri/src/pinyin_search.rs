@@ -0,0 +1,184 @@
+// src-tauri/src/pinyin_search.rs
+
+use pinyin::ToPinyin;
+
+use crate::{
+    library_search::escape_like,
+    model::{Music, PinyinSearchResult, Playlist},
+    my_util::DbPool,
+};
+
+/// 一段文本的全拼和首字母，均为小写且不含空格和标点
+pub struct PinyinKeys {
+    pub full: String,
+    pub initials: String,
+}
+
+/// 汉字转为不带声调的拼音；字母和数字按单词保留，首字母取每个单词的第一个字符。
+/// 多音字取最常用的读音
+pub fn pinyin_keys(text: &str) -> PinyinKeys {
+    let mut full = String::new();
+    let mut initials = String::new();
+    let mut in_word = false;
+
+    for ch in text.chars() {
+        if let Some(pinyin) = ch.to_pinyin() {
+            let plain = pinyin.plain();
+            full.push_str(plain);
+            initials.extend(plain.chars().next());
+            in_word = false;
+        } else if ch.is_alphanumeric() {
+            let lower = ch.to_lowercase();
+            if !in_word {
+                initials.extend(ch.to_lowercase());
+            }
+            full.extend(lower);
+            in_word = true;
+        } else {
+            in_word = false;
+        }
+    }
+
+    PinyinKeys { full, initials }
+}
+
+/// 用户输入的拼音可能带空格或隔音符，例如 "zhou jie lun"、"xi'an"
+fn normalize_query(query: &str) -> String {
+    query
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// 补全迁移之前写入的数据，以及导入数据库时直接插入的行
+pub async fn backfill_pinyin(pool: &DbPool) -> Result<(), sqlx::Error> {
+    let musics: Vec<(String, String, String)> = sqlx::query_as(
+        "SELECT song_id, title, artist FROM music WHERE title_pinyin IS NULL OR artist_pinyin IS NULL",
+    )
+    .fetch_all(pool)
+    .await?;
+    let playlists: Vec<(i64, String)> =
+        sqlx::query_as("SELECT id, name FROM playlist WHERE name_pinyin IS NULL")
+            .fetch_all(pool)
+            .await?;
+    if musics.is_empty() && playlists.is_empty() {
+        return Ok(());
+    }
+
+    let mut tx = pool.begin().await?;
+    for (song_id, title, artist) in &musics {
+        let title_keys = pinyin_keys(title);
+        let artist_keys = pinyin_keys(artist);
+        sqlx::query(
+            "UPDATE music SET title_pinyin = ?, title_initials = ?, artist_pinyin = ?, artist_initials = ? WHERE song_id = ?",
+        )
+        .bind(title_keys.full)
+        .bind(title_keys.initials)
+        .bind(artist_keys.full)
+        .bind(artist_keys.initials)
+        .bind(song_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+    for (id, name) in &playlists {
+        let keys = pinyin_keys(name);
+        sqlx::query("UPDATE playlist SET name_pinyin = ?, name_initials = ? WHERE id = ?")
+            .bind(keys.full)
+            .bind(keys.initials)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    println!(
+        "已补全 {} 首歌曲和 {} 个歌单的拼音",
+        musics.len(),
+        playlists.len()
+    );
+    Ok(())
+}
+
+/// 按汉字、全拼或首字母检索歌曲和歌单。
+/// 排序：汉字完全匹配 > 汉字包含 > 首字母/全拼前缀 > 首字母/全拼包含
+pub async fn search_by_pinyin(
+    pool: &DbPool,
+    query: &str,
+    limit: i64,
+) -> Result<PinyinSearchResult, String> {
+    let text = query.trim();
+    let keys = normalize_query(text);
+    if text.is_empty() {
+        return Ok(PinyinSearchResult {
+            music_list: Vec::new(),
+            playlists: Vec::new(),
+        });
+    }
+    let limit = limit.clamp(1, 200);
+    let text_like = format!("%{}%", escape_like(text));
+    // 输入中没有字母数字时 (例如只有标点) 绑定 NULL，不参与拼音匹配
+    let keys = (!keys.is_empty()).then(|| escape_like(&keys));
+    let keys_prefix = keys.as_ref().map(|k| format!("{}%", k));
+    let keys_like = keys.as_ref().map(|k| format!("%{}%", k));
+
+    let music_list = sqlx::query_as::<_, Music>(
+        r#"
+        SELECT * FROM (
+            SELECT m.*,
+                CASE
+                    WHEN m.title = ?1 OR m.artist = ?1 THEN 0
+                    WHEN m.title LIKE ?2 ESCAPE '\' OR m.artist LIKE ?2 ESCAPE '\' THEN 1
+                    WHEN m.title_initials LIKE ?3 ESCAPE '\' OR m.artist_initials LIKE ?3 ESCAPE '\'
+                      OR m.title_pinyin LIKE ?3 ESCAPE '\' OR m.artist_pinyin LIKE ?3 ESCAPE '\' THEN 2
+                    WHEN m.title_initials LIKE ?4 ESCAPE '\' OR m.artist_initials LIKE ?4 ESCAPE '\'
+                      OR m.title_pinyin LIKE ?4 ESCAPE '\' OR m.artist_pinyin LIKE ?4 ESCAPE '\' THEN 3
+                END AS match_rank
+            FROM music m
+        )
+        WHERE match_rank IS NOT NULL
+        ORDER BY match_rank, length(title), title
+        LIMIT ?5
+        "#,
+    )
+    .bind(text)
+    .bind(&text_like)
+    .bind(&keys_prefix)
+    .bind(&keys_like)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let playlists = sqlx::query_as::<_, Playlist>(
+        r#"
+        SELECT * FROM (
+            SELECT p.*,
+                CASE
+                    WHEN p.name = ?1 THEN 0
+                    WHEN p.name LIKE ?2 ESCAPE '\' THEN 1
+                    WHEN p.name_initials LIKE ?3 ESCAPE '\' OR p.name_pinyin LIKE ?3 ESCAPE '\' THEN 2
+                    WHEN p.name_initials LIKE ?4 ESCAPE '\' OR p.name_pinyin LIKE ?4 ESCAPE '\' THEN 3
+                END AS match_rank
+            FROM playlist p
+            WHERE p.deleted_at IS NULL
+        )
+        WHERE match_rank IS NOT NULL
+        ORDER BY match_rank, length(name), name
+        LIMIT ?5
+        "#,
+    )
+    .bind(text)
+    .bind(&text_like)
+    .bind(&keys_prefix)
+    .bind(&keys_like)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(PinyinSearchResult {
+        music_list,
+        playlists,
+    })
+}
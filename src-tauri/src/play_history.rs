@@ -0,0 +1,203 @@
+// src-tauri/src/play_history.rs
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::{
+    model::{PlayHistoryItem, PlayHistoryPage, RecordPlayPayload},
+    my_util::DbPool,
+};
+
+/// 收听超过该时长 (或歌曲时长的一半) 才算一次有效收听
+const MIN_LISTEN_SECS: f64 = 30.0;
+
+/// 是否算作一次真正的收听：完整播放，或收听时长达到阈值，较短的歌曲以时长的一半为准。
+/// 记录收听和统计查询都使用这条 SQL (需要 play_event 别名 e 和 music 别名 m)
+pub fn real_listen_sql() -> String {
+    format!(
+        "(e.completed = 1 OR e.listened_secs >= MIN({min}, COALESCE(NULLIF(m.duration_secs, 0) / 2.0, {min})))",
+        min = MIN_LISTEN_SECS,
+    )
+}
+
+/// 统一为 UTC 的 RFC 3339 格式，保证按字符串排序即按时间排序
+fn normalize_started_at(started_at: Option<&str>) -> Result<String, String> {
+    let time = match started_at {
+        Some(s) => DateTime::parse_from_rfc3339(s)
+            .map_err(|e| format!("无效的开始时间 {}: {}", s, e))?
+            .with_timezone(&Utc),
+        None => Utc::now(),
+    };
+    Ok(time.to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+/// 记录一次收听，返回记录 ID。有效收听会同时更新 music.last_played_at
+pub async fn record_play(pool: &DbPool, payload: RecordPlayPayload) -> Result<i64, String> {
+    let started_at = normalize_started_at(payload.started_at.as_deref())?;
+    let listened_secs = payload.listened_secs.max(0.0);
+
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+
+    let exists: Option<i64> = sqlx::query_scalar("SELECT 1 FROM music WHERE song_id = ?")
+        .bind(&payload.song_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    if exists.is_none() {
+        return Err("数据库中未找到该歌曲".to_string());
+    }
+
+    let event_id = sqlx::query(
+        "INSERT INTO play_event (song_id, started_at, listened_secs, completed, skipped, playlist_id) VALUES (?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&payload.song_id)
+    .bind(&started_at)
+    .bind(listened_secs)
+    .bind(payload.completed)
+    .bind(payload.skipped && !payload.completed)
+    .bind(payload.playlist_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?
+    .last_insert_rowid();
+
+    // 只有有效收听才更新，补记较早的收听时不能让 last_played_at 倒退
+    sqlx::query(&format!(
+        r#"
+        UPDATE music SET last_played_at = ?1
+        WHERE song_id = ?2
+          AND (last_played_at IS NULL OR last_played_at < ?1)
+          AND EXISTS (
+              SELECT 1 FROM play_event e JOIN music m ON m.song_id = e.song_id
+              WHERE e.id = ?3 AND {real}
+          )
+        "#,
+        real = real_listen_sql(),
+    ))
+    .bind(&started_at)
+    .bind(&payload.song_id)
+    .bind(event_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(event_id)
+}
+
+/// 按时间倒序分页查询播放历史，可以只看某一首歌
+pub async fn get_play_history(
+    pool: &DbPool,
+    song_id: Option<&str>,
+    limit: i64,
+    offset: i64,
+) -> Result<PlayHistoryPage, String> {
+    let limit = limit.clamp(1, 500);
+    let offset = offset.max(0);
+
+    let total: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM play_event WHERE ?1 IS NULL OR song_id = ?1")
+            .bind(song_id)
+            .fetch_one(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+    let items = sqlx::query_as::<_, PlayHistoryItem>(
+        r#"
+        SELECT
+            e.id AS event_id, e.started_at, e.listened_secs, e.completed, e.skipped, e.playlist_id,
+            m.*
+        FROM play_event e
+        JOIN music m ON m.song_id = e.song_id
+        WHERE ?1 IS NULL OR e.song_id = ?1
+        ORDER BY e.started_at DESC, e.id DESC
+        LIMIT ?2 OFFSET ?3
+        "#,
+    )
+    .bind(song_id)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(PlayHistoryPage { items, total })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::my_util::test_pool;
+
+    fn payload(song_id: &str, listened_secs: f64, completed: bool) -> RecordPlayPayload {
+        RecordPlayPayload {
+            song_id: song_id.to_string(),
+            started_at: None,
+            listened_secs,
+            completed,
+            skipped: false,
+            playlist_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn short_songs_use_half_duration() {
+        let pool = test_pool().await;
+        let cases = [
+            (0.0, true, Some(200), true),
+            (30.0, false, Some(200), true),
+            (29.9, false, Some(200), false),
+            (10.0, false, Some(20), true),
+            (9.0, false, Some(20), false),
+            (29.0, false, Some(0), false),
+            (30.0, false, None, true),
+        ];
+        for (i, (listened_secs, completed, duration_secs, expected)) in
+            cases.into_iter().enumerate()
+        {
+            let song_id = i.to_string();
+            sqlx::query(
+                "INSERT INTO music (song_id, title, artist, url, duration_secs) VALUES (?, ?, '', '', ?)",
+            )
+            .bind(&song_id)
+            .bind(&song_id)
+            .bind(duration_secs)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+            record_play(&pool, payload(&song_id, listened_secs, completed))
+                .await
+                .unwrap();
+            let last_played_at: Option<String> =
+                sqlx::query_scalar("SELECT last_played_at FROM music WHERE song_id = ?")
+                    .bind(&song_id)
+                    .fetch_one(&pool)
+                    .await
+                    .unwrap();
+            assert_eq!(
+                last_played_at.is_some(),
+                expected,
+                "{} {} {:?}",
+                listened_secs,
+                completed,
+                duration_secs
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn unknown_song_is_an_error() {
+        let pool = test_pool().await;
+        assert!(
+            record_play(&pool, payload("missing", 60.0, true))
+                .await
+                .is_err()
+        );
+        let events: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM play_event")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(events, 0);
+    }
+}
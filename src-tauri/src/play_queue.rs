@@ -0,0 +1,512 @@
+// src-tauri/src/play_queue.rs
+
+//! 播放队列和播放状态保存在数据库中，应用被系统结束 (例如 Android 后台回收) 后可以恢复。
+//! 每次修改队列后发送 play-queue-changed 事件，负载为最新的 PlayQueueState。
+
+use sqlx::SqliteConnection;
+use tauri::{AppHandle, Emitter};
+
+use crate::{
+    model::{Music, PlayQueueState},
+    music::save_music,
+    my_util::DbPool,
+    source::canonical_song_id,
+};
+
+pub const PLAY_QUEUE_EVENT: &str = "play-queue-changed";
+
+const PLAY_MODES: [&str; 3] = ["sequence", "single", "shuffle"];
+
+#[derive(sqlx::FromRow)]
+struct Session {
+    current_index: i64,
+    /// 正在播放的歌曲，恢复队列时用来重新定位 current_index
+    song_id: Option<String>,
+    play_mode: String,
+    position_secs: f64,
+    playlist_id: Option<i64>,
+}
+
+async fn load_queue_ids(conn: &mut SqliteConnection) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar("SELECT song_id FROM play_queue ORDER BY position")
+        .fetch_all(&mut *conn)
+        .await
+}
+
+async fn write_queue_ids(
+    conn: &mut SqliteConnection,
+    song_ids: &[String],
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM play_queue")
+        .execute(&mut *conn)
+        .await?;
+    for (position, song_id) in song_ids.iter().enumerate() {
+        sqlx::query("INSERT INTO play_queue (position, song_id) VALUES (?, ?)")
+            .bind(position as i64)
+            .bind(song_id)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+async fn load_session(conn: &mut SqliteConnection) -> Result<Session, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT current_index, song_id, play_mode, CAST(position_secs AS REAL) AS position_secs,
+            playlist_id
+         FROM play_session WHERE id = 1",
+    )
+    .fetch_one(&mut *conn)
+    .await
+}
+
+async fn write_session(conn: &mut SqliteConnection, session: &Session) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE play_session SET current_index = ?, song_id = ?, play_mode = ?, position_secs = ?,
+            playlist_id = ?, updated_at = strftime('%Y-%m-%d %H:%M:%f', 'now', 'localtime')
+         WHERE id = 1",
+    )
+    .bind(session.current_index)
+    .bind(&session.song_id)
+    .bind(&session.play_mode)
+    .bind(session.position_secs)
+    .bind(session.playlist_id)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_play_queue(pool: &DbPool) -> Result<PlayQueueState, String> {
+    // duration_secs 列为 INTEGER，整数值不能直接解码为 f64
+    let songs: Vec<Music> = sqlx::query_as(
+        r#"
+        SELECT
+            m.song_id, m.title, m.artist, m.url, m.lyric, m.cover_url,
+            CAST(m.duration_secs AS REAL) AS duration_secs,
+            m.play_url, m.download_mp3, m.download_extra, m.download_mp3_id, m.play_id,
+            m.file_path, m.last_played_at, m.source, m.format, m.mime
+        FROM play_queue q
+        INNER JOIN music m ON q.song_id = m.song_id
+        ORDER BY q.position
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    let session = load_session(&mut conn).await.map_err(|e| e.to_string())?;
+
+    Ok(PlayQueueState {
+        songs,
+        current_index: session.current_index,
+        play_mode: session.play_mode,
+        position_secs: session.position_secs,
+        playlist_id: session.playlist_id,
+    })
+}
+
+fn emit_queue_changed(app_handle: &AppHandle, state: &PlayQueueState) {
+    if let Err(e) = app_handle.emit(PLAY_QUEUE_EVENT, state) {
+        eprintln!("发送播放队列事件失败: {}", e);
+    }
+}
+
+/// 在一个事务中修改队列和播放状态，提交后发送最新的队列
+async fn modify_queue(
+    app_handle: &AppHandle,
+    pool: &DbPool,
+    modify: impl FnOnce(&mut Vec<String>, &mut Session) -> Result<(), String>,
+) -> Result<PlayQueueState, String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let mut song_ids = load_queue_ids(&mut tx).await.map_err(|e| e.to_string())?;
+    let mut session = load_session(&mut tx).await.map_err(|e| e.to_string())?;
+
+    modify(&mut song_ids, &mut session)?;
+    if song_ids.is_empty() {
+        session.current_index = -1;
+    } else {
+        session.current_index = session.current_index.clamp(-1, song_ids.len() as i64 - 1);
+    }
+    session.song_id = usize::try_from(session.current_index)
+        .ok()
+        .and_then(|i| song_ids.get(i))
+        .cloned();
+
+    write_queue_ids(&mut tx, &song_ids)
+        .await
+        .map_err(|e| e.to_string())?;
+    write_session(&mut tx, &session)
+        .await
+        .map_err(|e| e.to_string())?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    let state = get_play_queue(pool).await?;
+    emit_queue_changed(app_handle, &state);
+    Ok(state)
+}
+
+/// 与 save_music 一样补全旧版本没有音源前缀的 song_id
+fn song_ids_of(songs: &[Music]) -> Vec<String> {
+    songs
+        .iter()
+        .map(|m| canonical_song_id(&m.song_id))
+        .collect()
+}
+
+/// 用新的歌曲列表替换队列并从 start_index 开始播放
+pub async fn set_play_queue(
+    app_handle: &AppHandle,
+    pool: &DbPool,
+    songs: Vec<Music>,
+    start_index: i64,
+    playlist_id: Option<i64>,
+) -> Result<PlayQueueState, String> {
+    // 搜索结果中的歌曲可能还没有保存到曲库
+    save_music(pool, &songs).await.map_err(|e| e.to_string())?;
+    let new_ids = song_ids_of(&songs);
+    modify_queue(app_handle, pool, |song_ids, session| {
+        *song_ids = new_ids;
+        session.current_index = start_index;
+        session.position_secs = 0.0;
+        session.playlist_id = playlist_id;
+        Ok(())
+    })
+    .await
+}
+
+/// 添加到队列末尾
+pub async fn enqueue(
+    app_handle: &AppHandle,
+    pool: &DbPool,
+    songs: Vec<Music>,
+) -> Result<PlayQueueState, String> {
+    save_music(pool, &songs).await.map_err(|e| e.to_string())?;
+    let new_ids = song_ids_of(&songs);
+    modify_queue(app_handle, pool, |song_ids, _| {
+        song_ids.extend(new_ids);
+        Ok(())
+    })
+    .await
+}
+
+/// 插入到正在播放的歌曲之后，作为下一首播放
+pub async fn insert_next(
+    app_handle: &AppHandle,
+    pool: &DbPool,
+    songs: Vec<Music>,
+) -> Result<PlayQueueState, String> {
+    save_music(pool, &songs).await.map_err(|e| e.to_string())?;
+    let new_ids = song_ids_of(&songs);
+    modify_queue(app_handle, pool, |song_ids, session| {
+        let at = ((session.current_index + 1).max(0) as usize).min(song_ids.len());
+        song_ids.splice(at..at, new_ids);
+        Ok(())
+    })
+    .await
+}
+
+/// 按下标移除。移除正在播放的歌曲时，当前下标指向它后面的歌曲
+pub async fn remove_from_queue(
+    app_handle: &AppHandle,
+    pool: &DbPool,
+    indices: Vec<usize>,
+) -> Result<PlayQueueState, String> {
+    modify_queue(app_handle, pool, |song_ids, session| {
+        remove_indices(song_ids, session, &indices);
+        Ok(())
+    })
+    .await
+}
+
+fn remove_indices(song_ids: &mut Vec<String>, session: &mut Session, indices: &[usize]) {
+    let mut removed_before = 0;
+    let mut removed_current = false;
+    let mut index = 0;
+    song_ids.retain(|_| {
+        let keep = !indices.contains(&index);
+        if !keep {
+            if (index as i64) < session.current_index {
+                removed_before += 1;
+            } else if index as i64 == session.current_index {
+                removed_current = true;
+            }
+        }
+        index += 1;
+        keep
+    });
+    if session.current_index >= 0 {
+        session.current_index -= removed_before;
+    }
+    if removed_current {
+        session.position_secs = 0.0;
+    }
+}
+
+/// 把 from 位置的歌曲移动到 to，正在播放的歌曲跟着移动
+pub async fn move_in_queue(
+    app_handle: &AppHandle,
+    pool: &DbPool,
+    from: usize,
+    to: usize,
+) -> Result<PlayQueueState, String> {
+    modify_queue(app_handle, pool, |song_ids, session| {
+        move_song(song_ids, session, from, to)
+    })
+    .await
+}
+
+fn move_song(
+    song_ids: &mut Vec<String>,
+    session: &mut Session,
+    from: usize,
+    to: usize,
+) -> Result<(), String> {
+    if from >= song_ids.len() || to >= song_ids.len() {
+        return Err("队列下标超出范围".to_string());
+    }
+    let song_id = song_ids.remove(from);
+    song_ids.insert(to, song_id);
+
+    let (from, to, current) = (from as i64, to as i64, session.current_index);
+    if current == from {
+        session.current_index = to;
+    } else if from < current && current <= to {
+        session.current_index -= 1;
+    } else if to <= current && current < from {
+        session.current_index += 1;
+    }
+    Ok(())
+}
+
+/// 切换到队列中的另一首歌 (上一首、下一首或点击队列中的歌曲)
+pub async fn set_queue_index(
+    app_handle: &AppHandle,
+    pool: &DbPool,
+    index: i64,
+) -> Result<PlayQueueState, String> {
+    modify_queue(app_handle, pool, |song_ids, session| {
+        if index >= song_ids.len() as i64 {
+            return Err("队列下标超出范围".to_string());
+        }
+        session.current_index = index;
+        session.position_secs = 0.0;
+        Ok(())
+    })
+    .await
+}
+
+pub async fn set_play_mode(
+    app_handle: &AppHandle,
+    pool: &DbPool,
+    mode: String,
+) -> Result<PlayQueueState, String> {
+    if !PLAY_MODES.contains(&mode.as_str()) {
+        return Err(format!("不支持的播放模式: {}", mode));
+    }
+    modify_queue(app_handle, pool, |_, session| {
+        session.play_mode = mode;
+        Ok(())
+    })
+    .await
+}
+
+pub async fn clear_play_queue(
+    app_handle: &AppHandle,
+    pool: &DbPool,
+) -> Result<PlayQueueState, String> {
+    modify_queue(app_handle, pool, |song_ids, session| {
+        song_ids.clear();
+        session.position_secs = 0.0;
+        session.playlist_id = None;
+        Ok(())
+    })
+    .await
+}
+
+/// 播放过程中定期保存进度。只更新播放状态，不发送事件
+pub async fn save_play_position(pool: &DbPool, position_secs: f64) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE play_session SET position_secs = ?,
+            updated_at = strftime('%Y-%m-%d %H:%M:%f', 'now', 'localtime')
+         WHERE id = 1",
+    )
+    .bind(position_secs.max(0.0))
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// 重新定位正在播放的歌曲。positions 为队列中剩余歌曲原来的位置，
+/// 被删除的歌曲留下空位，当前歌曲之前每有一个空位，下标就前移一位。
+/// 返回新的下标，以及正在播放的歌曲是否还在队列中
+fn relocate_current(
+    song_ids: &[String],
+    positions: &[i64],
+    current_index: i64,
+    song_id: Option<&str>,
+) -> (i64, bool) {
+    if current_index < 0 {
+        return (-1, true);
+    }
+    let shifted = positions.partition_point(|p| *p < current_index);
+    match song_id {
+        Some(id) if song_ids.get(shifted).is_some_and(|s| s == id) => (shifted as i64, true),
+        Some(id) => match song_ids.iter().position(|s| s == id) {
+            Some(index) => (index as i64, true),
+            // 歌曲已被删除，接着播放它后面的歌曲
+            None => (shifted as i64, false),
+        },
+        // 旧版本没有记录 song_id，只能按位置判断
+        None => (
+            shifted as i64,
+            positions.get(shifted) == Some(&current_index),
+        ),
+    }
+}
+
+/// 启动时恢复上次的播放队列：曲库中已删除的歌曲会随外键一起从队列中移除，
+/// 这里重新编号并按 song_id 校正当前下标，然后发送给前端
+pub async fn restore_session(app_handle: &AppHandle, pool: &DbPool) -> Result<(), String> {
+    let positions: Vec<i64> =
+        sqlx::query_scalar("SELECT position FROM play_queue ORDER BY position")
+            .fetch_all(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+    modify_queue(app_handle, pool, |song_ids, session| {
+        let (index, found) = relocate_current(
+            song_ids,
+            &positions,
+            session.current_index,
+            session.song_id.as_deref(),
+        );
+        session.current_index = index;
+        if !found {
+            session.position_secs = 0.0;
+        }
+        Ok(())
+    })
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    fn session(current_index: i64) -> Session {
+        Session {
+            current_index,
+            song_id: None,
+            play_mode: "sequence".to_string(),
+            position_secs: 42.0,
+            playlist_id: None,
+        }
+    }
+
+    #[test]
+    fn song_ids_of_adds_missing_source_prefix() {
+        let songs: Vec<Music> = ["7516", "gequhai:1093", "local:0a1b"]
+            .into_iter()
+            .map(|song_id| {
+                serde_json::from_value(serde_json::json!({
+                    "song_id": song_id, "title": "", "artist": "", "url": ""
+                }))
+                .unwrap()
+            })
+            .collect();
+        assert_eq!(
+            song_ids_of(&songs),
+            ids(&["gequbao:7516", "gequhai:1093", "local:0a1b"])
+        );
+    }
+
+    #[test]
+    fn remove_before_current_shifts_index() {
+        let mut song_ids = ids(&["a", "b", "c", "d"]);
+        let mut session = session(2);
+        remove_indices(&mut song_ids, &mut session, &[0, 3]);
+        assert_eq!(song_ids, ids(&["b", "c"]));
+        assert_eq!(session.current_index, 1);
+        assert_eq!(session.position_secs, 42.0);
+    }
+
+    #[test]
+    fn remove_current_moves_to_next_song() {
+        let mut song_ids = ids(&["a", "b", "c"]);
+        let mut session = session(1);
+        remove_indices(&mut song_ids, &mut session, &[1]);
+        assert_eq!(song_ids, ids(&["a", "c"]));
+        assert_eq!(session.current_index, 1);
+        assert_eq!(session.position_secs, 0.0);
+    }
+
+    #[test]
+    fn remove_without_current_keeps_minus_one() {
+        let mut song_ids = ids(&["a", "b"]);
+        let mut session = session(-1);
+        remove_indices(&mut song_ids, &mut session, &[0]);
+        assert_eq!(session.current_index, -1);
+    }
+
+    #[test]
+    fn move_keeps_current_song() {
+        // (from, to, 移动前的下标, 移动后的下标)
+        let cases = [
+            (1, 3, 1, 3),
+            (0, 2, 1, 0),
+            (0, 2, 2, 1),
+            (3, 1, 1, 2),
+            (3, 1, 2, 3),
+            (0, 1, 3, 3),
+            (2, 2, 2, 2),
+        ];
+        for (from, to, current, expected) in cases {
+            let mut song_ids = ids(&["a", "b", "c", "d"]);
+            let current_id = song_ids[current as usize].clone();
+            let mut session = session(current);
+            move_song(&mut song_ids, &mut session, from, to).unwrap();
+            assert_eq!(session.current_index, expected, "{} -> {}", from, to);
+            assert_eq!(song_ids[expected as usize], current_id);
+        }
+        let mut session = session(0);
+        assert!(move_song(&mut ids(&["a"]), &mut session, 0, 1).is_err());
+    }
+
+    #[test]
+    fn relocate_after_deleted_songs() {
+        // 原队列 a b c d e，正在播放 d (下标 3)，b 被删除
+        let song_ids = ids(&["a", "c", "d", "e"]);
+        assert_eq!(
+            relocate_current(&song_ids, &[0, 2, 3, 4], 3, Some("d")),
+            (2, true)
+        );
+        // d 本身也被删除，接着播放 e
+        let song_ids = ids(&["a", "c", "e"]);
+        assert_eq!(
+            relocate_current(&song_ids, &[0, 2, 4], 3, Some("d")),
+            (2, false)
+        );
+        // 队列末尾的歌曲被删除
+        assert_eq!(
+            relocate_current(&ids(&["a"]), &[0], 1, Some("b")),
+            (1, false)
+        );
+        // 同一首歌出现多次时保持原来那一次
+        let song_ids = ids(&["x", "a", "x"]);
+        assert_eq!(
+            relocate_current(&song_ids, &[0, 2, 3], 3, Some("x")),
+            (2, true)
+        );
+        assert_eq!(
+            relocate_current(&song_ids, &[0, 1, 2], -1, None),
+            (-1, true)
+        );
+        assert_eq!(relocate_current(&song_ids, &[0, 2, 3], 2, None), (1, true));
+        assert_eq!(relocate_current(&song_ids, &[0, 2, 3], 1, None), (1, false));
+    }
+}
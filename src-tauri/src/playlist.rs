@@ -4,14 +4,17 @@ use std::{
 };
 
 use chrono::Local;
-use sqlx::SqlitePool;
+use sqlx::{SqliteConnection, SqlitePool};
 use tauri::AppHandle;
 use tauri::Manager;
 use tokio::fs;
 
 use crate::{
     model::{AppSetting, Music, Playlist, PlaylistInfo, PlaylistMusicItem},
-    my_util::{DbPool, get_app_setting},
+    my_util::{DbPool, MIGRATOR, resolve_export_dir},
+    pinyin_search::{backfill_pinyin, pinyin_keys},
+    smart_playlist,
+    source::split_song_id,
 };
 
 pub async fn create_playlist(pool: &DbPool) -> Result<i64, sqlx::Error> {
@@ -20,24 +23,125 @@ pub async fn create_playlist(pool: &DbPool) -> Result<i64, sqlx::Error> {
         .await?;
 
     let new_name = format!("我的歌单{}", count + 1);
+    let keys = pinyin_keys(&new_name);
 
-    let result = sqlx::query("INSERT INTO playlist (name) VALUES (?)")
-        .bind(new_name)
-        .execute(pool)
-        .await?;
+    let result =
+        sqlx::query("INSERT INTO playlist (name, name_pinyin, name_initials) VALUES (?, ?, ?)")
+            .bind(new_name)
+            .bind(keys.full)
+            .bind(keys.initials)
+            .execute(pool)
+            .await?;
 
     Ok(result.last_insert_rowid())
 }
 
-pub async fn delete_playlist(pool: &DbPool, playlist_id: i64) -> Result<(), sqlx::Error> {
-    sqlx::query("DELETE FROM playlist_music WHERE playlist_id = ?")
-        .bind(playlist_id)
-        .execute(pool)
-        .await?;
-    sqlx::query("DELETE FROM playlist WHERE id = ?")
+/// 新建指定名称的歌单并按顺序加入歌曲 (导入歌单文件时使用)，封面取第一首有封面的歌曲
+pub async fn create_playlist_with_songs(
+    pool: &DbPool,
+    name: &str,
+    song_ids: &[String],
+) -> Result<i64, sqlx::Error> {
+    let keys = pinyin_keys(name);
+    let mut tx = pool.begin().await?;
+
+    let result =
+        sqlx::query("INSERT INTO playlist (name, name_pinyin, name_initials) VALUES (?, ?, ?)")
+            .bind(name)
+            .bind(keys.full)
+            .bind(keys.initials)
+            .execute(&mut *tx)
+            .await?;
+    let playlist_id = result.last_insert_rowid();
+
+    // 同一首歌只保留第一次出现的位置
+    let mut unique_ids: Vec<&String> = Vec::with_capacity(song_ids.len());
+    for song_id in song_ids {
+        if !unique_ids.contains(&song_id) {
+            unique_ids.push(song_id);
+        }
+    }
+    for (position, song_id) in unique_ids.into_iter().enumerate() {
+        sqlx::query("INSERT INTO playlist_music (playlist_id, song_id, position) VALUES (?, ?, ?)")
+            .bind(playlist_id)
+            .bind(song_id)
+            .bind(position as i64)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    sqlx::query(
+        r#"
+            UPDATE playlist SET cover_path = (
+                SELECT m.cover_url FROM playlist_music pm
+                INNER JOIN music m ON pm.song_id = m.song_id
+                WHERE pm.playlist_id = ? AND m.cover_url IS NOT NULL
+                ORDER BY pm.position LIMIT 1
+            )
+            WHERE id = ?
+        "#,
+    )
+    .bind(playlist_id)
+    .bind(playlist_id)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(playlist_id)
+}
+
+/// 按顺序把歌曲追加到歌单末尾 (手动顺序)，已在歌单中的歌曲跳过
+pub async fn append_songs_to_playlist(
+    pool: &DbPool,
+    playlist_id: i64,
+    song_ids: &[String],
+) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    if !is_live_playlist(&mut tx, playlist_id)
+        .await
+        .map_err(|e| e.to_string())?
+    {
+        return Err("歌单不存在或已被删除".to_string());
+    }
+    if is_smart_playlist(&mut tx, playlist_id)
+        .await
+        .map_err(|e| e.to_string())?
+    {
+        return Err("智能歌单的歌曲由规则决定，不能手动添加或移除".to_string());
+    }
+
+    let mut next_position: i64 = sqlx::query_scalar(
+        "SELECT COALESCE(MAX(position) + 1, 0) FROM playlist_music WHERE playlist_id = ?",
+    )
+    .bind(playlist_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    for song_id in song_ids {
+        let result = sqlx::query(
+            "INSERT OR IGNORE INTO playlist_music (playlist_id, song_id, position) VALUES (?, ?, ?)",
+        )
         .bind(playlist_id)
-        .execute(pool)
-        .await?;
+        .bind(song_id)
+        .bind(next_position)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+        next_position += result.rows_affected() as i64;
+    }
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// 把歌单移入回收站，歌曲关系保留以便恢复，过期后由 trash::purge_expired_trash 彻底删除
+pub async fn delete_playlist(pool: &DbPool, playlist_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE playlist SET deleted_at = strftime('%Y-%m-%d %H:%M:%f', 'now', 'localtime') WHERE id = ? AND deleted_at IS NULL",
+    )
+    .bind(playlist_id)
+    .execute(pool)
+    .await?;
     Ok(())
 }
 
@@ -46,8 +150,11 @@ pub async fn rename_playlist(
     playlist_id: i64,
     new_name: String,
 ) -> Result<(), sqlx::Error> {
-    sqlx::query("UPDATE playlist SET name = ? WHERE id = ?")
+    let keys = pinyin_keys(&new_name);
+    sqlx::query("UPDATE playlist SET name = ?, name_pinyin = ?, name_initials = ? WHERE id = ?")
         .bind(new_name)
+        .bind(keys.full)
+        .bind(keys.initials)
         .bind(playlist_id)
         .execute(pool)
         .await?;
@@ -67,21 +174,64 @@ pub async fn get_all_playlists(
             p.created_at,
             p.updated_at,
             COUNT(ps.song_id) as song_count,
-            EXISTS(SELECT 1 FROM playlist_music WHERE playlist_id = p.id AND song_id = ?) as is_in
+            EXISTS(SELECT 1 FROM playlist_music WHERE playlist_id = p.id AND song_id = ?) as is_in,
+            p.smart_rules IS NOT NULL as is_smart,
+            p.sort_mode
         FROM
             playlist p
         LEFT JOIN
             playlist_music ps ON p.id = ps.playlist_id
+        WHERE
+            p.deleted_at IS NULL
         GROUP BY
             p.id
         ORDER BY
             p.created_at ASC
     "#;
 
-    let playlists = sqlx::query_as::<_, PlaylistInfo>(sql)
+    let mut playlists = sqlx::query_as::<_, PlaylistInfo>(sql)
         .bind(song_id) // 3. 绑定可选参数。如果 song_id 是 None，sqlx 会将其作为 NULL 绑定
         .fetch_all(pool)
         .await?;
+
+    // 智能歌单的歌曲数量按规则实时计算
+    for playlist in playlists.iter_mut().filter(|p| p.is_smart) {
+        if let Some(rules) = smart_playlist::get_smart_playlist_rules(pool, playlist.id).await? {
+            playlist.song_count = smart_playlist::count_smart_playlist_music(pool, &rules).await?;
+        }
+    }
+    Ok(playlists)
+}
+
+/// 托盘菜单中的最近歌单：正在播放的歌单排在最前，其次按最近一次从歌单中播放的时间，
+/// 没有播放过的歌单按修改时间排在后面
+pub async fn get_recent_playlists(
+    pool: &DbPool,
+    limit: usize,
+) -> Result<Vec<PlaylistInfo>, sqlx::Error> {
+    let mut playlists = get_all_playlists(pool, None).await?;
+    let last_played: HashMap<i64, String> = sqlx::query_as(
+        "SELECT playlist_id, MAX(started_at) FROM play_event
+         WHERE playlist_id IS NOT NULL GROUP BY playlist_id",
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .collect();
+    let playing_id: Option<i64> =
+        sqlx::query_scalar("SELECT playlist_id FROM play_session WHERE id = 1")
+            .fetch_optional(pool)
+            .await?
+            .flatten();
+
+    playlists.sort_by_key(|p| {
+        std::cmp::Reverse((
+            Some(p.id) == playing_id,
+            last_played.get(&p.id).cloned(),
+            p.updated_at.clone(),
+        ))
+    });
+    playlists.truncate(limit);
     Ok(playlists)
 }
 
@@ -89,7 +239,12 @@ pub async fn get_music_by_playlist_id(
     pool: &DbPool,
     playlist_id: i64,
 ) -> Result<Vec<PlaylistMusicItem>, sqlx::Error> {
-    let music_list = sqlx::query_as::<_, PlaylistMusicItem>(
+    if let Some(rules) = smart_playlist::get_smart_playlist_rules(pool, playlist_id).await? {
+        return smart_playlist::get_smart_playlist_music(pool, &rules).await;
+    }
+
+    let order_by = get_playlist_order_clause(pool, playlist_id).await?;
+    let sql = format!(
         r#"
             SELECT
                 m.song_id,
@@ -104,16 +259,224 @@ pub async fn get_music_by_playlist_id(
             WHERE
                 pm.playlist_id = ?
             ORDER BY
-                pm.position DESC
+                {}
         "#,
-    )
-    .bind(playlist_id)
-    .fetch_all(pool)
-    .await?;
+        order_by
+    );
+    let music_list = sqlx::query_as::<_, PlaylistMusicItem>(&sql)
+        .bind(playlist_id)
+        .fetch_all(pool)
+        .await?;
 
     Ok(music_list)
 }
 
+const MANUAL_ORDER: &str = "pm.position ASC, pm.added_to_list_at DESC";
+
+/// 歌单内歌曲排序方式对应的 ORDER BY 子句 (pm 为 playlist_music，m 为 music)。
+/// 未知的排序方式返回 None
+pub fn playlist_order_clause(sort_mode: &str) -> Option<&'static str> {
+    match sort_mode {
+        "manual" => Some(MANUAL_ORDER),
+        "added_desc" => Some("pm.added_to_list_at DESC, pm.position ASC"),
+        "title" => Some("m.title COLLATE NOCASE ASC, pm.position ASC"),
+        "artist" => {
+            Some("m.artist COLLATE NOCASE ASC, m.title COLLATE NOCASE ASC, pm.position ASC")
+        }
+        "last_played" => Some("m.last_played_at IS NULL, m.last_played_at DESC, pm.position ASC"),
+        _ => None,
+    }
+}
+
+/// 读取歌单当前排序方式对应的 ORDER BY 子句，歌单不存在时按手动顺序
+pub async fn get_playlist_order_clause(
+    pool: &DbPool,
+    playlist_id: i64,
+) -> Result<&'static str, sqlx::Error> {
+    let sort_mode: Option<String> =
+        sqlx::query_scalar("SELECT sort_mode FROM playlist WHERE id = ?")
+            .bind(playlist_id)
+            .fetch_optional(pool)
+            .await?;
+    Ok(sort_mode
+        .as_deref()
+        .and_then(playlist_order_clause)
+        .unwrap_or(MANUAL_ORDER))
+}
+
+/// 歌单存在且不在回收站中
+pub async fn is_live_playlist(
+    conn: &mut SqliteConnection,
+    playlist_id: i64,
+) -> Result<bool, sqlx::Error> {
+    let live: Option<i64> =
+        sqlx::query_scalar("SELECT 1 FROM playlist WHERE id = ? AND deleted_at IS NULL")
+            .bind(playlist_id)
+            .fetch_optional(conn)
+            .await?;
+    Ok(live.is_some())
+}
+
+pub async fn is_smart_playlist(
+    conn: &mut SqliteConnection,
+    playlist_id: i64,
+) -> Result<bool, sqlx::Error> {
+    let is_smart: Option<bool> =
+        sqlx::query_scalar("SELECT smart_rules IS NOT NULL FROM playlist WHERE id = ?")
+            .bind(playlist_id)
+            .fetch_optional(conn)
+            .await?;
+    Ok(is_smart.unwrap_or(false))
+}
+
+pub async fn set_playlist_sort_mode(
+    pool: &DbPool,
+    playlist_id: i64,
+    sort_mode: &str,
+) -> Result<(), String> {
+    if playlist_order_clause(sort_mode).is_none() {
+        return Err(format!("不支持的排序方式: {}", sort_mode));
+    }
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    if is_smart_playlist(&mut conn, playlist_id)
+        .await
+        .map_err(|e| e.to_string())?
+    {
+        return Err("智能歌单的排序方式在规则中设置".to_string());
+    }
+    sqlx::query("UPDATE playlist SET sort_mode = ? WHERE id = ?")
+        .bind(sort_mode)
+        .bind(playlist_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// 按歌单当前的显示顺序读取歌曲 ID
+async fn get_displayed_order(
+    conn: &mut SqliteConnection,
+    playlist_id: i64,
+) -> Result<Vec<String>, sqlx::Error> {
+    let sort_mode: String = sqlx::query_scalar("SELECT sort_mode FROM playlist WHERE id = ?")
+        .bind(playlist_id)
+        .fetch_one(&mut *conn)
+        .await?;
+    let order_by = playlist_order_clause(&sort_mode).unwrap_or(MANUAL_ORDER);
+    let sql = format!(
+        "SELECT pm.song_id FROM playlist_music pm INNER JOIN music m ON pm.song_id = m.song_id WHERE pm.playlist_id = ? ORDER BY {}",
+        order_by
+    );
+    sqlx::query_scalar(&sql)
+        .bind(playlist_id)
+        .fetch_all(conn)
+        .await
+}
+
+/// 按给定顺序把 position 写为 0..n-1
+async fn write_positions(
+    conn: &mut SqliteConnection,
+    playlist_id: i64,
+    song_ids: &[String],
+) -> Result<(), sqlx::Error> {
+    for (position, song_id) in song_ids.iter().enumerate() {
+        sqlx::query("UPDATE playlist_music SET position = ? WHERE playlist_id = ? AND song_id = ?")
+            .bind(position as i64)
+            .bind(playlist_id)
+            .bind(song_id)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// 删除或合并歌曲后重新编号，保证手动顺序的 position 连续且不重复
+pub async fn compact_positions(
+    conn: &mut SqliteConnection,
+    playlist_id: i64,
+) -> Result<(), sqlx::Error> {
+    let sql = format!(
+        "SELECT song_id FROM playlist_music pm WHERE playlist_id = ? ORDER BY {}",
+        MANUAL_ORDER
+    );
+    let song_ids: Vec<String> = sqlx::query_scalar(&sql)
+        .bind(playlist_id)
+        .fetch_all(&mut *conn)
+        .await?;
+    write_positions(conn, playlist_id, &song_ids).await
+}
+
+/// 以当前显示顺序为基础写入新的手动顺序，并把歌单切换为手动排序
+async fn save_manual_order(
+    pool: &DbPool,
+    playlist_id: i64,
+    reorder: impl FnOnce(Vec<String>) -> Vec<String>,
+) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    if is_smart_playlist(&mut tx, playlist_id)
+        .await
+        .map_err(|e| e.to_string())?
+    {
+        return Err("智能歌单的顺序由规则决定，不能手动调整".to_string());
+    }
+
+    let current = get_displayed_order(&mut tx, playlist_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    write_positions(&mut tx, playlist_id, &reorder(current))
+        .await
+        .map_err(|e| e.to_string())?;
+    sqlx::query("UPDATE playlist SET sort_mode = 'manual' WHERE id = ?")
+        .bind(playlist_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// 把若干首歌移动到 to_index (移动后第一首所在的下标)，被移动的歌曲保持原来的相对顺序。
+/// 不在歌单中的歌曲会被忽略
+pub async fn move_songs_in_playlist(
+    pool: &DbPool,
+    playlist_id: i64,
+    song_ids: Vec<String>,
+    to_index: usize,
+) -> Result<(), String> {
+    save_manual_order(pool, playlist_id, |current| {
+        let (moved, mut rest): (Vec<String>, Vec<String>) =
+            current.into_iter().partition(|id| song_ids.contains(id));
+        let at = to_index.min(rest.len());
+        rest.splice(at..at, moved);
+        rest
+    })
+    .await
+}
+
+/// 按 song_ids 的顺序重排整个歌单，未列出的歌曲保持原顺序排在后面
+pub async fn set_playlist_order(
+    pool: &DbPool,
+    playlist_id: i64,
+    song_ids: Vec<String>,
+) -> Result<(), String> {
+    save_manual_order(pool, playlist_id, |current| {
+        let mut ordered: Vec<String> = Vec::with_capacity(current.len());
+        for song_id in song_ids {
+            if current.contains(&song_id) && !ordered.contains(&song_id) {
+                ordered.push(song_id);
+            }
+        }
+        let rest: Vec<String> = current
+            .into_iter()
+            .filter(|id| !ordered.contains(id))
+            .collect();
+        ordered.extend(rest);
+        ordered
+    })
+    .await
+}
+
 /// [新增] 更新一个歌单的封面图片路径
 pub async fn update_playlist_cover(
     pool: &DbPool,
@@ -129,38 +492,14 @@ pub async fn update_playlist_cover(
 }
 
 pub async fn export_db_file(_app_handle: AppHandle, pool: &DbPool) -> Result<String, String> {
-    // 1. 获取下载子路径，提供默认值 "MusicBox"
-    let sub_path = get_app_setting(pool, "download_path".to_string())
-        .await
-        .map_err(|e| e.to_string())?
-        .unwrap_or_else(|| "MusicBox".to_string());
-
     let app_data_dir = _app_handle
         .path()
         .app_data_dir()
         .or_else(|_| Err("无法获取应用数据目录".to_string()))?;
-
-    #[cfg(target_os = "android")]
-    let base_path = PathBuf::from("/storage/emulated/0/Download");
-
-    #[cfg(not(target_os = "android"))]
-    let base_path = _app_handle
-        .path()
-        .download_dir()
-        .or_else(|_| Err("无法获取系统的下载目录".to_string()))?;
-
     let source_path = app_data_dir.join("musicbox.db");
 
-    // 2. 将基础目录与从数据库读取的子目录名拼接
-    //    同时进行安全净化，防止 ".." 等路径遍历字符
-    let download_path = base_path.join(sub_path.replace("..", ""));
-
-    // 3. 确保最终的目录存在，如果不存在则创建
-    if !download_path.exists() {
-        tokio::fs::create_dir_all(&download_path)
-            .await
-            .map_err(|e| format!("创建下载目录 '{}' 失败: {}", download_path.display(), e))?;
-    }
+    // 导出目录：系统下载目录下的 download_path 子目录
+    let (download_path, sub_path) = resolve_export_dir(&_app_handle, pool).await?;
 
     let formatted_now_str = Local::now().format("%Y%m%d_%H%M%S").to_string();
 
@@ -191,12 +530,12 @@ pub async fn export_db_file(_app_handle: AppHandle, pool: &DbPool) -> Result<Str
 
     if ok {
         #[cfg(target_os = "android")]
-        let final_message = format!(
-            "导出完成！文件已保存至手机 Download/{} 文件夹。",
-            sub_path.replace("..", "")
-        );
+        let final_message = format!("导出完成！文件已保存至手机 Download/{} 文件夹。", sub_path);
         #[cfg(not(target_os = "android"))]
-        let final_message = format!("导出完成！");
+        let final_message = {
+            let _ = sub_path;
+            format!("导出完成！")
+        };
 
         Ok(final_message)
     } else {
@@ -288,6 +627,12 @@ async fn handle_merge(import_path: &Path, db_path: &Path) -> Result<(), String>
         .await
         .map_err(|e| format!("无法打开导入的数据库文件: {}", e))?;
 
+    // 旧版本导出的数据库需要先升级到当前结构 (例如 song_id 的音源前缀)
+    MIGRATOR
+        .run(&import_pool)
+        .await
+        .map_err(|e| format!("升级导入的数据库失败: {}", e))?;
+
     // 4. 开始事务
     let mut tx = current_pool.begin().await.map_err(|e| e.to_string())?;
 
@@ -297,21 +642,29 @@ async fn handle_merge(import_path: &Path, db_path: &Path) -> Result<(), String>
         .await
         .map_err(|e| e.to_string())?;
     for music in musics {
-        sqlx::query("INSERT OR IGNORE INTO music (song_id, title, artist, url, lyric, cover_url, duration_secs, play_url, download_mp3, download_extra, download_mp3_id, play_id, file_path, last_played_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)").bind(music.song_id).bind(music.title).bind(music.artist).bind(music.url).bind(music.lyric).bind(music.cover_url).bind(music.duration_secs).bind(music.play_url).bind(music.download_mp3).bind(music.download_extra).bind(music.download_mp3_id).bind(music.play_id).bind(music.file_path).bind(music.last_played_at)
+        let source = music
+            .source
+            .unwrap_or_else(|| split_song_id(&music.song_id).0.to_string());
+        sqlx::query("INSERT OR IGNORE INTO music (song_id, title, artist, url, lyric, cover_url, duration_secs, play_url, download_mp3, download_extra, download_mp3_id, play_id, file_path, last_played_at, source, format, mime) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)").bind(music.song_id).bind(music.title).bind(music.artist).bind(music.url).bind(music.lyric).bind(music.cover_url).bind(music.duration_secs).bind(music.play_url).bind(music.download_mp3).bind(music.download_extra).bind(music.download_mp3_id).bind(music.play_id).bind(music.file_path).bind(music.last_played_at).bind(source).bind(music.format).bind(music.mime)
             .execute(&mut *tx).await.map_err(|e| e.to_string())?;
     }
 
     // 6. [关键] 合并 `playlist` 表并建立 ID 映射
     let mut playlist_id_map: HashMap<i64, i64> = HashMap::new();
+    let mut imported_smart: Vec<i64> = Vec::new();
     let import_playlists: Vec<Playlist> = sqlx::query_as("SELECT * FROM playlist")
         .fetch_all(&import_pool)
         .await
         .map_err(|e| e.to_string())?;
 
-    for p in import_playlists {
+    // 回收站中的歌单不导入
+    for p in import_playlists
+        .into_iter()
+        .filter(|p| p.deleted_at.is_none())
+    {
         // 尝试在当前数据库中查找同名歌单
         let existing_playlist: Option<(i64,)> =
-            sqlx::query_as("SELECT id FROM playlist WHERE name = ?")
+            sqlx::query_as("SELECT id FROM playlist WHERE name = ? AND deleted_at IS NULL")
                 .bind(&p.name)
                 .fetch_optional(&mut *tx)
                 .await
@@ -322,18 +675,61 @@ async fn handle_merge(import_path: &Path, db_path: &Path) -> Result<(), String>
             playlist_id_map.insert(p.id, existing_id);
         } else {
             // 如果歌单不存在，则插入新歌单并获取新 ID
-            let result = sqlx::query("INSERT INTO playlist (name, cover_path) VALUES (?, ?)")
-                .bind(&p.name)
-                .bind(&p.cover_path)
-                .execute(&mut *tx)
-                .await
-                .map_err(|e| e.to_string())?;
+            let result = sqlx::query(
+                "INSERT INTO playlist (name, cover_path, smart_rules, sort_mode) VALUES (?, ?, ?, COALESCE(?, 'manual'))",
+            )
+            .bind(&p.name)
+            .bind(&p.cover_path)
+            .bind(&p.smart_rules)
+            .bind(&p.sort_mode)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
             let new_id = result.last_insert_rowid();
+            if p.smart_rules.is_some() {
+                imported_smart.push(new_id);
+            }
             // 将旧 ID 映射到新生成的 ID
             playlist_id_map.insert(p.id, new_id);
         }
     }
 
+    // 智能歌单规则中引用的歌单 ID 也需要映射
+    for new_id in imported_smart {
+        let (name, rules): (String, Option<String>) =
+            sqlx::query_as("SELECT name, smart_rules FROM playlist WHERE id = ?")
+                .bind(new_id)
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(|e| e.to_string())?;
+        let remapped = rules
+            .as_deref()
+            .and_then(|r| smart_playlist::remap_playlist_ids(r, &playlist_id_map));
+        let rules = match remapped {
+            Some((rules, removed)) => {
+                if !removed.is_empty() {
+                    eprintln!(
+                        "[Import] 智能歌单「{}」的 {} 条规则引用了未导入的歌单，已移除: {:?}",
+                        name,
+                        removed.len(),
+                        removed
+                    );
+                }
+                rules
+            }
+            None => {
+                eprintln!("[Import] 智能歌单「{}」的规则无法解析，已清空", name);
+                r#"{"rules":[]}"#.to_string()
+            }
+        };
+        sqlx::query("UPDATE playlist SET smart_rules = ? WHERE id = ?")
+            .bind(rules)
+            .bind(new_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
     // 7. [关键] 使用 ID 映射合并 `playlist_music` 关联表
     let relations: Vec<(i64, String, Option<i64>)> =
         sqlx::query_as("SELECT playlist_id, song_id, position FROM playlist_music")
@@ -353,6 +749,16 @@ async fn handle_merge(import_path: &Path, db_path: &Path) -> Result<(), String>
         }
     }
 
+    // 合并到已有歌单时 position 会重复，重新编号
+    let mut merged_ids: Vec<i64> = playlist_id_map.values().copied().collect();
+    merged_ids.sort_unstable();
+    merged_ids.dedup();
+    for playlist_id in merged_ids {
+        compact_positions(&mut tx, playlist_id)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
     // 8. 合并 `app_setting` 表 (使用 REPLACE 策略，保持不变)
     let settings: Vec<AppSetting> = sqlx::query_as("SELECT * FROM app_setting")
         .fetch_all(&import_pool)
@@ -370,6 +776,11 @@ async fn handle_merge(import_path: &Path, db_path: &Path) -> Result<(), String>
     // 9. 提交事务
     tx.commit().await.map_err(|e| e.to_string())?;
 
+    // 导入的歌曲和歌单直接插入，需要补全拼音列
+    backfill_pinyin(&current_pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
     // 10. 关闭连接池
     import_pool.close().await;
     current_pool.close().await;
@@ -0,0 +1,818 @@
+// src-tauri/src/playlist_io.rs
+
+//! 单个歌单的导出与导入，支持三种格式：
+//! - 扩展 M3U8：`#EXTINF:<秒>,<歌手> - <歌名>`，后面一行为音频文件位置
+//! - XSPF：标准的 XML 歌单格式，duration 以毫秒为单位
+//! - JSON：`model::PlaylistFile`，可以完整保留 song_id、音源等信息，例如
+//!   `{"format":"musicbox-playlist","version":1,"name":"我的歌单1","tracks":[{"song_id":"gequbao:65537","title":"晴天","artist":"周杰伦","duration_secs":269.0}]}`
+//!
+//! 导出时如果同时导出音频文件，已缓存歌曲的位置为歌单文件旁的相对路径，否则为来源页面的完整 URL。
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+
+use chrono::{SecondsFormat, Utc};
+use regex::Regex;
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    model::{Music, PlaylistFile, PlaylistFileTrack, PlaylistImportReport},
+    music::{
+        self, cache_file_name, export_file_stem, export_name_settings, exported_audio_format,
+        get_music_list_by_ids, register_cached_file, save_music,
+    },
+    my_util::{DbPool, calculate_file_hash, resolve_export_dir},
+    playlist::{create_playlist_with_songs, get_music_by_playlist_id},
+    source::{LOCAL_SOURCE_ID, SourceRegistry, namespaced_id, normalize_for_match, split_song_id},
+};
+
+const JSON_FORMAT_NAME: &str = "musicbox-playlist";
+const JSON_FORMAT_VERSION: u32 = 1;
+
+static XSPF_TRACK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?s)<track\b[^>]*>(.*?)</track>").unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PlaylistFormat {
+    M3u8,
+    Xspf,
+    Json,
+}
+
+impl PlaylistFormat {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "m3u" | "m3u8" => Some(Self::M3u8),
+            "xspf" => Some(Self::Xspf),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::M3u8 => "m3u8",
+            Self::Xspf => "xspf",
+            Self::Json => "json",
+        }
+    }
+
+    /// 优先按扩展名判断，无法判断时根据内容的第一个字符
+    fn detect(path: &Path, content: &str) -> Self {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .and_then(Self::from_name)
+            .unwrap_or_else(|| match content.trim_start().chars().next() {
+                Some('{') => Self::Json,
+                Some('<') => Self::Xspf,
+                _ => Self::M3u8,
+            })
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn unescape_xml(text: &str) -> String {
+    let text = text.trim();
+    if let Some(cdata) = text
+        .strip_prefix("<![CDATA[")
+        .and_then(|t| t.strip_suffix("]]>"))
+    {
+        return cdata.to_string();
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// 文件名中不能出现的字符
+fn sanitize_file_name(name: &str) -> String {
+    name.replace(&['/', '\\', ':', '*', '?', '"', '<', '>', '|'][..], "")
+}
+
+fn non_empty(text: &str) -> Option<String> {
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+pub fn render_m3u8(file: &PlaylistFile) -> String {
+    let mut out = String::from("#EXTM3U\n");
+    out.push_str(&format!("#PLAYLIST:{}\n", file.name));
+    for track in &file.tracks {
+        let Some(location) = track.location.as_deref().or(track.url.as_deref()) else {
+            // 既没有文件也没有链接的歌曲无法被其他播放器识别
+            continue;
+        };
+        let duration = track.duration_secs.map_or(-1, |d| d.round() as i64);
+        let display = if track.artist.is_empty() {
+            track.title.clone()
+        } else {
+            format!("{} - {}", track.artist, track.title)
+        };
+        out.push_str(&format!("#EXTINF:{},{}\n{}\n", duration, display, location));
+    }
+    out
+}
+
+pub fn render_xspf(file: &PlaylistFile) -> String {
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n",
+    );
+    out.push_str(&format!("  <title>{}</title>\n", escape_xml(&file.name)));
+    if let Some(date) = &file.exported_at {
+        out.push_str(&format!("  <date>{}</date>\n", escape_xml(date)));
+    }
+    out.push_str("  <trackList>\n");
+    for track in &file.tracks {
+        out.push_str("    <track>\n");
+        // XSPF 的 location 是 URI，相对路径需要百分号编码
+        let location = match (&track.location, &track.url) {
+            (Some(path), _) => Some(
+                path.split('/')
+                    .map(|part| urlencoding::encode(part).into_owned())
+                    .collect::<Vec<_>>()
+                    .join("/"),
+            ),
+            (None, Some(url)) => Some(url.clone()),
+            (None, None) => None,
+        };
+        if let Some(location) = location {
+            out.push_str(&format!(
+                "      <location>{}</location>\n",
+                escape_xml(&location)
+            ));
+        }
+        out.push_str(&format!(
+            "      <title>{}</title>\n",
+            escape_xml(&track.title)
+        ));
+        if !track.artist.is_empty() {
+            out.push_str(&format!(
+                "      <creator>{}</creator>\n",
+                escape_xml(&track.artist)
+            ));
+        }
+        if let Some(duration) = track.duration_secs {
+            out.push_str(&format!(
+                "      <duration>{}</duration>\n",
+                (duration * 1000.0).round() as i64
+            ));
+        }
+        if let Some(cover_url) = &track.cover_url {
+            out.push_str(&format!("      <image>{}</image>\n", escape_xml(cover_url)));
+        }
+        if let Some(url) = track.url.as_ref().filter(|_| track.location.is_some()) {
+            out.push_str(&format!("      <info>{}</info>\n", escape_xml(url)));
+        }
+        out.push_str("    </track>\n");
+    }
+    out.push_str("  </trackList>\n</playlist>\n");
+    out
+}
+
+/// 解析扩展 M3U，返回 (#PLAYLIST 中的歌单名, 歌曲)。
+/// 没有 #EXTINF 的条目以文件名作为歌名
+pub fn parse_m3u8(content: &str) -> (Option<String>, Vec<PlaylistFileTrack>) {
+    let mut name = None;
+    let mut tracks = Vec::new();
+    let mut pending: Option<PlaylistFileTrack> = None;
+
+    for line in content.trim_start_matches('\u{feff}').lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("#PLAYLIST:") {
+            name = non_empty(rest);
+        } else if let Some(rest) = line.strip_prefix("#EXTINF:") {
+            let (duration, display) = rest.split_once(',').unwrap_or((rest, ""));
+            // 时长后面可能带有 key="value" 形式的属性
+            let duration_secs = duration
+                .split_whitespace()
+                .next()
+                .and_then(|d| d.parse::<f64>().ok())
+                .filter(|d| *d >= 0.0);
+            let (artist, title) = display
+                .split_once(" - ")
+                .map_or(("", display), |(a, t)| (a, t));
+            pending = Some(PlaylistFileTrack {
+                title: title.trim().to_string(),
+                artist: artist.trim().to_string(),
+                duration_secs,
+                ..Default::default()
+            });
+        } else if !line.starts_with('#') {
+            let mut track = pending.take().unwrap_or_default();
+            if track.title.is_empty() {
+                track.title = Path::new(line.rsplit(['/', '\\']).next().unwrap_or(line))
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or(line)
+                    .to_string();
+            }
+            if line.starts_with("http://") || line.starts_with("https://") {
+                track.url = Some(line.to_string());
+            } else {
+                track.location = Some(line.to_string());
+            }
+            tracks.push(track);
+        }
+    }
+    (name, tracks)
+}
+
+fn xml_field(block: &str, tag: &str) -> Option<String> {
+    let pattern = format!(r"(?s)<{}\b[^>]*>(.*?)</{}>", tag, tag);
+    Regex::new(&pattern)
+        .ok()?
+        .captures(block)
+        .map(|c| unescape_xml(&c[1]))
+        .filter(|text| !text.is_empty())
+}
+
+/// 解析 XSPF，返回 (歌单标题, 歌曲)。只读取常用字段，不做完整的 XML 校验
+pub fn parse_xspf(content: &str) -> (Option<String>, Vec<PlaylistFileTrack>) {
+    let header = content.split("<trackList").next().unwrap_or_default();
+    let name = xml_field(header, "title");
+
+    let tracks = XSPF_TRACK
+        .captures_iter(content)
+        .map(|c| {
+            let block = &c[1];
+            let location = xml_field(block, "location");
+            let (url, location) = match location {
+                Some(l) if l.starts_with("http://") || l.starts_with("https://") => (Some(l), None),
+                Some(l) => (
+                    xml_field(block, "info"),
+                    Some(urlencoding::decode(&l).map(|d| d.into_owned()).unwrap_or(l)),
+                ),
+                None => (xml_field(block, "info"), None),
+            };
+            PlaylistFileTrack {
+                title: xml_field(block, "title").unwrap_or_default(),
+                artist: xml_field(block, "creator").unwrap_or_default(),
+                duration_secs: xml_field(block, "duration")
+                    .and_then(|d| d.parse::<f64>().ok())
+                    .map(|ms| ms / 1000.0),
+                cover_url: xml_field(block, "image"),
+                url,
+                location,
+                ..Default::default()
+            }
+        })
+        .collect();
+    (name, tracks)
+}
+
+fn parse_playlist_file(path: &Path, content: &str) -> Result<PlaylistFile, String> {
+    let fallback_name = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("导入的歌单")
+        .to_string();
+
+    let (name, tracks) = match PlaylistFormat::detect(path, content) {
+        PlaylistFormat::Json => {
+            let file: PlaylistFile = serde_json::from_str(content.trim_start_matches('\u{feff}'))
+                .map_err(|e| format!("歌单文件格式错误: {}", e))?;
+            if file.format != JSON_FORMAT_NAME {
+                return Err(format!("不支持的歌单文件格式: {}", file.format));
+            }
+            (non_empty(&file.name), file.tracks)
+        }
+        PlaylistFormat::Xspf => parse_xspf(content),
+        PlaylistFormat::M3u8 => parse_m3u8(content),
+    };
+
+    Ok(PlaylistFile {
+        format: JSON_FORMAT_NAME.to_string(),
+        version: JSON_FORMAT_VERSION,
+        name: name.unwrap_or(fallback_name),
+        exported_at: None,
+        tracks,
+    })
+}
+
+/// 按当前显示顺序读取歌单中的歌曲 (包括智能歌单)
+async fn load_playlist_music(pool: &DbPool, playlist_id: i64) -> Result<Vec<Music>, String> {
+    let items = get_music_by_playlist_id(pool, playlist_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    if items.is_empty() {
+        return Ok(Vec::new());
+    }
+    let ids: Vec<String> = items.into_iter().map(|item| item.song_id).collect();
+    let mut by_id: HashMap<String, Music> = get_music_list_by_ids(pool, ids.clone())
+        .await?
+        .unwrap_or_default()
+        .into_iter()
+        .map(|m| (m.song_id.clone(), m))
+        .collect();
+    Ok(ids.iter().filter_map(|id| by_id.remove(id)).collect())
+}
+
+/// 歌单文件中的一首歌，location 为导出的音频文件的相对路径
+fn export_track(
+    registry: &SourceRegistry,
+    music: Music,
+    location: Option<String>,
+) -> PlaylistFileTrack {
+    PlaylistFileTrack {
+        source: Some(
+            music
+                .source
+                .clone()
+                .unwrap_or_else(|| split_song_id(&music.song_id).0.to_string()),
+        ),
+        // 其他播放器无法识别站点内的相对路径
+        url: registry.page_url(&music),
+        song_id: Some(music.song_id),
+        title: music.title,
+        artist: music.artist,
+        duration_secs: music.duration_secs,
+        cover_url: music.cover_url.filter(|url| url.starts_with("http")),
+        location,
+    }
+}
+
+/// 导出歌单到下载目录。include_audio 为 true 时同时导出已缓存的音频文件，
+/// 歌单中以相对路径引用它们
+pub async fn export_playlist(
+    app_handle: AppHandle,
+    pool: &DbPool,
+    playlist_id: i64,
+    format: &str,
+    include_audio: bool,
+) -> Result<String, String> {
+    let format =
+        PlaylistFormat::from_name(format).ok_or(format!("不支持的歌单格式: {}", format))?;
+    let name: String = sqlx::query_scalar("SELECT name FROM playlist WHERE id = ?")
+        .bind(playlist_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("歌单不存在".to_string())?;
+
+    let music_list = load_playlist_music(pool, playlist_id).await?;
+    let registry = app_handle.state::<SourceRegistry>();
+    let (export_dir, _) = resolve_export_dir(&app_handle, pool).await?;
+    let (name_format, remove_spaces) = export_name_settings(pool).await?;
+
+    let mut tracks = Vec::with_capacity(music_list.len());
+    let mut cached_ids = Vec::new();
+    for music in music_list {
+        let cached_path = music
+            .file_path
+            .as_deref()
+            .filter(|p| include_audio && !p.is_empty() && Path::new(p).exists());
+        let location = match cached_path {
+            Some(path) => {
+                cached_ids.push(music.song_id.clone());
+                let audio_format = exported_audio_format(&music, Path::new(path)).await;
+                Some(format!(
+                    "{}.{}",
+                    export_file_stem(&music, &name_format, remove_spaces),
+                    audio_format.extension()
+                ))
+            }
+            None => None,
+        };
+        tracks.push(export_track(&registry, music, location));
+    }
+
+    let file = PlaylistFile {
+        format: JSON_FORMAT_NAME.to_string(),
+        version: JSON_FORMAT_VERSION,
+        name: name.clone(),
+        exported_at: Some(Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)),
+        tracks,
+    };
+    let content = match format {
+        PlaylistFormat::M3u8 => render_m3u8(&file),
+        PlaylistFormat::Xspf => render_xspf(&file),
+        PlaylistFormat::Json => serde_json::to_string_pretty(&file).map_err(|e| e.to_string())?,
+    };
+
+    let file_name = format!("{}.{}", sanitize_file_name(&name), format.extension());
+    let playlist_path = export_dir.join(&file_name);
+    tokio::fs::write(&playlist_path, content)
+        .await
+        .map_err(|e| format!("写入 {} 失败: {}", playlist_path.display(), e))?;
+
+    let mut message = format!("歌单已导出至 {}", playlist_path.display());
+    if !cached_ids.is_empty() {
+        // 音频文件导出失败不影响歌单文件本身
+        let audio_result = music::export_music_file(app_handle, pool, cached_ids).await;
+        message.push('\n');
+        message.push_str(&audio_result.unwrap_or_else(|e| e));
+    }
+    Ok(message)
+}
+
+/// 曲库中所有歌曲的标题和歌手索引，用于按标题/歌手匹配导入的歌曲
+pub struct LibraryIndex {
+    /// 归一化后的标题 -> (song_id, 归一化后的歌手)
+    by_title: HashMap<String, Vec<(String, String)>>,
+}
+
+impl LibraryIndex {
+    pub async fn load(pool: &DbPool) -> Result<Self, sqlx::Error> {
+        let rows: Vec<(String, String, String)> =
+            sqlx::query_as("SELECT song_id, title, artist FROM music ORDER BY added_at")
+                .fetch_all(pool)
+                .await?;
+        let mut by_title: HashMap<String, Vec<(String, String)>> = HashMap::new();
+        for (song_id, title, artist) in rows {
+            by_title
+                .entry(normalize_for_match(&title))
+                .or_default()
+                .push((song_id, normalize_for_match(&artist)));
+        }
+        Ok(Self { by_title })
+    }
+
+    /// 标题必须一致；歌手优先完全一致，其次互相包含 (例如合唱歌曲)。
+    /// 没有歌手信息时取第一首同名歌曲
+    pub fn find(&self, title: &str, artist: &str) -> Option<&str> {
+        let candidates = self.by_title.get(&normalize_for_match(title))?;
+        let artist = normalize_for_match(artist);
+        if artist.is_empty() {
+            return candidates.first().map(|(id, _)| id.as_str());
+        }
+        candidates
+            .iter()
+            .find(|(_, a)| *a == artist)
+            .or_else(|| {
+                candidates
+                    .iter()
+                    .find(|(_, a)| !a.is_empty() && (a.contains(&artist) || artist.contains(a)))
+            })
+            .map(|(id, _)| id.as_str())
+    }
+
+    fn insert(&mut self, song_id: &str, title: &str, artist: &str) {
+        self.by_title
+            .entry(normalize_for_match(title))
+            .or_default()
+            .push((song_id.to_string(), normalize_for_match(artist)));
+    }
+}
+
+/// 把歌单中的位置解析为存在的本地文件。http(s) 地址和找不到的文件返回 None
+fn resolve_local_path(location: &str, base_dir: Option<&Path>) -> Option<PathBuf> {
+    if location.starts_with("http://") || location.starts_with("https://") {
+        return None;
+    }
+    let path = match location.strip_prefix("file://") {
+        Some(uri) => {
+            let decoded = urlencoding::decode(uri).ok()?.into_owned();
+            // Windows 的 file:///C:/... 去掉盘符前的斜杠
+            match decoded.strip_prefix('/') {
+                Some(rest) if rest.chars().nth(1) == Some(':') => PathBuf::from(rest),
+                _ => PathBuf::from(decoded),
+            }
+        }
+        None => PathBuf::from(location),
+    };
+    let path = match base_dir {
+        Some(base) if path.is_relative() => base.join(path),
+        _ => path,
+    };
+    path.is_file().then_some(path)
+}
+
+enum TrackMatch {
+    /// 曲库中已有的歌曲
+    Library(String),
+    /// 由本地文件新建的歌曲
+    Local(String),
+    Unmatched,
+}
+
+/// 把本地音频文件复制到缓存目录并新建歌曲，song_id 由文件内容的哈希生成，重复导入同一文件不会新建
+async fn add_local_music(
+    pool: &DbPool,
+    cache_dir: &Path,
+    path: &Path,
+    track: &PlaylistFileTrack,
+) -> Result<TrackMatch, String> {
+    let hash_path = path.to_path_buf();
+    let hash = tauri::async_runtime::spawn_blocking(move || calculate_file_hash(hash_path))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("读取 {} 失败: {}", path.display(), e))?;
+    let song_id = namespaced_id(LOCAL_SOURCE_ID, &hash[..16]);
+
+    let exists: Option<String> = sqlx::query_scalar("SELECT song_id FROM music WHERE song_id = ?")
+        .bind(&song_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    if exists.is_some() {
+        return Ok(TrackMatch::Library(song_id));
+    }
+
+    let title = non_empty(&track.title).unwrap_or_else(|| {
+        path.file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default()
+            .to_string()
+    });
+    let music = Music {
+        song_id: song_id.clone(),
+        title,
+        artist: non_empty(&track.artist).unwrap_or_else(|| "未知歌手".to_string()),
+        url: path.to_string_lossy().into_owned(),
+        lyric: None,
+        cover_url: None,
+        duration_secs: track.duration_secs,
+        play_url: None,
+        download_mp3: None,
+        download_extra: None,
+        download_mp3_id: None,
+        play_id: None,
+        file_path: None,
+        last_played_at: None,
+        source: Some(LOCAL_SOURCE_ID.to_string()),
+        format: None,
+        mime: None,
+    };
+
+    // 媒体服务器只能访问缓存目录，播放前需要复制一份
+    let cached_path = cache_dir.join(cache_file_name(&music));
+    tokio::fs::copy(path, &cached_path)
+        .await
+        .map_err(|e| format!("复制 {} 失败: {}", path.display(), e))?;
+    save_music(pool, std::slice::from_ref(&music))
+        .await
+        .map_err(|e| e.to_string())?;
+    register_cached_file(pool, &song_id, &cached_path).await?;
+    Ok(TrackMatch::Local(song_id))
+}
+
+async fn match_track(
+    pool: &DbPool,
+    registry: &SourceRegistry,
+    index: &LibraryIndex,
+    cache_dir: &Path,
+    base_dir: Option<&Path>,
+    track: &PlaylistFileTrack,
+) -> Result<TrackMatch, String> {
+    if let Some(song_id) = track.song_id.as_deref() {
+        let exists: Option<String> =
+            sqlx::query_scalar("SELECT song_id FROM music WHERE song_id = ?")
+                .bind(song_id)
+                .fetch_optional(pool)
+                .await
+                .map_err(|e| e.to_string())?;
+        if let Some(song_id) = exists {
+            return Ok(TrackMatch::Library(song_id));
+        }
+    }
+
+    if let Some(path) = track
+        .location
+        .as_deref()
+        .and_then(|l| resolve_local_path(l, base_dir))
+    {
+        let existing: Option<String> =
+            sqlx::query_scalar("SELECT song_id FROM music WHERE file_path = ?")
+                .bind(path.to_string_lossy())
+                .fetch_optional(pool)
+                .await
+                .map_err(|e| e.to_string())?;
+        if let Some(song_id) = existing {
+            return Ok(TrackMatch::Library(song_id));
+        }
+        return add_local_music(pool, cache_dir, &path, track).await;
+    }
+
+    if let Some(url) = track.url.as_deref().filter(|u| !u.is_empty()) {
+        // 导出的是完整地址，曲库中保存的是相对路径，不同音源的相对路径可能相同
+        let (source_id, stored_url) = match registry.split_page_url(url) {
+            Some((source_id, path)) => (Some(source_id), path),
+            None => (None, url),
+        };
+        let candidates: Vec<String> =
+            sqlx::query_scalar("SELECT song_id FROM music WHERE url = ? OR url = ?")
+                .bind(url)
+                .bind(stored_url)
+                .fetch_all(pool)
+                .await
+                .map_err(|e| e.to_string())?;
+        let existing = candidates
+            .into_iter()
+            .find(|id| source_id.is_none_or(|source_id| split_song_id(id).0 == source_id));
+        if let Some(song_id) = existing {
+            return Ok(TrackMatch::Library(song_id));
+        }
+    }
+
+    Ok(index
+        .find(&track.title, &track.artist)
+        .map_or(TrackMatch::Unmatched, |id| {
+            TrackMatch::Library(id.to_string())
+        }))
+}
+
+/// 导入歌单文件并新建歌单。path 用于判断格式和解析相对路径，content 为文件内容
+/// (由前端读取，兼容 Android 的 content:// 地址)
+pub async fn import_playlist(
+    app_handle: &AppHandle,
+    pool: &DbPool,
+    path: &str,
+    content: &str,
+) -> Result<PlaylistImportReport, String> {
+    let path = Path::new(path);
+    let file = parse_playlist_file(path, content)?;
+    if file.tracks.is_empty() {
+        return Err("歌单文件中没有歌曲".to_string());
+    }
+
+    let cache_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|_| "无法获取应用数据目录".to_string())?
+        .join("music_cache");
+    tokio::fs::create_dir_all(&cache_dir)
+        .await
+        .map_err(|e| format!("创建缓存目录失败: {}", e))?;
+
+    let registry = app_handle.state::<SourceRegistry>();
+    let mut index = LibraryIndex::load(pool).await.map_err(|e| e.to_string())?;
+    let mut song_ids = Vec::with_capacity(file.tracks.len());
+    let mut matched = 0;
+    let mut added_local = 0;
+    let mut unmatched = Vec::new();
+    for track in &file.tracks {
+        match match_track(pool, &registry, &index, &cache_dir, path.parent(), track).await? {
+            TrackMatch::Library(song_id) => {
+                matched += 1;
+                song_ids.push(song_id);
+            }
+            TrackMatch::Local(song_id) => {
+                added_local += 1;
+                index.insert(&song_id, &track.title, &track.artist);
+                song_ids.push(song_id);
+            }
+            TrackMatch::Unmatched => unmatched.push(track.clone()),
+        }
+    }
+
+    let playlist_id = create_playlist_with_songs(pool, &file.name, &song_ids)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(PlaylistImportReport {
+        playlist_id,
+        name: file.name,
+        total: file.tracks.len(),
+        matched,
+        added_local,
+        unmatched,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::my_util::test_pool;
+
+    fn music(song_id: &str, url: &str) -> Music {
+        Music {
+            song_id: song_id.to_string(),
+            title: "晴天".to_string(),
+            artist: "周杰伦".to_string(),
+            url: url.to_string(),
+            lyric: None,
+            cover_url: None,
+            duration_secs: Some(269.0),
+            play_url: None,
+            download_mp3: None,
+            download_extra: None,
+            download_mp3_id: None,
+            play_id: None,
+            file_path: None,
+            last_played_at: None,
+            source: None,
+            format: None,
+            mime: None,
+        }
+    }
+
+    fn playlist_file(tracks: Vec<PlaylistFileTrack>) -> PlaylistFile {
+        PlaylistFile {
+            format: JSON_FORMAT_NAME.to_string(),
+            version: JSON_FORMAT_VERSION,
+            name: "我的歌单".to_string(),
+            exported_at: None,
+            tracks,
+        }
+    }
+
+    #[test]
+    fn export_uses_absolute_page_url() {
+        let registry = SourceRegistry::new();
+        let track = export_track(&registry, music("gequbao:7516", "/music/7516"), None);
+        assert_eq!(
+            track.url.as_deref(),
+            Some("https://www.gequbao.com/music/7516")
+        );
+        let track = export_track(&registry, music("gequhai:7516", "/music/7516"), None);
+        assert_eq!(
+            track.url.as_deref(),
+            Some("https://www.gequhai.net/music/7516")
+        );
+        // 没有前缀的旧 song_id 属于 gequbao
+        let track = export_track(&registry, music("7516", "/music/7516"), None);
+        assert_eq!(
+            track.url.as_deref(),
+            Some("https://www.gequbao.com/music/7516")
+        );
+        let track = export_track(&registry, music("local:abc", "/home/me/晴天.mp3"), None);
+        assert_eq!(track.url.as_deref(), Some("/home/me/晴天.mp3"));
+        let track = export_track(&registry, music("gequbao:1", ""), None);
+        assert_eq!(track.url, None);
+    }
+
+    #[test]
+    fn m3u8_and_xspf_contain_absolute_url() {
+        let registry = SourceRegistry::new();
+        let file = playlist_file(vec![
+            export_track(&registry, music("gequbao:7516", "/music/7516"), None),
+            export_track(
+                &registry,
+                music("gequhai:39425", "/music/39425"),
+                Some("周杰伦 - 晴天.mp3".to_string()),
+            ),
+        ]);
+
+        let m3u8 = render_m3u8(&file);
+        assert!(m3u8.contains("#EXTINF:269,周杰伦 - 晴天\nhttps://www.gequbao.com/music/7516\n"));
+        let (_, tracks) = parse_m3u8(&m3u8);
+        assert_eq!(
+            tracks[0].url.as_deref(),
+            Some("https://www.gequbao.com/music/7516")
+        );
+
+        let xspf = render_xspf(&file);
+        assert!(xspf.contains("<location>https://www.gequbao.com/music/7516</location>"));
+        assert!(xspf.contains("<info>https://www.gequhai.net/music/39425</info>"));
+        let (_, tracks) = parse_xspf(&xspf);
+        assert_eq!(
+            tracks[0].url.as_deref(),
+            Some("https://www.gequbao.com/music/7516")
+        );
+    }
+
+    #[tokio::test]
+    async fn absolute_url_matches_song_of_same_source() {
+        let pool = test_pool().await;
+        // 两个音源的相对路径相同，标题不同以免按歌名匹配
+        for (song_id, title) in [("gequbao:7516", "晴天"), ("gequhai:7516", "七里香")] {
+            sqlx::query(
+                "INSERT INTO music (song_id, title, artist, url) VALUES (?, ?, '周杰伦', '/music/7516')",
+            )
+            .bind(song_id)
+            .bind(title)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        let registry = SourceRegistry::new();
+        let index = LibraryIndex::load(&pool).await.unwrap();
+        let cache_dir = std::env::temp_dir();
+
+        let track = PlaylistFileTrack {
+            title: "未知".to_string(),
+            url: Some("https://www.gequhai.net/music/7516".to_string()),
+            ..Default::default()
+        };
+        let matched = match_track(&pool, &registry, &index, &cache_dir, None, &track)
+            .await
+            .unwrap();
+        assert!(matches!(matched, TrackMatch::Library(id) if id == "gequhai:7516"));
+
+        let track = PlaylistFileTrack {
+            title: "未知".to_string(),
+            url: Some("https://www.gequbao.com/music/7516".to_string()),
+            ..Default::default()
+        };
+        let matched = match_track(&pool, &registry, &index, &cache_dir, None, &track)
+            .await
+            .unwrap();
+        assert!(matches!(matched, TrackMatch::Library(id) if id == "gequbao:7516"));
+    }
+}
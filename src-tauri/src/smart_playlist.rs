@@ -0,0 +1,420 @@
+// src-tauri/src/smart_playlist.rs
+
+use std::collections::HashMap;
+
+use chrono::{Duration, Local, SecondsFormat, Utc};
+use sqlx::{QueryBuilder, Sqlite};
+
+use crate::{
+    library_search::escape_like,
+    model::{PlaylistMusicItem, SmartMatchMode, SmartOrder, SmartPlaylistRules, SmartRule},
+    my_util::DbPool,
+    pinyin_search::pinyin_keys,
+};
+
+pub fn parse_rules(json: &str) -> Result<SmartPlaylistRules, sqlx::Error> {
+    serde_json::from_str(json).map_err(|e| sqlx::Error::Decode(Box::new(e)))
+}
+
+/// 保存前检查规则，playlist_id 为正在编辑的歌单 (新建时为空)。
+/// 规则只能引用未删除的普通歌单，智能歌单之间不能互相引用，避免出现循环
+async fn validate_rules(
+    pool: &DbPool,
+    rules: &SmartPlaylistRules,
+    playlist_id: Option<i64>,
+) -> Result<(), String> {
+    if rules.limit.is_some_and(|limit| limit <= 0) {
+        return Err("歌曲数量上限必须大于 0".to_string());
+    }
+    for rule in &rules.rules {
+        match rule {
+            SmartRule::NotPlayedInDays { days }
+            | SmartRule::PlayedInDays { days }
+            | SmartRule::AddedInDays { days }
+                if *days < 0 =>
+            {
+                return Err("天数不能为负数".to_string());
+            }
+            SmartRule::InPlaylist { playlist_id: id }
+            | SmartRule::NotInPlaylist { playlist_id: id } => {
+                if Some(*id) == playlist_id {
+                    return Err("智能歌单的规则不能引用自身".to_string());
+                }
+                let referenced: Option<(bool, bool)> = sqlx::query_as(
+                    "SELECT smart_rules IS NOT NULL, deleted_at IS NOT NULL FROM playlist WHERE id = ?",
+                )
+                .bind(id)
+                .fetch_optional(pool)
+                .await
+                .map_err(|e| e.to_string())?;
+                match referenced {
+                    None | Some((_, true)) => return Err("规则引用的歌单不存在".to_string()),
+                    Some((true, _)) => return Err("智能歌单的规则不能引用其他智能歌单".to_string()),
+                    Some((false, false)) => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// last_played_at 为 UTC 的 RFC 3339 字符串
+fn played_cutoff(days: i64) -> String {
+    (Utc::now() - Duration::days(days)).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// added_at 由 SQLite 的 strftime('%Y-%m-%d %H:%M:%f', 'now', 'localtime') 生成
+fn added_cutoff(days: i64) -> String {
+    (Local::now() - Duration::days(days))
+        .format("%Y-%m-%d %H:%M:%S%.3f")
+        .to_string()
+}
+
+/// 未删除歌单中的歌曲，后面拼接歌单 ID
+const PLAYLIST_SONGS_SQL: &str = "SELECT pm.song_id FROM playlist_music pm \
+     JOIN playlist p ON p.id = pm.playlist_id \
+     WHERE p.deleted_at IS NULL AND pm.playlist_id = ";
+
+fn push_condition(builder: &mut QueryBuilder<'_, Sqlite>, rule: &SmartRule) {
+    match rule {
+        SmartRule::TitleContains { value } => {
+            builder
+                .push("m.title LIKE ")
+                .push_bind(format!("%{}%", escape_like(value)))
+                .push(" ESCAPE '\\'");
+        }
+        SmartRule::ArtistContains { value } => {
+            builder
+                .push("m.artist LIKE ")
+                .push_bind(format!("%{}%", escape_like(value)))
+                .push(" ESCAPE '\\'");
+        }
+        SmartRule::Source { value } => {
+            builder.push("m.source = ").push_bind(value.clone());
+        }
+        SmartRule::CachedOnly => {
+            builder.push("(m.file_path IS NOT NULL AND m.file_path != '')");
+        }
+        SmartRule::NotPlayedInDays { days } => {
+            builder
+                .push("(m.last_played_at IS NULL OR m.last_played_at < ")
+                .push_bind(played_cutoff(*days))
+                .push(")");
+        }
+        SmartRule::PlayedInDays { days } => {
+            builder
+                .push("m.last_played_at >= ")
+                .push_bind(played_cutoff(*days));
+        }
+        SmartRule::AddedInDays { days } => {
+            builder
+                .push("m.added_at >= ")
+                .push_bind(added_cutoff(*days));
+        }
+        // 引用的歌单在规则保存后被移入回收站时，按空歌单处理
+        SmartRule::InPlaylist { playlist_id } => {
+            builder
+                .push("m.song_id IN (")
+                .push(PLAYLIST_SONGS_SQL)
+                .push_bind(*playlist_id)
+                .push(")");
+        }
+        SmartRule::NotInPlaylist { playlist_id } => {
+            builder
+                .push("m.song_id NOT IN (")
+                .push(PLAYLIST_SONGS_SQL)
+                .push_bind(*playlist_id)
+                .push(")");
+        }
+    }
+}
+
+/// 拼接 WHERE、ORDER BY 和 LIMIT。没有规则时匹配全部歌曲
+fn push_filter(builder: &mut QueryBuilder<'_, Sqlite>, rules: &SmartPlaylistRules) {
+    builder.push(" WHERE ");
+    if rules.rules.is_empty() {
+        builder.push("1 = 1");
+    }
+    let joiner = match rules.match_mode {
+        SmartMatchMode::All => " AND ",
+        SmartMatchMode::Any => " OR ",
+    };
+    for (i, rule) in rules.rules.iter().enumerate() {
+        if i > 0 {
+            builder.push(joiner);
+        }
+        builder.push("(");
+        push_condition(builder, rule);
+        builder.push(")");
+    }
+
+    builder.push(match rules.order {
+        SmartOrder::AddedDesc => " ORDER BY m.added_at DESC",
+        SmartOrder::LastPlayedDesc => " ORDER BY m.last_played_at IS NULL, m.last_played_at DESC",
+        SmartOrder::Title => " ORDER BY m.title COLLATE NOCASE",
+        SmartOrder::Random => " ORDER BY RANDOM()",
+    });
+    if let Some(limit) = rules.limit {
+        builder.push(" LIMIT ").push_bind(limit);
+    }
+}
+
+/// 按规则实时计算智能歌单中的歌曲，返回结构与普通歌单相同
+pub async fn get_smart_playlist_music(
+    pool: &DbPool,
+    rules: &SmartPlaylistRules,
+) -> Result<Vec<PlaylistMusicItem>, sqlx::Error> {
+    let mut builder = QueryBuilder::<Sqlite>::new(
+        "SELECT m.song_id, m.title, m.artist, m.cover_url, m.file_path FROM music m",
+    );
+    push_filter(&mut builder, rules);
+    builder
+        .build_query_as::<PlaylistMusicItem>()
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn count_smart_playlist_music(
+    pool: &DbPool,
+    rules: &SmartPlaylistRules,
+) -> Result<i64, sqlx::Error> {
+    let mut builder =
+        QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM (SELECT m.song_id FROM music m");
+    push_filter(&mut builder, rules);
+    builder.push(")");
+    builder.build_query_scalar::<i64>().fetch_one(pool).await
+}
+
+pub async fn create_smart_playlist(
+    pool: &DbPool,
+    name: &str,
+    rules: &SmartPlaylistRules,
+) -> Result<i64, String> {
+    validate_rules(pool, rules, None).await?;
+    let rules_json = serde_json::to_string(rules).map_err(|e| e.to_string())?;
+    let keys = pinyin_keys(name);
+
+    let result = sqlx::query(
+        "INSERT INTO playlist (name, name_pinyin, name_initials, smart_rules) VALUES (?, ?, ?, ?)",
+    )
+    .bind(name)
+    .bind(keys.full)
+    .bind(keys.initials)
+    .bind(rules_json)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(result.last_insert_rowid())
+}
+
+pub async fn update_smart_playlist_rules(
+    pool: &DbPool,
+    playlist_id: i64,
+    rules: &SmartPlaylistRules,
+) -> Result<(), String> {
+    validate_rules(pool, rules, Some(playlist_id)).await?;
+    let rules_json = serde_json::to_string(rules).map_err(|e| e.to_string())?;
+
+    let result =
+        sqlx::query("UPDATE playlist SET smart_rules = ? WHERE id = ? AND smart_rules IS NOT NULL")
+            .bind(rules_json)
+            .bind(playlist_id)
+            .execute(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+    if result.rows_affected() == 0 {
+        return Err("该歌单不是智能歌单".to_string());
+    }
+    Ok(())
+}
+
+/// 普通歌单返回 None
+pub async fn get_smart_playlist_rules(
+    pool: &DbPool,
+    playlist_id: i64,
+) -> Result<Option<SmartPlaylistRules>, sqlx::Error> {
+    let rules: Option<String> = sqlx::query_scalar("SELECT smart_rules FROM playlist WHERE id = ?")
+        .bind(playlist_id)
+        .fetch_optional(pool)
+        .await?
+        .flatten();
+    rules.as_deref().map(parse_rules).transpose()
+}
+
+/// 导入其他客户端的数据库时歌单 ID 会变化，规则中引用的歌单需要换成新 ID。
+/// 引用了未导入歌单的规则会被移除，返回映射后的规则和被移除的规则
+pub fn remap_playlist_ids(
+    rules_json: &str,
+    id_map: &HashMap<i64, i64>,
+) -> Option<(String, Vec<SmartRule>)> {
+    let mut rules = parse_rules(rules_json).ok()?;
+    let mut removed = Vec::new();
+    let mut kept = Vec::with_capacity(rules.rules.len());
+    for mut rule in rules.rules {
+        match &mut rule {
+            SmartRule::InPlaylist { playlist_id } | SmartRule::NotInPlaylist { playlist_id } => {
+                match id_map.get(playlist_id) {
+                    Some(new_id) => {
+                        *playlist_id = *new_id;
+                        kept.push(rule);
+                    }
+                    None => removed.push(rule),
+                }
+            }
+            _ => kept.push(rule),
+        }
+    }
+    rules.rules = kept;
+    let json = serde_json::to_string(&rules).ok()?;
+    Some((json, removed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{model::SmartMatchMode, my_util::test_pool};
+
+    async fn setup() -> DbPool {
+        let pool = test_pool().await;
+        for song_id in ["a", "b", "c"] {
+            sqlx::query("INSERT INTO music (song_id, title, artist, url) VALUES (?, ?, '', '')")
+                .bind(song_id)
+                .bind(song_id)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        // 1: 普通歌单 [a, b]，2: 回收站中的歌单 [c]
+        sqlx::query(
+            "INSERT INTO playlist (id, name, deleted_at) VALUES (1, '普通', NULL), (2, '已删除', '2026-01-01');
+             INSERT INTO playlist_music (playlist_id, song_id, position) VALUES (1, 'a', 0), (1, 'b', 1), (2, 'c', 0);",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        pool
+    }
+
+    fn rules(rules: Vec<SmartRule>) -> SmartPlaylistRules {
+        SmartPlaylistRules {
+            match_mode: SmartMatchMode::All,
+            rules,
+            order: SmartOrder::Title,
+            limit: None,
+        }
+    }
+
+    async fn song_ids(pool: &DbPool, rules: &SmartPlaylistRules) -> Vec<String> {
+        get_smart_playlist_music(pool, rules)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|m| m.song_id)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn rules_reference_only_live_regular_playlists() {
+        let pool = setup().await;
+        let smart_id = create_smart_playlist(
+            &pool,
+            "智能",
+            &rules(vec![SmartRule::InPlaylist { playlist_id: 1 }]),
+        )
+        .await
+        .unwrap();
+
+        for (playlist_id, expected) in [
+            (2, "规则引用的歌单不存在"),
+            (99, "规则引用的歌单不存在"),
+            (smart_id, "智能歌单的规则不能引用其他智能歌单"),
+        ] {
+            let result = create_smart_playlist(
+                &pool,
+                "引用",
+                &rules(vec![SmartRule::NotInPlaylist { playlist_id }]),
+            )
+            .await;
+            assert_eq!(result, Err(expected.to_string()));
+        }
+
+        let result = update_smart_playlist_rules(
+            &pool,
+            smart_id,
+            &rules(vec![SmartRule::InPlaylist {
+                playlist_id: smart_id,
+            }]),
+        )
+        .await;
+        assert_eq!(result, Err("智能歌单的规则不能引用自身".to_string()));
+    }
+
+    #[tokio::test]
+    async fn deleted_playlist_counts_as_empty() {
+        let pool = setup().await;
+        assert_eq!(
+            song_ids(
+                &pool,
+                &rules(vec![SmartRule::InPlaylist { playlist_id: 1 }])
+            )
+            .await,
+            ["a", "b"]
+        );
+        assert!(
+            song_ids(
+                &pool,
+                &rules(vec![SmartRule::InPlaylist { playlist_id: 2 }])
+            )
+            .await
+            .is_empty()
+        );
+        assert_eq!(
+            song_ids(
+                &pool,
+                &rules(vec![SmartRule::NotInPlaylist { playlist_id: 2 }])
+            )
+            .await,
+            ["a", "b", "c"]
+        );
+
+        // 规则保存后歌单被删除
+        crate::playlist::delete_playlist(&pool, 1).await.unwrap();
+        assert!(
+            song_ids(
+                &pool,
+                &rules(vec![SmartRule::InPlaylist { playlist_id: 1 }])
+            )
+            .await
+            .is_empty()
+        );
+    }
+
+    #[test]
+    fn remap_reports_rules_outside_the_import() {
+        let rules_json = r#"{"rules":[
+            {"type":"in_playlist","playlist_id":1},
+            {"type":"not_in_playlist","playlist_id":2},
+            {"type":"cached_only"}
+        ]}"#;
+        let id_map = HashMap::from([(1, 10)]);
+
+        let (json, removed) = remap_playlist_ids(rules_json, &id_map).unwrap();
+        let rules = parse_rules(&json).unwrap().rules;
+        assert_eq!(rules.len(), 2);
+        assert!(matches!(
+            rules[0],
+            SmartRule::InPlaylist { playlist_id: 10 }
+        ));
+        assert!(matches!(rules[1], SmartRule::CachedOnly));
+        assert_eq!(removed.len(), 1);
+        assert!(matches!(
+            removed[0],
+            SmartRule::NotInPlaylist { playlist_id: 2 }
+        ));
+
+        assert!(remap_playlist_ids("not json", &id_map).is_none());
+    }
+}
@@ -0,0 +1,422 @@
+// src-tauri/src/source/gequbao.rs
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+use scraper::{ElementRef, Html, Node, Selector};
+use serde_json::Value;
+use tauri_plugin_http::reqwest;
+
+use super::{MusicSource, SourceFuture};
+use crate::model::{Music, SearchResult, UpdateDetailPayload};
+
+pub const BASE_URL: &str = "https://www.gequbao.com";
+/// gequhai 与 gequbao 使用同一套页面模板，只是域名不同
+pub const GEQUHAI_BASE_URL: &str = "https://www.gequhai.net";
+
+const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/108.0.0.0 Safari/537.36";
+
+/// 搜索结果中的一行，包含歌名 (.text-primary)、歌手 (.text-jade) 和详情链接
+static ROW_SELECTOR: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse(".row.no-gutters.py-2d5").unwrap());
+static TITLE_SELECTOR: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse(".text-primary").unwrap());
+static ARTIST_SELECTOR: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse(".text-jade").unwrap());
+static LINK_SELECTOR: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse(r#"a[href^="/music/"]"#).unwrap());
+/// 详情页中被 JS 字符串包裹的 appData
+static APP_DATA: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"window\.appData = JSON\.parse\(\s*'((?:[^'\\]|\\.)*?)'\s*\)"#).unwrap()
+});
+static COVER_SELECTOR: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse(r#"meta[property="og:image"]"#).unwrap());
+static LYRIC_SELECTOR: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse("#content-lrc").unwrap());
+
+pub struct GequbaoSource {
+    id: &'static str,
+    base_url: &'static str,
+    client: reqwest::Client,
+}
+
+impl GequbaoSource {
+    pub fn new() -> Self {
+        Self::with_base_url("gequbao", BASE_URL)
+    }
+
+    pub fn gequhai() -> Self {
+        Self::with_base_url("gequhai", GEQUHAI_BASE_URL)
+    }
+
+    /// 同模板的镜像站点，只需要换一个 id 和域名
+    pub fn with_base_url(id: &'static str, base_url: &'static str) -> Self {
+        let client = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .build()
+            .unwrap_or_default();
+        Self {
+            id,
+            base_url,
+            client,
+        }
+    }
+
+    async fn get_text(&self, url: &str) -> Result<String, String> {
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| format!("网络请求失败: {}", e))?;
+        if !response.status().is_success() {
+            return Err(format!("请求页面失败，状态码: {}", response.status()));
+        }
+        response.text().await.map_err(|e| e.to_string())
+    }
+}
+
+impl Default for GequbaoSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MusicSource for GequbaoSource {
+    fn id(&self) -> &'static str {
+        self.id
+    }
+
+    fn base_url(&self) -> &'static str {
+        self.base_url
+    }
+
+    fn search<'a>(&'a self, keyword: &'a str) -> SourceFuture<'a, SearchResult> {
+        Box::pin(async move {
+            let search_url = format!("{}/s/{}", self.base_url, urlencoding::encode(keyword));
+            let html = self.get_text(&search_url).await?;
+            Ok(SearchResult {
+                music_list: parse_search_html(&html),
+                has_more: false,
+            })
+        })
+    }
+
+    fn fetch_detail<'a>(&'a self, music: &'a Music) -> SourceFuture<'a, UpdateDetailPayload> {
+        Box::pin(async move {
+            let detail_url = format!("{}{}", self.base_url, music.url);
+            let html = self.get_text(&detail_url).await?;
+            let mut payload = parse_detail_html(&music.song_id, &html)?;
+            // 详情页中没有时长信息，沿用已有的数据
+            payload.duration_secs = music.duration_secs;
+            Ok(payload)
+        })
+    }
+
+    fn resolve_play_url<'a>(&'a self, music: &'a Music) -> SourceFuture<'a, String> {
+        Box::pin(async move {
+            let play_id = music
+                .play_id
+                .as_deref()
+                .ok_or("歌曲缺少 play_id，无法获取播放地址".to_string())?;
+
+            let response = self
+                .client
+                .post(format!("{}/api/play-url", self.base_url))
+                .header(
+                    "Content-Type",
+                    "application/x-www-form-urlencoded; charset=UTF-8",
+                )
+                .header("Origin", self.base_url)
+                .header("Referer", format!("{}{}", self.base_url, music.url))
+                .header("X-Requested-With", "XMLHttpRequest")
+                .body(format!("id={}", urlencoding::encode(play_id)))
+                .send()
+                .await
+                .map_err(|e| format!("请求播放API失败: {}", e))?;
+
+            if !response.status().is_success() {
+                return Err(format!("请求播放API失败: {}", response.status()));
+            }
+
+            let body = response.text().await.map_err(|e| e.to_string())?;
+            parse_play_url_response(&body)
+        })
+    }
+}
+
+/// 解析搜索结果页，每一行包含歌名 (.text-primary)、歌手 (.text-jade) 和详情链接
+pub fn parse_search_html(html: &str) -> Vec<Music> {
+    let document = Html::parse_document(html);
+    document
+        .select(&ROW_SELECTOR)
+        .filter_map(|row| {
+            let title = element_text(row.select(&TITLE_SELECTOR).next()?);
+            let artist = element_text(row.select(&ARTIST_SELECTOR).next()?);
+            let href = row.select(&LINK_SELECTOR).next()?.attr("href")?;
+            let song_id = href.split("/music/").nth(1)?.trim();
+
+            if title.is_empty() || artist.is_empty() || song_id.is_empty() {
+                return None;
+            }
+
+            Some(Music {
+                song_id: song_id.to_string(),
+                title,
+                artist,
+                // 保存相对 URL，后续拼接成完整链接
+                url: href.to_string(),
+                lyric: None,
+                cover_url: None,
+                duration_secs: None,
+                play_url: None,
+                download_mp3: None,
+                download_extra: None,
+                download_mp3_id: None,
+                play_id: None,
+                file_path: None,
+                last_played_at: None,
+                source: None,
+                format: None,
+                mime: None,
+            })
+        })
+        .collect()
+}
+
+/// 解析详情页：window.appData 中的 play_id/mp3_id，og:image 封面和 #content-lrc 歌词
+pub fn parse_detail_html(song_id: &str, html: &str) -> Result<UpdateDetailPayload, String> {
+    let escaped = APP_DATA
+        .captures(html)
+        .and_then(|c| c.get(1))
+        .ok_or("在详情页中未能解析出 appData".to_string())?
+        .as_str();
+
+    // appData 是一个被 JS 字符串包裹的 JSON，需要先反转义一次再解析
+    let json_string: String = serde_json::from_str(&format!("\"{}\"", escaped))
+        .map_err(|e| format!("appData 反转义失败: {}", e))?;
+    let app_data: Value =
+        serde_json::from_str(&json_string).map_err(|e| format!("appData 解析失败: {}", e))?;
+
+    let play_id = json_value_to_string(&app_data["play_id"]);
+    let mp3_id = json_value_to_string(&app_data["mp3_id"]);
+    if play_id.is_none() || mp3_id.is_none() {
+        return Err("appData 中缺少 play_id 或 mp3_id".to_string());
+    }
+
+    let download_extra = app_data["mp3_extra_urls"]
+        .get(0)
+        .and_then(|u| u["share_link"].as_str())
+        .map(|s| s.to_string());
+
+    let document = Html::parse_document(html);
+    let cover_url = document
+        .select(&COVER_SELECTOR)
+        .next()
+        .and_then(|m| m.attr("content"))
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .or_else(|| json_value_to_string(&app_data["mp3_cover"]))
+        .map(|s| s.replace("http://", "https://"));
+
+    let lyric = document
+        .select(&LYRIC_SELECTOR)
+        .next()
+        .map(lyric_text)
+        .filter(|s| !s.trim().is_empty());
+
+    Ok(UpdateDetailPayload {
+        song_id: song_id.to_string(),
+        lyric,
+        cover_url,
+        duration_secs: None,
+        play_url: None,
+        download_mp3: None,
+        download_extra,
+        download_mp3_id: mp3_id,
+        play_id,
+    })
+}
+
+/// 解析 /api/play-url 的返回：{"code":1,"data":{"url":"..."},"msg":""}
+pub fn parse_play_url_response(body: &str) -> Result<String, String> {
+    let data: Value =
+        serde_json::from_str(body).map_err(|e| format!("播放API返回解析失败: {}", e))?;
+
+    match data["data"]["url"].as_str() {
+        Some(url) if data["code"].as_i64() == Some(1) && !url.is_empty() => Ok(url.to_string()),
+        _ => Err(data["msg"]
+            .as_str()
+            .filter(|m| !m.is_empty())
+            .unwrap_or("播放API未返回有效的URL")
+            .to_string()),
+    }
+}
+
+fn element_text(element: ElementRef) -> String {
+    element.text().collect::<String>().trim().to_string()
+}
+
+/// 歌词以 <br> 分行，按节点拼接文本，遇到 <br> 时换行。
+/// 源码中 <br /> 后面通常还有换行符，文本节点中的换行不算作分行
+fn lyric_text(element: ElementRef) -> String {
+    let mut lyric = String::new();
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => lyric.extend(text.chars().filter(|c| !matches!(c, '\n' | '\r'))),
+            Node::Element(e) if e.name() == "br" => lyric.push('\n'),
+            Node::Element(_) => {
+                if let Some(child_element) = ElementRef::wrap(child) {
+                    lyric.push_str(&child_element.text().collect::<String>());
+                }
+            }
+            _ => {}
+        }
+    }
+    lyric
+}
+
+fn json_value_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) if !s.is_empty() => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEARCH_HTML: &str = include_str!("../../tests/fixtures/gequbao_search.html");
+    const DETAIL_HTML: &str = include_str!("../../tests/fixtures/gequbao_detail.html");
+    const PLAY_URL_JSON: &str = include_str!("../../tests/fixtures/gequbao_play_url.json");
+    const PLAY_URL_ERROR_JSON: &str =
+        include_str!("../../tests/fixtures/gequbao_play_url_error.json");
+    const GEQUHAI_SEARCH_HTML: &str = include_str!("../../tests/fixtures/gequhai_search.html");
+    const GEQUHAI_DETAIL_HTML: &str = include_str!("../../tests/fixtures/gequhai_detail.html");
+
+    #[test]
+    fn search_skips_header_and_incomplete_rows() {
+        let list = parse_search_html(SEARCH_HTML);
+        let summary: Vec<_> = list
+            .iter()
+            .map(|m| (m.song_id.as_str(), m.title.as_str(), m.artist.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("7516", "晴天", "周杰伦"),
+                ("39425", "晴天 (Live)", "周杰伦 & 五月天"),
+            ]
+        );
+        // 保存相对地址，请求时再拼接 base_url
+        assert_eq!(list[0].url, "/music/7516");
+    }
+
+    #[test]
+    fn detail_reads_app_data_cover_and_lyric() {
+        let payload = parse_detail_html("7516", DETAIL_HTML).unwrap();
+        assert_eq!(payload.song_id, "7516");
+        assert_eq!(
+            payload.play_id.as_deref(),
+            Some("ZFpXRUpRU1NWcjhMSEFuZ3R4R3c9")
+        );
+        // 数字形式的 mp3_id 也转换为字符串
+        assert_eq!(payload.download_mp3_id.as_deref(), Some("7516"));
+        assert_eq!(
+            payload.download_extra.as_deref(),
+            Some("https://pan.quark.cn/s/5f1c0a2b7e3d")
+        );
+        // og:image 优先，并改为 https
+        assert_eq!(
+            payload.cover_url.as_deref(),
+            Some("https://img1.kuwo.cn/star/albumcover/500/38/71/1272200948.jpg")
+        );
+        let lyric = payload.lyric.unwrap();
+        let lines: Vec<_> = lyric.lines().map(str::trim).collect();
+        assert_eq!(lines[0], "[ti:晴天]");
+        assert_eq!(lines[3], "[offset:+200]");
+        assert_eq!(lines[4], "[00:00.00]晴天 - 周杰伦");
+        assert_eq!(lines[5], "[00:29.59]故事的小黄花");
+        assert_eq!(lines[8], "[00:40.13]随记忆一直晃到现在");
+    }
+
+    #[test]
+    fn detail_without_app_data_is_an_error() {
+        let html = DETAIL_HTML.replace("window.appData", "window.otherData");
+        assert!(parse_detail_html("7516", &html).is_err());
+    }
+
+    #[test]
+    fn play_url_response() {
+        assert_eq!(
+            parse_play_url_response(PLAY_URL_JSON).unwrap(),
+            "https://er-sycdn.kuwo.cn/6c1b1e4d/66f2a0b1/resource/n3/84/43/1866243524.mp3"
+        );
+        assert_eq!(
+            parse_play_url_response(PLAY_URL_ERROR_JSON).unwrap_err(),
+            "播放地址获取失败，请稍后再试"
+        );
+        assert_eq!(
+            parse_play_url_response(r#"{"code":1,"data":{"url":""},"msg":""}"#).unwrap_err(),
+            "播放API未返回有效的URL"
+        );
+        assert!(parse_play_url_response("<html>502</html>").is_err());
+    }
+
+    /// 固定的页面样本不会随网站改版更新，这里访问真实网站检查解析是否还有效。
+    /// 需要联网，手动运行：cargo test live_site -- --ignored
+    #[tokio::test]
+    #[ignore]
+    async fn live_site_still_parses() {
+        let source = GequbaoSource::new();
+        let result = source.search("周杰伦 晴天").await.unwrap();
+        let mut music = result
+            .music_list
+            .into_iter()
+            .next()
+            .expect("搜索结果页没有解析出歌曲");
+        assert!(music.url.starts_with("/music/"));
+
+        let detail = source.fetch_detail(&music).await.unwrap();
+        assert!(detail.cover_url.is_some(), "没有解析出封面");
+        assert!(detail.lyric.is_some(), "没有解析出歌词");
+        music.play_id = detail.play_id;
+        let play_url = source.resolve_play_url(&music).await.unwrap();
+        assert!(play_url.starts_with("http"));
+    }
+
+    #[test]
+    fn gequhai_search_uses_same_template() {
+        let list = parse_search_html(GEQUHAI_SEARCH_HTML);
+        let summary: Vec<_> = list
+            .iter()
+            .map(|m| (m.song_id.as_str(), m.title.as_str(), m.artist.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("1093", "七里香", "周杰伦"),
+                ("88213", "七里香 (钢琴版)", "纯音乐")
+            ]
+        );
+        assert_eq!(list[1].url, "/music/88213");
+    }
+
+    #[test]
+    fn gequhai_detail_falls_back_to_app_data_cover() {
+        let payload = parse_detail_html("1093", GEQUHAI_DETAIL_HTML).unwrap();
+        assert_eq!(payload.play_id.as_deref(), Some("cUdaVG9LNnRkS2J3"));
+        assert_eq!(payload.download_mp3_id.as_deref(), Some("1093"));
+        assert_eq!(payload.download_extra, None);
+        // og:image 为空时使用 appData 中的封面
+        assert_eq!(
+            payload.cover_url.as_deref(),
+            Some("https://img4.kuwo.cn/star/albumcover/500/49/0/2364185736.jpg")
+        );
+        let lyric = payload.lyric.unwrap();
+        assert_eq!(lyric.lines().count(), 3);
+        assert!(lyric.starts_with("[00:00.00]七里香 - 周杰伦\n[00:24.10]窗外的麻雀"));
+    }
+}
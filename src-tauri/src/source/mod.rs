@@ -0,0 +1,333 @@
+// src-tauri/src/source/mod.rs
+
+pub mod gequbao;
+
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use tauri::AppHandle;
+
+use crate::{
+    model::{Music, SearchResult, UpdateDetailPayload},
+    music,
+    my_util::{DbPool, get_app_setting},
+};
+
+/// 音源方法返回的 Future，统一用 String 作为错误类型，方便直接透传给前端
+pub type SourceFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, String>> + Send + 'a>>;
+
+/// 一个音乐网站的抓取实现：搜索、解析详情页、获取播放地址
+pub trait MusicSource: Send + Sync {
+    /// 音源的唯一标识，例如 "gequbao"
+    fn id(&self) -> &'static str;
+
+    /// 站点地址，例如 "https://www.gequbao.com"。Music.url 保存的是站点内的相对路径
+    fn base_url(&self) -> &'static str;
+
+    /// 根据关键词搜索，返回的 Music 只包含基础信息 (song_id/title/artist/url)，
+    /// 其中 song_id 是站点的原始 ID，由 SourceRegistry 负责加上命名空间
+    fn search<'a>(&'a self, keyword: &'a str) -> SourceFuture<'a, SearchResult>;
+
+    /// 抓取详情页，构建用于 update_music_detail 的 payload (不包含 play_url)
+    fn fetch_detail<'a>(&'a self, music: &'a Music) -> SourceFuture<'a, UpdateDetailPayload>;
+
+    /// 根据详情中的 play_id 等信息，获取真实的播放地址
+    fn resolve_play_url<'a>(&'a self, music: &'a Music) -> SourceFuture<'a, String>;
+}
+
+pub type SharedSource = Arc<dyn MusicSource>;
+
+/// 从本地文件导入的歌曲，没有对应的在线音源，只能播放缓存中的副本
+pub const LOCAL_SOURCE_ID: &str = "local";
+
+/// 迁移前的 song_id 都来自 gequbao，没有命名空间前缀时按它处理
+pub const DEFAULT_SOURCE_ID: &str = "gequbao";
+
+const SONG_ID_SEPARATOR: char = ':';
+
+/// 生成带音源前缀的 song_id，例如 "gequbao:65537"，避免不同站点的 ID 冲突
+pub fn namespaced_id(source_id: &str, raw_id: &str) -> String {
+    format!("{}{}{}", source_id, SONG_ID_SEPARATOR, raw_id)
+}
+
+/// 拆分 song_id 为 (音源, 原始 ID)
+pub fn split_song_id(song_id: &str) -> (&str, &str) {
+    song_id
+        .split_once(SONG_ID_SEPARATOR)
+        .unwrap_or((DEFAULT_SOURCE_ID, song_id))
+}
+
+/// 补上旧版本 song_id 缺少的音源前缀 (与迁移 0004 相同)。
+/// 前端缓存和旧版本的播放队列中还保存着没有前缀的 ID，写入曲库前需要先转换
+pub fn canonical_song_id(song_id: &str) -> String {
+    if song_id.contains(SONG_ID_SEPARATOR) {
+        song_id.to_string()
+    } else {
+        namespaced_id(DEFAULT_SOURCE_ID, song_id)
+    }
+}
+
+/// 用于跨音源匹配同一首歌：忽略大小写、空白和标点
+pub fn normalize_for_match(s: &str) -> String {
+    s.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// 所有已注册的音源，注册顺序即默认的回退顺序
+pub struct SourceRegistry {
+    sources: Vec<SharedSource>,
+}
+
+impl SourceRegistry {
+    pub fn new() -> Self {
+        Self {
+            sources: vec![
+                Arc::new(gequbao::GequbaoSource::new()),
+                Arc::new(gequbao::GequbaoSource::gequhai()),
+            ],
+        }
+    }
+
+    pub fn get(&self, source_id: &str) -> Option<&SharedSource> {
+        self.sources.iter().find(|s| s.id() == source_id)
+    }
+
+    pub fn ids(&self) -> Vec<&'static str> {
+        self.sources.iter().map(|s| s.id()).collect()
+    }
+
+    /// 歌曲所属的音源，优先使用 source 字段，否则从 song_id 前缀推断
+    pub fn source_of(&self, music: &Music) -> Result<&SharedSource, String> {
+        let source_id = music
+            .source
+            .as_deref()
+            .unwrap_or_else(|| split_song_id(&music.song_id).0);
+        self.get(source_id)
+            .ok_or_else(|| format!("未知的音源: {}", source_id))
+    }
+
+    /// 歌曲来源页面的完整地址，导出给其他播放器时使用。
+    /// 在线音源的相对路径拼上站点地址，本地导入的歌曲等其他地址原样返回
+    pub fn page_url(&self, music: &Music) -> Option<String> {
+        if music.url.is_empty() {
+            return None;
+        }
+        match self.source_of(music) {
+            Ok(source) if music.url.starts_with('/') => {
+                Some(format!("{}{}", source.base_url(), music.url))
+            }
+            _ => Some(music.url.clone()),
+        }
+    }
+
+    /// page_url 的逆操作：属于某个音源的完整地址拆分为 (音源, 相对路径)
+    pub fn split_page_url<'a>(&self, url: &'a str) -> Option<(&'static str, &'a str)> {
+        self.sources.iter().find_map(|source| {
+            url.strip_prefix(source.base_url())
+                .filter(|path| path.starts_with('/'))
+                .map(|path| (source.id(), path))
+        })
+    }
+
+    /// 回退顺序，可通过设置项 source_order (逗号分隔的音源 id) 调整，未列出的音源排在最后
+    pub async fn fallback_order(&self, pool: &DbPool) -> Vec<SharedSource> {
+        let order = get_app_setting(pool, "source_order".to_string())
+            .await
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+
+        let mut ordered: Vec<SharedSource> = order
+            .split(',')
+            .filter_map(|id| self.get(id.trim()).cloned())
+            .collect();
+        for source in &self.sources {
+            if !ordered.iter().any(|s| s.id() == source.id()) {
+                ordered.push(source.clone());
+            }
+        }
+        ordered
+    }
+}
+
+impl Default for SourceRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+async fn get_music_by_id(pool: &DbPool, song_id: &str) -> Result<Music, String> {
+    music::get_music_list_by_ids(pool, vec![song_id.to_string()])
+        .await?
+        .and_then(|list| list.into_iter().next())
+        .ok_or("数据库中未找到该歌曲的基本信息".to_string())
+}
+
+/// 在指定音源上搜索，给结果加上命名空间后保存到本地数据库
+async fn search_on_source(
+    pool: &DbPool,
+    source: &dyn MusicSource,
+    keyword: &str,
+) -> Result<SearchResult, String> {
+    let mut result = source.search(keyword).await?;
+
+    for music in result.music_list.iter_mut() {
+        music.song_id = namespaced_id(source.id(), &music.song_id);
+        music.source = Some(source.id().to_string());
+    }
+
+    if !result.music_list.is_empty() {
+        music::save_music(pool, &result.music_list)
+            .await
+            .map_err(|e| e.to_string())?;
+        println!(
+            "成功将 {} 首歌曲 ({}) 保存到本地数据库！",
+            result.music_list.len(),
+            source.id()
+        );
+    }
+
+    Ok(result)
+}
+
+/// 搜索并保存结果。未指定音源时按回退顺序使用第一个搜索成功的音源
+pub async fn search_music(
+    pool: &DbPool,
+    registry: &SourceRegistry,
+    source_id: Option<&str>,
+    keyword: &str,
+) -> Result<SearchResult, String> {
+    if let Some(source_id) = source_id {
+        let source = registry
+            .get(source_id)
+            .ok_or_else(|| format!("未知的音源: {}", source_id))?;
+        return search_on_source(pool, source.as_ref(), keyword).await;
+    }
+
+    let mut last_error = "没有可用的音源".to_string();
+    for source in registry.fallback_order(pool).await {
+        match search_on_source(pool, source.as_ref(), keyword).await {
+            Ok(result) => return Ok(result),
+            Err(e) => {
+                eprintln!("[Source] {} 搜索失败: {}", source.id(), e);
+                last_error = e;
+            }
+        }
+    }
+    Err(last_error)
+}
+
+/// 抓取详情和播放地址，写回数据库后返回最新的 Music
+pub async fn fetch_music_detail(
+    app_handle: &AppHandle,
+    pool: &DbPool,
+    registry: &SourceRegistry,
+    song_id: &str,
+) -> Result<Music, String> {
+    let music = get_music_by_id(pool, song_id).await?;
+    let source = registry.source_of(&music)?;
+
+    let payload = source.fetch_detail(&music).await?;
+    music::update_music_detail(app_handle, pool, payload)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // 详情写入后 play_id 已就绪，播放地址获取失败时会回退到其他音源
+    resolve_play_url(pool, registry, song_id).await?;
+
+    get_music_by_id(pool, song_id).await
+}
+
+/// 在歌曲自己的音源上获取播放地址，缺少 play_id 时先抓取详情
+async fn resolve_on_own_source(source: &dyn MusicSource, music: &Music) -> Result<String, String> {
+    if music.play_id.is_some() {
+        return source.resolve_play_url(music).await;
+    }
+
+    let payload = source.fetch_detail(music).await?;
+    let music = Music {
+        play_id: payload.play_id,
+        ..music.clone()
+    };
+    source.resolve_play_url(&music).await
+}
+
+/// 在其他音源上搜索同名同歌手的歌曲，并获取它的播放地址
+async fn resolve_on_other_source(
+    pool: &DbPool,
+    source: &dyn MusicSource,
+    music: &Music,
+) -> Result<String, String> {
+    let keyword = format!("{} {}", music.title, music.artist);
+    let result = search_on_source(pool, source, &keyword).await?;
+
+    let title = normalize_for_match(&music.title);
+    let artist = normalize_for_match(&music.artist);
+    let matched = result
+        .music_list
+        .into_iter()
+        .find(|m| {
+            normalize_for_match(&m.title) == title && normalize_for_match(&m.artist) == artist
+        })
+        .ok_or_else(|| format!("{} 中没有匹配的歌曲", source.id()))?;
+
+    println!(
+        "[Source] 在 {} 中匹配到 {} - {}: {}",
+        source.id(),
+        music.title,
+        music.artist,
+        matched.song_id
+    );
+    resolve_on_own_source(source, &matched).await
+}
+
+/// 获取播放地址：先在歌曲自己的音源上获取，失败后按回退顺序在其他音源上按标题/歌手匹配
+pub async fn resolve_play_url_with_fallback(
+    pool: &DbPool,
+    registry: &SourceRegistry,
+    music: &Music,
+) -> Result<String, String> {
+    let mut last_error = "没有可用的音源".to_string();
+
+    let own_source = registry.source_of(music).ok().cloned();
+    if let Some(source) = &own_source {
+        match resolve_on_own_source(source.as_ref(), music).await {
+            Ok(play_url) => return Ok(play_url),
+            Err(e) => {
+                eprintln!("[Source] {} 获取播放地址失败: {}", source.id(), e);
+                last_error = e;
+            }
+        }
+    }
+
+    for source in registry.fallback_order(pool).await {
+        if own_source.as_ref().is_some_and(|s| s.id() == source.id()) {
+            continue;
+        }
+        match resolve_on_other_source(pool, source.as_ref(), music).await {
+            Ok(play_url) => return Ok(play_url),
+            Err(e) => {
+                eprintln!("[Source] {} 回退失败: {}", source.id(), e);
+                last_error = e;
+            }
+        }
+    }
+    Err(last_error)
+}
+
+/// 重新获取播放地址 (播放地址有时效性)，并更新到数据库
+pub async fn resolve_play_url(
+    pool: &DbPool,
+    registry: &SourceRegistry,
+    song_id: &str,
+) -> Result<String, String> {
+    let music = get_music_by_id(pool, song_id).await?;
+    let play_url = resolve_play_url_with_fallback(pool, registry, &music).await?;
+
+    music::update_music_play_url(pool, song_id, &play_url)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(play_url)
+}
@@ -0,0 +1,546 @@
+// src-tauri/src/stream_proxy.rs
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::PathBuf,
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+use tauri::{AppHandle, Manager};
+use tokio::sync::watch;
+
+use crate::{
+    audio_format::{self, AudioFormat},
+    download,
+    media_server::{self, ByteRange, MediaBody, MediaRequest, MediaResponse},
+    model::Music,
+    music,
+    my_util::DbPool,
+};
+
+/// 边下边播的路由：/stream/{song_id}
+pub const STREAM_PREFIX: &str = "stream/";
+
+/// 超过这个时间没有新数据，认为下载已经卡住，断开播放器的连接
+const STALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 判断格式需要的文件头长度
+const SNIFF_LEN: u64 = 64;
+
+#[derive(Debug, Clone, Default)]
+struct InflightState {
+    /// 已经收到响应头并打开了 .part 文件
+    started: bool,
+    downloaded: u64,
+    total: Option<u64>,
+    /// 每次丢弃 .part 重新下载时加一，之前打开的读取方需要断开
+    generation: u64,
+    /// 下载结束后的结果，成功时为最终的缓存文件路径
+    result: Option<Result<PathBuf, String>>,
+}
+
+/// 一个正在写入 music_cache 的下载，播放器的请求可以从中读取已下载的部分
+pub struct InflightDownload {
+    part_path: PathBuf,
+    state: Mutex<InflightState>,
+    changed: Condvar,
+    finished: watch::Sender<bool>,
+}
+
+impl InflightDownload {
+    fn new(part_path: PathBuf) -> Self {
+        Self {
+            part_path,
+            state: Mutex::new(InflightState::default()),
+            changed: Condvar::new(),
+            finished: watch::channel(false).0,
+        }
+    }
+
+    /// 下载进度回调，数据已经写入 .part 文件
+    pub fn update(&self, downloaded: u64, total: Option<u64>) {
+        let mut state = self.state.lock().unwrap();
+        state.started = true;
+        state.downloaded = downloaded;
+        state.total = total;
+        drop(state);
+        self.changed.notify_all();
+    }
+
+    /// 换了播放地址、.part 被丢弃后重新开始计数。已经在读取旧文件的播放请求会收到错误，
+    /// 新的请求等待新的响应头
+    pub fn reset(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.started = false;
+        state.downloaded = 0;
+        state.total = None;
+        state.generation += 1;
+        drop(state);
+        self.changed.notify_all();
+    }
+
+    /// 只记录第一次的结果，之后的调用 (例如 guard 被释放时) 不会覆盖
+    fn finish(&self, result: Result<PathBuf, String>) {
+        let mut state = self.state.lock().unwrap();
+        if state.result.is_none() {
+            state.result = Some(result);
+        }
+        drop(state);
+        self.changed.notify_all();
+        self.finished.send_replace(true);
+    }
+
+    /// 阻塞等待直到条件满足，下载卡住时返回 None
+    fn wait_until(&self, condition: impl Fn(&InflightState) -> bool) -> Option<InflightState> {
+        self.wait_until_stalled(STALL_TIMEOUT, condition)
+    }
+
+    /// 每次收到新数据都重新计时，连续 stall_timeout 没有进展才算超时，
+    /// 等待整首歌下载完成时不会因为总耗时较长而断开
+    fn wait_until_stalled(
+        &self,
+        stall_timeout: Duration,
+        condition: impl Fn(&InflightState) -> bool,
+    ) -> Option<InflightState> {
+        let mut state = self.state.lock().unwrap();
+        let mut downloaded = state.downloaded;
+        let mut deadline = Instant::now() + stall_timeout;
+        loop {
+            if condition(&state) {
+                return Some(state.clone());
+            }
+            if state.downloaded != downloaded {
+                downloaded = state.downloaded;
+                deadline = Instant::now() + stall_timeout;
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return None;
+            }
+            state = self.changed.wait_timeout(state, remaining).unwrap().0;
+        }
+    }
+
+    /// 等待下载结束，返回最终的缓存文件路径
+    pub async fn wait_finished(&self) -> Result<PathBuf, String> {
+        let mut receiver = self.finished.subscribe();
+        let _ = receiver.wait_for(|finished| *finished).await;
+        self.state
+            .lock()
+            .unwrap()
+            .result
+            .clone()
+            .unwrap_or_else(|| Err("下载已取消".to_string()))
+    }
+}
+
+type InflightMap = Arc<Mutex<HashMap<String, Arc<InflightDownload>>>>;
+
+/// 所有正在进行的缓存下载，保证同一首歌同时只有一个下载在写 .part 文件
+#[derive(Default)]
+pub struct InflightDownloads {
+    downloads: InflightMap,
+}
+
+impl InflightDownloads {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, song_id: &str) -> Option<Arc<InflightDownload>> {
+        self.downloads.lock().unwrap().get(song_id).cloned()
+    }
+
+    /// 登记一个新的下载。同一首歌已经在下载时返回已有的下载
+    pub fn begin(
+        &self,
+        song_id: &str,
+        part_path: PathBuf,
+    ) -> Result<InflightGuard, Arc<InflightDownload>> {
+        let mut downloads = self.downloads.lock().unwrap();
+        if let Some(existing) = downloads.get(song_id) {
+            return Err(existing.clone());
+        }
+
+        let download = Arc::new(InflightDownload::new(part_path));
+        downloads.insert(song_id.to_string(), download.clone());
+        Ok(InflightGuard {
+            song_id: song_id.to_string(),
+            download,
+            downloads: self.downloads.clone(),
+        })
+    }
+}
+
+/// 下载的所有权。释放时从登记表中移除；如果还没有结果 (例如下载任务被取消)，
+/// 等待中的播放请求会收到错误
+pub struct InflightGuard {
+    song_id: String,
+    download: Arc<InflightDownload>,
+    downloads: InflightMap,
+}
+
+impl InflightGuard {
+    pub fn download(&self) -> &Arc<InflightDownload> {
+        &self.download
+    }
+
+    pub fn finish(self, result: Result<PathBuf, String>) {
+        self.download.finish(result);
+    }
+}
+
+impl Drop for InflightGuard {
+    fn drop(&mut self) {
+        let mut downloads = self.downloads.lock().unwrap();
+        if downloads
+            .get(&self.song_id)
+            .is_some_and(|d| Arc::ptr_eq(d, &self.download))
+        {
+            downloads.remove(&self.song_id);
+        }
+        drop(downloads);
+        self.download.finish(Err("下载已取消".to_string()));
+    }
+}
+
+/// 从正在增长的 .part 文件中读取数据，读到尚未下载的位置时等待下载进度
+struct GrowingFileReader {
+    download: Arc<InflightDownload>,
+    file: File,
+    /// 打开 file 时的下载轮次
+    generation: u64,
+    pos: u64,
+    /// 读取的结束位置 (不含)，为空时读到下载结束
+    end: Option<u64>,
+}
+
+impl Read for GrowingFileReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.end.is_some_and(|end| self.pos >= end) {
+            return Ok(0);
+        }
+
+        let pos = self.pos;
+        let generation = self.generation;
+        let state = self
+            .download
+            .wait_until(|s| s.generation != generation || s.downloaded > pos || s.result.is_some())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::TimedOut, "等待下载数据超时"))?;
+
+        if state.generation != generation {
+            return Err(io::Error::other("下载已重新开始"));
+        }
+
+        if state.downloaded <= pos {
+            return match state.result {
+                Some(Err(e)) => Err(io::Error::other(e)),
+                _ => Ok(0),
+            };
+        }
+
+        let mut available = state.downloaded - pos;
+        if let Some(end) = self.end {
+            available = available.min(end - pos);
+        }
+        let len = (buf.len() as u64).min(available) as usize;
+
+        self.file.seek(SeekFrom::Start(pos))?;
+        let read = self.file.read(&mut buf[..len])?;
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+fn get_music(pool: &DbPool, song_id: &str) -> Option<Music> {
+    tauri::async_runtime::block_on(music::get_music_list_by_ids(
+        pool,
+        vec![song_id.to_string()],
+    ))
+    .ok()
+    .flatten()
+    .and_then(|list| list.into_iter().next())
+}
+
+enum StreamSource {
+    Downloading(Arc<InflightDownload>),
+    /// 磁盘上已有完整的缓存文件 (数据库还没有记录)
+    Cached(PathBuf),
+}
+
+/// 开始一个新的下载，或者加入同一首歌已有的下载
+fn attach_or_start(
+    app_handle: &AppHandle,
+    pool: &DbPool,
+    music: Music,
+) -> Result<StreamSource, String> {
+    let inflight = app_handle.state::<InflightDownloads>();
+    if let Some(download) = inflight.get(&music.song_id) {
+        return Ok(StreamSource::Downloading(download));
+    }
+
+    let cache_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("无法获取应用数据目录: {}", e))?
+        .join("music_cache");
+    if let Some(existing_path) = music::find_cached_file(&cache_dir, &music) {
+        return Ok(StreamSource::Cached(existing_path));
+    }
+    std::fs::create_dir_all(&cache_dir).map_err(|e| format!("创建缓存目录失败: {}", e))?;
+
+    let local_path = cache_dir.join(music::cache_file_name(&music));
+    let guard = match inflight.begin(&music.song_id, download::part_path(&local_path)) {
+        Ok(guard) => guard,
+        Err(existing) => return Ok(StreamSource::Downloading(existing)),
+    };
+    let download = guard.download().clone();
+
+    println!("[Stream] 边下边播: {}", music.title);
+    let app_handle = app_handle.clone();
+    let pool = pool.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) =
+            music::download_into_cache(&app_handle, &pool, &music, &local_path, guard).await
+        {
+            eprintln!("[Stream] 缓存 {} 失败: {}", music.title, e);
+        }
+    });
+
+    Ok(StreamSource::Downloading(download))
+}
+
+/// 读取已下载部分的文件头判断 Content-Type
+fn sniff_mime(download: &InflightDownload, music: &Music) -> &'static str {
+    let known = music
+        .format
+        .as_deref()
+        .and_then(AudioFormat::from_extension);
+    if let Some(format) = known {
+        return format.mime();
+    }
+
+    let header = download
+        .wait_until(|s| s.downloaded >= SNIFF_LEN || s.result.is_some())
+        .and_then(|_| {
+            let mut header = Vec::new();
+            File::open(&download.part_path)
+                .and_then(|f| f.take(SNIFF_LEN).read_to_end(&mut header))
+                .ok()
+                .map(|_| header)
+        })
+        .unwrap_or_default();
+    AudioFormat::sniff(&header)
+        .unwrap_or(audio_format::DEFAULT_FORMAT)
+        .mime()
+}
+
+/// /stream/{song_id}：已缓存时直接返回文件；否则一边从远程下载到 music_cache，
+/// 一边把已下载的部分返回给播放器，Range 请求从已下载的部分读取，
+/// 超出部分会等待下载进度。下载完成后由 download_into_cache 记录 file_path
+pub fn handle_stream(
+    app_handle: &AppHandle,
+    song_id: &str,
+    request: &MediaRequest,
+) -> MediaResponse {
+    let Some(pool) = app_handle.try_state::<DbPool>().map(|p| p.inner().clone()) else {
+        // 数据库还没有初始化完成
+        return MediaResponse::empty(503);
+    };
+    let Some(music) = get_music(&pool, song_id) else {
+        return MediaResponse::empty(404);
+    };
+
+    let cached_path = music
+        .file_path
+        .as_deref()
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from)
+        .filter(|p| p.exists());
+    if let Some(path) = cached_path {
+        return media_server::serve_file(&path, request);
+    }
+
+    let download = match attach_or_start(app_handle, &pool, music.clone()) {
+        Ok(StreamSource::Downloading(download)) => download,
+        Ok(StreamSource::Cached(path)) => return media_server::serve_file(&path, request),
+        Err(e) => {
+            eprintln!("[Stream] {}", e);
+            return MediaResponse::empty(500);
+        }
+    };
+
+    // 等待远程服务器返回响应头，拿到文件总大小
+    let Some(state) = download.wait_until(|s| s.started || s.result.is_some()) else {
+        return MediaResponse::empty(504);
+    };
+    match &state.result {
+        Some(Ok(path)) => return media_server::serve_file(path, request),
+        Some(Err(e)) => {
+            eprintln!("[Stream] {} 下载失败: {}", music.title, e);
+            return MediaResponse::empty(502);
+        }
+        None => {}
+    }
+
+    let file = match File::open(&download.part_path) {
+        Ok(file) => file,
+        Err(_) => {
+            // 打开之前下载恰好完成，.part 已经被重命名
+            return match download
+                .wait_until(|s| s.result.is_some())
+                .and_then(|s| s.result)
+            {
+                Some(Ok(path)) => media_server::serve_file(&path, request),
+                _ => MediaResponse::empty(502),
+            };
+        }
+    };
+
+    let mut headers = vec![
+        ("Content-Type", sniff_mime(&download, &music).to_string()),
+        ("Cache-Control", "no-cache".to_string()),
+    ];
+
+    let Some(total) = state.total else {
+        // 远程没有返回长度，无法支持 Range，按数据流返回
+        return MediaResponse {
+            status: 200,
+            headers,
+            body: MediaBody::Stream {
+                reader: Box::new(GrowingFileReader {
+                    download,
+                    file,
+                    generation: state.generation,
+                    pos: 0,
+                    end: None,
+                }),
+                len: None,
+            },
+        };
+    };
+
+    headers.push(("Accept-Ranges", "bytes".to_string()));
+    let range = request
+        .range
+        .as_deref()
+        .map_or(ByteRange::Ignored, |r| media_server::parse_range(r, total));
+
+    let (status, start, end) = match range {
+        ByteRange::Satisfiable(start, end) => {
+            headers.push((
+                "Content-Range",
+                format!("bytes {}-{}/{}", start, end, total),
+            ));
+            (206, start, end)
+        }
+        ByteRange::Unsatisfiable => {
+            headers.push(("Content-Range", format!("bytes */{}", total)));
+            return MediaResponse::empty(416).with_headers(headers);
+        }
+        ByteRange::Ignored if total == 0 => return MediaResponse::empty(200).with_headers(headers),
+        ByteRange::Ignored => (200, 0, total - 1),
+    };
+
+    MediaResponse {
+        status,
+        headers,
+        body: MediaBody::Stream {
+            reader: Box::new(GrowingFileReader {
+                download,
+                file,
+                generation: state.generation,
+                pos: start,
+                end: Some(end + 1),
+            }),
+            len: Some(end - start + 1),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+
+    const TIMEOUT: Duration = Duration::from_millis(200);
+
+    #[test]
+    fn wait_returns_when_condition_met() {
+        let download = Arc::new(InflightDownload::new(PathBuf::from("song.part")));
+        let writer = download.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            writer.update(100, Some(1000));
+        });
+        let state = download
+            .wait_until_stalled(TIMEOUT, |s| s.downloaded >= 100)
+            .unwrap();
+        assert!(state.started);
+        assert_eq!(state.total, Some(1000));
+    }
+
+    #[test]
+    fn wait_times_out_without_progress() {
+        let download = InflightDownload::new(PathBuf::from("song.part"));
+        download.update(10, None);
+        let started = Instant::now();
+        assert!(
+            download
+                .wait_until_stalled(TIMEOUT, |s| s.result.is_some())
+                .is_none()
+        );
+        assert!(started.elapsed() >= TIMEOUT);
+    }
+
+    #[test]
+    fn progress_restarts_stall_timeout() {
+        let download = Arc::new(InflightDownload::new(PathBuf::from("song.part")));
+        let writer = download.clone();
+        // 总耗时超过超时时间，但每次写入的间隔都小于超时时间
+        thread::spawn(move || {
+            for i in 1..=6 {
+                thread::sleep(TIMEOUT / 2);
+                writer.update(i * 100, Some(600));
+            }
+            writer.finish(Ok(PathBuf::from("song.mp3")));
+        });
+        let started = Instant::now();
+        let state = download
+            .wait_until_stalled(TIMEOUT, |s| s.result.is_some())
+            .unwrap();
+        assert!(started.elapsed() > TIMEOUT);
+        assert_eq!(state.downloaded, 600);
+        assert_eq!(state.result, Some(Ok(PathBuf::from("song.mp3"))));
+    }
+
+    #[test]
+    fn reset_disconnects_open_readers() {
+        let dir = tempfile::tempdir().unwrap();
+        let part_path = dir.path().join("song.mp3.part");
+        std::fs::write(&part_path, b"0123456789").unwrap();
+
+        let download = Arc::new(InflightDownload::new(part_path.clone()));
+        download.update(10, Some(20));
+        let mut reader = GrowingFileReader {
+            download: download.clone(),
+            file: File::open(&part_path).unwrap(),
+            generation: 0,
+            pos: 0,
+            end: None,
+        };
+        let mut buf = [0; 4];
+        assert_eq!(reader.read(&mut buf).unwrap(), 4);
+
+        // 换了播放地址，.part 重新开始写入
+        download.reset();
+        let state = download.state.lock().unwrap().clone();
+        assert!(!state.started);
+        assert_eq!((state.downloaded, state.total), (0, None));
+        assert!(reader.read(&mut buf).is_err());
+    }
+}
@@ -0,0 +1,124 @@
+// src-tauri/src/tagging.rs
+
+use std::path::Path;
+
+use id3::{
+    Tag, TagLike, Version,
+    frame::{
+        ExtendedText, Lyrics, Picture, PictureType, SynchronisedLyrics, SynchronisedLyricsType,
+        TimestampFormat,
+    },
+};
+
+use crate::{
+    lyric,
+    model::{Music, ParsedLyric, TrackGain},
+};
+
+/// ID3 中语言字段使用 ISO-639-2 代码
+const LYRIC_LANG: &str = "chi";
+
+/// 根据文件头判断封面图片的 MIME 类型，cover_cache 中的文件名不一定带有正确的扩展名
+fn sniff_image_mime(data: &[u8]) -> &'static str {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        "image/png"
+    } else if data.starts_with(b"GIF8") {
+        "image/gif"
+    } else if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        "image/webp"
+    } else {
+        "image/jpeg"
+    }
+}
+
+/// SYLT 中每行一个时间点，双语歌词的翻译作为同一时间的另一行
+fn synced_lyric_content(parsed: &ParsedLyric) -> Vec<(u32, String)> {
+    parsed
+        .lines
+        .iter()
+        .flat_map(|line| {
+            let time = u32::try_from(line.time_ms).unwrap_or(u32::MAX);
+            std::iter::once((time, line.text.clone()))
+                .chain(line.translation.clone().map(|t| (time, t)))
+        })
+        .collect()
+}
+
+/// ReplayGain 使用 TXXX 帧，按 foobar2000 等播放器的惯例命名
+fn set_replaygain(tag: &mut Tag, gain: &TrackGain) {
+    if let Some(gain_db) = gain.gain_db {
+        tag.add_frame(ExtendedText {
+            description: "REPLAYGAIN_TRACK_GAIN".to_string(),
+            value: format!("{:.2} dB", gain_db),
+        });
+    }
+    if let Some(peak) = gain.true_peak {
+        tag.add_frame(ExtendedText {
+            description: "REPLAYGAIN_TRACK_PEAK".to_string(),
+            value: format!("{:.6}", peak),
+        });
+    }
+}
+
+/// 将歌曲信息写入 MP3 文件的 ID3v2.4 标签：
+/// TIT2/TPE1 来自标题和歌手，APIC 来自本地封面缓存，USLT/SYLT 来自歌词，
+/// 传入响度分析结果时同时写入 ReplayGain。文件中已有的其他标签帧会保留
+pub fn write_id3_tags(
+    path: &Path,
+    music: &Music,
+    cover_path: Option<&Path>,
+    gain: Option<&TrackGain>,
+) -> Result<(), String> {
+    let mut tag = id3::no_tag_ok(id3::partial_tag_ok(Tag::read_from_path(path)))
+        .map_err(|e| format!("读取 ID3 标签失败: {}", e))?
+        .unwrap_or_default();
+
+    tag.set_title(music.title.as_str());
+    tag.set_artist(music.artist.as_str());
+    if let Some(secs) = music.duration_secs.filter(|s| *s > 0.0) {
+        tag.set_duration((secs * 1000.0).round() as u32);
+    }
+
+    if let Some(cover_path) = cover_path {
+        match std::fs::read(cover_path) {
+            Ok(data) => {
+                tag.remove_all_pictures();
+                tag.add_frame(Picture {
+                    mime_type: sniff_image_mime(&data).to_string(),
+                    picture_type: PictureType::CoverFront,
+                    description: String::new(),
+                    data,
+                });
+            }
+            Err(e) => eprintln!("读取封面 {} 失败: {}", cover_path.display(), e),
+        }
+    }
+
+    if let Some(lyric) = music.lyric.as_deref().filter(|l| !l.trim().is_empty()) {
+        let parsed = lyric::parse_lrc(lyric);
+        tag.remove_all_lyrics();
+        tag.add_frame(Lyrics {
+            lang: LYRIC_LANG.to_string(),
+            description: String::new(),
+            text: parsed.plain_text(),
+        });
+
+        tag.remove_all_synchronised_lyrics();
+        if parsed.synced {
+            tag.add_frame(SynchronisedLyrics {
+                lang: LYRIC_LANG.to_string(),
+                timestamp_format: TimestampFormat::Ms,
+                content_type: SynchronisedLyricsType::Lyrics,
+                description: String::new(),
+                content: synced_lyric_content(&parsed),
+            });
+        }
+    }
+
+    if let Some(gain) = gain {
+        set_replaygain(&mut tag, gain);
+    }
+
+    tag.write_to_path(path, Version::Id3v24)
+        .map_err(|e| format!("写入 ID3 标签失败: {}", e))
+}
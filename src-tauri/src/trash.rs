@@ -0,0 +1,245 @@
+// src-tauri/src/trash.rs
+
+//! 回收站：删除的歌单和从歌单中移除的歌曲先放入回收站，可以恢复；
+//! 超过保留天数后在启动时彻底删除。
+
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection};
+
+use crate::{
+    model::{TrashContents, TrashedPlaylist, TrashedSong},
+    my_util::{DbPool, get_app_setting},
+    playlist::compact_positions,
+};
+
+/// 回收站保留天数的设置项
+pub const RETENTION_DAYS_KEY: &str = "trash_retention_days";
+const DEFAULT_RETENTION_DAYS: i64 = 30;
+
+#[derive(sqlx::FromRow)]
+struct TrashEntry {
+    id: i64,
+    playlist_id: i64,
+    song_id: String,
+    position: i64,
+    added_to_list_at: String,
+    playlist_deleted: bool,
+}
+
+pub async fn get_retention_days(pool: &DbPool) -> Result<i64, sqlx::Error> {
+    let value = get_app_setting(pool, RETENTION_DAYS_KEY.to_string()).await?;
+    Ok(value
+        .and_then(|v| v.parse::<i64>().ok())
+        .filter(|days| *days >= 0)
+        .unwrap_or(DEFAULT_RETENTION_DAYS))
+}
+
+/// 从歌单移除歌曲前调用，记下原来的位置
+pub async fn record_removed_song(
+    conn: &mut SqliteConnection,
+    playlist_id: i64,
+    song_id: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT OR REPLACE INTO playlist_music_trash (playlist_id, song_id, position, added_to_list_at)
+         SELECT playlist_id, song_id, position, added_to_list_at FROM playlist_music
+         WHERE playlist_id = ? AND song_id = ?",
+    )
+    .bind(playlist_id)
+    .bind(song_id)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn list_trash(pool: &DbPool) -> Result<TrashContents, sqlx::Error> {
+    let playlists: Vec<TrashedPlaylist> = sqlx::query_as(
+        r#"
+        SELECT
+            p.id,
+            p.name,
+            p.cover_path,
+            (SELECT COUNT(*) FROM playlist_music pm WHERE pm.playlist_id = p.id) AS song_count,
+            p.smart_rules IS NOT NULL AS is_smart,
+            p.deleted_at
+        FROM playlist p
+        WHERE p.deleted_at IS NOT NULL
+        ORDER BY p.deleted_at DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    // 已经重新加入歌单的歌曲和已删除歌单中的歌曲不单独列出
+    let songs: Vec<TrashedSong> = sqlx::query_as(
+        r#"
+        SELECT
+            t.id,
+            t.playlist_id,
+            p.name AS playlist_name,
+            t.song_id,
+            m.title,
+            m.artist,
+            m.cover_url,
+            t.deleted_at
+        FROM playlist_music_trash t
+        INNER JOIN playlist p ON t.playlist_id = p.id
+        INNER JOIN music m ON t.song_id = m.song_id
+        WHERE p.deleted_at IS NULL
+            AND NOT EXISTS (
+                SELECT 1 FROM playlist_music pm
+                WHERE pm.playlist_id = t.playlist_id AND pm.song_id = t.song_id
+            )
+        ORDER BY t.deleted_at DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(TrashContents {
+        playlists,
+        songs,
+        retention_days: get_retention_days(pool).await?,
+    })
+}
+
+pub async fn restore_playlist(pool: &DbPool, playlist_id: i64) -> Result<(), String> {
+    let result = sqlx::query(
+        "UPDATE playlist SET deleted_at = NULL WHERE id = ? AND deleted_at IS NOT NULL",
+    )
+    .bind(playlist_id)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    if result.rows_affected() == 0 {
+        return Err("歌单不在回收站中".to_string());
+    }
+    Ok(())
+}
+
+/// 把移除的歌曲放回原来的位置。歌曲已经重新加入歌单时只清除回收站记录
+pub async fn restore_removed_songs(pool: &DbPool, trash_ids: Vec<i64>) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+
+    let mut builder = QueryBuilder::<Sqlite>::new(
+        "SELECT t.id, t.playlist_id, t.song_id, t.position, t.added_to_list_at,
+            p.deleted_at IS NOT NULL AS playlist_deleted
+         FROM playlist_music_trash t INNER JOIN playlist p ON t.playlist_id = p.id
+         WHERE t.id IN (",
+    );
+    let mut ids = builder.separated(", ");
+    for id in &trash_ids {
+        ids.push_bind(*id);
+    }
+    builder.push(") ORDER BY t.playlist_id, t.position");
+    let entries: Vec<TrashEntry> = builder
+        .build_query_as()
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    if entries.iter().any(|e| e.playlist_deleted) {
+        return Err("所在歌单已被删除，请先恢复歌单".to_string());
+    }
+
+    let mut playlist_ids: Vec<i64> = Vec::new();
+    for entry in entries {
+        let TrashEntry {
+            id,
+            playlist_id,
+            song_id,
+            position,
+            added_to_list_at,
+            ..
+        } = entry;
+        // 按位置从小到大插入，先插入的歌曲不会影响后面歌曲的原位置
+        let result = sqlx::query(
+            "INSERT OR IGNORE INTO playlist_music (playlist_id, song_id, position, added_to_list_at)
+             VALUES (?, ?, ?, ?)",
+        )
+        .bind(playlist_id)
+        .bind(&song_id)
+        .bind(position)
+        .bind(added_to_list_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+        if result.rows_affected() > 0 {
+            sqlx::query(
+                "UPDATE playlist_music SET position = position + 1
+                 WHERE playlist_id = ? AND position >= ? AND song_id != ?",
+            )
+            .bind(playlist_id)
+            .bind(position)
+            .bind(&song_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+        }
+        sqlx::query("DELETE FROM playlist_music_trash WHERE id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+        if !playlist_ids.contains(&playlist_id) {
+            playlist_ids.push(playlist_id);
+        }
+    }
+
+    for playlist_id in playlist_ids {
+        compact_positions(&mut tx, playlist_id)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// 彻底删除回收站中 deleted_at 早于截止时间的内容，cutoff 为 None 时清空回收站
+async fn purge(pool: &DbPool, cutoff: Option<String>) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let playlist_filter = "deleted_at IS NOT NULL AND deleted_at < COALESCE(?, '9999')";
+    sqlx::query(&format!(
+        "DELETE FROM playlist_music WHERE playlist_id IN (SELECT id FROM playlist WHERE {})",
+        playlist_filter
+    ))
+    .bind(&cutoff)
+    .execute(&mut *tx)
+    .await?;
+    // playlist_music_trash 中的记录随歌单一起删除
+    let playlists = sqlx::query(&format!("DELETE FROM playlist WHERE {}", playlist_filter))
+        .bind(&cutoff)
+        .execute(&mut *tx)
+        .await?;
+    let songs =
+        sqlx::query("DELETE FROM playlist_music_trash WHERE deleted_at < COALESCE(?, '9999')")
+            .bind(&cutoff)
+            .execute(&mut *tx)
+            .await?;
+
+    tx.commit().await?;
+    Ok(playlists.rows_affected() + songs.rows_affected())
+}
+
+pub async fn empty_trash(pool: &DbPool) -> Result<(), sqlx::Error> {
+    purge(pool, None).await.map(|_| ())
+}
+
+/// 启动时的维护任务：删除超过保留天数的回收站内容
+pub async fn purge_expired_trash(pool: &DbPool) -> Result<(), String> {
+    let days = get_retention_days(pool).await.map_err(|e| e.to_string())?;
+    if days == 0 {
+        return Ok(());
+    }
+    // deleted_at 与 created_at 一样是本地时间
+    let cutoff: String =
+        sqlx::query_scalar("SELECT strftime('%Y-%m-%d %H:%M:%f', 'now', 'localtime', ?)")
+            .bind(format!("-{} days", days))
+            .fetch_one(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+    let purged = purge(pool, Some(cutoff)).await.map_err(|e| e.to_string())?;
+    if purged > 0 {
+        println!("[Trash] Purged {} expired items.", purged);
+    }
+    Ok(())
+}
@@ -0,0 +1,167 @@
+// src-tauri/src/tray.rs
+
+//! 桌面端的托盘图标：播放控制、最近歌单，提示文本显示正在播放的歌曲。
+//! 播放状态或歌单变化后调用 refresh 重建菜单，播放相关的操作以 media-control 事件交给前端。
+
+use tauri::{
+    AppHandle, Manager, Wry,
+    image::Image,
+    menu::{IsMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu},
+    tray::{MouseButton, TrayIconBuilder, TrayIconEvent},
+};
+
+use crate::{
+    media_session::{self, MediaControl, MediaSession, NowPlaying},
+    model::PlaylistInfo,
+    my_util::DbPool,
+    playlist,
+};
+
+const TRAY_ID: &str = "main";
+const RECENT_PLAYLIST_LIMIT: usize = 8;
+/// 最近歌单菜单项的 ID 为 "playlist:<歌单 ID>"
+const PLAYLIST_ITEM_PREFIX: &str = "playlist:";
+
+/// 同时只重建一次菜单，保证最后一次重建读到的是最新状态
+static REBUILD_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+fn show_main_window(app_handle: &AppHandle) {
+    if let Some(window) = app_handle.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+fn tooltip(now_playing: &NowPlaying) -> String {
+    match now_playing.song_id {
+        Some(_) => format!("{} - {}", now_playing.title, now_playing.artist),
+        None => "MusicBox".to_string(),
+    }
+}
+
+fn build_menu(
+    app_handle: &AppHandle,
+    now_playing: &NowPlaying,
+    playlists: &[PlaylistInfo],
+) -> tauri::Result<Menu<Wry>> {
+    let has_song = now_playing.song_id.is_some();
+    let play_pause_text = if now_playing.playing {
+        "暂停"
+    } else {
+        "播放"
+    };
+    let play_pause = MenuItem::with_id(
+        app_handle,
+        "play_pause",
+        play_pause_text,
+        has_song,
+        None::<&str>,
+    )?;
+    let previous = MenuItem::with_id(app_handle, "previous", "上一首", has_song, None::<&str>)?;
+    let next = MenuItem::with_id(app_handle, "next", "下一首", has_song, None::<&str>)?;
+
+    let playlist_items = playlists
+        .iter()
+        .map(|p| {
+            let id = format!("{}{}", PLAYLIST_ITEM_PREFIX, p.id);
+            MenuItem::with_id(app_handle, id, &p.name, true, None::<&str>)
+        })
+        .collect::<tauri::Result<Vec<_>>>()?;
+    let playlist_refs: Vec<&dyn IsMenuItem<Wry>> = playlist_items
+        .iter()
+        .map(|item| item as &dyn IsMenuItem<Wry>)
+        .collect();
+    let recent = Submenu::with_id_and_items(
+        app_handle,
+        "recent_playlists",
+        "最近歌单",
+        !playlist_refs.is_empty(),
+        &playlist_refs,
+    )?;
+
+    let show = MenuItem::with_id(app_handle, "show", "显示", true, None::<&str>)?;
+    let quit = MenuItem::with_id(app_handle, "quit", "退出", true, None::<&str>)?;
+    Menu::with_items(
+        app_handle,
+        &[
+            &play_pause,
+            &previous,
+            &next,
+            &PredefinedMenuItem::separator(app_handle)?,
+            &recent,
+            &PredefinedMenuItem::separator(app_handle)?,
+            &show,
+            &quit,
+        ],
+    )
+}
+
+fn on_menu_event(app_handle: &AppHandle, id: &str) {
+    match id {
+        "quit" => app_handle.exit(0),
+        "show" => show_main_window(app_handle),
+        "play_pause" => media_session::emit_control(app_handle, MediaControl::PlayPause),
+        "previous" => media_session::emit_control(app_handle, MediaControl::Previous),
+        "next" => media_session::emit_control(app_handle, MediaControl::Next),
+        _ => {
+            let playlist_id = id
+                .strip_prefix(PLAYLIST_ITEM_PREFIX)
+                .and_then(|id| id.parse().ok());
+            if let Some(playlist_id) = playlist_id {
+                media_session::emit_control(app_handle, MediaControl::PlayPlaylist { playlist_id });
+                show_main_window(app_handle);
+            }
+        }
+    }
+}
+
+/// 创建托盘图标。此时数据库还没有初始化，最近歌单等数据库就绪后由 refresh 填充
+pub fn create(app_handle: &AppHandle) -> tauri::Result<()> {
+    let menu = build_menu(app_handle, &NowPlaying::default(), &[])?;
+    TrayIconBuilder::with_id(TRAY_ID)
+        .menu(&menu)
+        .tooltip("MusicBox")
+        .icon(Image::from_bytes(include_bytes!("../icons/icon.png"))?)
+        .on_menu_event(|app, event| on_menu_event(app, event.id().as_ref()))
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                ..
+            } = event
+            {
+                show_main_window(tray.app_handle());
+            }
+        })
+        .build(app_handle)?;
+    Ok(())
+}
+
+async fn rebuild(app_handle: &AppHandle) -> Result<(), String> {
+    let _guard = REBUILD_LOCK.lock().await;
+    let Some(tray) = app_handle.tray_by_id(TRAY_ID) else {
+        return Ok(());
+    };
+    let now_playing = app_handle.state::<MediaSession>().now_playing();
+    let playlists = match app_handle.try_state::<DbPool>() {
+        Some(pool) => playlist::get_recent_playlists(pool.inner(), RECENT_PLAYLIST_LIMIT)
+            .await
+            .map_err(|e| e.to_string())?,
+        None => Vec::new(),
+    };
+
+    let menu = build_menu(app_handle, &now_playing, &playlists).map_err(|e| e.to_string())?;
+    tray.set_menu(Some(menu)).map_err(|e| e.to_string())?;
+    tray.set_tooltip(Some(tooltip(&now_playing)))
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// 在后台重建托盘菜单和提示文本，播放状态或歌单列表变化后调用
+pub fn refresh(app_handle: &AppHandle) {
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = rebuild(&app_handle).await {
+            eprintln!("[Tray] 更新托盘菜单失败: {}", e);
+        }
+    });
+}
@@ -16,10 +16,28 @@ import { checkForUpdates } from "./util/updater";
 import PrivacyPage from "./pages/Setting/Privacy";
 import CacheManagePage from "./pages/Setting/cacheManage";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import PlaylistCacheManagePage from "./pages/Setting/PlaylistCacheManage";
-import { buildMusicFileUrl } from "./util";
+import TrashPage from "./pages/Setting/Trash";
+import { buildPlaybackUrl } from "./util";
+import type {
+  MediaControl,
+  Music,
+  NativePlayerState,
+  PlaylistMusic,
+  PlayQueueState,
+  TrackGain,
+} from "./types";
 
 const { Header } = Layout;
+
+interface ListenSession {
+  songId: string;
+  startedAt: string;
+  listenedSecs: number;
+  lastTime: number; // 上一次 timeupdate 时的播放位置
+  playlistId: number | null;
+}
 const { Title } = Typography;
 
 const AppHeader = () => (
@@ -62,18 +80,54 @@ const AppContent = () => {
     handleClose,
     setCurrentTime, // 获取新的 action
     setDuration, // 获取新的 action
+    syncPlayQueue,
+    restorePlayQueue,
+    playbackEngine,
+    playQueue,
+    playingMusicIndex,
+    playMode,
+    volumeNormalization,
   } = useAppStore();
 
   const messageApi = useGlobalMessage();
   const modalApi = useGlobalModal();
   const audioRef = useRef<HTMLAudioElement>(null);
+  // 当前这次收听的统计，切歌、播放结束或关闭窗口时上报为一条播放记录
+  const listenRef = useRef<ListenSession | null>(null);
+  // 最近一次交给原生引擎的歌曲，以及已经无缝切换过去、不需要再加载的下一首
+  const nativeMusicRef = useRef<Music | null>(null);
+  const gaplessSongIdRef = useRef<string | null>(null);
+  // 最近一次同步给系统媒体控制的歌曲
+  const mediaSongIdRef = useRef<string | null>(null);
+
+  const flushListen = (completed: boolean, skipped: boolean) => {
+    const listen = listenRef.current;
+    listenRef.current = null;
+    if (!listen || (!completed && listen.listenedSecs < 1)) return;
+    invoke("record_play_event", {
+      payload: {
+        song_id: listen.songId,
+        started_at: listen.startedAt,
+        listened_secs: listen.listenedSecs,
+        completed,
+        skipped: skipped && !completed,
+        playlist_id: listen.playlistId,
+      },
+    }).catch(console.error);
+  };
 
   useEffect(() => {
     const audio = audioRef.current;
     if (!audio) return;
 
+    // 使用原生引擎时 <audio> 只保持暂停
+    if (playbackEngine === "native") {
+      if (!audio.paused) audio.pause();
+      return;
+    }
+
     // --- 同步歌曲源 ---
-    const buildPath = buildMusicFileUrl(currentMusic?.file_path);
+    const buildPath = buildPlaybackUrl(currentMusic);
     if (currentMusic && buildPath) {
       if (audio.src !== buildPath) {
         audio.src = buildPath;
@@ -81,16 +135,19 @@ const AppContent = () => {
     } else {
       audio.src = "";
     }
+    // 从原生引擎切换回来时，歌曲没变不会重新加载，直接跳到原生引擎播放到的位置
+    const { resumePositionSecs } = useAppStore.getState();
+    if (resumePositionSecs && audio.readyState > 0) {
+      audio.currentTime = resumePositionSecs;
+      useAppStore.setState({ resumePositionSecs: null });
+    }
 
     // --- 同步播放/暂停状态 ---
     setTimeout(() => {
       if (isPlaying) {
         // 检查是否已暂停，避免不必要的 play() 调用
         if (audio.paused) {
-          if (!currentMusic?.file_path || !buildPath) return; // 如果没有播放链接，就不尝试播放
-          invoke("update_music_last_play_time", {
-            songId: currentMusic.song_id,
-          }).catch(console.error);
+          if (!currentMusic || !buildPath) return; // 如果没有播放链接，就不尝试播放
           audio.play().catch((e) => console.error("播放失败:", e));
         }
       } else {
@@ -100,7 +157,58 @@ const AppContent = () => {
         }
       }
     }, 90);
-  }, [currentMusic, isPlaying]); // 同时监听歌曲和播放状态的变化
+  }, [currentMusic, isPlaying, playbackEngine]); // 同时监听歌曲和播放状态的变化
+
+  // 切到其他歌曲或关闭播放器时，上一首记为跳过
+  useEffect(() => {
+    const listen = listenRef.current;
+    if (listen && listen.songId !== currentMusic?.song_id) {
+      flushListen(false, true);
+    }
+  }, [currentMusic?.song_id]);
+
+  useEffect(() => {
+    const audio = audioRef.current;
+    if (!audio) return;
+
+    const onPlay = () => {
+      const { currentMusic, playingPlaylistId } = useAppStore.getState();
+      if (!currentMusic) return;
+      if (listenRef.current?.songId === currentMusic.song_id) return; // 暂停后继续，属于同一次收听
+      flushListen(false, true);
+      listenRef.current = {
+        songId: currentMusic.song_id,
+        startedAt: new Date().toISOString(),
+        listenedSecs: 0,
+        lastTime: audio.currentTime,
+        playlistId: playingPlaylistId,
+      };
+    };
+    // 只累计正常播放的时长，拖动进度条产生的跳变不计入
+    const onListenProgress = () => {
+      const listen = listenRef.current;
+      if (!listen) return;
+      const delta = audio.currentTime - listen.lastTime;
+      if (delta > 0 && delta < 2) {
+        listen.listenedSecs += delta;
+      }
+      listen.lastTime = audio.currentTime;
+    };
+    const onEnded = () => flushListen(true, false);
+    const onUnload = () => flushListen(false, false);
+
+    audio.addEventListener("play", onPlay);
+    audio.addEventListener("timeupdate", onListenProgress);
+    audio.addEventListener("ended", onEnded);
+    window.addEventListener("beforeunload", onUnload);
+
+    return () => {
+      audio.removeEventListener("play", onPlay);
+      audio.removeEventListener("timeupdate", onListenProgress);
+      audio.removeEventListener("ended", onEnded);
+      window.removeEventListener("beforeunload", onUnload);
+    };
+  }, []);
 
   useEffect(() => {
     const audio = audioRef.current;
@@ -108,37 +216,260 @@ const AppContent = () => {
 
     const onTimeUpdate = () => setCurrentTime(audio.currentTime);
     const onDurationChange = () => setDuration(audio.duration);
+    const onLoadedMetadata = () => {
+      onDurationChange();
+      // 恢复上次的播放进度
+      const { resumePositionSecs } = useAppStore.getState();
+      if (resumePositionSecs) {
+        audio.currentTime = resumePositionSecs;
+        useAppStore.setState({ resumePositionSecs: null });
+      }
+    };
 
     audio.addEventListener("timeupdate", onTimeUpdate);
     audio.addEventListener("durationchange", onDurationChange);
-    audio.addEventListener("loadedmetadata", onDurationChange);
+    audio.addEventListener("loadedmetadata", onLoadedMetadata);
 
     return () => {
       audio.removeEventListener("timeupdate", onTimeUpdate);
       audio.removeEventListener("durationchange", onDurationChange);
-      audio.removeEventListener("loadedmetadata", onDurationChange);
+      audio.removeEventListener("loadedmetadata", onLoadedMetadata);
     };
   }, [setCurrentTime, setDuration]);
 
+  // 播放队列保存在后端：启动时恢复，之后跟随后端的修改事件
+  useEffect(() => {
+    const restore = () =>
+      restorePlayQueue().catch((e) => console.error("恢复播放队列失败:", e));
+    // 需要等持久化的状态加载完成，旧版本的队列才能迁移到后端
+    if (useAppStore.persist.hasHydrated()) restore();
+    const unsubscribe = useAppStore.persist.onFinishHydration(restore);
+    const unlisten = listen<PlayQueueState>("play-queue-changed", (event) =>
+      syncPlayQueue(event.payload)
+    );
+    return () => {
+      unsubscribe();
+      unlisten.then((fn) => fn());
+    };
+  }, [restorePlayQueue, syncPlayQueue]);
+
   useEffect(() => {
     const audio = audioRef.current;
-    const buildPath = buildMusicFileUrl(currentMusic?.file_path);
+    const buildPath = buildPlaybackUrl(currentMusic);
     if (!audio || !buildPath) {
       handleClose(); // 如果没有播放链接，就关闭播放器
       return;
     }
 
+    if (playbackEngine === "native") return;
     if (buildPath && audio.src !== buildPath) {
       audio.src = buildPath;
       if (isPlaying && !audio.played) {
-        invoke("update_music_last_play_time", {
-          songId: currentMusic?.song_id,
-        }).catch(console.error);
         audio.play().catch((e) => console.error("自动播放失败:", e));
       }
     }
   }, [currentMusic, handleNext]);
 
+  // --- 原生播放引擎 ---
+  useEffect(() => {
+    if (playbackEngine !== "native") {
+      nativeMusicRef.current = null;
+      return;
+    }
+    if (!currentMusic) {
+      nativeMusicRef.current = null;
+      invoke("native_stop").catch(console.error);
+      return;
+    }
+    if (currentMusic === nativeMusicRef.current) return;
+    nativeMusicRef.current = currentMusic;
+    if (gaplessSongIdRef.current === currentMusic.song_id) {
+      gaplessSongIdRef.current = null;
+      return;
+    }
+
+    const { resumePositionSecs } = useAppStore.getState();
+    useAppStore.setState({ resumePositionSecs: null });
+    invoke("native_play", {
+      music: currentMusic,
+      startSecs: resumePositionSecs ?? 0,
+    })
+      .then(() => {
+        if (!useAppStore.getState().isPlaying) return invoke("native_pause");
+      })
+      .catch((e) => messageApi.error(`播放失败: ${e}`));
+  }, [currentMusic, playbackEngine]);
+
+  useEffect(() => {
+    if (playbackEngine !== "native" || !currentMusic) return;
+    invoke(isPlaying ? "native_resume" : "native_pause").catch(console.error);
+  }, [isPlaying, playbackEngine]);
+
+  // 音量均衡：<audio> 和原生引擎的音量都不能超过 1，比目标响度小的歌曲不会被放大
+  useEffect(() => {
+    if (!currentMusic) return;
+    let cancelled = false;
+    const applyVolume = (volume: number) => {
+      if (cancelled) return;
+      if (playbackEngine === "native") {
+        invoke("native_set_volume", { volume }).catch(console.error);
+      } else if (audioRef.current) {
+        audioRef.current.volume = volume;
+      }
+    };
+    if (!volumeNormalization) {
+      applyVolume(1);
+    } else {
+      invoke<TrackGain>("get_track_gain", { songId: currentMusic.song_id })
+        .then((gain) => applyVolume(Math.min(1, gain.volume_scale)))
+        .catch(() => applyVolume(1));
+    }
+    return () => {
+      cancelled = true;
+    };
+  }, [currentMusic?.song_id, volumeNormalization, playbackEngine]);
+
+  // 顺序播放时预加载下一首，当前歌曲播完后无缝衔接
+  useEffect(() => {
+    if (playbackEngine !== "native" || !currentMusic) return;
+    const next =
+      playMode === "sequence" && playQueue.length > 0
+        ? playQueue[(playingMusicIndex + 1) % playQueue.length]
+        : null;
+    invoke("native_set_next", { music: next }).catch(console.error);
+  }, [currentMusic, playQueue, playingMusicIndex, playMode, playbackEngine]);
+
+  useEffect(() => {
+    const onPosition = (state: NativePlayerState) => {
+      const { playbackEngine, currentMusic, playingPlaylistId } =
+        useAppStore.getState();
+      if (playbackEngine !== "native" || !state.song_id) return;
+      setCurrentTime(state.position_secs);
+      if (state.duration_secs) setDuration(state.duration_secs);
+
+      // 与 <audio> 的 play/timeupdate 一样统计收听时长
+      const listen = listenRef.current;
+      if (listen?.songId === state.song_id) {
+        const delta = state.position_secs - listen.lastTime;
+        if (delta > 0 && delta < 2) {
+          listen.listenedSecs += delta;
+        }
+        listen.lastTime = state.position_secs;
+      } else if (state.song_id === currentMusic?.song_id) {
+        flushListen(false, true);
+        listenRef.current = {
+          songId: state.song_id,
+          startedAt: new Date().toISOString(),
+          listenedSecs: 0,
+          lastTime: state.position_secs,
+          playlistId: playingPlaylistId,
+        };
+      }
+    };
+    const onTrackChanged = (state: NativePlayerState) => {
+      const { currentMusic, handleNext } = useAppStore.getState();
+      if (!state.song_id || state.song_id === currentMusic?.song_id) return;
+      // 预加载的下一首已经开始播放，只需要让队列跟上
+      flushListen(true, false);
+      gaplessSongIdRef.current = state.song_id;
+      handleNext();
+    };
+
+    const unlisteners = [
+      listen<NativePlayerState>("native-player-position", (event) =>
+        onPosition(event.payload)
+      ),
+      listen<NativePlayerState>("native-player-track-changed", (event) =>
+        onTrackChanged(event.payload)
+      ),
+      listen("native-player-ended", () => {
+        flushListen(true, false);
+        useAppStore.getState().handleNext();
+      }),
+      listen<string>("native-player-error", (event) =>
+        messageApi.error(`播放失败: ${event.payload}`)
+      ),
+    ];
+    return () => {
+      unlisteners.forEach((unlisten) => unlisten.then((fn) => fn()));
+    };
+  }, [messageApi, setCurrentTime, setDuration]);
+
+  // 把正在播放的歌曲同步给系统媒体控制 (Linux 上为 MPRIS)
+  useEffect(() => {
+    const songId = currentMusic?.song_id ?? null;
+    const { currentTime, resumePositionSecs } = useAppStore.getState();
+    // 刚切歌时 currentTime 还是上一首的进度
+    const positionSecs =
+      songId === mediaSongIdRef.current ? currentTime : resumePositionSecs ?? 0;
+    mediaSongIdRef.current = songId;
+    invoke("update_media_session", {
+      songId,
+      playing: isPlaying,
+      positionSecs,
+    }).catch(console.error);
+  }, [currentMusic?.song_id, isPlaying]);
+
+  // 托盘的最近歌单：随机播放模式下从随机一首开始
+  const playPlaylist = async (playlistId: number) => {
+    try {
+      const songs = await invoke<PlaylistMusic[]>("get_music_by_playlist_id", {
+        playlistId,
+      });
+      if (songs.length === 0) {
+        messageApi.warning("歌单中没有歌曲");
+        return;
+      }
+      const { playMode, startPlayback } = useAppStore.getState();
+      const startIndex =
+        playMode === "shuffle" ? Math.floor(Math.random() * songs.length) : 0;
+      await startPlayback(songs, startIndex, playlistId);
+    } catch (error) {
+      messageApi.error("播放歌单失败");
+      console.error(error);
+    }
+  };
+
+  // 系统媒体控制、键盘媒体键和托盘菜单的操作
+  useEffect(() => {
+    const unlisten = listen<MediaControl>("media-control", (event) => {
+      const { isPlaying, handlePlayPause, handleNext, handlePrev, seekTo } =
+        useAppStore.getState();
+      const control = event.payload;
+      switch (control.action) {
+        case "play":
+          if (!isPlaying) handlePlayPause();
+          break;
+        case "pause":
+          if (isPlaying) handlePlayPause();
+          break;
+        case "play_pause":
+          handlePlayPause();
+          break;
+        case "next":
+          handleNext();
+          break;
+        case "previous":
+          handlePrev();
+          break;
+        case "stop":
+          // 停止后保留播放队列，回到歌曲开头
+          if (isPlaying) handlePlayPause();
+          seekTo(audioRef.current, 0);
+          break;
+        case "seek":
+          seekTo(audioRef.current, control.position_secs);
+          break;
+        case "play_playlist":
+          playPlaylist(control.playlist_id);
+          break;
+      }
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [messageApi]);
+
   useEffect(() => {
     const timer = setTimeout(() => {
       checkForUpdates({ force: false, messageApi, modalApi });
@@ -178,6 +509,7 @@ const AppContent = () => {
           <Route path="/setting/about" element={<AboutPage />} />
           <Route path="/setting/privacy" element={<PrivacyPage />} />
           <Route path="/setting/cache" element={<CacheManagePage />} />
+          <Route path="/setting/trash" element={<TrashPage />} />
           <Route
             path="/setting/cache/playlist/:playlistId"
             element={<PlaylistCacheManagePage />}
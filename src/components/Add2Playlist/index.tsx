@@ -52,7 +52,7 @@ const AddToPlaylistButton = memo(({
         const result: PlaylistInfo[] = await invoke("get_all_playlists", {
           songId: song.song_id,
         });
-        setPlaylists(result);
+        setPlaylists(result.filter((p) => !p.is_smart));
       } catch (error) {
         console.error("获取歌单列表失败:", error);
         messageApi.destroy();
@@ -1,56 +1,64 @@
-import React, { useMemo, useRef, useEffect } from "react";
+import React, { useState, useRef, useEffect } from "react";
+import { invoke } from "@tauri-apps/api/core";
 import { primaryThemeColor } from "../../main";
 
-// 定义解析后的歌词行接口
+// 后端 lyric 模块解析后的歌词行
 interface LyricLine {
-  time: number; // 歌词开始时间 (秒)
+  time_ms: number; // 歌词开始时间 (毫秒)，已应用 [offset:]
   text: string; // 歌词文本
+  translation?: string; // 双语歌词中的翻译
+}
+
+interface ParsedLyric {
+  synced: boolean;
+  lines: LyricLine[];
 }
 
 // 定义组件 Props
 interface LyricScrollerProps {
-  lyricText: string; // 完整的 LRC 格式歌词文本
+  songId: string;
+  lyricText: string; // 完整的 LRC 格式歌词文本，变化时重新获取解析结果
   currentTime: number; // 当前播放时间 (秒)
 }
 
-// LRC 歌词解析函数
-const parseLyric = (lrcText: string): LyricLine[] => {
-  const lines = lrcText.split("\n");
-  const result: LyricLine[] = [];
-  const timeRegex = /\[(\d{2}):(\d{2})\.(\d{2,3})\]/;
-
-  for (const line of lines) {
-    const match = line.match(timeRegex);
-    if (match) {
-      const minutes = parseInt(match[1], 10);
-      const seconds = parseInt(match[2], 10);
-      const milliseconds = parseInt(match[3].padEnd(3, "0"), 10); // 兼容xx和xxx格式
-      const time = minutes * 60 + seconds + milliseconds / 1000;
-      const text = line.replace(timeRegex, "").trim();
-      if (text) {
-        result.push({ time, text });
-      }
-    }
-  }
-  return result;
-};
-
 const LyricScroller: React.FC<LyricScrollerProps> = ({
+  songId,
   lyricText,
   currentTime,
 }) => {
   const lyricContainerRef = useRef<HTMLDivElement>(null);
+  const [parsed, setParsed] = useState<ParsedLyric>({ synced: false, lines: [] });
+
+  // 歌词由后端解析，详情加载完成后 lyricText 变化时重新获取
+  useEffect(() => {
+    let cancelled = false;
+    if (!songId || !lyricText) {
+      setParsed({ synced: false, lines: [] });
+      return;
+    }
+    invoke<ParsedLyric>("get_parsed_lyric", { songId })
+      .then((result) => {
+        if (!cancelled) setParsed(result);
+      })
+      .catch((error) => console.error("获取歌词失败:", error));
+    return () => {
+      cancelled = true;
+    };
+  }, [songId, lyricText]);
 
-  // 使用 useMemo 解析歌词，避免不必要的重复解析
-  const parsedLyrics = useMemo(() => parseLyric(lyricText), [lyricText]);
+  const parsedLyrics = parsed.lines;
 
-  // 找到当前应该高亮的歌词行索引
-  const currentLineIndex = parsedLyrics.findIndex((line, index) => {
-    const nextLine = parsedLyrics[index + 1];
-    return (
-      currentTime >= line.time && (!nextLine || currentTime < nextLine.time)
-    );
-  });
+  // 找到当前应该高亮的歌词行索引 (纯文本歌词不高亮)
+  const currentTimeMs = currentTime * 1000;
+  const currentLineIndex = parsed.synced
+    ? parsedLyrics.findIndex((line, index) => {
+        const nextLine = parsedLyrics[index + 1];
+        return (
+          currentTimeMs >= line.time_ms &&
+          (!nextLine || currentTimeMs < nextLine.time_ms)
+        );
+      })
+    : -1;
 
   // 使用 useEffect 实现自动滚动
   useEffect(() => {
@@ -101,6 +109,12 @@ const LyricScroller: React.FC<LyricScrollerProps> = ({
           }}
         >
           {line.text}
+          {line.translation && (
+            <>
+              <br />
+              <span style={{ fontSize: "0.75rem" }}>{line.translation}</span>
+            </>
+          )}
         </p>
       ))}
     </div>
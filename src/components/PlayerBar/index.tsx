@@ -117,6 +117,7 @@ const PlayerBar: React.FC<PlayerBarProps> = ({
               style={{ minWidth: "70%", borderBottom: "0.0625rem solid #ffb5b5ff" }}
             >
               <LyricScroller
+                songId={currentMusic.song_id}
                 lyricText={currentMusic.lyric || ""}
                 currentTime={currentTime}
               />
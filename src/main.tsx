@@ -7,6 +7,7 @@ import ReactDOM from 'react-dom/client';
 import App from './App';
 import { ConfigProvider, message, Modal } from 'antd'; // 1. 额外导入 antd 的 App 和 message
 import { MessageContext, ModalContext } from './components/MessageHook'; // 2. 导入我们创建的 Context
+import { initMediaServer } from './util';
 export const primaryThemeColor = "#F08080";
 
 // 3. 创建一个包含所有 Provider 的顶层组件
@@ -54,4 +55,7 @@ const Root = () => {
 };
 
 
-ReactDOM.createRoot(document.getElementById('root')!).render(<Root />);
\ No newline at end of file
+// 播放和封面地址依赖媒体服务器的端口和令牌，先取到再渲染
+initMediaServer().finally(() => {
+  ReactDOM.createRoot(document.getElementById('root')!).render(<Root />);
+});
\ No newline at end of file
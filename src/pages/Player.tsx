@@ -48,16 +48,14 @@ const PlayerPage: React.FC<PlayerPageProps> = ({ audioRef }) => {
     cyclePlayMode,
     addDownloadingId,
     removeDownloadingId,
+    seekTo,
   } = useAppStore();
 
   const messageApi = useGlobalMessage();
 
-  // [关键] Seek 操作直接在页面内部处理，因为它需要 audioRef
+  // [关键] Seek 需要 audioRef，使用原生引擎时交给后端处理
   const handleSeek = (value: number) => {
-    const audio = audioRef.current;
-    if (audio) {
-      audio.currentTime = value;
-    }
+    seekTo(audioRef.current, value);
   };
 
   if (!currentMusic) {
@@ -138,6 +136,7 @@ const PlayerPage: React.FC<PlayerPageProps> = ({ audioRef }) => {
         {/* 歌词滚动器 */}
         <div className="lyric-scroller-wrapper">
           <LyricScroller
+            songId={currentMusic.song_id}
             lyricText={currentMusic.lyric || ""}
             currentTime={currentTime}
           />
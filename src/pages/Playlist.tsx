@@ -10,6 +10,8 @@ import {
   Input,
   TableProps,
   Table,
+  Select,
+  Dropdown,
 } from "antd";
 import {
   DownloadOutlined,
@@ -18,10 +20,12 @@ import {
   DeleteOutlined,
   CaretDownOutlined,
   PlusOutlined,
+  VerticalAlignTopOutlined,
+  ExportOutlined,
 } from "@ant-design/icons";
 import { invoke } from "@tauri-apps/api/core";
 import { useAppStore } from "../store";
-import type { PlaylistInfo, Music, PlaylistMusic } from "../types";
+import type { PlaylistInfo, Music, PlaylistMusic, PlaylistSortMode } from "../types";
 import "./Playlist.css"; // 我们将为它创建专属的 CSS
 import { useGlobalMessage } from "../components/MessageHook";
 import { buildCoverUrl } from "../util";
@@ -30,6 +34,14 @@ import { primaryThemeColor } from "../main";
 const { Title, Text } = Typography;
 const { Search } = Input;
 
+const sortModeOptions: { value: PlaylistSortMode; label: string }[] = [
+  { value: "manual", label: "手动排序" },
+  { value: "added_desc", label: "最近添加" },
+  { value: "title", label: "歌曲名" },
+  { value: "artist", label: "歌手" },
+  { value: "last_played", label: "最近播放" },
+];
+
 const PlaylistPage: React.FC = () => {
   // --- 全局状态 ---
   const { startPlayback, cyclePlayMode, saveSongWithNotifications, addDownloadingId, removeDownloadingId, currentPlaylistId, setCurrentPlaylistId } = useAppStore();
@@ -153,10 +165,21 @@ const PlaylistPage: React.FC = () => {
     {
       title: "操作",
       key: "action",
-      width: 80,
+      width: 112,
       align: "center",
       render: (_text, record) => (
         <Flex gap="small">
+          {!selectedPlaylist?.is_smart && (
+            <Button
+              type="text"
+              shape="circle"
+              icon={<VerticalAlignTopOutlined />}
+              onClick={(e) => {
+                e.stopPropagation();
+                handleMoveToTop(record);
+              }}
+            />
+          )}
           <Button
             type="text"
             shape="circle"
@@ -272,7 +295,7 @@ const PlaylistPage: React.FC = () => {
 
   const handlePlaySong = (index: number) => {
     // 将 PlaylistMusic[] 转换为 Music[]
-    startPlayback(selectedPlaylistMusic, index).then(() => !selectedPlaylistMusic[index].cover_url && refreshData()).catch((error) => console.error(error));
+    startPlayback(selectedPlaylistMusic, index, selectedPlaylist?.id ?? null).then(() => !selectedPlaylistMusic[index].cover_url && refreshData()).catch((error) => console.error(error));
   };
 
   const handleRemoveFromPlaylist = async (music: Music) => {
@@ -292,6 +315,54 @@ const PlaylistPage: React.FC = () => {
     }
   };
 
+  // 置顶会把歌单切换为手动排序
+  const handleMoveToTop = async (music: Music) => {
+    if (!selectedPlaylist) return;
+    try {
+      await invoke("move_songs_in_playlist", {
+        playlistId: selectedPlaylist.id,
+        songIds: [music.song_id],
+        toIndex: 0,
+      });
+      refreshData();
+    } catch (error) {
+      messageApi.error(`操作失败: ${error}`);
+      console.error(error);
+    }
+  };
+
+  const handleSortModeChange = async (sortMode: PlaylistSortMode) => {
+    if (!selectedPlaylist) return;
+    try {
+      await invoke("set_playlist_sort_mode", {
+        playlistId: selectedPlaylist.id,
+        sortMode,
+      });
+      refreshData();
+    } catch (error) {
+      messageApi.error(`操作失败: ${error}`);
+      console.error(error);
+    }
+  };
+
+  // 导出歌单文件，已缓存的歌曲同时导出音频，歌单中以相对路径引用
+  const handleExportPlaylist = async (format: string) => {
+    if (!selectedPlaylist) return;
+    messageApi.info("歌单导出中...");
+    try {
+      const result: string = await invoke("export_playlist", {
+        playlistId: selectedPlaylist.id,
+        format,
+        includeAudio: format !== "json",
+      });
+      messageApi.destroy();
+      messageApi.success(result);
+    } catch (error) {
+      messageApi.destroy();
+      messageApi.error(`导出失败: ${error}`);
+    }
+  };
+
   // [新增] 播放整个歌单的函数
   const handlePlayAll = (mode: "sequence" | "shuffle") => {
     if (selectedPlaylistMusic.length === 0) return;
@@ -306,7 +377,7 @@ const PlaylistPage: React.FC = () => {
       `${mode === "sequence" ? "顺序" : "随机"} 播放 ${selectedPlaylist?.name || "歌单"
       }, 即将播放 ${selectedPlaylistMusic[startIndex].title}`
     );
-    startPlayback(selectedPlaylistMusic, startIndex).catch((error) =>
+    startPlayback(selectedPlaylistMusic, startIndex, selectedPlaylist?.id ?? null).catch((error) =>
       console.error(error)
     );
   };
@@ -357,7 +428,7 @@ const PlaylistPage: React.FC = () => {
     }
     await invoke('delete_playlist', { playlistId: p_id }).then(() => {
       messageApi.destroy()
-      messageApi.success(`删除歌单完成`)
+      messageApi.success(`歌单已移入回收站`)
       fetchPlaylists().then((result: any) => {
         setCurrentPlaylistId(p_id === currentPlaylistId ? result.at(0).id : currentPlaylistId)
         fetchPlaylistMusic()
@@ -452,14 +523,36 @@ const PlaylistPage: React.FC = () => {
                 >
                   下载全部
                 </Button>
+                <Dropdown
+                  menu={{
+                    items: [
+                      { key: "m3u8", label: "M3U8" },
+                      { key: "xspf", label: "XSPF" },
+                      { key: "json", label: "JSON" },
+                    ],
+                    onClick: ({ key }) => handleExportPlaylist(key),
+                  }}
+                >
+                  <Button icon={<ExportOutlined />}>导出</Button>
+                </Dropdown>
               </Flex>
               {/* [新增] 搜索框 */}
-              <Search
-                placeholder="在歌单中搜索..."
-                allowClear
-                onChange={(e) => setSearchText(e.target.value)}
-                style={{ width: "100%" }}
-              />
+              <Flex gap="small">
+                <Search
+                  placeholder="在歌单中搜索..."
+                  allowClear
+                  onChange={(e) => setSearchText(e.target.value)}
+                  style={{ flex: 1 }}
+                />
+                {!selectedPlaylist.is_smart && (
+                  <Select
+                    value={selectedPlaylist.sort_mode}
+                    options={sortModeOptions}
+                    onChange={handleSortModeChange}
+                    style={{ width: 110 }}
+                  />
+                )}
+              </Flex>
             </Flex>
           </div>
           {/* --- 可滚动的内容 --- */}
@@ -49,7 +49,7 @@ const SearchPage: React.FC = () => {
 
   const handlePlayFromSearch = (index: number) => {
     // 关键：将整个 musicList 作为播放队列传入
-    startPlayback(musicList, index).then(() => {
+    startPlayback(musicList, index, null).then(() => {
       messageApi.destroy();
       messageApi.success(`开始播放 ${musicList[index].title}`);
     }).catch(error => {
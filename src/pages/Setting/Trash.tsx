@@ -0,0 +1,222 @@
+// src/pages/Setting/Trash.tsx
+
+import React, { useCallback, useEffect, useState } from "react";
+import {
+  Button,
+  Card,
+  Flex,
+  InputNumber,
+  List,
+  Space,
+  Spin,
+  Typography,
+} from "antd";
+import { invoke } from "@tauri-apps/api/core";
+import {
+  ArrowLeftOutlined,
+  DeleteOutlined,
+  UndoOutlined,
+} from "@ant-design/icons";
+import { useNavigate } from "react-router-dom";
+import { useGlobalMessage, useGlobalModal } from "../../components/MessageHook";
+import { primaryThemeColor } from "../../main";
+import { buildCoverUrl, formatRelativeTime } from "../../util";
+import { TrashContents } from "../../types";
+
+const { Text } = Typography;
+
+const TrashPage: React.FC = () => {
+  const navigate = useNavigate();
+  const messageApi = useGlobalMessage();
+  const modalApi = useGlobalModal();
+  const [loading, setLoading] = useState(true);
+  const [trash, setTrash] = useState<TrashContents | null>(null);
+  const [retentionDays, setRetentionDays] = useState<number | null>(30);
+
+  const fetchData = useCallback(async () => {
+    try {
+      setLoading(true);
+      const result = await invoke<TrashContents>("list_trash");
+      setTrash(result);
+      setRetentionDays(result.retention_days);
+    } catch (error: any) {
+      messageApi.error(`加载回收站失败: ${error}`);
+    } finally {
+      setLoading(false);
+    }
+  }, [messageApi]);
+
+  useEffect(() => {
+    fetchData();
+  }, [fetchData]);
+
+  const handleRestorePlaylist = async (playlistId: number) => {
+    try {
+      await invoke("restore_playlist", { playlistId });
+      messageApi.success("歌单已恢复");
+      fetchData();
+    } catch (error: any) {
+      messageApi.error(`恢复失败: ${error}`);
+    }
+  };
+
+  const handleRestoreSongs = async (trashIds: number[]) => {
+    try {
+      await invoke("restore_removed_songs", { trashIds });
+      messageApi.success("歌曲已恢复到原歌单");
+      fetchData();
+    } catch (error: any) {
+      messageApi.error(`恢复失败: ${error}`);
+    }
+  };
+
+  const handleEmptyTrash = () => {
+    modalApi.confirm({
+      title: "清空回收站",
+      content: "回收站中的歌单和歌曲将被彻底删除，此操作不可恢复。",
+      okText: "清空",
+      okButtonProps: { danger: true },
+      cancelText: "取消",
+      onOk: async () => {
+        try {
+          await invoke("empty_trash");
+          messageApi.success("回收站已清空");
+          fetchData();
+        } catch (error: any) {
+          messageApi.error(`清空失败: ${error}`);
+        }
+      },
+    });
+  };
+
+  const handleSaveRetention = async () => {
+    try {
+      await invoke("save_app_setting", {
+        key: "trash_retention_days",
+        value: String(retentionDays ?? 0),
+      });
+      messageApi.success("已保存");
+    } catch (error: any) {
+      messageApi.error(`保存失败: ${error}`);
+    }
+  };
+
+  return (
+    <Spin spinning={loading}>
+      <Flex vertical gap="1rem">
+        <Card
+          title={
+            <Flex align="center">
+              <ArrowLeftOutlined
+                onClick={() => navigate(-1)}
+                style={{
+                  marginRight: "1rem",
+                  color: primaryThemeColor,
+                  fontSize: "1rem",
+                }}
+              />
+              <span>回收站</span>
+            </Flex>
+          }
+          extra={
+            <Button danger icon={<DeleteOutlined />} onClick={handleEmptyTrash}>
+              清空
+            </Button>
+          }
+        >
+          <Space align="center" wrap>
+            <Text>自动删除超过</Text>
+            <InputNumber
+              min={0}
+              max={365}
+              value={retentionDays}
+              onChange={setRetentionDays}
+            />
+            <Text>天的内容 (0 表示不自动删除)</Text>
+            <Button type="primary" onClick={handleSaveRetention}>
+              保存
+            </Button>
+          </Space>
+        </Card>
+
+        <Card title="已删除的歌单">
+          <List
+            itemLayout="horizontal"
+            dataSource={trash?.playlists ?? []}
+            locale={{ emptyText: "没有已删除的歌单" }}
+            renderItem={(item) => (
+              <List.Item
+                actions={[
+                  <Button
+                    icon={<UndoOutlined />}
+                    onClick={() => handleRestorePlaylist(item.id)}
+                  >
+                    恢复
+                  </Button>,
+                ]}
+              >
+                <List.Item.Meta
+                  avatar={
+                    <img
+                      width={48}
+                      alt="cover"
+                      src={buildCoverUrl(item.cover_path)}
+                    />
+                  }
+                  title={item.name}
+                  description={`${item.is_smart ? "智能歌单" : `${item.song_count} 首`}, ${formatRelativeTime(item.deleted_at)}删除`}
+                />
+              </List.Item>
+            )}
+          />
+        </Card>
+
+        <Card
+          title="移出歌单的歌曲"
+          extra={
+            trash && trash.songs.length > 0 ? (
+              <Button
+                icon={<UndoOutlined />}
+                onClick={() => handleRestoreSongs(trash.songs.map((s) => s.id))}
+              >
+                全部恢复
+              </Button>
+            ) : null
+          }
+        >
+          <List
+            itemLayout="horizontal"
+            dataSource={trash?.songs ?? []}
+            locale={{ emptyText: "没有移出歌单的歌曲" }}
+            renderItem={(item) => (
+              <List.Item
+                actions={[
+                  <Button
+                    icon={<UndoOutlined />}
+                    onClick={() => handleRestoreSongs([item.id])}
+                  >
+                    恢复
+                  </Button>,
+                ]}
+              >
+                <List.Item.Meta
+                  avatar={
+                    <img
+                      width={48}
+                      alt="cover"
+                      src={buildCoverUrl(item.cover_url)}
+                    />
+                  }
+                  title={`${item.title} - ${item.artist}`}
+                  description={`${formatRelativeTime(item.deleted_at)}从“${item.playlist_name}”移除`}
+                />
+              </List.Item>
+            )}
+          />
+        </Card>
+      </Flex>
+    </Spin>
+  );
+};
+
+export default TrashPage;
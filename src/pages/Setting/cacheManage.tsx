@@ -9,6 +9,7 @@ import {
   Flex,
   Form,
   List,
+  Radio,
   Slider,
   Space,
   Spin,
@@ -16,12 +17,17 @@ import {
   Typography,
 } from "antd";
 import { invoke } from "@tauri-apps/api/core";
-import { ArrowLeftOutlined, DeleteOutlined } from "@ant-design/icons";
+import {
+  ArrowLeftOutlined,
+  DeleteOutlined,
+  MergeCellsOutlined,
+} from "@ant-design/icons";
 import { useNavigate } from "react-router-dom";
 import { useGlobalMessage, useGlobalModal } from "../../components/MessageHook";
 import { primaryThemeColor } from "../../main";
 import { useAppStore } from "../../store";
-import { buildCoverUrl } from "../../util";
+import { buildCoverUrl, formatSize } from "../../util";
+import { DuplicateGroup, MergeDuplicatesResult } from "../../types";
 
 const { Text } = Typography;
 
@@ -56,6 +62,12 @@ const CacheManagePage: React.FC = () => {
   const [playlistsInfo, setPlaylistsInfo] = useState<PlaylistCacheInfo[]>([]);
   const { currentMusic, handleClose } = useAppStore();
   const [form] = Form.useForm();
+  const [duplicateGroups, setDuplicateGroups] = useState<
+    DuplicateGroup[] | null
+  >(null);
+  // 每组选中保留的歌曲，key 为组内第一首歌的 song_id
+  const [keepChoices, setKeepChoices] = useState<Record<string, string>>({});
+  const [findingDuplicates, setFindingDuplicates] = useState(false);
 
   // 拉取所有缓存数据的函数
   const fetchData = useCallback(async () => {
@@ -171,6 +183,58 @@ const CacheManagePage: React.FC = () => {
     }
   };
 
+  const handleFindDuplicates = async () => {
+    try {
+      setFindingDuplicates(true);
+      const groups = await invoke<DuplicateGroup[]>("find_duplicate_songs");
+      setDuplicateGroups(groups);
+      setKeepChoices(
+        Object.fromEntries(
+          groups.map((g) => [g.songs[0].song_id, g.keep_song_id])
+        )
+      );
+      if (groups.length === 0) messageApi.info("没有发现重复歌曲");
+    } catch (error: any) {
+      messageApi.error(`查找重复歌曲失败: ${error}`);
+    } finally {
+      setFindingDuplicates(false);
+    }
+  };
+
+  const handleMergeGroup = (group: DuplicateGroup) => {
+    const groupKey = group.songs[0].song_id;
+    const keepId = keepChoices[groupKey] ?? group.keep_song_id;
+    const duplicateIds = group.songs
+      .map((s) => s.song_id)
+      .filter((id) => id !== keepId);
+    modalApi.confirm({
+      title: "合并重复歌曲",
+      content: `将保留选中的歌曲，其余 ${duplicateIds.length} 首的歌单和播放记录合并过来后删除，多余的缓存文件也会被删除。`,
+      okText: "合并",
+      cancelText: "取消",
+      onOk: async () => {
+        try {
+          if (currentMusic && duplicateIds.includes(currentMusic.song_id))
+            handleClose();
+          const result = await invoke<MergeDuplicatesResult>(
+            "merge_duplicate_songs",
+            { keepSongId: keepId, duplicateIds }
+          );
+          messageApi.success(
+            `已合并 ${result.merged} 首，删除 ${result.removed_files} 个缓存文件`
+          );
+          setDuplicateGroups(
+            (groups) =>
+              groups?.filter((g) => g.songs[0].song_id !== groupKey) ?? null
+          );
+          fetchData();
+        } catch (error: any) {
+          messageApi.error(`合并失败: ${error}`);
+        }
+      },
+    });
+  };
+
   return (
     <Spin spinning={loading}>
       <Flex vertical gap="1rem">
@@ -314,6 +378,67 @@ const CacheManagePage: React.FC = () => {
           </List>
         </Card>
 
+        <Card
+          title="重复歌曲"
+          extra={
+            <Button loading={findingDuplicates} onClick={handleFindDuplicates}>
+              查找
+            </Button>
+          }
+        >
+          {duplicateGroups === null ? (
+            <Text type="secondary">
+              按标题、歌手、时长和缓存文件内容查找曲库中的重复歌曲
+            </Text>
+          ) : (
+            <List
+              itemLayout="vertical"
+              dataSource={duplicateGroups}
+              renderItem={(group) => (
+                <List.Item
+                  actions={[
+                    <Button
+                      icon={<MergeCellsOutlined />}
+                      onClick={() => handleMergeGroup(group)}
+                    >
+                      合并
+                    </Button>,
+                  ]}
+                >
+                  <Text type="secondary">
+                    {group.reasons.includes("file_hash")
+                      ? "缓存文件相同"
+                      : "标题和歌手相同"}
+                  </Text>
+                  <Radio.Group
+                    value={keepChoices[group.songs[0].song_id]}
+                    onChange={(e) =>
+                      setKeepChoices((choices) => ({
+                        ...choices,
+                        [group.songs[0].song_id]: e.target.value,
+                      }))
+                    }
+                  >
+                    <Space direction="vertical">
+                      {group.songs.map((song) => (
+                        <Radio key={song.song_id} value={song.song_id}>
+                          {song.title} - {song.artist}
+                          <Text type="secondary">
+                            {` (${song.source ?? "未知来源"}, ${song.playlist_count} 个歌单, 播放 ${song.play_count} 次${song.file_path
+                              ? `, ${song.format ?? ""} ${formatSize(song.file_size_bytes)}`
+                              : ""
+                              })`}
+                          </Text>
+                        </Radio>
+                      ))}
+                    </Space>
+                  </Radio.Group>
+                </List.Item>
+              )}
+            />
+          )}
+        </Card>
+
         <Card title="歌单管理">
           <List
             itemLayout="horizontal"
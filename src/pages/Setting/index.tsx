@@ -11,16 +11,22 @@ import {
 } from "antd";
 import React, { useEffect, useState } from "react";
 import {
+  BarChartOutlined,
+  CloudDownloadOutlined,
   ClearOutlined,
   DatabaseOutlined,
   DownloadOutlined,
   ExportOutlined,
+  FundOutlined,
   FileProtectOutlined,
+  FileTextOutlined,
   ImportOutlined,
   InfoCircleOutlined,
+  RestOutlined,
   SelectOutlined,
   SendOutlined,
   SettingOutlined,
+  SoundOutlined,
 } from "@ant-design/icons";
 import { useGlobalMessage, useGlobalModal } from "../../components/MessageHook";
 import { useNavigate } from "react-router-dom";
@@ -29,10 +35,12 @@ import { invoke } from "@tauri-apps/api/core";
 import { primaryThemeColor } from "../../main";
 import { getVersion } from "@tauri-apps/api/app";
 import { open } from "@tauri-apps/plugin-dialog";
-import { readFile } from "@tauri-apps/plugin-fs";
+import { readFile, readTextFile } from "@tauri-apps/plugin-fs";
 import { relaunch } from "@tauri-apps/plugin-process";
 import { open as openShell } from "@tauri-apps/plugin-shell";
 import { platform } from "@tauri-apps/plugin-os";
+import type { ExternalImportReport, PlaylistImportReport } from "../../types";
+import { useAppStore } from "../../store";
 
 const { Paragraph } = Typography;
 
@@ -43,10 +51,19 @@ const SettingPage: React.FC = () => {
   const modalApi = useGlobalModal();
   const navigate = useNavigate();
   const [form] = Form.useForm();
+  const {
+    playbackEngine,
+    setPlaybackEngine,
+    volumeNormalization,
+    setVolumeNormalization,
+  } = useAppStore();
   const [loading, setLoading] = useState(true);
   const [downloadSettingOpen, setDownloadSettingOpen] = useState(false);
   const [cacheSize, setCacheSize] = useState("计算中...");
   const [appVersion, setAppVersion] = useState("");
+  const [externalReport, setExternalReport] = useState<ExternalImportReport | null>(null);
+  // 有歧义的条目: 序号 -> 选中的 song_id (空字符串表示跳过)
+  const [ambiguousChoices, setAmbiguousChoices] = useState<Record<number, string>>({});
 
   const settings = [
     {
@@ -70,6 +87,36 @@ const SettingPage: React.FC = () => {
       desc: `管理缓存的音乐文件`,
       extra: cacheSize,
     },
+    {
+      tag: "trash",
+      title: "回收站",
+      icon: (
+        <RestOutlined
+          style={{ fontSize: iconSize, color: primaryThemeColor }}
+        />
+      ),
+      desc: "恢复删除的歌单和移出歌单的歌曲",
+    },
+    {
+      tag: "playbackEngine",
+      title: "原生播放引擎",
+      icon: (
+        <SoundOutlined
+          style={{ fontSize: iconSize, color: primaryThemeColor }}
+        />
+      ),
+      desc: "支持无缝播放，窗口隐藏后也不会中断 (仅桌面端)",
+      extra: playbackEngine === "native" ? "已开启" : "已关闭",
+    },
+    {
+      tag: "volumeNormalization",
+      title: "音量均衡",
+      icon: (
+        <FundOutlined style={{ fontSize: iconSize, color: primaryThemeColor }} />
+      ),
+      desc: "根据响度分析调整音量，让每首歌听起来一样响",
+      extra: volumeNormalization ? "已开启" : "已关闭",
+    },
     {
       tag: "importLocal",
       title: "导入本地音乐",
@@ -96,6 +143,36 @@ const SettingPage: React.FC = () => {
       ),
       desc: "导入来自其他MusicBox客户端的播放列表",
     },
+    {
+      tag: "importPlaylistFile",
+      title: "导入歌单文件",
+      icon: (
+        <FileTextOutlined
+          style={{ fontSize: iconSize, color: primaryThemeColor }}
+        />
+      ),
+      desc: "导入 M3U8、XSPF 或 JSON 格式的歌单",
+    },
+    {
+      tag: "importExternal",
+      title: "从其他音乐 App 导入",
+      icon: (
+        <CloudDownloadOutlined
+          style={{ fontSize: iconSize, color: primaryThemeColor }}
+        />
+      ),
+      desc: "导入网易云、QQ 音乐导出的歌单或“歌名 - 歌手”文本列表",
+    },
+    {
+      tag: "yearReview",
+      title: "年度听歌报告",
+      icon: (
+        <BarChartOutlined
+          style={{ fontSize: iconSize, color: primaryThemeColor }}
+        />
+      ),
+      desc: "导出今年的听歌统计 (HTML 和 JSON)",
+    },
     {
       tag: "updater",
       title: "检查更新",
@@ -195,6 +272,19 @@ const SettingPage: React.FC = () => {
       messageApi.error("导出失败, 请稍后重试");
     }
   };
+  const handleExportYearReview = async () => {
+    messageApi.info("报告生成中...");
+    try {
+      const result: string = await invoke("export_year_review", {
+        year: new Date().getFullYear(),
+      });
+      messageApi.destroy();
+      messageApi.success(result);
+    } catch (error) {
+      messageApi.destroy();
+      messageApi.error(`导出失败: ${error}`);
+    }
+  };
   const handleImportDB = async () => {
     const selectedPath = await open({
       multiple: false,
@@ -245,6 +335,117 @@ const SettingPage: React.FC = () => {
       cancelButtonProps: { danger: true },
     });
   };
+  const handleImportPlaylistFile = async () => {
+    const selectedPath = await open({
+      multiple: false,
+      filters: [{ name: "Playlist", extensions: ["m3u", "m3u8", "xspf", "json"] }],
+      title: "选择要导入的歌单文件",
+    });
+    if (typeof selectedPath !== "string") {
+      messageApi.info("您取消了选择");
+      return;
+    }
+    try {
+      messageApi.loading("正在导入歌单...", 0);
+      const content = await readTextFile(selectedPath);
+      const report: PlaylistImportReport = await invoke("import_playlist", {
+        path: selectedPath,
+        content,
+      });
+      messageApi.destroy();
+      modalApi.info({
+        title: `已导入歌单“${report.name}”`,
+        content: (
+          <div>
+            <Paragraph>
+              共 {report.total} 首，匹配曲库 {report.matched} 首，新增本地歌曲{" "}
+              {report.added_local} 首，未匹配 {report.unmatched.length} 首。
+            </Paragraph>
+            {report.unmatched.slice(0, 20).map((track, index) => (
+              <Paragraph key={index} type="secondary" style={{ margin: 0 }}>
+                {track.artist ? `${track.artist} - ${track.title}` : track.title}
+              </Paragraph>
+            ))}
+          </div>
+        ),
+      });
+    } catch (error) {
+      messageApi.destroy();
+      messageApi.error(`导入失败: ${error}`);
+    }
+  };
+  const performExternalImport = async (
+    path: string,
+    content: string,
+    searchSource: boolean
+  ) => {
+    try {
+      messageApi.loading(
+        searchSource ? "正在匹配并搜索音源，可能需要几分钟..." : "正在匹配曲库...",
+        0
+      );
+      const report: ExternalImportReport = await invoke(
+        "import_external_playlist",
+        { path, content, searchSource }
+      );
+      messageApi.destroy();
+      setAmbiguousChoices(
+        Object.fromEntries(
+          report.ambiguous.map((m) => [m.index, m.candidates[0].song_id])
+        )
+      );
+      setExternalReport(report);
+    } catch (error) {
+      messageApi.destroy();
+      messageApi.error(`导入失败: ${error}`);
+    }
+  };
+  const handleImportExternal = async () => {
+    const selectedPath = await open({
+      multiple: false,
+      filters: [{ name: "Playlist", extensions: ["json", "txt"] }],
+      title: "选择其他音乐 App 导出的歌单",
+    });
+    if (typeof selectedPath !== "string") {
+      messageApi.info("您取消了选择");
+      return;
+    }
+    let content: string;
+    try {
+      content = await readTextFile(selectedPath);
+    } catch (error) {
+      messageApi.error(`读取文件失败: ${error}`);
+      return;
+    }
+    modalApi.confirm({
+      title: "是否在音源上搜索?",
+      content: "曲库中找不到的歌曲可以在音源上搜索匹配，歌曲较多时需要较长时间。",
+      okText: "搜索音源",
+      cancelText: "只匹配曲库",
+      onOk: () => performExternalImport(selectedPath, content, true),
+      onCancel: () => performExternalImport(selectedPath, content, false),
+    });
+  };
+  const handleConfirmExternalImport = async () => {
+    if (!externalReport) return;
+    const songIds = externalReport.ambiguous
+      .map((m) => ambiguousChoices[m.index])
+      .filter((id) => !!id);
+    try {
+      if (songIds.length > 0) {
+        await invoke("confirm_import_matches", {
+          playlistId: externalReport.playlist_id,
+          songIds,
+        });
+      }
+      messageApi.success(
+        `歌单“${externalReport.name}”已导入 ${externalReport.matched.length + songIds.length} 首歌曲`
+      );
+      setExternalReport(null);
+    } catch (error) {
+      messageApi.error(`操作失败: ${error}`);
+    }
+  };
   const handleUpdater = () => {
     messageApi.info("正在检查更新...", 1);
     checkForUpdates({ force: true, messageApi, modalApi }); // 强制检查
@@ -288,6 +489,15 @@ App 版本: v${appVersion}
   const handleAbout = () => {
     navigate("/setting/about");
   };
+  const handleTogglePlaybackEngine = () => {
+    if (platform() === "android" || platform() === "ios") {
+      messageApi.info("移动端暂不支持原生播放引擎");
+      return;
+    }
+    const engine = playbackEngine === "native" ? "webview" : "native";
+    setPlaybackEngine(engine);
+    messageApi.success(engine === "native" ? "已切换到原生播放引擎" : "已切换到系统播放器");
+  };
   const handleReset = () => {
     messageApi.info(`功能尚未完成, 请等待后续版本`, 1);
   };
@@ -300,6 +510,16 @@ App 版本: v${appVersion}
       case "manageCache":
         handleManageCache();
         break;
+      case "trash":
+        navigate("/setting/trash");
+        break;
+      case "playbackEngine":
+        handleTogglePlaybackEngine();
+        break;
+      case "volumeNormalization":
+        setVolumeNormalization(!volumeNormalization);
+        messageApi.success(volumeNormalization ? "已关闭音量均衡" : "已开启音量均衡");
+        break;
       case "importLocal":
         handleImportLocal();
         break;
@@ -309,6 +529,15 @@ App 版本: v${appVersion}
       case "importDB":
         handleImportDB();
         break;
+      case "importPlaylistFile":
+        handleImportPlaylistFile();
+        break;
+      case "importExternal":
+        handleImportExternal();
+        break;
+      case "yearReview":
+        handleExportYearReview();
+        break;
       case "updater":
         handleUpdater();
         break;
@@ -341,6 +570,14 @@ App 版本: v${appVersion}
         key: "filename_remove_spaces",
         value: String(values.filenameRemoveSpaces),
       });
+      await invoke("save_app_setting", {
+        key: "export_write_tags",
+        value: String(values.exportWriteTags),
+      });
+      await invoke("save_app_setting", {
+        key: "export_replaygain",
+        value: String(values.exportReplayGain),
+      });
       messageApi.success("设置已保存！");
       setDownloadSettingOpen(false);
     } catch (error) {
@@ -361,11 +598,19 @@ App 版本: v${appVersion}
         const filenameRemoveSpaces = await invoke("get_app_setting", {
           key: "filename_remove_spaces",
         });
+        const exportWriteTags = await invoke("get_app_setting", {
+          key: "export_write_tags",
+        });
+        const exportReplayGain = await invoke("get_app_setting", {
+          key: "export_replaygain",
+        });
 
         form.setFieldsValue({
           downloadPath: downloadPath || "MusicBox",
           filenameFormat: filenameFormat || "title_artist",
           filenameRemoveSpaces: filenameRemoveSpaces === "true",
+          exportWriteTags: exportWriteTags !== "false",
+          exportReplayGain: exportReplayGain === "true",
         });
       } catch (error) {
         messageApi.error("加载设置失败");
@@ -455,6 +700,22 @@ App 版本: v${appVersion}
             <Checkbox>移除文件名中的空格</Checkbox>
           </Form.Item>
 
+          <Form.Item
+            name="exportWriteTags"
+            valuePropName="checked"
+            tooltip="在导出的文件中写入歌名、歌手、封面和歌词，缓存中的原文件不受影响"
+          >
+            <Checkbox>导出时写入歌曲信息 (ID3 标签)</Checkbox>
+          </Form.Item>
+
+          <Form.Item
+            name="exportReplayGain"
+            valuePropName="checked"
+            tooltip="根据响度分析结果写入 ReplayGain 音量信息，需要同时开启写入歌曲信息"
+          >
+            <Checkbox>导出时写入 ReplayGain 音量信息</Checkbox>
+          </Form.Item>
+
           <Form.Item>
             <Button type="primary" htmlType="submit">
               保存下载设置
@@ -462,6 +723,58 @@ App 版本: v${appVersion}
           </Form.Item>
         </Form>
       </Modal>
+      <Modal
+        title={externalReport ? `导入“${externalReport.name}”` : ""}
+        open={externalReport !== null}
+        okText="确认"
+        cancelText="关闭"
+        onOk={handleConfirmExternalImport}
+        onCancel={() => setExternalReport(null)}
+      >
+        {externalReport && (
+          <div style={{ maxHeight: "60vh", overflowY: "auto" }}>
+            <Paragraph>
+              共 {externalReport.total} 首，已匹配 {externalReport.matched.length}{" "}
+              首，待确认 {externalReport.ambiguous.length} 首，未找到{" "}
+              {externalReport.missing.length} 首。
+            </Paragraph>
+            {externalReport.ambiguous.map((m) => (
+              <div key={m.index} style={{ marginBottom: 12 }}>
+                <Paragraph strong style={{ marginBottom: 4 }}>
+                  {m.track.artist ? `${m.track.title} - ${m.track.artist}` : m.track.title}
+                </Paragraph>
+                <Radio.Group
+                  value={ambiguousChoices[m.index] ?? ""}
+                  onChange={(e) =>
+                    setAmbiguousChoices((prev) => ({ ...prev, [m.index]: e.target.value }))
+                  }
+                >
+                  <Flex vertical>
+                    {m.candidates.map((c) => (
+                      <Radio key={c.song_id} value={c.song_id}>
+                        {c.title} - {c.artist} ({Math.round(c.score * 100)}%)
+                      </Radio>
+                    ))}
+                    <Radio value="">跳过</Radio>
+                  </Flex>
+                </Radio.Group>
+              </div>
+            ))}
+            {externalReport.missing.length > 0 && (
+              <>
+                <Paragraph strong style={{ marginBottom: 4 }}>
+                  未找到
+                </Paragraph>
+                {externalReport.missing.map((track, index) => (
+                  <Paragraph key={index} type="secondary" style={{ margin: 0 }}>
+                    {track.artist ? `${track.title} - ${track.artist}` : track.title}
+                  </Paragraph>
+                ))}
+              </>
+            )}
+          </div>
+        )}
+      </Modal>
     </Flex>
   );
 };
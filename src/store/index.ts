@@ -1,7 +1,7 @@
 import { create } from "zustand";
 import { persist, createJSONStorage, StateStorage } from "zustand/middleware";
 import { invoke } from "@tauri-apps/api/core";
-import { Music } from "../types";
+import { Music, PlayQueueState } from "../types";
 import { musicDetail, searchMusic } from "../util/crawler";
 import {
   isPermissionGranted,
@@ -34,6 +34,19 @@ const tauriStorage: StateStorage = {
 };
 
 export type PlayMode = "sequence" | "single" | "shuffle";
+// webview: 使用页面中的 <audio> 播放；native: 使用后端的原生播放引擎
+export type PlaybackEngine = "webview" | "native";
+
+// 旧版本缓存的 song_id 没有音源前缀，与后端的数据库迁移一样按 gequbao 补上
+const DEFAULT_SOURCE_ID = "gequbao";
+const withSourcePrefix = (music: Music): Music =>
+  music.song_id.includes(":")
+    ? music
+    : { ...music, song_id: `${DEFAULT_SOURCE_ID}:${music.song_id}` };
+
+// 播放进度写入后端的最小间隔 (毫秒)
+const POSITION_SAVE_INTERVAL = 5000;
+let lastPositionSavedAt = 0;
 
 // 2. 定义 store 的 state 和 actions 的类型
 interface AppState {
@@ -53,12 +66,18 @@ interface AppState {
   playMode: PlayMode; // [新增] 播放模式
   currentTime: number;
   duration: number;
+  resumePositionSecs: number | null; // 恢复上次的播放队列后，加载歌曲时跳转到的位置
   floatPlayerCollapsed: boolean;
+  playbackEngine: PlaybackEngine;
+  setPlaybackEngine: (engine: PlaybackEngine) => void;
+  volumeNormalization: boolean; // 按响度分析结果调整每首歌的音量
+  setVolumeNormalization: (enabled: boolean) => void;
   setFloatPlayerCollapsed: (collapse?: boolean) => void;
 
   // 歌单状态
   currentPlaylistId?: number | null;
   setCurrentPlaylistId: (playlistId: number) => void;
+  playingPlaylistId: number | null; // 当前播放队列来自哪个歌单，用于播放记录
 
   // 下载状态
   downloadingIds: Set<string>;
@@ -67,8 +86,8 @@ interface AppState {
 
   // Actions
   handleSearch: (value: string) => Promise<void>;
-  handleDetail: (music: Music) => Promise<Music>;
-  startPlayback: (songs: Music[], startIndex: number) => Promise<void>;
+  handleDetail: (music: Music, stream?: boolean) => Promise<Music>;
+  startPlayback: (songs: Music[], startIndex: number, playlistId?: number | null) => Promise<void>;
   handlePlayPause: () => void;
   _playIndexMusic: (index: number) => void;
   handleNext: () => void;
@@ -77,7 +96,10 @@ interface AppState {
   handleClose: () => void;
   setCurrentTime: (time: number) => void;
   setDuration: (duration: number) => void;
+  seekTo: (audio: HTMLAudioElement | null, time: number) => void; // 跳转进度，按播放引擎分别处理
   cyclePlayMode: (mode?: PlayMode) => Promise<string>; // [新增] 切换播放模式
+  syncPlayQueue: (queue: PlayQueueState) => void; // 应用后端推送的播放队列
+  restorePlayQueue: () => Promise<void>; // 启动时从后端恢复播放队列
   saveSongWithNotifications: (musicList: Music[]) => Promise<string>; // 下载时发送通知
 }
 
@@ -100,11 +122,24 @@ export const useAppStore = create<AppState>()(
       isPlaying: false,
       currentTime: 0,
       duration: 0,
+      resumePositionSecs: null,
       floatPlayerCollapsed: false,
+      playbackEngine: "webview",
+      volumeNormalization: false,
+      setVolumeNormalization: (enabled) => set({ volumeNormalization: enabled }),
+      setPlaybackEngine: (engine) => {
+        if (engine === get().playbackEngine) return;
+        // 切换前先停止原生引擎，避免两个引擎同时发声
+        if (engine === "webview") {
+          invoke("native_stop").catch(console.error);
+        }
+        set({ playbackEngine: engine, resumePositionSecs: get().currentTime || null });
+      },
       setFloatPlayerCollapsed: (collapse?: boolean) => { (collapse !== undefined) ? set({ floatPlayerCollapsed: collapse }) : set({ floatPlayerCollapsed: !get().floatPlayerCollapsed }) },
 
       currentPlaylistId: null,
       setCurrentPlaylistId: (playlistId?: number | null) => { set({ currentPlaylistId: playlistId }) },
+      playingPlaylistId: null,
 
       downloadingIds: new Set(), // <-- 初始化为空 Set
       addDownloadingId: (id) => {
@@ -152,9 +187,9 @@ export const useAppStore = create<AppState>()(
         }
       },
 
-      handleDetail: async (music: Music) => {
+      handleDetail: async (music: Music, stream?: boolean) => {
         try {
-          const result = await musicDetail(music);
+          const result = await musicDetail(music, { stream });
           return result;
         } catch (error) {
           throw error;
@@ -165,17 +200,30 @@ export const useAppStore = create<AppState>()(
         set((state) => ({ isPlaying: !state.isPlaying }));
       },
 
-      startPlayback: async (musicList, startIndex) => {
+      startPlayback: async (musicList, startIndex, playlistId) => {
         if (!musicList || musicList.length === 0) return;
 
-        // 1. 设置播放队列
-        set({ playQueue: musicList, playingMusicIndex: startIndex });
+        // 1. 设置播放队列 (切换队列内的歌曲时 playlistId 为 undefined，保持原来的歌单)
+        const sameQueue = musicList === get().playQueue && playlistId === undefined;
+        set({ playQueue: musicList, playingMusicIndex: startIndex, resumePositionSecs: null });
+        if (playlistId !== undefined) {
+          set({ playingPlaylistId: playlistId });
+        }
+        // 同步到后端保存，应用被结束后可以恢复
+        (sameQueue
+          ? invoke("set_queue_index", { index: startIndex })
+          : invoke("set_play_queue", {
+            songs: musicList,
+            startIndex,
+            playlistId: get().playingPlaylistId,
+          })
+        ).catch((e) => console.error("保存播放队列失败:", e));
 
         // 2. 获取歌曲详情并开始播放
         const musicToPlay = musicList[startIndex];
         try {
           await get()
-            .handleDetail(musicToPlay)
+            .handleDetail(musicToPlay, true)
             .then((music: Music) => {
               set({ isPlaying: true, currentMusic: music });
             });
@@ -271,20 +319,73 @@ export const useAppStore = create<AppState>()(
           playingMusicIndex: -1,
           playQueue: [],
         });
+        invoke("clear_play_queue").catch((e) => console.error("清空播放队列失败:", e));
+      },
+      setCurrentTime: (time) => {
+        set({ currentTime: time });
+        const now = Date.now();
+        if (get().currentMusic && now - lastPositionSavedAt >= POSITION_SAVE_INTERVAL) {
+          lastPositionSavedAt = now;
+          invoke("save_play_position", { positionSecs: time }).catch(console.error);
+        }
       },
-      setCurrentTime: (time) => set({ currentTime: time }),
       setDuration: (duration) => set({ duration: duration }),
+      seekTo: (audio, time) => {
+        const { playbackEngine, currentMusic, isPlaying } = get();
+        if (playbackEngine === "native") {
+          invoke("native_seek", { positionSecs: time }).catch(console.error);
+          set({ currentTime: time });
+        } else if (audio) {
+          audio.currentTime = time;
+        }
+        // 系统媒体控制按上报的进度推算当前位置，跳转后需要重新同步
+        invoke("update_media_session", {
+          songId: currentMusic?.song_id ?? null,
+          playing: isPlaying,
+          positionSecs: time,
+        }).catch(console.error);
+      },
       cyclePlayMode: async (mode?: PlayMode) => {
         const { playMode } = get();
-        if (mode && playMode.includes(mode)) {
-          set({ playMode: mode });
-          return mode;
-        }
         const modes: PlayMode[] = ["sequence", "single", "shuffle"];
-        const currentIndex = modes.indexOf(playMode);
-        const nextIndex = (currentIndex + 1) % modes.length;
-        set({ playMode: modes[nextIndex] });
-        return modes[nextIndex]; // 返回新的播放模式
+        const newMode =
+          mode && playMode.includes(mode)
+            ? mode
+            : modes[(modes.indexOf(playMode) + 1) % modes.length];
+        set({ playMode: newMode });
+        invoke("set_play_mode", { mode: newMode }).catch((e) =>
+          console.error("保存播放模式失败:", e)
+        );
+        return newMode; // 返回新的播放模式
+      },
+      syncPlayQueue: (queue) => {
+        set({
+          playQueue: queue.songs,
+          playingMusicIndex: queue.current_index,
+          playMode: queue.play_mode,
+          playingPlaylistId: queue.playlist_id,
+        });
+      },
+      restorePlayQueue: async () => {
+        const { playQueue, playingMusicIndex, playingPlaylistId, playMode, currentMusic, syncPlayQueue } = get();
+        const queue = await invoke<PlayQueueState>("get_play_queue");
+        if (queue.songs.length === 0 && playQueue.length > 0) {
+          // 旧版本只在前端保存了播放队列，第一次启动时迁移到后端
+          const migrated = await invoke<PlayQueueState>("set_play_queue", {
+            songs: playQueue,
+            startIndex: playingMusicIndex,
+            playlistId: playingPlaylistId,
+          });
+          await invoke("set_play_mode", { mode: playMode });
+          syncPlayQueue({ ...migrated, play_mode: playMode });
+          return;
+        }
+        syncPlayQueue(queue);
+        // 上次播放的歌曲从保存的进度继续
+        const current = queue.songs[queue.current_index];
+        if (current && current.song_id === currentMusic?.song_id) {
+          set({ resumePositionSecs: queue.position_secs });
+        }
       },
       saveSongWithNotifications: async (musicList?: Music[]) => {
         const { handleSave, currentMusic } = get();
@@ -339,15 +440,30 @@ export const useAppStore = create<AppState>()(
     {
       name: "frontend-cache",
       storage: createJSONStorage(() => tauriStorage),
+      // 1: song_id 加上了音源前缀
+      version: 1,
+      migrate: (persisted: any, version) => {
+        if (version < 1 && persisted) {
+          if (persisted.currentMusic) {
+            persisted.currentMusic = withSourcePrefix(persisted.currentMusic);
+          }
+          // playQueue 只有更早的版本会缓存，启动时由 restorePlayQueue 迁移到后端
+          for (const key of ["musicList", "playQueue"]) {
+            if (Array.isArray(persisted[key])) {
+              persisted[key] = persisted[key].map(withSourcePrefix);
+            }
+          }
+        }
+        return persisted;
+      },
       partialize: (state) => ({
         currentMusic: state.currentMusic,
-        playingMusicIndex: state.playingMusicIndex,
         musicList: state.musicList,
         currentKeyword: state.currentKeyword,
-        playQueue: state.playQueue,
-        playMode: state.playMode,
         currentPlaylistId: state.currentPlaylistId,
         floatPlayerCollapsed: state.floatPlayerCollapsed,
+        playbackEngine: state.playbackEngine,
+        volumeNormalization: state.volumeNormalization,
       }),
     }
   )
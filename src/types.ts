@@ -11,6 +11,9 @@ export interface MusicDetail {
   play_url?: string;
   file_path?: string;
   last_played_at?: string;
+  // 缓存文件的实际格式 (扩展名) 和 MIME 类型
+  format?: string;
+  mime?: string;
 }
 
 export interface Music extends MusicDetail {
@@ -26,12 +29,17 @@ export interface SearchResult {
   has_more: boolean;
 }
 
+// 歌单内歌曲的排序方式，manual 为手动拖动的顺序
+export type PlaylistSortMode = "manual" | "added_desc" | "title" | "artist" | "last_played";
+
 export interface PlaylistInfo {
   id: number;
   name: string;
   cover_path: string;
   song_count: number;
   is_in: boolean;
+  is_smart?: boolean; // 智能歌单的歌曲由规则计算，不能手动添加
+  sort_mode: PlaylistSortMode;
 
   created_at: string; // 可选字段，可能在某些 API 中不存在
   updated_at: string; // 可选字段，可能在某些 API 中不存在
@@ -57,3 +65,142 @@ export interface CachedMusicInfo {
   last_played_at?: string;
   file_size_bytes: number;
 }
+
+// 歌单文件 (M3U8/XSPF/JSON) 中的一首歌
+export interface PlaylistFileTrack {
+  song_id?: string;
+  title: string;
+  artist: string;
+  duration_secs?: number;
+  source?: string;
+  url?: string;
+  cover_url?: string;
+  location?: string;
+}
+
+export interface PlaylistImportReport {
+  playlist_id: number;
+  name: string;
+  total: number;
+  matched: number;
+  added_local: number;
+  unmatched: PlaylistFileTrack[];
+}
+
+// 从其他音乐 App 的导出文件中读取的一首歌
+export interface ExternalTrack {
+  title: string;
+  artist: string;
+  album?: string;
+  duration_secs?: number;
+}
+
+export interface ImportCandidate {
+  song_id: string;
+  title: string;
+  artist: string;
+  source?: string;
+  score: number;
+}
+
+export interface ExternalTrackMatch {
+  index: number;
+  track: ExternalTrack;
+  candidates: ImportCandidate[];
+}
+
+export interface ExternalImportReport {
+  playlist_id: number;
+  name: string;
+  total: number;
+  matched: ExternalTrackMatch[];
+  ambiguous: ExternalTrackMatch[];
+  missing: ExternalTrack[];
+}
+
+// 后端保存的播放队列，修改后通过 play-queue-changed 事件推送
+export interface PlayQueueState {
+  songs: Music[];
+  current_index: number;
+  play_mode: "sequence" | "single" | "shuffle";
+  position_secs: number;
+  playlist_id: number | null;
+}
+
+// 音量均衡：volume_scale 为调整到目标响度的音量倍数，未分析时为 1
+export interface TrackGain {
+  song_id: string;
+  loudness_lufs: number | null;
+  true_peak: number | null;
+  gain_db: number | null;
+  volume_scale: number;
+}
+
+// 原生播放引擎的状态，也是 native-player-* 事件的负载
+export interface NativePlayerState {
+  song_id: string | null;
+  position_secs: number;
+  duration_secs: number | null;
+  playing: boolean;
+  volume: number;
+}
+
+// 系统媒体控制 (MPRIS、托盘菜单) 发来的操作，media-control 事件的负载
+export type MediaControl =
+  | { action: "play" | "pause" | "play_pause" | "next" | "previous" | "stop" }
+  | { action: "seek"; position_secs: number }
+  | { action: "play_playlist"; playlist_id: number };
+
+export interface TrashedPlaylist {
+  id: number;
+  name: string;
+  cover_path?: string;
+  song_count: number;
+  is_smart: boolean;
+  deleted_at: string;
+}
+
+// id 为回收站记录的 ID，恢复时传给 restore_removed_songs
+export interface TrashedSong {
+  id: number;
+  playlist_id: number;
+  playlist_name: string;
+  song_id: string;
+  title: string;
+  artist: string;
+  cover_url?: string;
+  deleted_at: string;
+}
+
+export interface TrashContents {
+  playlists: TrashedPlaylist[];
+  songs: TrashedSong[];
+  retention_days: number;
+}
+
+export interface DuplicateSong {
+  song_id: string;
+  title: string;
+  artist: string;
+  source?: string;
+  duration_secs?: number;
+  file_path?: string;
+  format?: string;
+  added_at: string;
+  playlist_count: number;
+  play_count: number;
+  file_size_bytes: number;
+}
+
+// reasons: metadata (标题/歌手/时长一致)、file_hash (缓存文件相同)
+export interface DuplicateGroup {
+  reasons: string[];
+  keep_song_id: string;
+  songs: DuplicateSong[];
+}
+
+export interface MergeDuplicatesResult {
+  kept_song_id: string;
+  merged: number;
+  removed_files: number;
+}
@@ -1,96 +1,31 @@
 // src/crawler.ts (或你的API文件)
 
-import { fetch as tauriFetch } from "@tauri-apps/plugin-http";
 import { invoke } from "@tauri-apps/api/core";
 import { Music, SearchResult } from "../types"; // 确保类型定义正确
 
-const parser = new DOMParser();
-
-// export const BASE_URL = "https://www.gequhai.net"; // 基础URL
-export const BASE_URL = "https://www.gequbao.com"; // 基础URL
-
 /**
- * [重构后] 根据关键词搜索音乐
+ * 根据关键词搜索音乐
+ * 页面抓取和解析已经迁移到 Rust 端的 source 模块，搜索结果会由后端保存到数据库
  * @param keyword 搜索关键词
- * @param page 页码
  * @returns Promise<SearchResult>
  */
 export const searchMusic = async (keyword: string): Promise<SearchResult> => {
-  const searchUrl = `${BASE_URL}/s/${encodeURIComponent(keyword)}`;
-
   try {
-    const response = await tauriFetch(searchUrl, {
-      method: "GET",
-    });
-
-    if (!response.ok) {
-      throw new Error(`请求搜索页失败: ${response.status}`);
-    }
-
-    const htmlText = (await response.text()) as string;
-    const doc = parser.parseFromString(htmlText, "text/html");
-
-    const rows = doc.querySelectorAll(".row.no-gutters.py-2d5");
-
-    const results = Array.from(rows)
-      .map((row) => {
-        // 2. 在每一行内寻找特定的元素
-        // 歌名通常在 text-primary 类下
-        const title = row.querySelector(".text-primary")?.textContent?.trim();
-        // 歌手通常在 text-jade 类下
-        const artist = row.querySelector(".text-jade")?.textContent?.trim();
-        // 链接找 href 包含 /music/ 的
-        const linkElement = row.querySelector('a[href^="/music/"]');
-        const href = linkElement?.getAttribute("href");
-
-        return { title, artist, href };
-      })
-      .filter((item) => item.href); // 过滤掉无效行
-    const musicList: Music[] = [];
-
-    results.forEach(({ title, artist, href }) => {
-      const detailUrl = href;
-
-      if (title && artist && detailUrl) {
-        // 4. [核心修改] 从 URL 中提取 song_id，新方式更简单可靠
-        // 通过分割字符串 "/music/" 来获取后面的 ID 部分
-        const songId = detailUrl.split("/music/")[1];
-
-        if (songId) {
-          musicList.push({
-            song_id: songId,
-            title: title.trim(),
-            artist: artist.trim(),
-            // 保存相对 URL，后续拼接成完整链接
-            url: detailUrl,
-          });
-        }
-      }
-    });
-
-    const hasMore = false; // musicList.length > 0;
-
-    // 6. 保存到数据库的逻辑保持不变，依然健壮
-    if (musicList.length > 0) {
-      try {
-        await invoke("save_music", { musicList }); // 'save_music' 必须与 Rust command 的函数名完全一致
-        console.log("成功将", musicList.length, "首歌曲保存到本地数据库！");
-      } catch (error) {
-        console.error("调用 save_music 失败:", error);
-      }
-    }
-
-    return {
-      music_list: musicList,
-      has_more: hasMore,
-    };
+    return await invoke<SearchResult>("search_music", { keyword });
   } catch (error) {
-    console.error("前端爬虫搜索失败:", error);
+    console.error("搜索失败:", error);
     throw error; // 将错误继续向上抛出，让 UI 层可以捕获并显示
   }
 };
 
-export const musicDetail = async (music: Music): Promise<Music> => {
+/**
+ * 获取歌曲详情和播放地址，并缓存歌曲文件
+ * @param options.stream 为 true 时不等待缓存完成，由播放器通过 /stream 边下边播
+ */
+export const musicDetail = async (
+  music: Music,
+  options: { stream?: boolean } = {}
+): Promise<Music> => {
   try {
     const dbMusic = await invoke<Music[]>("get_music_list_by_ids", {
       songIds: [music.song_id],
@@ -99,7 +34,6 @@ export const musicDetail = async (music: Music): Promise<Music> => {
     if (!dbMusic) {
       throw new Error("数据库中未找到该歌曲的基本信息");
     }
-    const fullDetailUrl = `${BASE_URL}${dbMusic.url}`;
 
     if (dbMusic && dbMusic.play_id && dbMusic.file_path) {
       console.log(`(DB) 已有详情，直接返回: ${music.title}`);
@@ -109,44 +43,23 @@ export const musicDetail = async (music: Music): Promise<Music> => {
       };
     }
 
-    console.log(`(Crawler) 数据库无详情，开始爬取: ${music.title}`);
-    music = await fetchMusicDetailInfo(music, fullDetailUrl);
-
-    const play_url = await fetchMusicPlayUrl(music, fullDetailUrl);
+    console.log(`(Source) 数据库无详情，开始获取: ${music.title}`);
+    // 后端负责抓取详情、获取播放地址并写回数据库
+    const finalMusic = await invoke<Music>("fetch_music_detail", {
+      songId: music.song_id,
+    });
 
-    const payload = {
-      ...dbMusic,
-      play_id: music.play_id,
-      lyric: music.lyric,
-      cover_url: music.cover_url,
-      duration: music.duration,
-      download_extra: music.download_extra,
-      download_mp3_id: music.download_mp3_id,
-      file_path: music.file_path,
-      last_played_at: music.last_played_at,
-      play_url: play_url,
-      download_mp3: play_url,
-    };
+    if (options.stream) {
+      console.log(`(Source) 边下边播: ${music.title}`);
+      return { ...finalMusic, file_path: undefined };
+    }
 
-    // 调用后端更新数据库
-    await invoke("update_music_detail", { payload: payload });
     const file_path = await invoke<string | undefined>(
       "cache_music_and_get_file_path",
-      { music: payload },
+      { music: finalMusic },
     );
-    console.log(`(Crawler) 成功爬取并更新到数据库: ${music.title}`);
+    console.log(`(Source) 成功获取并更新到数据库: ${music.title}`);
 
-    console.log(`(DB) 重新获取刚更新的详情: ${music.title}`);
-    const finalMusic = await invoke<Music[]>("get_music_list_by_ids", {
-      songIds: [music.song_id],
-    }).then((res) => (res ? res[0] : null));
-
-    if (!finalMusic) {
-      throw new Error("更新详情后未能从数据库中重新获取，请检查数据库逻辑！");
-    }
-
-    // 返回从数据库中拿到的最终数据，它可能包含了后端处理过的信息（如 Base64 封面）
-    console.log(`(Crawler) 最终返回详情: ${finalMusic}`);
     return { ...finalMusic, file_path };
   } catch (error) {
     console.error(`获取歌曲 '${music.title}' 详情的完整流程失败:`, error);
@@ -155,85 +68,3 @@ export const musicDetail = async (music: Music): Promise<Music> => {
     return music;
   }
 };
-
-export const fetchMusicPlayUrl = async (
-  music: Music,
-  fullDetailUrl: string,
-): Promise<string | null> => {
-  const apiUrl = `${BASE_URL}/api/play-url`;
-
-  const apiResponse = await tauriFetch(apiUrl, {
-    method: "POST",
-    body: `id=${music.play_id}`,
-    headers: {
-      "Content-Type": "application/x-www-form-urlencoded; charset=UTF-8",
-      Origin: BASE_URL,
-      Referer: fullDetailUrl,
-      "X-Requested-With": "XMLHttpRequest",
-      "User-Agent":
-        "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/108.0.0.0 Safari/537.36",
-    },
-  });
-
-  if (!apiResponse.ok) {
-    throw new Error(`请求播放API失败: ${apiResponse.status}`);
-  }
-  const apiData = (await apiResponse.json()) as any;
-  if (apiData?.code !== 1 || !apiData?.data?.url) {
-    throw new Error(apiData.msg || "播放API未返回有效的URL");
-  }
-  return apiData.data.url;
-};
-
-export const fetchMusicDetailInfo = async (
-  music: Music,
-  fullDetailUrl: string,
-): Promise<Music> => {
-  const response = await tauriFetch(fullDetailUrl, { method: "GET" });
-  if (!response.ok) {
-    throw new Error(`请求详情页失败: ${response.status}`);
-  }
-  const htmlText = (await response.text()) as string;
-
-  // 步骤 2: [核心修改] 使用正则表达式从 HTML 中提取 window.appData
-  const appDataMatch = htmlText.match(
-    /window\.appData = JSON\.parse\(\s*'((?:[^'\\]|\\.)*?)'\s*\)/,
-  );
-  if (!appDataMatch || !appDataMatch[1]) {
-    throw new Error("在详情页中未能解析出 appData");
-  }
-  const jsonString = JSON.parse(`"${appDataMatch[1]}"`);
-
-  const appData = JSON.parse(jsonString);
-
-  // 从 appData 中提取关键信息
-  const playId = appData.play_id;
-  const mp3Id = appData.mp3_id.toString();
-  const extraUrls = appData?.mp3_extra_urls;
-
-  if (!playId || !mp3Id) {
-    throw new Error("appData 中缺少 play_id 或 mp3_id");
-  }
-
-  // 步骤 3: 使用 DOMParser 解析静态信息（作为备用和补充）
-  const doc = parser.parseFromString(htmlText, "text/html");
-  const cover_url = doc.querySelector<HTMLMetaElement>(
-    'meta[property="og:image"]',
-  )?.content;
-  const lyric =
-    doc
-      .getElementById("content-lrc")
-      ?.innerHTML.replace(/<br\s*\/?>/gi, "\n") || "";
-
-  return {
-    ...music,
-    play_id: playId,
-    song_id: music.song_id,
-    lyric: lyric,
-    cover_url: (cover_url || appData.mp3_cover)?.replace("http://", "https://"),
-    duration: music.duration, // 假设 music 对象中已有 duration
-    download_extra:
-      extraUrls && extraUrls.length > 0 ? extraUrls[0].share_link : undefined,
-    download_mp3_id: mp3Id,
-  };
-};
@@ -1,5 +1,30 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// 本地媒体服务器的默认地址，端口被占用时后端会改用其他端口
 export const MEDIA_ADDR = "127.0.0.1:38915";
 
+interface MediaServerInfo {
+  base_url: string;
+  token: string;
+}
+
+let mediaBaseUrl = `http://${MEDIA_ADDR}`;
+let mediaToken = "";
+
+// 启动时获取媒体服务器的实际地址和本次启动的访问令牌，之后拼接的地址都会带上令牌
+export const initMediaServer = async (): Promise<void> => {
+  try {
+    const info = await invoke<MediaServerInfo>("get_media_server_info");
+    mediaBaseUrl = info.base_url;
+    mediaToken = info.token;
+  } catch (error) {
+    console.error("获取媒体服务器信息失败:", error);
+  }
+};
+
+const buildMediaUrl = (path: string): string =>
+  `${mediaBaseUrl}/${path}?token=${encodeURIComponent(mediaToken)}`;
+
 export const sanitizeFilename = (name: string): string => {
   return name.replace(/[\\/:\*\?"<>\|]/g, "");
 };
@@ -47,7 +72,26 @@ export const buildMusicFileUrl = (file_path: string | null | undefined): string
   if (!file_path) {
     return ""; // 默认封面
   }
-  return `http://${MEDIA_ADDR}/music_cache/${file_path}`;
+  return buildMediaUrl(`music_cache/${file_path}`);
+};
+
+// 未缓存的歌曲通过本地媒体服务器边下边播，下载完成后自动写入缓存
+export const buildStreamUrl = (song_id: string | null | undefined): string => {
+  if (!song_id) {
+    return "";
+  }
+  return buildMediaUrl(`stream/${encodeURIComponent(song_id)}`);
+};
+
+export const buildPlaybackUrl = (
+  music: { song_id?: string; file_path?: string } | null | undefined
+): string => {
+  if (!music) {
+    return "";
+  }
+  return music.file_path
+    ? buildMusicFileUrl(music.file_path)
+    : buildStreamUrl(music.song_id);
 };
 
 export const buildCoverUrl = (coverPath: string | null | undefined): string => {
@@ -59,5 +103,5 @@ export const buildCoverUrl = (coverPath: string | null | undefined): string => {
     return coverPath;
   }
   // 否则，它是文件名，我们拼接本地服务器地址
-  return `http://${MEDIA_ADDR}/cover_cache/${coverPath}`;
+  return buildMediaUrl(`cover_cache/${coverPath}`);
 };